@@ -0,0 +1,129 @@
+//! Compares the `match` dispatch of `segment_element` with a `phf::Map` of
+//! parsers indexed by Element ID.
+//!
+//! The match is kept as the map is not faster: both take the same time on
+//! the Top-Level Elements of the test file, and the map is about 20% slower
+//! on small elements, as hashing the ID costs more than the comparisons the
+//! compiler generates for the match. Run with
+//! `cargo bench --bench segment_element`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use nom::bytes::streaming::take;
+use nom::sequence::pair;
+
+use matroska::ebml::{checksum, crc, elem_size, vid, EbmlParsable, EbmlResult, Error, ErrorKind};
+use matroska::elements::{self, segment_element, SegmentElement};
+use matroska::serializer::ebml::{encode_element_id, encode_vint};
+
+type ElementParser = for<'a> fn(&'a [u8]) -> EbmlResult<'a, SegmentElement<'a>>;
+
+static PARSERS: phf::Map<u32, ElementParser> = phf::phf_map! {
+    0x114D9B74_u32 => |i| parse::<elements::SeekHead>(i).map(|(i, e)| (i, SegmentElement::SeekHead(e))),
+    0x1549A966_u32 => |i| parse::<elements::Info>(i).map(|(i, e)| (i, SegmentElement::Info(e))),
+    0x1F43B675_u32 => |i| parse::<elements::Cluster>(i).map(|(i, e)| (i, SegmentElement::Cluster(e))),
+    0x1043A770_u32 => |i| parse::<elements::Chapters>(i).map(|(i, e)| (i, SegmentElement::Chapters(e))),
+    0x1254C367_u32 => |i| parse::<elements::Tags>(i).map(|(i, e)| (i, SegmentElement::Tags(e))),
+    0x1941A469_u32 => |i| parse::<elements::Attachments>(i).map(|(i, e)| (i, SegmentElement::Attachments(e))),
+    0x1654AE6B_u32 => |i| parse::<elements::Tracks>(i).map(|(i, e)| (i, SegmentElement::Tracks(e))),
+    0x1C53BB6B_u32 => |i| parse::<elements::Cues>(i).map(|(i, e)| (i, SegmentElement::Cues(e))),
+    0xEC_u32 => |i| {
+        let (i, size) = elem_size(i)?;
+        take(size)(i).map(|(i, _)| (i, SegmentElement::Void(size)))
+    },
+};
+
+// The size, CRC-32 and data of an element whose ID was parsed, as done by
+// `segment_element`.
+fn parse<'a, O: EbmlParsable<'a>>(input: &'a [u8]) -> EbmlResult<'a, O> {
+    let (i, (size, crc)) = pair(elem_size, crc)(input)?;
+    let size = match crc {
+        Some(_) => size.checked_sub(6).ok_or(nom::Err::Error(Error {
+            id: 0,
+            kind: ErrorKind::CrcLongerThanElement,
+        }))?,
+        None => size,
+    };
+    let (i, data) = checksum(crc, take(size))(i)?;
+
+    match O::try_parse(data) {
+        Ok(o) => Ok((i, o)),
+        Err(kind) => Err(nom::Err::Error(Error { id: 0, kind })),
+    }
+}
+
+fn segment_element_phf(input: &[u8]) -> EbmlResult<'_, SegmentElement<'_>> {
+    let (i, id) = vid(input)?;
+    match PARSERS.get(&id) {
+        Some(parser) => parser(i),
+        None => {
+            let (i, size) = elem_size(i)?;
+            take(size)(i).map(|(i, _)| (i, SegmentElement::Unknown(id, Some(size))))
+        }
+    }
+}
+
+// The element of ID `id` holding `data`.
+fn encode_element(id: u32, data: &[u8]) -> Option<Vec<u8>> {
+    let mut element = encode_element_id(id).ok()?;
+    element.extend(encode_vint(data.len() as u64).ok()?);
+    element.extend_from_slice(data);
+    Some(element)
+}
+
+// Parses all the elements of `data`, returning their number.
+fn parse_all(data: &[u8], parser: fn(&[u8]) -> EbmlResult<'_, SegmentElement<'_>>) -> usize {
+    let mut input = data;
+    let mut count = 0;
+    while !input.is_empty() {
+        let (rest, element) = parser(input).unwrap();
+        std::hint::black_box(element);
+        input = rest;
+        count += 1;
+    }
+    count
+}
+
+// The Top-Level Elements of the test file, followed by the small elements
+// for which the dispatch matters most.
+fn inputs() -> Vec<(&'static str, Vec<u8>)> {
+    let webm = std::fs::read("assets/bbb-vp9-opus.webm").unwrap();
+    let (rest, _) = matroska::ebml::ebml_header(&webm).unwrap();
+    let (segment, _) = elements::segment(rest).unwrap();
+
+    let mut info = encode_element(0x4D80, b"bench").unwrap();
+    info.extend(encode_element(0x5741, b"bench").unwrap());
+    let mut seek = encode_element(0x53AB, &[0x15, 0x49, 0xA9, 0x66]).unwrap();
+    seek.extend(encode_element(0x53AC, &[0]).unwrap());
+    let elements = [
+        encode_element(0x1549A966, &info).unwrap(),
+        encode_element(0x114D9B74, &encode_element(0x4DBB, &seek).unwrap()).unwrap(),
+        encode_element(0xEC, &[0; 4]).unwrap(),
+        encode_element(0x1F43B675, &encode_element(0xE7, &[1]).unwrap()).unwrap(),
+        encode_element(0x12345678, &[0; 2]).unwrap(),
+    ];
+    let small = elements
+        .iter()
+        .cycle()
+        .take(60_000)
+        .flatten()
+        .copied()
+        .collect();
+
+    vec![("bbb-vp9-opus", segment.to_vec()), ("small", small)]
+}
+
+fn dispatch(c: &mut Criterion) {
+    for (name, data) in inputs() {
+        let count = parse_all(&data, segment_element);
+        assert_eq!(count, parse_all(&data, segment_element_phf));
+
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_function("match", |b| b.iter(|| parse_all(&data, segment_element)));
+        group.bench_function("phf", |b| b.iter(|| parse_all(&data, segment_element_phf)));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, dispatch);
+criterion_main!(benches);
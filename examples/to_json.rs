@@ -0,0 +1,29 @@
+//! Dumps the metadata of a file, everything but the Clusters and the Cues,
+//! as JSON on the standard output.
+//!
+//! ```text
+//! cargo run --features serde --example to_json -- video.mkv
+//! ```
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+
+use matroska::reader::MatroskaReader;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    pretty_env_logger::init();
+    let mut args = env::args();
+    let _ = args.next().expect("first arg is program path");
+    let filename = args.next().ok_or("expected file path")?;
+
+    let mut reader = MatroskaReader::new(File::open(filename)?)?;
+    let metadata = reader.metadata_only()?;
+
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &metadata)?;
+    writeln!(stdout)?;
+
+    Ok(())
+}
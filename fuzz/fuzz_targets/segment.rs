@@ -0,0 +1,22 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use matroska::ebml::{vid, vint};
+use matroska::elements::segment_element;
+use matroska::reader::MatroskaReader;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = vint(data);
+    let _ = vid(data);
+    let _ = MatroskaReader::probe(data);
+
+    if let Ok((_, element)) = segment_element(data) {
+        let _ = element.into_owned();
+    }
+
+    if let Ok(mut reader) = MatroskaReader::new(Cursor::new(data)) {
+        while let Ok(Some(_)) = reader.next_element() {}
+    }
+});
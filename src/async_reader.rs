@@ -0,0 +1,229 @@
+use circular::Buffer;
+use nom::Offset;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::ebml::{
+    ebml_header, element_size, is_unknown_size, with_crc_validation, EbmlHeader, EbmlResult,
+};
+use crate::elements::{segment, segment_element, SegmentElement};
+use crate::reader::{placeholder_header, DEFAULT_BUFFER_SIZE};
+use crate::Error;
+
+/// Reads the elements of a Matroska Segment from any [AsyncRead] source.
+///
+/// This is the asynchronous counterpart of
+/// [MatroskaReader](crate::reader::MatroskaReader), available with the
+/// `tokio` feature.
+///
+/// ```no_run
+/// # async fn run() -> Result<(), matroska::Error> {
+/// use matroska::async_reader::AsyncMatroskaReader;
+/// use matroska::elements::SegmentElement;
+///
+/// let file = tokio::fs::File::open("video.mkv").await?;
+/// let mut reader = AsyncMatroskaReader::new(file).await?;
+///
+/// while let Some(element) = reader.next_element().await? {
+///     if let SegmentElement::Cluster(cluster) = element {
+///         println!("cluster at {}", cluster.timestamp);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncMatroskaReader<R> {
+    source: R,
+    buffer: Buffer,
+    header: EbmlHeader,
+    segment_size: Option<u64>,
+    // Offset in the source of the first byte of the Segment data
+    segment_start: u64,
+    // Offset in the source of the first byte of `buffer.data()`
+    position: u64,
+    // Size of the element returned by the last `next_element` call,
+    // which is released on the next call
+    pending: usize,
+    crc_validation: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncMatroskaReader<R> {
+    /// Creates a reader and parses the EBML Header and the Segment header.
+    pub async fn new(source: R) -> Result<Self, Error> {
+        let mut reader = AsyncMatroskaReader {
+            source,
+            buffer: Buffer::with_capacity(DEFAULT_BUFFER_SIZE),
+            header: placeholder_header(),
+            segment_size: None,
+            segment_start: 0,
+            position: 0,
+            pending: 0,
+            crc_validation: false,
+        };
+
+        reader.fill_element().await?.ok_or(Error::UnexpectedEof)?;
+        reader.header = reader.parse(ebml_header).await?;
+
+        let start = reader.position;
+        let (_, size) = reader.parse(segment).await?;
+        reader.segment_start = reader.position;
+        reader.segment_size = size.filter(|&size| !is_unknown_size(size, reader.position - start));
+
+        Ok(reader)
+    }
+
+    /// Enables the validation of the CRC-32 Elements, see
+    /// [MatroskaReader::with_crc_validation](crate::reader::MatroskaReader::with_crc_validation).
+    pub fn with_crc_validation(mut self, enabled: bool) -> Self {
+        self.crc_validation = enabled;
+        self
+    }
+
+    /// The EBML Header of the file.
+    pub fn header(&self) -> &EbmlHeader {
+        &self.header
+    }
+
+    /// The size of the Segment data, or `None` if it is unknown
+    /// (e.g. for live streams).
+    pub fn segment_size(&self) -> Option<u64> {
+        self.segment_size
+    }
+
+    /// Reads the next Top-Level Element of the Segment.
+    ///
+    /// Returns `Ok(None)` at the end of the Segment. The returned element
+    /// borrows the internal buffer, use [SegmentElement::into_owned] to
+    /// keep it around.
+    pub async fn next_element(&mut self) -> Result<Option<SegmentElement<'_>>, Error> {
+        self.consume(self.pending);
+        self.pending = 0;
+
+        if let Some(size) = self.segment_size {
+            if self.position >= self.segment_start + size {
+                return Ok(None);
+            }
+        }
+
+        let size = match self.fill_element().await? {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+
+        let data = &self.buffer.data()[..size];
+        match with_crc_validation(self.crc_validation, || segment_element(data)) {
+            Ok((_, element)) => {
+                self.pending = size;
+                Ok(Some(element))
+            }
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(e.into()),
+            Err(nom::Err::Incomplete(_)) => Err(Error::UnexpectedEof),
+        }
+    }
+
+    // Makes sure the whole element at the start of the buffer is available
+    // and returns its size, or `None` if the source ended between elements.
+    async fn fill_element(&mut self) -> Result<Option<usize>, Error> {
+        let size = loop {
+            match element_size(self.buffer.data()) {
+                Ok((_, size)) => break size,
+                Err(nom::Err::Incomplete(_)) => {
+                    if self.fill().await? == 0 {
+                        return if self.buffer.empty() {
+                            Ok(None)
+                        } else {
+                            Err(Error::UnexpectedEof)
+                        };
+                    }
+                }
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => return Err(e.into()),
+            }
+        };
+
+        if size > self.buffer.capacity() {
+            return Err(Error::BufferTooSmall(size));
+        }
+
+        while self.buffer.available_data() < size {
+            if self.fill().await? == 0 {
+                return Err(Error::UnexpectedEof);
+            }
+        }
+
+        Ok(Some(size))
+    }
+
+    // Runs a parser returning owned data on the buffer, refilling it as needed.
+    async fn parse<O>(&mut self, parser: impl Fn(&[u8]) -> EbmlResult<'_, O>) -> Result<O, Error> {
+        loop {
+            match with_crc_validation(self.crc_validation, || parser(self.buffer.data())) {
+                Ok((i, o)) => {
+                    let consumed = self.buffer.data().offset(i);
+                    self.consume(consumed);
+                    return Ok(o);
+                }
+                Err(nom::Err::Incomplete(_)) => {
+                    if self.fill().await? == 0 {
+                        return Err(Error::UnexpectedEof);
+                    }
+                }
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn fill(&mut self) -> Result<usize, Error> {
+        if self.buffer.available_space() == 0 {
+            self.buffer.shift();
+        }
+
+        let sz = self.source.read(self.buffer.space()).await?;
+        self.buffer.fill(sz);
+
+        Ok(sz)
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.position += self.buffer.consume(count) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::MatroskaReader;
+
+    #[tokio::test]
+    async fn same_elements_as_sync_reader() {
+        let path = "assets/matroska_test_w1_1/test5.mkv";
+
+        let file = tokio::fs::File::open(path).await.unwrap();
+        let mut reader = AsyncMatroskaReader::new(file).await.unwrap();
+        let sync_reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+
+        assert_eq!(reader.header(), sync_reader.header());
+        assert_eq!(reader.segment_size(), sync_reader.segment_size());
+
+        for expected in sync_reader {
+            let element = reader.next_element().await.unwrap();
+            assert_eq!(
+                element.map(SegmentElement::into_owned),
+                Some(expected.unwrap())
+            );
+        }
+        assert!(reader.next_element().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn truncated_input() {
+        let data = include_bytes!("../assets/bbb-vp9-opus.webm");
+        let mut reader = AsyncMatroskaReader::new(&data[..2000]).await.unwrap();
+
+        let res = loop {
+            match reader.next_element().await {
+                Ok(Some(_)) => continue,
+                res => break res.map(|_| ()),
+            }
+        };
+        assert!(matches!(res, Err(Error::UnexpectedEof)));
+    }
+}
@@ -0,0 +1,209 @@
+//! Navigation in the chapters of an edition during playback.
+
+use crate::elements::{ChapterAtom, Chapters, EditionEntry};
+
+/// Looks up the chapters of an edition from a playback position, for
+/// chapter lists and skip controls.
+///
+/// A chapter without a ChapterTimeEnd lasts until the start of the next
+/// chapter at its level, or until the end of its parent. The disabled
+/// chapters are ignored, the hidden ones are not since they are still
+/// played.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::chapters::MatroskaChapterPlayer;
+/// use matroska::file::MatroskaFile;
+///
+/// let file = MatroskaFile::from_seekable(File::open("video.mkv")?)?;
+/// if let Some(player) = file.chapters().and_then(MatroskaChapterPlayer::from_chapters) {
+///     let position = 90_000_000_000;
+///     if let Some(chapter) = player.current_chapter(position) {
+///         println!("playing {}", chapter.title("eng").unwrap_or_default());
+///     }
+///     println!("next chapter at {:?}", player.next_chapter_start(position));
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MatroskaChapterPlayer<'a> {
+    edition: &'a EditionEntry,
+}
+
+impl<'a> MatroskaChapterPlayer<'a> {
+    /// Creates a player for the chapters of `edition`.
+    pub fn new(edition: &'a EditionEntry) -> Self {
+        MatroskaChapterPlayer { edition }
+    }
+
+    /// Creates a player for the default edition, or the first one if none
+    /// is marked as default.
+    pub fn from_chapters(chapters: &'a Chapters) -> Option<Self> {
+        chapters
+            .editions
+            .iter()
+            .find(|edition| edition.flag_default)
+            .or(chapters.editions.first())
+            .map(Self::new)
+    }
+
+    /// The edition of the chapters.
+    pub fn edition(&self) -> &'a EditionEntry {
+        self.edition
+    }
+
+    /// The innermost chapter playing at `pos_ns`.
+    pub fn current_chapter(&self, pos_ns: u64) -> Option<&'a ChapterAtom> {
+        let mut current = None;
+        let mut chapters = &self.edition.chapters[..];
+        let mut end = u64::MAX;
+
+        // Goes down the nested chapters containing the position
+        while let Some((chapter, chapter_end)) = find_chapter(chapters, end, pos_ns) {
+            current = Some(chapter);
+            chapters = &chapter.chapters;
+            end = chapter_end;
+        }
+
+        current
+    }
+
+    /// The start of the first chapter starting after `pos_ns`, at any
+    /// level.
+    pub fn next_chapter_start(&self, pos_ns: u64) -> Option<u64> {
+        starts(&self.edition.chapters)
+            .into_iter()
+            .filter(|&start| start > pos_ns)
+            .min()
+    }
+
+    /// The start of the last chapter starting before `pos_ns`, at any
+    /// level.
+    pub fn previous_chapter_start(&self, pos_ns: u64) -> Option<u64> {
+        starts(&self.edition.chapters)
+            .into_iter()
+            .filter(|&start| start < pos_ns)
+            .max()
+    }
+}
+
+// The chapter of `chapters` containing `pos_ns`, with its end. The
+// chapters end at `parent_end` at the latest.
+fn find_chapter(
+    chapters: &[ChapterAtom],
+    parent_end: u64,
+    pos_ns: u64,
+) -> Option<(&ChapterAtom, u64)> {
+    let enabled = || chapters.iter().filter(|chapter| chapter.flag_enabled);
+
+    enabled().find_map(|chapter| {
+        let end = chapter.time_end.unwrap_or_else(|| {
+            enabled()
+                .map(|next| next.time_start)
+                .filter(|&start| start > chapter.time_start)
+                .min()
+                .unwrap_or(parent_end)
+        });
+        let end = end.min(parent_end);
+
+        (chapter.time_start <= pos_ns && pos_ns < end).then_some((chapter, end))
+    })
+}
+
+// The start of the enabled chapters and of their children.
+fn starts(chapters: &[ChapterAtom]) -> Vec<u64> {
+    chapters
+        .iter()
+        .filter(|chapter| chapter.flag_enabled)
+        .flat_map(|chapter| {
+            let mut starts = starts(&chapter.chapters);
+            starts.push(chapter.time_start);
+            starts
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(uid: u64, time_start: u64, time_end: Option<u64>) -> ChapterAtom {
+        ChapterAtom {
+            uid,
+            string_uid: None,
+            time_start,
+            time_end,
+            flag_hidden: false,
+            flag_enabled: true,
+            segment_uuid: None,
+            segment_edition_uid: None,
+            physical_equiv: None,
+            track: None,
+            displays: Vec::new(),
+            chapters: Vec::new(),
+        }
+    }
+
+    fn edition(chapters: Vec<ChapterAtom>) -> EditionEntry {
+        EditionEntry {
+            uid: None,
+            flag_hidden: false,
+            flag_default: false,
+            flag_ordered: false,
+            chapters,
+        }
+    }
+
+    #[test]
+    fn current_chapter() {
+        // Two chapters without an end, the second one split in two parts
+        // and a disabled chapter after them
+        let mut second = chapter(2, 10, None);
+        second.chapters = vec![chapter(21, 10, Some(15)), chapter(22, 15, Some(30))];
+        let mut disabled = chapter(3, 20, Some(25));
+        disabled.flag_enabled = false;
+        let edition = edition(vec![chapter(1, 0, None), second, disabled]);
+        let player = MatroskaChapterPlayer::new(&edition);
+
+        let uid = |pos| player.current_chapter(pos).map(|chapter| chapter.uid);
+        assert_eq!(uid(0), Some(1));
+        assert_eq!(uid(9), Some(1));
+        assert_eq!(uid(10), Some(21));
+        assert_eq!(uid(15), Some(22));
+        assert_eq!(uid(22), Some(22));
+        // The children end with their parent
+        assert_eq!(uid(40), Some(2));
+
+        let edition = self::edition(vec![chapter(1, 5, Some(10))]);
+        let player = MatroskaChapterPlayer::new(&edition);
+        assert_eq!(player.current_chapter(2), None);
+        assert_eq!(player.current_chapter(10), None);
+    }
+
+    #[test]
+    fn chapter_starts() {
+        let mut second = chapter(2, 10, Some(30));
+        second.chapters = vec![chapter(21, 10, Some(15)), chapter(22, 15, Some(30))];
+        let mut disabled = chapter(3, 20, Some(25));
+        disabled.flag_enabled = false;
+        let edition = edition(vec![chapter(1, 0, Some(10)), second, disabled]);
+        let player = MatroskaChapterPlayer::new(&edition);
+
+        assert_eq!(player.next_chapter_start(0), Some(10));
+        assert_eq!(player.next_chapter_start(10), Some(15));
+        assert_eq!(player.next_chapter_start(15), None);
+        assert_eq!(player.previous_chapter_start(15), Some(10));
+        assert_eq!(player.previous_chapter_start(12), Some(10));
+        assert_eq!(player.previous_chapter_start(40), Some(15));
+        assert_eq!(player.previous_chapter_start(0), None);
+
+        let mut default = self::edition(vec![chapter(4, 0, None)]);
+        default.flag_default = true;
+        let chapters = Chapters {
+            editions: vec![edition.clone(), default],
+        };
+        let player = MatroskaChapterPlayer::from_chapters(&chapters).unwrap();
+        assert_eq!(player.edition().chapters[0].uid, 4);
+    }
+}
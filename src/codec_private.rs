@@ -0,0 +1,451 @@
+//! Parsers for the CodecPrivate of the most common codecs.
+//!
+//! The layout of the data depends on the codec, see
+//! [TrackEntry::parsed_codec_private](crate::elements::TrackEntry::parsed_codec_private)
+//! to parse it according to the CodecID of a track.
+
+use nom::{
+    bytes::complete::{tag, take},
+    combinator::{map, rest, verify},
+    multi::{count, length_data},
+    number::complete::{be_u16, be_u32, be_u8, le_i16, le_u16, le_u32},
+    sequence::tuple,
+    IResult,
+};
+
+use crate::elements::{laced_frames, Lacing};
+use crate::Error;
+
+/// The CodecPrivate of a track, parsed according to its CodecID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecPrivate {
+    /// `V_MPEG4/ISO/AVC`
+    Avc(AvcConfig),
+    /// `V_MPEGH/ISO/HEVC`
+    Hevc(HevcConfig),
+    /// `V_AV1`
+    Av1(Av1Config),
+    /// `A_OPUS`
+    Opus(OpusHead),
+}
+
+/// The AVCDecoderConfigurationRecord of H.264 tracks, defined in
+/// ISO/IEC 14496-15.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcConfig {
+    pub profile: u8,
+    pub profile_compatibility: u8,
+    pub level: u8,
+    /// The size of the length prefix of the NAL units in the blocks.
+    pub nal_length_size: u8,
+    pub sps: Vec<Vec<u8>>,
+    pub pps: Vec<Vec<u8>>,
+}
+
+/// The HEVCDecoderConfigurationRecord of H.265 tracks, defined in
+/// ISO/IEC 14496-15.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcConfig {
+    pub general_profile_space: u8,
+    pub general_tier_flag: bool,
+    pub general_profile_idc: u8,
+    pub general_profile_compatibility_flags: u32,
+    /// The 48 bits of the general constraint indicator flags.
+    pub general_constraint_indicator_flags: u64,
+    pub general_level_idc: u8,
+    pub min_spatial_segmentation_idc: u16,
+    pub parallelism_type: u8,
+    pub chroma_format_idc: u8,
+    pub bit_depth_luma: u8,
+    pub bit_depth_chroma: u8,
+    pub avg_frame_rate: u16,
+    pub constant_frame_rate: u8,
+    pub num_temporal_layers: u8,
+    pub temporal_id_nested: bool,
+    /// The size of the length prefix of the NAL units in the blocks.
+    pub nal_length_size: u8,
+    pub arrays: Vec<HevcNalArray>,
+}
+
+/// The NAL units of one type in a [HevcConfig], e.g. the VPS, SPS or PPS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HevcNalArray {
+    pub array_completeness: bool,
+    pub nal_unit_type: u8,
+    pub nal_units: Vec<Vec<u8>>,
+}
+
+/// The AV1CodecConfigurationRecord of AV1 tracks, defined in the
+/// [AV1 Codec ISO Media File Format Binding](https://aomediacodec.github.io/av1-isobmff/#av1codecconfigurationbox-syntax).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Av1Config {
+    pub seq_profile: u8,
+    pub seq_level_idx_0: u8,
+    pub seq_tier_0: bool,
+    pub high_bitdepth: bool,
+    pub twelve_bit: bool,
+    pub monochrome: bool,
+    pub chroma_subsampling_x: bool,
+    pub chroma_subsampling_y: bool,
+    pub chroma_sample_position: u8,
+    pub initial_presentation_delay: Option<u8>,
+    /// The Sequence Header OBU and the Metadata OBUs following the record.
+    pub config_obus: Vec<u8>,
+}
+
+/// The identification header of Opus tracks, defined in
+/// [RFC 7845](https://www.rfc-editor.org/rfc/rfc7845#section-5.1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusHead {
+    pub version: u8,
+    pub channel_count: u8,
+    /// The number of samples at 48 kHz to discard at the start.
+    pub pre_skip: u16,
+    /// The sample rate of the original input, for information only.
+    pub input_sample_rate: u32,
+    /// The gain to apply to the output, in Q7.8 dB.
+    pub output_gain: i16,
+    pub channel_mapping_family: u8,
+    /// The channel mapping table, absent for the family 0 (mono or
+    /// stereo).
+    pub channel_mapping: Option<OpusChannelMapping>,
+}
+
+/// The channel mapping table of an [OpusHead].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusChannelMapping {
+    pub stream_count: u8,
+    pub coupled_count: u8,
+    /// The index of the decoded channel of each output channel.
+    pub mapping: Vec<u8>,
+}
+
+/// The three header packets of Vorbis tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VorbisHeaders<'a> {
+    pub identification: &'a [u8],
+    pub comment: &'a [u8],
+    pub setup: &'a [u8],
+}
+
+impl<'a> VorbisHeaders<'a> {
+    /// Splits the CodecPrivate of a Vorbis track, where the packets are
+    /// stored with Xiph lacing.
+    pub fn from_codec_private(data: &'a [u8]) -> Result<Self, Error> {
+        let packets = laced_frames(&Lacing::Xiph, data).map_err(|_| Error::InvalidCodecPrivate)?;
+
+        // Each packet starts with its type
+        match packets[..] {
+            [identification, comment, setup]
+                if identification.first() == Some(&1)
+                    && comment.first() == Some(&3)
+                    && setup.first() == Some(&5) =>
+            {
+                Ok(VorbisHeaders {
+                    identification,
+                    comment,
+                    setup,
+                })
+            }
+            _ => Err(Error::InvalidCodecPrivate),
+        }
+    }
+}
+
+fn nal_units(n: usize) -> impl Fn(&[u8]) -> IResult<&[u8], Vec<Vec<u8>>> {
+    move |input| count(map(length_data(be_u16), <[u8]>::to_vec), n)(input)
+}
+
+pub fn avc_config(input: &[u8]) -> IResult<&[u8], AvcConfig> {
+    let (i, _) = tag([1])(input)?;
+    let (i, (profile, profile_compatibility, level)) = tuple((be_u8, be_u8, be_u8))(i)?;
+    let (i, nal_length_size) = be_u8(i)?;
+    let (i, sps_count) = be_u8(i)?;
+    let (i, sps) = nal_units(usize::from(sps_count & 0x1F))(i)?;
+    let (i, pps_count) = be_u8(i)?;
+    let (i, pps) = nal_units(usize::from(pps_count))(i)?;
+
+    // The extension of the High profiles, if any, is left in the input
+    Ok((
+        i,
+        AvcConfig {
+            profile,
+            profile_compatibility,
+            level,
+            nal_length_size: (nal_length_size & 0x03) + 1,
+            sps,
+            pps,
+        },
+    ))
+}
+
+fn hevc_nal_array(input: &[u8]) -> IResult<&[u8], HevcNalArray> {
+    let (i, kind) = be_u8(input)?;
+    let (i, n) = be_u16(i)?;
+    let (i, nal_units) = nal_units(usize::from(n))(i)?;
+
+    Ok((
+        i,
+        HevcNalArray {
+            array_completeness: kind & 0x80 != 0,
+            nal_unit_type: kind & 0x3F,
+            nal_units,
+        },
+    ))
+}
+
+pub fn hevc_config(input: &[u8]) -> IResult<&[u8], HevcConfig> {
+    let (i, _) = tag([1])(input)?;
+    let (i, profile) = be_u8(i)?;
+    let (i, general_profile_compatibility_flags) = be_u32(i)?;
+    let (i, constraints) = take(6usize)(i)?;
+    let (i, general_level_idc) = be_u8(i)?;
+    let (i, min_spatial_segmentation_idc) = be_u16(i)?;
+    let (i, parallelism_type) = be_u8(i)?;
+    let (i, chroma_format_idc) = be_u8(i)?;
+    let (i, bit_depth_luma) = be_u8(i)?;
+    let (i, bit_depth_chroma) = be_u8(i)?;
+    let (i, avg_frame_rate) = be_u16(i)?;
+    let (i, flags) = be_u8(i)?;
+    let (i, num_arrays) = be_u8(i)?;
+    let (i, arrays) = count(hevc_nal_array, usize::from(num_arrays))(i)?;
+
+    Ok((
+        i,
+        HevcConfig {
+            general_profile_space: profile >> 6,
+            general_tier_flag: profile & 0x20 != 0,
+            general_profile_idc: profile & 0x1F,
+            general_profile_compatibility_flags,
+            general_constraint_indicator_flags: constraints
+                .iter()
+                .fold(0, |acc, &b| acc << 8 | u64::from(b)),
+            general_level_idc,
+            min_spatial_segmentation_idc: min_spatial_segmentation_idc & 0x0FFF,
+            parallelism_type: parallelism_type & 0x03,
+            chroma_format_idc: chroma_format_idc & 0x03,
+            bit_depth_luma: (bit_depth_luma & 0x07) + 8,
+            bit_depth_chroma: (bit_depth_chroma & 0x07) + 8,
+            avg_frame_rate,
+            constant_frame_rate: flags >> 6,
+            num_temporal_layers: (flags >> 3) & 0x07,
+            temporal_id_nested: flags & 0x04 != 0,
+            nal_length_size: (flags & 0x03) + 1,
+            arrays,
+        },
+    ))
+}
+
+pub fn av1_config(input: &[u8]) -> IResult<&[u8], Av1Config> {
+    // The marker bit followed by the version 1
+    let (i, _) = tag([0x81])(input)?;
+    let (i, profile) = be_u8(i)?;
+    let (i, flags) = be_u8(i)?;
+    let (i, delay) = verify(be_u8, |delay| delay & 0xE0 == 0)(i)?;
+    let (i, config_obus) = rest(i)?;
+
+    Ok((
+        i,
+        Av1Config {
+            seq_profile: profile >> 5,
+            seq_level_idx_0: profile & 0x1F,
+            seq_tier_0: flags & 0x80 != 0,
+            high_bitdepth: flags & 0x40 != 0,
+            twelve_bit: flags & 0x20 != 0,
+            monochrome: flags & 0x10 != 0,
+            chroma_subsampling_x: flags & 0x08 != 0,
+            chroma_subsampling_y: flags & 0x04 != 0,
+            chroma_sample_position: flags & 0x03,
+            initial_presentation_delay: (delay & 0x10 != 0).then_some((delay & 0x0F) + 1),
+            config_obus: config_obus.to_vec(),
+        },
+    ))
+}
+
+pub fn opus_head(input: &[u8]) -> IResult<&[u8], OpusHead> {
+    let (i, _) = tag("OpusHead")(input)?;
+    // Only the major version, in the upper 4 bits, is incompatible
+    let (i, version) = verify(be_u8, |version| version >> 4 == 0)(i)?;
+    let (i, channel_count) = be_u8(i)?;
+    let (i, pre_skip) = le_u16(i)?;
+    let (i, input_sample_rate) = le_u32(i)?;
+    let (i, output_gain) = le_i16(i)?;
+    let (i, channel_mapping_family) = be_u8(i)?;
+
+    let (i, channel_mapping) = if channel_mapping_family == 0 {
+        (i, None)
+    } else {
+        let (i, (stream_count, coupled_count)) = tuple((be_u8, be_u8))(i)?;
+        let (i, mapping) = take(channel_count)(i)?;
+        let mapping = OpusChannelMapping {
+            stream_count,
+            coupled_count,
+            mapping: mapping.to_vec(),
+        };
+        (i, Some(mapping))
+    };
+
+    Ok((
+        i,
+        OpusHead {
+            version,
+            channel_count,
+            pre_skip,
+            input_sample_rate,
+            output_gain,
+            channel_mapping_family,
+            channel_mapping,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn avc() {
+        // From assets/single_stream.mkv, High profile level 1.3
+        let data = hex(concat!(
+            "0164000dffe10019",
+            "6764000dacd94141fb011000000300100000030320f1429960",
+            "010006",
+            "68ebe3cb22c0"
+        ));
+        let (_, config) = avc_config(&data).unwrap();
+        assert_eq!((config.profile, config.level), (100, 13));
+        assert_eq!(config.nal_length_size, 4);
+        assert_eq!(config.sps.len(), 1);
+        assert_eq!(config.sps[0].len(), 25);
+        assert_eq!(config.sps[0][0] & 0x1F, 7);
+        assert_eq!(config.pps, [hex("68ebe3cb22c0")]);
+
+        assert!(avc_config(&data[..20]).is_err());
+        assert!(avc_config(&[0; 8]).is_err());
+    }
+
+    #[test]
+    fn hevc() {
+        // Main profile level 3.1, with one VPS, SPS and PPS
+        let data = hex(concat!(
+            "0101600000009000000000005df000fcfdf8f800000f03",
+            "a000010018",
+            "40010c01ffff01600000030090000003000003005d959809",
+            "a10001000a",
+            "42010101600000030090",
+            "a200010007",
+            "4401c172b46240"
+        ));
+        let (i, config) = hevc_config(&data).unwrap();
+        assert!(i.is_empty());
+
+        assert_eq!(config.general_profile_idc, 1);
+        assert!(!config.general_tier_flag);
+        assert_eq!(config.general_profile_compatibility_flags, 0x6000_0000);
+        assert_eq!(config.general_constraint_indicator_flags, 0x9000_0000_0000);
+        assert_eq!(config.general_level_idc, 93);
+        assert_eq!(config.chroma_format_idc, 1);
+        assert_eq!((config.bit_depth_luma, config.bit_depth_chroma), (8, 8));
+        assert_eq!(config.num_temporal_layers, 1);
+        assert!(config.temporal_id_nested);
+        assert_eq!(config.nal_length_size, 4);
+
+        let types: Vec<_> = config.arrays.iter().map(|a| a.nal_unit_type).collect();
+        assert_eq!(types, [32, 33, 34]);
+        assert!(config.arrays.iter().all(|a| a.array_completeness));
+        assert_eq!(config.arrays[2].nal_units, [hex("4401c172b46240")]);
+    }
+
+    #[test]
+    fn av1() {
+        // Main profile level 4.0 4:2:0, followed by its Sequence Header OBU
+        let data = hex("81080c000a0b0000002cc4ffdf006802");
+        let (_, config) = av1_config(&data).unwrap();
+
+        assert_eq!(config.seq_profile, 0);
+        assert_eq!(config.seq_level_idx_0, 8);
+        assert!(!config.seq_tier_0 && !config.high_bitdepth && !config.monochrome);
+        assert!(config.chroma_subsampling_x && config.chroma_subsampling_y);
+        assert_eq!(config.initial_presentation_delay, None);
+        assert_eq!(config.config_obus, &data[4..]);
+
+        let mut delayed = data.clone();
+        delayed[3] = 0x13;
+        assert_eq!(
+            av1_config(&delayed).unwrap().1.initial_presentation_delay,
+            Some(4)
+        );
+
+        // Version 2 is unknown
+        assert!(av1_config(&[0x82, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn opus() {
+        // From assets/bbb-vp9-opus.webm
+        let data = hex("4f707573486561640101380180bb0000000000");
+        let (_, head) = opus_head(&data).unwrap();
+        assert_eq!(
+            head,
+            OpusHead {
+                version: 1,
+                channel_count: 1,
+                pre_skip: 312,
+                input_sample_rate: 48000,
+                output_gain: 0,
+                channel_mapping_family: 0,
+                channel_mapping: None,
+            }
+        );
+
+        // 5.1 with the Vorbis channel order
+        let data = hex("4f707573486561640106380180bb000000ff010402000401020305");
+        let (_, head) = opus_head(&data).unwrap();
+        assert_eq!(head.output_gain, -256);
+        assert_eq!(
+            head.channel_mapping,
+            Some(OpusChannelMapping {
+                stream_count: 4,
+                coupled_count: 2,
+                mapping: vec![0, 4, 1, 2, 3, 5],
+            })
+        );
+
+        assert!(opus_head(&data[..20]).is_err());
+        assert!(opus_head(b"OpusTags").is_err());
+    }
+
+    #[test]
+    fn vorbis() {
+        let file = std::fs::File::open("assets/big-buck-bunny_trailer.webm").unwrap();
+        let mut reader = crate::reader::MatroskaReader::new(file).unwrap();
+        while reader.tracks().is_none() {
+            reader.next_element().unwrap();
+        }
+        let track = &reader.tracks().unwrap().tracks[1];
+        assert_eq!(track.codec_id, "A_VORBIS");
+        let data = track.codec_private.as_deref().unwrap();
+
+        let headers = VorbisHeaders::from_codec_private(data).unwrap();
+        assert_eq!(headers.identification.len(), 30);
+        assert_eq!(&headers.identification[..7], b"\x01vorbis");
+        assert_eq!(headers.comment.len(), 58);
+        assert_eq!(&headers.comment[..7], b"\x03vorbis");
+        assert_eq!(&headers.setup[..7], b"\x05vorbis");
+        assert_eq!(headers.setup.len(), data.len() - 3 - 30 - 58);
+
+        assert!(matches!(
+            VorbisHeaders::from_codec_private(&data[..40]),
+            Err(Error::InvalidCodecPrivate)
+        ));
+        // Only two packets
+        assert!(VorbisHeaders::from_codec_private(&[1, 1, 1, 3]).is_err());
+    }
+}
@@ -17,8 +17,8 @@ use av_format::{
 use crate::{
     ebml::{self, ebml_err, ebml_header, EbmlHeader, ErrorKind},
     elements::{
-        segment, segment_element, simple_block, Audio, Cluster, Info, SeekHead, SegmentElement,
-        TrackEntry, TrackType, Tracks, Video,
+        segment, segment_element, Audio, Cluster, Info, SeekHead, SegmentElement, TrackEntry,
+        TrackType, Tracks, Video,
     },
 };
 
@@ -254,14 +254,32 @@ impl<'a> Cluster<'a> {
     pub fn generate_packets(&self, tracks: &Tracks) -> Vec<Event> {
         let mut v = Vec::new();
 
-        for block_data in self.simple_block.iter() {
-            if let Ok((i, block)) = simple_block(block_data) {
-                debug!("parsing simple block: {:?}", block);
-                if let Some(index) = tracks.lookup(block.track_number) {
+        for block in self.simple_block.iter() {
+            debug!(
+                "parsing simple block: track {} timestamp {}",
+                block.track_number, block.timestamp
+            );
+            if let Some(index) = tracks.lookup(block.track_number) {
+                let frames = match block.frames() {
+                    Ok(frames) => frames,
+                    Err(e) => {
+                        error!("error parsing simple block: {e:?}");
+                        continue;
+                    }
+                };
+
+                for (n, frame) in frames.into_iter().enumerate() {
+                    // Only the first frame of a lace has a known timestamp
+                    let pts = if n == 0 {
+                        Some(i64::from(block.timestamp))
+                    } else {
+                        None
+                    };
+
                     let packet = Packet {
-                        data: i.into(),
+                        data: frame.into(),
                         t: TimeInfo {
-                            pts: Some(i64::from(block.timestamp)),
+                            pts,
                             dts: None,
                             duration: None,
                             timebase: None,
@@ -275,8 +293,6 @@ impl<'a> Cluster<'a> {
 
                     v.push(Event::NewPacket(packet));
                 }
-            } else {
-                error!("error parsing simple block");
             }
         }
 
@@ -319,8 +335,8 @@ mod tests {
 
     use nom::Offset;
 
+    use crate::elements::Uuid;
     use av_format::{buffer::*, demuxer::Context};
-    use uuid::Uuid;
 
     use crate::elements::Seek;
 
@@ -373,7 +389,7 @@ mod tests {
         assert_eq!(
             demuxer.info.unwrap(),
             Info {
-                segment_uid: Some(Uuid::try_parse("ed157223-369d-f02d-cf50-76a5fea70034").unwrap()),
+                segment_uid: Some(Uuid::from_u128(0xed157223369df02dcf5076a5fea70034)),
                 segment_filename: None,
                 prev_uid: None,
                 prev_filename: None,
@@ -400,19 +416,25 @@ mod tests {
                         track_type: 1,
                         flag_enabled: 1,
                         flag_default: 1,
-                        flag_forced: 0,
+                        flag_forced: false,
+                        flag_hearing_impaired: false,
+                        flag_visual_impaired: false,
+                        flag_text_descriptions: false,
+                        flag_original: false,
+                        flag_commentary: false,
                         flag_lacing: 0,
                         default_duration: None,
                         default_decoded_field_duration: None,
                         track_timestamp_scale: 1.0,
                         max_block_addition_id: 0,
+                        block_addition_mappings: vec![],
                         name: None,
                         language: String::from("eng"),
                         language_ietf: None,
                         codec_id: String::from("V_VP9"),
                         codec_private: None,
                         codec_name: None,
-                        attachment_link: None,
+                        attachment_links: vec![],
                         codec_delay: 0,
                         seek_pre_roll: 0,
                         video: Some(Video {
@@ -446,12 +468,18 @@ mod tests {
                         track_type: 2,
                         flag_enabled: 1,
                         flag_default: 1,
-                        flag_forced: 0,
+                        flag_forced: false,
+                        flag_hearing_impaired: false,
+                        flag_visual_impaired: false,
+                        flag_text_descriptions: false,
+                        flag_original: false,
+                        flag_commentary: false,
                         flag_lacing: 0,
                         default_duration: None,
                         default_decoded_field_duration: None,
                         track_timestamp_scale: 1.0,
                         max_block_addition_id: 0,
+                        block_addition_mappings: vec![],
                         name: None,
                         language: String::from("eng"),
                         language_ietf: None,
@@ -461,7 +489,7 @@ mod tests {
                             0x80, 0xBB, 0x00, 0x00, 0x00, 0x00, 0x00,
                         ]),
                         codec_name: None,
-                        attachment_link: None,
+                        attachment_links: vec![],
                         codec_delay: 6500000,
                         seek_pre_roll: 0,
                         video: None,
@@ -470,6 +498,7 @@ mod tests {
                             output_sampling_frequency: None,
                             channels: 1,
                             bit_depth: Some(32),
+                            channel_positions: None,
                         }),
                         track_translate: vec![],
                         track_operation: None,
@@ -0,0 +1,450 @@
+//! The [Display](fmt::Display) of the elements, in the indented style of
+//! mkvinfo.
+//!
+//! Each element is shown at the depth it has in a Segment, e.g. the fields
+//! of a [TrackEntry] under `| + Track`. The lines are not terminated by a
+//! newline.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::ebml::{civil_from_days, EbmlDate, EbmlHeader, EPOCH_DAYS};
+use crate::elements::{
+    Attachments, Audio, ChapterAtom, Chapters, Cluster, Cues, EditionEntry, Info, SeekHead,
+    SegmentElement, Tags, TrackEntry, Tracks, Video,
+};
+
+// Writes a line at `depth`, 0 being the level of the Top-Level Elements.
+fn line(f: &mut fmt::Formatter<'_>, depth: usize, args: fmt::Arguments<'_>) -> fmt::Result {
+    write!(f, "|{:depth$}+ {args}", "")
+}
+
+// Writes the line on its own line after the previous ones.
+fn next_line(f: &mut fmt::Formatter<'_>, depth: usize, args: fmt::Arguments<'_>) -> fmt::Result {
+    writeln!(f)?;
+    line(f, depth, args)
+}
+
+fn format_duration(duration: Duration) -> String {
+    let hrs = duration.as_secs() / 3600;
+    let mins = (duration.as_secs() % 3600) / 60;
+    let secs = duration.as_secs_f64() % 60.0;
+    format!("{hrs:02}:{mins:02}:{secs:012.9}")
+}
+
+impl fmt::Display for EbmlDate {
+    /// Formats the date as RFC 3339, in UTC.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.div_euclid(1_000_000_000);
+        let nanos = self.0.rem_euclid(1_000_000_000);
+        let time = secs.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(secs.div_euclid(86_400) + EPOCH_DAYS);
+
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        )?;
+        if nanos > 0 {
+            let fraction = format!("{nanos:09}");
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write!(f, "Z")
+    }
+}
+
+impl fmt::Display for EbmlHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+ EBML head")?;
+        next_line(f, 0, format_args!("EBML version: {}", self.version))?;
+        next_line(
+            f,
+            0,
+            format_args!("EBML read version: {}", self.read_version),
+        )?;
+        next_line(
+            f,
+            0,
+            format_args!("Maximum EBML ID length: {}", self.max_id_length),
+        )?;
+        next_line(
+            f,
+            0,
+            format_args!("Maximum EBML size length: {}", self.max_size_length),
+        )?;
+        next_line(f, 0, format_args!("Document type: {}", self.doc_type))?;
+        next_line(
+            f,
+            0,
+            format_args!("Document type version: {}", self.doc_type_version),
+        )?;
+        next_line(
+            f,
+            0,
+            format_args!("Document type read version: {}", self.doc_type_read_version),
+        )
+    }
+}
+
+impl fmt::Display for SegmentElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SegmentElement::SeekHead(seek_head) => seek_head.fmt(f),
+            SegmentElement::Info(info) => info.fmt(f),
+            SegmentElement::Tracks(tracks) => tracks.fmt(f),
+            SegmentElement::Chapters(chapters) => chapters.fmt(f),
+            SegmentElement::Cluster(cluster) => cluster.fmt(f),
+            SegmentElement::Cues(cues) => cues.fmt(f),
+            SegmentElement::Attachments(attachments) => attachments.fmt(f),
+            SegmentElement::Tags(tags) => tags.fmt(f),
+            SegmentElement::Void(size) => line(f, 0, format_args!("EbmlVoid (size: {size})")),
+            SegmentElement::Unknown(id, Some(size)) => {
+                line(f, 0, format_args!("Unknown element {id:#X} (size: {size})"))
+            }
+            SegmentElement::Unknown(id, None) => {
+                line(f, 0, format_args!("Unknown element {id:#X}"))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SeekHead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Seek head"))?;
+        for seek in &self.positions {
+            let name = seek.element_id().name();
+            let [a, b, c, d] = seek.id;
+
+            next_line(f, 1, format_args!("Seek entry"))?;
+            next_line(
+                f,
+                2,
+                format_args!("Seek ID: {a:#x} {b:#x} {c:#x} {d:#x} ({name})"),
+            )?;
+            next_line(f, 2, format_args!("Seek position: {}", seek.position))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Segment information"))?;
+        if let Some(uid) = &self.segment_uid {
+            next_line(f, 1, format_args!("Segment UID: {uid}"))?;
+        }
+        next_line(
+            f,
+            1,
+            format_args!("Timestamp scale: {}", self.timestamp_scale),
+        )?;
+        if let Some(duration) = self.duration {
+            let nanos = (duration * self.timestamp_scale as f64).round() as u64;
+            next_line(
+                f,
+                1,
+                format_args!("Duration: {}", format_duration(Duration::from_nanos(nanos))),
+            )?;
+        }
+        if let Some(title) = &self.title {
+            next_line(f, 1, format_args!("Title: {title}"))?;
+        }
+        next_line(
+            f,
+            1,
+            format_args!("Multiplexing application: {}", self.muxing_app),
+        )?;
+        next_line(
+            f,
+            1,
+            format_args!("Writing application: {}", self.writing_app),
+        )?;
+        if let Some(date) = &self.date_utc {
+            next_line(f, 1, format_args!("Date: {date}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tracks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Tracks"))?;
+        for track in &self.tracks {
+            write!(f, "\n{track}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for TrackEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 1, format_args!("Track"))?;
+        next_line(f, 2, format_args!("Track number: {}", self.track_number))?;
+        next_line(f, 2, format_args!("Track UID: {}", self.track_uid))?;
+        next_line(f, 2, format_args!("Track type: {}", self.track_type))?;
+        next_line(f, 2, format_args!("Lacing flag: {}", self.flag_lacing))?;
+        next_line(f, 2, format_args!("Default flag: {}", self.flag_default))?;
+        if let Some(name) = &self.name {
+            next_line(f, 2, format_args!("Name: {name}"))?;
+        }
+        next_line(f, 2, format_args!("Language: {}", self.language))?;
+        next_line(f, 2, format_args!("Codec ID: {}", self.codec_id))?;
+        next_line(
+            f,
+            2,
+            format_args!(
+                "Codec private: length {}",
+                self.codec_private.as_ref().map_or(0, |data| data.len())
+            ),
+        )?;
+        if let Some(duration) = self.default_duration {
+            next_line(f, 2, format_args!("Default duration: {duration}"))?;
+        }
+        if let Some(video) = &self.video {
+            write!(f, "\n{video}")?;
+        }
+        if let Some(audio) = &self.audio {
+            write!(f, "\n{audio}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Video {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 2, format_args!("Video track"))?;
+        next_line(f, 3, format_args!("Pixel width: {}", self.pixel_width))?;
+        next_line(f, 3, format_args!("Pixel height: {}", self.pixel_height))?;
+        next_line(f, 3, format_args!("Interlaced: {}", self.flag_interlaced))?;
+        if let Some(width) = self.display_width {
+            next_line(f, 3, format_args!("Display width: {width}"))?;
+        }
+        if let Some(height) = self.display_height {
+            next_line(f, 3, format_args!("Display height: {height}"))?;
+        }
+        next_line(f, 3, format_args!("Display unit: {}", self.display_unit))
+    }
+}
+
+impl fmt::Display for Audio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 2, format_args!("Audio track"))?;
+        next_line(
+            f,
+            3,
+            format_args!("Sampling frequency: {}", self.sampling_frequency),
+        )?;
+        if let Some(frequency) = self.output_sampling_frequency {
+            next_line(f, 3, format_args!("Output sampling frequency: {frequency}"))?;
+        }
+        next_line(f, 3, format_args!("Channels: {}", self.channels))?;
+        if let Some(bit_depth) = self.bit_depth {
+            next_line(f, 3, format_args!("Bit depth: {bit_depth}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Cluster<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Cluster"))?;
+        next_line(f, 1, format_args!("Cluster timestamp: {}", self.timestamp))?;
+        if let Some(position) = self.position {
+            next_line(f, 1, format_args!("Cluster position: {position}"))?;
+        }
+        if let Some(size) = self.prev_size {
+            next_line(f, 1, format_args!("Cluster previous size: {size}"))?;
+        }
+        next_line(
+            f,
+            1,
+            format_args!("Simple blocks: {}", self.simple_block.len()),
+        )?;
+        next_line(
+            f,
+            1,
+            format_args!("Block groups: {}", self.block_group.len()),
+        )
+    }
+}
+
+impl fmt::Display for Cues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Cues"))?;
+        next_line(f, 1, format_args!("Cue points: {}", self.cue_points.len()))
+    }
+}
+
+impl fmt::Display for Chapters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Chapters"))?;
+        for edition in &self.editions {
+            write!(f, "\n{edition}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for EditionEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 1, format_args!("Edition entry"))?;
+        if let Some(uid) = self.uid {
+            next_line(f, 2, format_args!("Edition UID: {uid}"))?;
+        }
+        for chapter in &self.chapters {
+            writeln!(f)?;
+            fmt_chapter(f, chapter, 2)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ChapterAtom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_chapter(f, self, 2)
+    }
+}
+
+fn fmt_chapter(f: &mut fmt::Formatter<'_>, chapter: &ChapterAtom, depth: usize) -> fmt::Result {
+    line(f, depth, format_args!("Chapter atom"))?;
+    next_line(f, depth + 1, format_args!("Chapter UID: {}", chapter.uid))?;
+    next_line(
+        f,
+        depth + 1,
+        format_args!(
+            "Chapter time start: {}",
+            format_duration(Duration::from_nanos(chapter.time_start))
+        ),
+    )?;
+    if let Some(end) = chapter.time_end {
+        next_line(
+            f,
+            depth + 1,
+            format_args!(
+                "Chapter time end: {}",
+                format_duration(Duration::from_nanos(end))
+            ),
+        )?;
+    }
+    for display in &chapter.displays {
+        next_line(f, depth + 1, format_args!("Chapter display"))?;
+        next_line(
+            f,
+            depth + 2,
+            format_args!("Chapter string: {}", display.string),
+        )?;
+        for language in &display.language {
+            next_line(f, depth + 2, format_args!("Chapter language: {language}"))?;
+        }
+    }
+    for chapter in &chapter.chapters {
+        writeln!(f)?;
+        fmt_chapter(f, chapter, depth + 1)?;
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Attachments {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Attachments"))?;
+        for file in &self.files {
+            next_line(f, 1, format_args!("Attached"))?;
+            next_line(f, 2, format_args!("File name: {}", file.name))?;
+            next_line(f, 2, format_args!("MIME type: {}", file.media_type))?;
+            next_line(f, 2, format_args!("File data: size {}", file.data.len()))?;
+            next_line(f, 2, format_args!("File UID: {}", file.uid))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        line(f, 0, format_args!("Tags"))?;
+        for tag in &self.tags {
+            next_line(f, 1, format_args!("Tag"))?;
+            for simple_tag in &tag.simple_tags {
+                next_line(f, 2, format_args!("Simple"))?;
+                next_line(f, 3, format_args!("Name: {}", simple_tag.name))?;
+                match &simple_tag.string {
+                    Some(string) => next_line(f, 3, format_args!("String: {string}"))?,
+                    None => next_line(
+                        f,
+                        3,
+                        format_args!(
+                            "Binary: size {}",
+                            simple_tag.binary.as_ref().map_or(0, |data| data.len())
+                        ),
+                    )?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::reader::MatroskaReader;
+
+    #[test]
+    fn mkvinfo_style() {
+        let data = std::fs::read("assets/bbb-vp9-opus.webm").unwrap();
+        let mut reader = MatroskaReader::new(Cursor::new(data)).unwrap();
+
+        let header = reader.header().to_string();
+        assert!(header.starts_with("+ EBML head\n|+ EBML version: 1\n"));
+        assert!(header.ends_with("|+ Document type read version: 2"));
+
+        let mut lines = Vec::new();
+        while let Some(element) = reader.next_element().unwrap() {
+            lines.extend(element.to_string().lines().map(String::from));
+        }
+        for expected in [
+            "|+ Seek head",
+            "| + Seek entry",
+            "|  + Seek ID: 0x15 0x49 0xa9 0x66 (Info)",
+            "|+ Segment information",
+            "| + Timestamp scale: 1000000",
+            "|+ Tracks",
+            "| + Track",
+            "|  + Codec ID: V_VP9",
+            "|  + Video track",
+            "|   + Pixel width: 640",
+            "|  + Audio track",
+            "|   + Channels: 1",
+            "|+ Cluster",
+            "| + Cluster timestamp: 0",
+            "| + Simple blocks: 75",
+            "|+ Cues",
+        ] {
+            assert!(lines.iter().any(|l| l == expected), "{expected}");
+        }
+    }
+
+    #[test]
+    fn date() {
+        assert_eq!(EbmlDate(0).to_string(), "2001-01-01T00:00:00Z");
+        assert_eq!(EbmlDate(-1_000_000_000).to_string(), "2000-12-31T23:59:59Z");
+        // 2010-08-21T07:23:03.5Z
+        assert_eq!(
+            EbmlDate(304_068_183_500_000_000).to_string(),
+            "2010-08-21T07:23:03.5Z"
+        );
+    }
+}
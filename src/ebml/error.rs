@@ -71,7 +71,17 @@ pub enum ErrorKind {
     BinaryWidthIncorrect(u16),
 
     /// A CRC-32 element was found, but the checksum did not match.
-    Crc32Mismatch,
+    /// `expected` is the value stored in the file, `actual` the checksum
+    /// of the data.
+    Crc32Mismatch { expected: u32, actual: u32 },
+
+    /// An element starts with a CRC-32 element, but its declared length is
+    /// smaller than the 6 octets of the CRC-32 element.
+    CrcLongerThanElement,
+
+    /// The lacing header of a block is malformed, or the frame sizes it
+    /// declares do not match the size of the block.
+    InvalidLacing,
 }
 
 /// Create an error with the given ID and [ErrorKind].
@@ -79,10 +89,10 @@ pub fn ebml_err<'a, T>(id: u32, kind: ErrorKind) -> nom::IResult<&'a [u8], T, Er
     Err(nom::Err::Error(Error { id, kind }))
 }
 
-impl std::fmt::Debug for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Error")
-            .field("id", &format!("{:#0X}", self.id))
+            .field("id", &alloc::format!("{:#0X}", self.id))
             .field("kind", &self.kind)
             .finish()
     }
@@ -1,4 +1,10 @@
 macro_rules! unwrap_value {
+    (0, $field_name:ident, Vec<$field_type:ty>, $field_id:literal) => {
+        let $field_name = $field_name.unwrap_or_default();
+    };
+    (1, $field_name:ident, Vec<$field_type:ty>, $field_id:literal) => {
+        let $field_name = $crate::ebml::get_required($field_name, $field_id)?;
+    };
     ($field_name:ident, Option<$field_type:ty>, $field_id:literal) => {};
     ($field_name:ident, $field_type:ty, $field_id:literal, $default:expr) => {
         let $field_name = $field_name.unwrap_or($default);
@@ -12,8 +18,10 @@ macro_rules! unwrap_parser {
     ($field_id:literal, Option<$field_type:ty>) => {
         $crate::ebml::macros::unwrap_parser!($field_id, $field_type)
     };
+    // Parsed as one or more Elements, so that the permutation tries again
+    // when they are not first
     ($field_id:literal, 0, Vec<$field_type:ty>) => {
-        nom::multi::many0($crate::ebml::macros::unwrap_parser!($field_id, $field_type))
+        nom::multi::many1($crate::ebml::macros::unwrap_parser!($field_id, $field_type))
     };
     ($field_id:literal, 1, Vec<$field_type:ty>) => {
         nom::multi::many1($crate::ebml::macros::unwrap_parser!($field_id, $field_type))
@@ -27,12 +35,12 @@ macro_rules! impl_ebml_master {
     (
         $(#[$outer:meta])*
         struct $name:ident$(<$lifetime:lifetime>)? {
-            $([$field_id:literal] $field_name:ident: ($($field_type:tt)+) $([$lower_bound:tt..])? $(= $default:expr)?,)+
+            $($(#[$field_meta:meta])* [$field_id:literal] $field_name:ident: ($($field_type:tt)+) $([$lower_bound:tt..])? $(= $default:expr)?,)+
         }
     ) => {
         $(#[$outer])*
         pub struct $name$(<$lifetime>)? {
-            $(pub $field_name: $($field_type)+,)+
+            $($(#[$field_meta])* pub $field_name: $($field_type)+,)+
         }
 
         impl<'p$(:$lifetime, $lifetime)?> $crate::ebml::EbmlParsable<'p> for $name$(<$lifetime>)? {
@@ -55,7 +63,7 @@ macro_rules! impl_ebml_master {
                     log::warn!("{} unused bytes left after parsing {}", i.len(), stringify!($name));
                 }
 
-                $($crate::ebml::macros::unwrap_value!($field_name, $($field_type)+, $field_id $(, $default)?);)*
+                $($crate::ebml::macros::unwrap_value!($($lower_bound,)? $field_name, $($field_type)+, $field_id $(, $default)?);)*
 
                 Ok(
                     Self {
@@ -1,3 +1,5 @@
+use alloc::string::String;
+
 mod error;
 pub(crate) mod macros;
 mod parse;
@@ -7,10 +9,12 @@ mod tests;
 
 pub use self::error::{ebml_err, Error, ErrorKind};
 pub use self::parse::*;
+pub(crate) use self::parse::{element_size, is_unknown_size};
 
 self::macros::impl_ebml_master! {
     // Element ID 0x1A45DFA3
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct EbmlHeader {
         [0x4286] version: (u32) = 1,
         [0x42F7] read_version: (u32) = 1,
@@ -22,8 +26,10 @@ self::macros::impl_ebml_master! {
     }
 }
 
-pub fn ebml_header(input: &[u8]) -> EbmlResult<EbmlHeader> {
-    ebml_element(0x1A45DFA3)(input)
+/// Parses the EBML Header at the start of a stream, returning `Incomplete`
+/// with the number of bytes missing if the input ends within it.
+pub fn ebml_header(input: &[u8]) -> EbmlResult<'_, EbmlHeader> {
+    ebml_element_streaming(0x1A45DFA3)(input)
 }
 
 /// Map of known deprecated Element IDs and their EBML Paths
@@ -1,4 +1,10 @@
-use std::ops::{BitOr, Shl};
+use alloc::{string::String, vec::Vec};
+use core::ops::{BitOr, Shl};
+#[cfg(feature = "std")]
+use std::{
+    cell::Cell,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use crc::{Algorithm, Crc};
 use log::trace;
@@ -9,7 +15,6 @@ use nom::{
     Err::Incomplete,
     Needed, Parser,
 };
-use uuid::Uuid;
 
 use super::error::{ebml_err, Error, ErrorKind};
 
@@ -25,18 +30,30 @@ pub trait EbmlParsable<'a>: Sized {
 }
 
 // Parsable implementation for the integer types
-trait Int: From<u8> + Shl<Self, Output = Self> + BitOr<Self, Output = Self> {}
+trait Int: From<u8> + Shl<Self, Output = Self> + BitOr<Self, Output = Self> {
+    // The value the bytes are shifted into, all ones for negative numbers
+    fn initial(_data: &[u8]) -> Self {
+        Self::from(0)
+    }
+}
 impl Int for u64 {}
 impl Int for u32 {}
-impl Int for i64 {}
+impl Int for i64 {
+    fn initial(data: &[u8]) -> Self {
+        match data.first() {
+            Some(b) if b & 0x80 != 0 => -1,
+            _ => 0,
+        }
+    }
+}
 
 impl<'a, T: Int> EbmlParsable<'a> for T {
     fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
-        if data.len() > std::mem::size_of::<T>() {
+        if data.len() > core::mem::size_of::<T>() {
             return Err(ErrorKind::IntTooWide);
         }
 
-        let mut val = Self::from(0);
+        let mut val = Self::initial(data);
         for b in data {
             val = (val << Self::from(8)) | Self::from(*b);
         }
@@ -45,6 +62,13 @@ impl<'a, T: Int> EbmlParsable<'a> for T {
     }
 }
 
+// Flags are unsigned integers, any value other than 0 being true
+impl<'a> EbmlParsable<'a> for bool {
+    fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
+        u64::try_parse(data).map(|value| value != 0)
+    }
+}
+
 // FIXME: Define and double-check float parsing behaviour in error cases
 // FIXME: Also implement a test suite for that
 impl<'a> EbmlParsable<'a> for f64 {
@@ -58,25 +82,85 @@ impl<'a> EbmlParsable<'a> for f64 {
     }
 }
 
-/// Date Element. Contains the number of nanoseconds since
-/// 2001-01-01T00:00:00.000000000 UTC.
+/// Date Element. Contains the number of nanoseconds since the Matroska
+/// epoch, 2001-01-01T00:00:00.000000000 UTC.
 ///
-/// This struct can't really do anything by itself. If you want
-/// date/time handling, you should use a crate like [time].
-///
-/// [time]: https://crates.io/crates/time
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Date(pub i64);
+/// With the `time` feature, `Info::date_utc_as_offset_date_time` converts
+/// the DateUTC to a `time::OffsetDateTime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EbmlDate(pub i64);
+
+impl EbmlDate {
+    /// Nanoseconds from the Unix epoch to the Matroska epoch.
+    pub const UNIX_EPOCH_OFFSET_NS: i64 = 978_307_200_000_000_000;
+
+    /// The date `ns` nanoseconds after the Unix epoch, saturating at the
+    /// bounds of the type.
+    pub fn from_unix_ns(ns: i64) -> EbmlDate {
+        EbmlDate(ns.saturating_sub(Self::UNIX_EPOCH_OFFSET_NS))
+    }
+
+    /// The nanoseconds since the Unix epoch, saturating at the bounds of
+    /// [i64].
+    pub fn to_unix_timestamp_ns(self) -> i64 {
+        self.0.saturating_add(Self::UNIX_EPOCH_OFFSET_NS)
+    }
+
+    /// The date as a [SystemTime].
+    #[cfg(feature = "std")]
+    pub fn to_system_time(self) -> SystemTime {
+        let ns = i128::from(self.0) + i128::from(Self::UNIX_EPOCH_OFFSET_NS);
+        let offset = Duration::from_nanos(ns.unsigned_abs() as u64);
+
+        if ns < 0 {
+            UNIX_EPOCH - offset
+        } else {
+            UNIX_EPOCH + offset
+        }
+    }
 
-impl<'a> EbmlParsable<'a> for Date {
+    /// The date of a [SystemTime], saturating at the bounds of the type.
+    #[cfg(feature = "std")]
+    pub fn from_system_time(t: SystemTime) -> EbmlDate {
+        let ns = match t.duration_since(UNIX_EPOCH) {
+            Ok(after) => i128::try_from(after.as_nanos()).unwrap_or(i128::MAX),
+            Err(before) => -i128::try_from(before.duration().as_nanos()).unwrap_or(i128::MAX),
+        };
+        let ns = ns - i128::from(Self::UNIX_EPOCH_OFFSET_NS);
+
+        EbmlDate(ns.clamp(i64::MIN.into(), i64::MAX.into()) as i64)
+    }
+}
+
+impl<'a> EbmlParsable<'a> for EbmlDate {
     fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
         match data.len() {
-            0 | 8 => i64::try_parse(data).map(Date),
+            0 | 8 => i64::try_parse(data).map(EbmlDate),
             _ => Err(ErrorKind::DateWidthIncorrect),
         }
     }
 }
 
+// Days between 1970-01-01 and 2001-01-01, the Matroska epoch
+#[cfg(feature = "std")]
+pub(crate) const EPOCH_DAYS: i64 = 11_323;
+
+// From http://howardhinnant.github.io/date_algorithms.html
+#[cfg(feature = "std")]
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
 impl<'a> EbmlParsable<'a> for String {
     fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
         String::from_utf8(data.to_vec()).map_err(|_| ErrorKind::StringNotUtf8)
@@ -103,12 +187,6 @@ impl<'a> EbmlParsable<'a> for &'a [u8] {
     }
 }
 
-impl<'a> EbmlParsable<'a> for Uuid {
-    fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
-        <[u8; 16] as EbmlParsable>::try_parse(data).map(Uuid::from_bytes)
-    }
-}
-
 // FIXME: Better error handling (via append?)
 pub fn get_required<T>(val: Option<T>, id: u32) -> Result<T, ErrorKind> {
     val.ok_or_else(|| {
@@ -117,18 +195,35 @@ pub fn get_required<T>(val: Option<T>, id: u32) -> Result<T, ErrorKind> {
     })
 }
 
+/// Parses the Element `id`, which must be whole in the input.
+///
+/// The Child Elements are parsed within the data of their parent, where
+/// missing bytes mean that the Element is truncated: the `Incomplete` of
+/// [ebml_element_streaming] is turned into an error.
 pub fn ebml_element<'a, O: EbmlParsable<'a>>(id: u32) -> impl Fn(&'a [u8]) -> EbmlResult<'a, O> {
     move |i| {
-        let (i, mut size) = complete(preceded(check_id(id), elem_size))(i)?;
-        let (i, crc) = if O::has_crc() { crc(i)? } else { (i, None) };
+        complete(ebml_element_streaming(id))(i).map_err(|e| {
+            e.map(|e| match e.kind {
+                ErrorKind::Nom(nom::error::ErrorKind::Complete) => Error { id, ..e },
+                _ => e,
+            })
+        })
+    }
+}
 
-        if crc.is_some() {
-            // The CRC-32 Element is 6 bytes long,
-            // and we already consumed them above.
-            size -= 6;
-        }
+/// Parses the Element `id` from the start of a stream.
+///
+/// Returns `Incomplete` with the number of bytes missing, when known, if
+/// the input ends within the Element header or data.
+pub fn ebml_element_streaming<'a, O: EbmlParsable<'a>>(
+    id: u32,
+) -> impl Fn(&'a [u8]) -> EbmlResult<'a, O> {
+    move |i| {
+        let (i, size) = preceded(check_id(id), elem_size)(i)?;
+        let (i, crc) = if O::has_crc() { crc(i)? } else { (i, None) };
+        let size = size_after_crc(id, size, crc)?;
 
-        let (i, data) = checksum(crc, complete(take(size)))(i)?;
+        let (i, data) = checksum(crc, take(size))(i).map_err(|e| e.map(|e| Error { id, ..e }))?;
         match O::try_parse(data) {
             Ok(o) => Ok((i, o)),
             Err(kind) => ebml_err(id, kind),
@@ -136,6 +231,24 @@ pub fn ebml_element<'a, O: EbmlParsable<'a>>(id: u32) -> impl Fn(&'a [u8]) -> Eb
     }
 }
 
+/// The size of the data of an element after its CRC-32 Element, if any,
+/// which is 6 bytes long.
+///
+/// Fails when the size of the element is smaller than its CRC-32 Element.
+pub(crate) fn size_after_crc(
+    id: u32,
+    size: usize,
+    crc: Option<u32>,
+) -> Result<usize, nom::Err<Error>> {
+    match crc {
+        Some(_) => size.checked_sub(6).ok_or(nom::Err::Error(Error {
+            id,
+            kind: ErrorKind::CrcLongerThanElement,
+        })),
+        None => Ok(size),
+    }
+}
+
 pub fn check_id<'a>(id: u32) -> impl Fn(&'a [u8]) -> EbmlResult<'a, u32> {
     move |input| {
         let (i, o) = vid(input)?;
@@ -148,14 +261,14 @@ pub fn check_id<'a>(id: u32) -> impl Fn(&'a [u8]) -> EbmlResult<'a, u32> {
     }
 }
 
-pub fn void(input: &[u8]) -> EbmlResult<&[u8]> {
+pub fn void(input: &[u8]) -> EbmlResult<'_, &[u8]> {
     ebml_element(0xEC)(input)
 }
 
 /// Consumes an entire EBML Element, and returns the ID if successful.
-pub fn skip_element(input: &[u8]) -> EbmlResult<u32> {
+pub fn skip_element(input: &[u8]) -> EbmlResult<'_, u32> {
     let (i, (id, size, crc)) = tuple((vid, elem_size, crc))(input)?;
-    let size = if crc.is_some() { size - 6 } else { size };
+    let size = size_after_crc(id, size, crc)?;
     let (i, _) = checksum(crc, take(size))(i)?;
     Ok((i, id))
 }
@@ -165,7 +278,42 @@ const CRC: Crc<u32> = Crc::<u32>::new(&Algorithm {
     ..crc::CRC_32_ISO_HDLC
 });
 
-pub fn crc(input: &[u8]) -> EbmlResult<Option<u32>> {
+#[cfg(feature = "std")]
+thread_local! {
+    static CRC_VALIDATION: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Runs `f` with the validation of CRC-32 Elements enabled or disabled
+/// for the parsers called on the current thread.
+///
+/// The parsers validate the checksums by default.
+#[cfg(feature = "std")]
+pub fn with_crc_validation<T>(enabled: bool, f: impl FnOnce() -> T) -> T {
+    // Restores the previous setting even if `f` panics
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CRC_VALIDATION.with(|v| v.set(self.0));
+        }
+    }
+
+    let _restore = Restore(CRC_VALIDATION.with(|v| v.replace(enabled)));
+    f()
+}
+
+// Without std the checksums are always validated.
+#[cfg(feature = "std")]
+fn crc_validation() -> bool {
+    CRC_VALIDATION.with(Cell::get)
+}
+
+#[cfg(not(feature = "std"))]
+fn crc_validation() -> bool {
+    true
+}
+
+pub fn crc(input: &[u8]) -> EbmlResult<'_, Option<u32>> {
     opt(map(ebml_element::<[u8; 4]>(0xBF), u32::from_le_bytes))(input)
 }
 
@@ -181,13 +329,25 @@ where
 
         // FIXME: don't just return an error, the spec has well-defined CRC error handling
         match crc {
-            Some(cs) if cs != CRC.checksum(o) => ebml_err(0, ErrorKind::Crc32Mismatch),
+            Some(expected) if crc_validation() => {
+                let actual = CRC.checksum(o);
+                if expected == actual {
+                    Ok((i, o))
+                } else {
+                    ebml_err(0, ErrorKind::Crc32Mismatch { expected, actual })
+                }
+            }
             _ => Ok((i, o)),
         }
     }
 }
 
-pub fn vint(input: &[u8]) -> EbmlResult<u64> {
+/// Variable-size integer, as used for the Element Data Sizes.
+///
+/// The value is returned as stored, with VINT_DATA bits all ones when the
+/// size is unknown. See [encode_vint](crate::serializer::ebml::encode_vint)
+/// for the encoder.
+pub fn vint(input: &[u8]) -> EbmlResult<'_, u64> {
     if input.is_empty() {
         return Err(Incomplete(Needed::new(1)));
     }
@@ -200,7 +360,7 @@ pub fn vint(input: &[u8]) -> EbmlResult<u64> {
     }
 
     if input.len() <= len as usize {
-        return Err(Incomplete(Needed::new(1)));
+        return Err(Incomplete(Needed::new(len as usize + 1 - input.len())));
     }
 
     let mut val = u64::from(v ^ (1 << (7 - len)));
@@ -222,9 +382,19 @@ pub fn vint(input: &[u8]) -> EbmlResult<u64> {
     Ok((&input[len as usize + 1..], val))
 }
 
+/// Signed variable-size integer, as used by EBML lacing.
+///
+/// The value is stored as an unsigned VINT, shifted by half of its range.
+pub fn signed_vint(input: &[u8]) -> EbmlResult<'_, i64> {
+    let (i, val) = vint(input)?;
+    let len = input.len() - i.len();
+
+    Ok((i, val as i64 - ((1 << (7 * len - 1)) - 1)))
+}
+
 // The take combinator can only accept `usize`, so we need to make
 // sure that the `vint` fits inside those bounds.
-pub fn elem_size(input: &[u8]) -> EbmlResult<usize> {
+pub fn elem_size(input: &[u8]) -> EbmlResult<'_, usize> {
     map_res(vint, |u| {
         usize::try_from(u).map_err(|_| {
             log::error!("Element Data Size does not fit into usize");
@@ -236,17 +406,33 @@ pub fn elem_size(input: &[u8]) -> EbmlResult<usize> {
     })(input)
 }
 
+/// Element Data Size and data of a Float Element, which is 4 or 8 bytes
+/// long.
+///
+/// The floats of 4 bytes are widened to [f64]. See
+/// [encode_float32](crate::serializer::ebml::encode_float32) and
+/// [encode_float64](crate::serializer::ebml::encode_float64) for the
+/// encoders.
+pub fn ebml_float(input: &[u8]) -> EbmlResult<'_, f64> {
+    let (i, data) = nom::multi::length_data(elem_size)(input)?;
+
+    match f64::try_parse(data) {
+        Ok(value) => Ok((i, value)),
+        Err(kind) => ebml_err(0, kind),
+    }
+}
+
 // The ID are represented in the specification as their binary representation
 // do not drop the marker bit.
-pub fn vid(input: &[u8]) -> EbmlResult<u32> {
+pub fn vid(input: &[u8]) -> EbmlResult<'_, u32> {
     if input.is_empty() {
         return Err(Incomplete(Needed::new(1)));
     }
 
     let len = 1 + input[0].leading_zeros() as usize;
 
-    if input.len() <= len {
-        return Err(Incomplete(Needed::new(1)));
+    if input.len() < len {
+        return Err(Incomplete(Needed::new(len - input.len())));
     }
 
     match u32::try_parse(&input[..len]) {
@@ -254,3 +440,19 @@ pub fn vid(input: &[u8]) -> EbmlResult<u32> {
         Err(_) => ebml_err(0, ErrorKind::IDTooWide),
     }
 }
+
+// Total size of the element (ID, size and data) at the start of the input.
+pub(crate) fn element_size(input: &[u8]) -> EbmlResult<'_, usize> {
+    let (i, (_, size)) = tuple((vid, elem_size))(input)?;
+
+    match (input.len() - i.len()).checked_add(size) {
+        Some(total) => Ok((i, total)),
+        None => ebml_err(0, ErrorKind::ElementTooLarge),
+    }
+}
+
+// An Element Data Size with all VINT_DATA bits set to one means "unknown".
+pub(crate) fn is_unknown_size(size: u64, header_len: u64) -> bool {
+    let vint_len = header_len.saturating_sub(4);
+    (1..=8).contains(&vint_len) && size == (1 << (7 * vint_len)) - 1
+}
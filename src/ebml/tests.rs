@@ -87,6 +87,22 @@ fn floats() {
     }
 }
 
+#[test]
+fn signed_integers() {
+    let tests: [(&[u8], i64); 6] = [
+        (&[], 0),
+        (&[0x05], 5),
+        (&[0xEC], -20),
+        (&[0xFF, 0x9C], -100),
+        (&[0x00, 0x9C], 156),
+        (&[0x80, 0, 0, 0, 0, 0, 0, 0], i64::MIN),
+    ];
+
+    for (bytes, expected) in tests {
+        assert_eq!(i64::try_parse(bytes), Ok(expected));
+    }
+}
+
 #[test]
 fn variable_integer() {
     let val01 = [0b10000000];
@@ -97,6 +113,83 @@ fn variable_integer() {
     }
 }
 
+#[test]
+fn dates() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let bytes = [0x00, 0x00, 0x00, 0x00, 0x3B, 0x9A, 0xCA, 0x00];
+    let date = EbmlDate::try_parse(&bytes).unwrap();
+    assert_eq!(date, EbmlDate(1_000_000_000));
+    assert_eq!(
+        EbmlDate::try_parse(&bytes[..4]),
+        Err(ErrorKind::DateWidthIncorrect)
+    );
+
+    assert_eq!(EbmlDate(0).to_unix_timestamp_ns(), 978_307_200_000_000_000);
+    assert_eq!(
+        EbmlDate::from_unix_ns(0),
+        EbmlDate(-978_307_200_000_000_000)
+    );
+    assert_eq!(EbmlDate(i64::MAX).to_unix_timestamp_ns(), i64::MAX);
+
+    let time = UNIX_EPOCH + Duration::from_secs(978_307_201);
+    assert_eq!(date.to_system_time(), time);
+    assert_eq!(EbmlDate::from_system_time(time), date);
+    let time = UNIX_EPOCH - Duration::from_secs(1);
+    assert_eq!(
+        EbmlDate::from_unix_ns(-1_000_000_000).to_system_time(),
+        time
+    );
+    assert_eq!(
+        EbmlDate::from_system_time(time),
+        EbmlDate(-978_307_201_000_000_000)
+    );
+}
+
+#[test]
+fn crc_validation_restored_after_panic() {
+    // Info holding a CRC-32 that does not match its data
+    let info = [
+        0x15, 0x49, 0xA9, 0x66, 0x8A, 0xBF, 0x84, 0, 0, 0, 0, 0x2A, 0xD7, 0xB1, 0x80,
+    ];
+    let parse = || crate::elements::segment_element(&info).is_ok();
+    assert!(!parse());
+
+    let res = std::panic::catch_unwind(|| {
+        with_crc_validation(false, || {
+            assert!(parse());
+            panic!("parser panicked");
+        })
+    });
+    assert!(res.is_err());
+    assert!(!parse());
+}
+
+#[test]
+fn incomplete_input() {
+    use nom::{Err::Incomplete, Needed};
+
+    let webm = include_bytes!("../../assets/bbb-vp9-opus.webm");
+    let (rest, _) = ebml_header(webm).unwrap();
+    let header_len = webm.len() - rest.len();
+
+    // The header has an Element Data Size of 8 bytes
+    assert_eq!(ebml_header(&webm[..2]), Err(Incomplete(Needed::new(2))));
+    assert_eq!(ebml_header(&webm[..10]), Err(Incomplete(Needed::new(2))));
+    assert_eq!(
+        ebml_header(&webm[..16]),
+        Err(Incomplete(Needed::new(header_len - 16)))
+    );
+    assert_eq!(vint(&[0x10, 0x00]), Err(Incomplete(Needed::new(2))));
+    assert_eq!(vid(&[0xEC]), Ok((&[][..], 0xEC)));
+
+    // Within a Master Element, the missing bytes are an error
+    match ebml_element::<u64>(0x4286)(&[0x42, 0x86, 0x82, 0x01]) {
+        Err(nom::Err::Error(e)) => assert_eq!(e.id, 0x4286),
+        res => panic!("expected an error, got {res:?}"),
+    }
+}
+
 fn mkv_headers() -> Vec<(&'static str, EbmlHeader)> {
     vec![
         ("test1.mkv", default_header()), // basic
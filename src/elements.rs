@@ -1,41 +1,99 @@
+use alloc::{borrow::Cow, string::String, vec, vec::Vec};
+
 use nom::{
     bytes::streaming::take,
-    combinator::{map, map_opt, opt},
+    combinator::{map, map_opt, opt, rest},
     number::streaming::{be_i16, be_u8},
     sequence::{pair, tuple},
 };
 
-pub use uuid::Uuid;
-
-use crate::ebml::{check_id, checksum, crc, elem_size, vid, vint, EbmlParsable, EbmlResult, Error};
-use crate::ebml::{macros::impl_ebml_master, Date};
+#[cfg(feature = "std")]
+use crate::codec_private::{
+    av1_config, avc_config, hevc_config, opus_head, CodecPrivate, OpusHead,
+};
+use crate::ebml::{
+    check_id, checksum, crc, ebml_element, elem_size, get_required, signed_vint, size_after_crc,
+    skip_element, vid, vint, void, EbmlParsable, EbmlResult, Error, ErrorKind, DEPRECATED,
+};
+use crate::ebml::{macros::impl_ebml_master, EbmlDate};
 use crate::elements;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SegmentElement<'a> {
     SeekHead(SeekHead),
     Info(Info),
     Tracks(Tracks),
-    // Chapters(Chapters),
+    Chapters(Chapters),
     Cluster(Cluster<'a>),
-    // Cues(Cues),
-    // Attachments(Attachments),
-    // Tags(Tags),
+    Cues(Cues),
+    Attachments(Attachments),
+    Tags(Tags),
     Void(usize),
     Unknown(u32, Option<usize>),
 }
 
+/// The Void Element (0xEC), used as padding.
+///
+/// The parsers skip it, so it only shows up as [SegmentElement::Void] at
+/// the top level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Void;
+
+impl Void {
+    /// Parses a Void Element and returns its total size, header included.
+    pub fn parse(input: &[u8]) -> EbmlResult<'_, usize> {
+        let (i, _) = void(input)?;
+        Ok((i, input.len() - i.len()))
+    }
+}
+
+impl<'a> SegmentElement<'a> {
+    /// The Element ID of the element.
+    pub fn id(&self) -> u32 {
+        use SegmentElement::*;
+
+        match self {
+            SeekHead(_) => 0x114D9B74,
+            Info(_) => 0x1549A966,
+            Tracks(_) => 0x1654AE6B,
+            Chapters(_) => 0x1043A770,
+            Cluster(_) => 0x1F43B675,
+            Cues(_) => 0x1C53BB6B,
+            Attachments(_) => 0x1941A469,
+            Tags(_) => 0x1254C367,
+            Void(_) => 0xEC,
+            Unknown(id, _) => *id,
+        }
+    }
+
+    /// Converts the element into one that owns all of its data.
+    pub fn into_owned(self) -> SegmentElement<'static> {
+        use SegmentElement::*;
+
+        match self {
+            SeekHead(s) => SeekHead(s),
+            Info(i) => Info(i),
+            Tracks(t) => Tracks(t),
+            Chapters(c) => Chapters(c),
+            Cues(c) => Cues(c),
+            Attachments(a) => Attachments(a),
+            Tags(t) => Tags(t),
+            Cluster(c) => Cluster(c.into_owned()),
+            Void(size) => Void(size),
+            Unknown(id, size) => Unknown(id, size),
+        }
+    }
+}
+
 // https://datatracker.ietf.org/doc/html/draft-lhomme-cellar-matroska-03#section-7.3.3
-pub fn segment(input: &[u8]) -> EbmlResult<(u32, Option<u64>)> {
+pub fn segment(input: &[u8]) -> EbmlResult<'_, (u32, Option<u64>)> {
     pair(check_id(0x18538067), opt(vint))(input)
 }
 
 pub(crate) fn sub_element<'a, O: EbmlParsable<'a>>(input: &'a [u8]) -> EbmlResult<'a, O> {
-    let (i, (mut size, crc)) = pair(elem_size, crc)(input)?;
-
-    if crc.is_some() {
-        size -= 6;
-    }
+    let (i, (size, crc)) = pair(elem_size, crc)(input)?;
+    let size = size_after_crc(0, size, crc)?;
 
     let (i, data) = checksum(crc, take(size))(i)?;
 
@@ -46,19 +104,20 @@ pub(crate) fn sub_element<'a, O: EbmlParsable<'a>>(input: &'a [u8]) -> EbmlResul
 }
 
 // Segment, the root element, has id 0x18538067
-pub fn segment_element(input: &[u8]) -> EbmlResult<SegmentElement> {
+pub fn segment_element(input: &[u8]) -> EbmlResult<'_, SegmentElement<'_>> {
     use SegmentElement::*;
 
     vid(input).and_then(|(i, id)| {
-        match id {
+        // Faster than a phf::Map of parsers, see benches/segment_element.rs
+        let res = match id {
             0x114D9B74 => sub_element::<elements::SeekHead>(i).map(|(i, sh)| (i, SeekHead(sh))),
             0x1549A966 => sub_element::<elements::Info>(i).map(|(i, info)| (i, Info(info))),
             0x1F43B675 => sub_element::<elements::Cluster>(i).map(|(i, cl)| (i, Cluster(cl))),
-            // 0x1043A770 => sub_element(|i| Ok((i, SegmentElement::Chapters(Chapters {}))))(i),
-            // 0x1254C367 => sub_element(|i| Ok((i, SegmentElement::Tags(Tags {}))))(i),
-            // 0x1941A469 => sub_element(|i| Ok((i, SegmentElement::Attachments(Attachments {}))))(i),
+            0x1043A770 => sub_element::<elements::Chapters>(i).map(|(i, c)| (i, Chapters(c))),
+            0x1254C367 => sub_element::<elements::Tags>(i).map(|(i, t)| (i, Tags(t))),
+            0x1941A469 => sub_element::<elements::Attachments>(i).map(|(i, a)| (i, Attachments(a))),
             0x1654AE6B => sub_element::<elements::Tracks>(i).map(|(i, tr)| (i, Tracks(tr))),
-            // 0x1C53BB6B => sub_element(|i| Ok((i, SegmentElement::Cues(Cues {}))))(i),
+            0x1C53BB6B => sub_element::<elements::Cues>(i).map(|(i, c)| (i, Cues(c))),
             0xEC => {
                 let (i, size) = elem_size(i)?;
                 take(size)(i).map(|(i, _)| (i, Void(size)))
@@ -72,13 +131,17 @@ pub fn segment_element(input: &[u8]) -> EbmlResult<SegmentElement> {
                     None => Ok((i, SegmentElement::Unknown(id, None))),
                 }
             }
-        }
+        };
+
+        // Errors of the nested parsers do not know the Top-Level Element ID
+        res.map_err(|e| e.map(|e| if e.id == 0 { Error { id, ..e } } else { e }))
     })
 }
 
 impl_ebml_master! {
     // Element ID 0x114D9B74
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct SeekHead {
         [0x4DBB] positions: (Vec<Seek>) [1..],
     }
@@ -87,16 +150,339 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0x4DBB
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Seek {
         [0x53AB] id: ([u8; 4]),
         [0x53AC] position: (u64),
     }
 }
 
+/// The EBML ID of an element, such as the SeekID of a [Seek].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementId(pub u32);
+
+impl ElementId {
+    pub const SEEK_HEAD: ElementId = ElementId(0x114D9B74);
+    pub const INFO: ElementId = ElementId(0x1549A966);
+    pub const TRACKS: ElementId = ElementId(0x1654AE6B);
+    pub const CHAPTERS: ElementId = ElementId(0x1043A770);
+    pub const CLUSTER: ElementId = ElementId(0x1F43B675);
+    pub const CUES: ElementId = ElementId(0x1C53BB6B);
+    pub const ATTACHMENTS: ElementId = ElementId(0x1941A469);
+    pub const TAGS: ElementId = ElementId(0x1254C367);
+
+    /// The name of the element in the specification, or `"Unknown"`.
+    pub fn name(self) -> &'static str {
+        match self.0 {
+            // EBML Header
+            0x1A45DFA3 => "EBML",
+            0x4286 => "EBMLVersion",
+            0x42F7 => "EBMLReadVersion",
+            0x42F2 => "EBMLMaxIDLength",
+            0x42F3 => "EBMLMaxSizeLength",
+            0x4282 => "DocType",
+            0x4287 => "DocTypeVersion",
+            0x4285 => "DocTypeReadVersion",
+            0x4281 => "DocTypeExtension",
+            0x4283 => "DocTypeExtensionName",
+            0x4284 => "DocTypeExtensionVersion",
+            0xEC => "Void",
+            0xBF => "CRC-32",
+
+            0x18538067 => "Segment",
+
+            // SeekHead
+            0x114D9B74 => "SeekHead",
+            0x4DBB => "Seek",
+            0x53AB => "SeekID",
+            0x53AC => "SeekPosition",
+
+            // Info
+            0x1549A966 => "Info",
+            0x73A4 => "SegmentUUID",
+            0x7384 => "SegmentFilename",
+            0x3CB923 => "PrevUUID",
+            0x3C83AB => "PrevFilename",
+            0x3EB923 => "NextUUID",
+            0x3E83BB => "NextFilename",
+            0x4444 => "SegmentFamily",
+            0x6924 => "ChapterTranslate",
+            0x69A5 => "ChapterTranslateID",
+            0x69BF => "ChapterTranslateCodec",
+            0x69FC => "ChapterTranslateEditionUID",
+            0x2AD7B1 => "TimestampScale",
+            0x4489 => "Duration",
+            0x4461 => "DateUTC",
+            0x7BA9 => "Title",
+            0x4D80 => "MuxingApp",
+            0x5741 => "WritingApp",
+
+            // Cluster
+            0x1F43B675 => "Cluster",
+            0xE7 => "Timestamp",
+            0x5854 => "SilentTracks",
+            0x58D7 => "SilentTrackNumber",
+            0xA7 => "Position",
+            0xAB => "PrevSize",
+            0xA3 => "SimpleBlock",
+            0xA0 => "BlockGroup",
+            0xA1 => "Block",
+            0xA2 => "BlockVirtual",
+            0x75A1 => "BlockAdditions",
+            0xA6 => "BlockMore",
+            0xA5 => "BlockAdditional",
+            0xEE => "BlockAddID",
+            0x9B => "BlockDuration",
+            0xFA => "ReferencePriority",
+            0xFB => "ReferenceBlock",
+            0xFD => "ReferenceVirtual",
+            0xA4 => "CodecState",
+            0x75A2 => "DiscardPadding",
+            0x8E => "Slices",
+            0xE8 => "TimeSlice",
+            0xCC => "LaceNumber",
+
+            // Tracks
+            0x1654AE6B => "Tracks",
+            0xAE => "TrackEntry",
+            0xD7 => "TrackNumber",
+            0x73C5 => "TrackUID",
+            0x83 => "TrackType",
+            0xB9 => "FlagEnabled",
+            0x88 => "FlagDefault",
+            0x55AA => "FlagForced",
+            0x55AB => "FlagHearingImpaired",
+            0x55AC => "FlagVisualImpaired",
+            0x55AD => "FlagTextDescriptions",
+            0x55AE => "FlagOriginal",
+            0x55AF => "FlagCommentary",
+            0x9C => "FlagLacing",
+            0x6DE7 => "MinCache",
+            0x6DF8 => "MaxCache",
+            0x23E383 => "DefaultDuration",
+            0x234E7A => "DefaultDecodedFieldDuration",
+            0x23314F => "TrackTimestampScale",
+            0x55EE => "MaxBlockAdditionID",
+            0x41E4 => "BlockAdditionMapping",
+            0x41F0 => "BlockAddIDValue",
+            0x41A4 => "BlockAddIDName",
+            0x41E7 => "BlockAddIDType",
+            0x41ED => "BlockAddIDExtraData",
+            0x536E => "Name",
+            0x22B59C => "Language",
+            0x22B59D => "LanguageBCP47",
+            0x86 => "CodecID",
+            0x63A2 => "CodecPrivate",
+            0x258688 => "CodecName",
+            0x7446 => "AttachmentLink",
+            0xAA => "CodecDecodeAll",
+            0x6FAB => "TrackOverlay",
+            0x56AA => "CodecDelay",
+            0x56BB => "SeekPreRoll",
+            0x6624 => "TrackTranslate",
+            0x66A5 => "TrackTranslateTrackID",
+            0x66BF => "TrackTranslateCodec",
+            0x66FC => "TrackTranslateEditionUID",
+
+            // Video
+            0xE0 => "Video",
+            0x9A => "FlagInterlaced",
+            0x9D => "FieldOrder",
+            0x53B8 => "StereoMode",
+            0x53C0 => "AlphaMode",
+            0x53B9 => "OldStereoMode",
+            0xB0 => "PixelWidth",
+            0xBA => "PixelHeight",
+            0x54AA => "PixelCropBottom",
+            0x54BB => "PixelCropTop",
+            0x54CC => "PixelCropLeft",
+            0x54DD => "PixelCropRight",
+            0x54B0 => "DisplayWidth",
+            0x54BA => "DisplayHeight",
+            0x54B2 => "DisplayUnit",
+            0x54B3 => "AspectRatioType",
+            0x2EB524 => "UncompressedFourCC",
+            0x2FB523 => "GammaValue",
+            0x2383E3 => "FrameRate",
+            0x55B0 => "Colour",
+            0x55B1 => "MatrixCoefficients",
+            0x55B2 => "BitsPerChannel",
+            0x55B3 => "ChromaSubsamplingHorz",
+            0x55B4 => "ChromaSubsamplingVert",
+            0x55B5 => "CbSubsamplingHorz",
+            0x55B6 => "CbSubsamplingVert",
+            0x55B7 => "ChromaSitingHorz",
+            0x55B8 => "ChromaSitingVert",
+            0x55B9 => "Range",
+            0x55BA => "TransferCharacteristics",
+            0x55BB => "Primaries",
+            0x55BC => "MaxCLL",
+            0x55BD => "MaxFALL",
+            0x55D0 => "MasteringMetadata",
+            0x55D1 => "PrimaryRChromaticityX",
+            0x55D2 => "PrimaryRChromaticityY",
+            0x55D3 => "PrimaryGChromaticityX",
+            0x55D4 => "PrimaryGChromaticityY",
+            0x55D5 => "PrimaryBChromaticityX",
+            0x55D6 => "PrimaryBChromaticityY",
+            0x55D7 => "WhitePointChromaticityX",
+            0x55D8 => "WhitePointChromaticityY",
+            0x55D9 => "LuminanceMax",
+            0x55DA => "LuminanceMin",
+            0x7670 => "Projection",
+            0x7671 => "ProjectionType",
+            0x7672 => "ProjectionPrivate",
+            0x7673 => "ProjectionPoseYaw",
+            0x7674 => "ProjectionPosePitch",
+            0x7675 => "ProjectionPoseRoll",
+
+            // Audio
+            0xE1 => "Audio",
+            0xB5 => "SamplingFrequency",
+            0x78B5 => "OutputSamplingFrequency",
+            0x9F => "Channels",
+            0x7D7B => "ChannelPositions",
+            0x6264 => "BitDepth",
+            0x52F1 => "Emphasis",
+
+            // TrackOperation
+            0xE2 => "TrackOperation",
+            0xE3 => "TrackCombinePlanes",
+            0xE4 => "TrackPlane",
+            0xE5 => "TrackPlaneUID",
+            0xE6 => "TrackPlaneType",
+            0xE9 => "TrackJoinBlocks",
+            0xED => "TrackJoinUID",
+
+            // ContentEncodings
+            0x6D80 => "ContentEncodings",
+            0x6240 => "ContentEncoding",
+            0x5031 => "ContentEncodingOrder",
+            0x5032 => "ContentEncodingScope",
+            0x5033 => "ContentEncodingType",
+            0x5034 => "ContentCompression",
+            0x4254 => "ContentCompAlgo",
+            0x4255 => "ContentCompSettings",
+            0x5035 => "ContentEncryption",
+            0x47E1 => "ContentEncAlgo",
+            0x47E2 => "ContentEncKeyID",
+            0x47E7 => "ContentEncAESSettings",
+            0x47E8 => "AESSettingsCipherMode",
+            0x47E3 => "ContentSignature",
+            0x47E4 => "ContentSigKeyID",
+            0x47E5 => "ContentSigAlgo",
+            0x47E6 => "ContentSigHashAlgo",
+
+            // Cues
+            0x1C53BB6B => "Cues",
+            0xBB => "CuePoint",
+            0xB3 => "CueTime",
+            0xB7 => "CueTrackPositions",
+            0xF7 => "CueTrack",
+            0xF1 => "CueClusterPosition",
+            0xF0 => "CueRelativePosition",
+            0xB2 => "CueDuration",
+            0x5378 => "CueBlockNumber",
+            0xEA => "CueCodecState",
+            0xDB => "CueReference",
+            0x96 => "CueRefTime",
+
+            // Attachments
+            0x1941A469 => "Attachments",
+            0x61A7 => "AttachedFile",
+            0x467E => "FileDescription",
+            0x466E => "FileName",
+            0x4660 => "FileMediaType",
+            0x465C => "FileData",
+            0x46AE => "FileUID",
+
+            // Chapters
+            0x1043A770 => "Chapters",
+            0x45B9 => "EditionEntry",
+            0x45BC => "EditionUID",
+            0x45BD => "EditionFlagHidden",
+            0x45DB => "EditionFlagDefault",
+            0x45DD => "EditionFlagOrdered",
+            0x4520 => "EditionDisplay",
+            0x4521 => "EditionString",
+            0x45E4 => "EditionLanguageIETF",
+            0xB6 => "ChapterAtom",
+            0x73C4 => "ChapterUID",
+            0x5654 => "ChapterStringUID",
+            0x91 => "ChapterTimeStart",
+            0x92 => "ChapterTimeEnd",
+            0x98 => "ChapterFlagHidden",
+            0x4598 => "ChapterFlagEnabled",
+            0x6E67 => "ChapterSegmentUUID",
+            0x4588 => "ChapterSkipType",
+            0x6EBC => "ChapterSegmentEditionUID",
+            0x63C3 => "ChapterPhysicalEquiv",
+            0x8F => "ChapterTrack",
+            0x89 => "ChapterTrackUID",
+            0x80 => "ChapterDisplay",
+            0x85 => "ChapString",
+            0x437C => "ChapLanguage",
+            0x437D => "ChapLanguageBCP47",
+            0x437E => "ChapCountry",
+            0x6944 => "ChapProcess",
+            0x6955 => "ChapProcessCodecID",
+            0x450D => "ChapProcessPrivate",
+            0x6911 => "ChapProcessCommand",
+            0x6922 => "ChapProcessTime",
+            0x6933 => "ChapProcessData",
+
+            // Tags
+            0x1254C367 => "Tags",
+            0x7373 => "Tag",
+            0x63C0 => "Targets",
+            0x68CA => "TargetTypeValue",
+            0x63CA => "TargetType",
+            0x63C5 => "TagTrackUID",
+            0x63C9 => "TagEditionUID",
+            0x63C4 => "TagChapterUID",
+            0x63C6 => "TagAttachmentUID",
+            0x67C8 => "SimpleTag",
+            0x45A3 => "TagName",
+            0x447A => "TagLanguage",
+            0x447B => "TagLanguageBCP47",
+            0x4484 => "TagDefault",
+            0x4487 => "TagString",
+            0x4485 => "TagBinary",
+
+            _ => "Unknown",
+        }
+    }
+}
+
+impl From<[u8; 4]> for ElementId {
+    fn from(id: [u8; 4]) -> Self {
+        ElementId(u32::from_be_bytes(id))
+    }
+}
+
+impl Seek {
+    /// The ID of the element the entry points to.
+    pub fn element_id(&self) -> ElementId {
+        ElementId::from(self.id)
+    }
+}
+
+impl SeekHead {
+    /// The position, relative to the start of the Segment data, of the
+    /// first element of ID `id` listed.
+    pub fn position_for_element(&self, id: ElementId) -> Option<u64> {
+        self.positions
+            .iter()
+            .find(|seek| seek.element_id() == id)
+            .map(|seek| seek.position)
+    }
+}
+
 // FIXME: Strings should be UTF-8, not ASCII
 impl_ebml_master! {
     // Element ID 0x1549A966
     #[derive(Debug, Default, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Info {
         [0x73A4] segment_uid: (Option<Uuid>),
         [0x7384] segment_filename: (Option<String>),
@@ -108,60 +494,637 @@ impl_ebml_master! {
         // [0x6924] chapter_translate: (Option<ChapterTranslate>),
         [0x2AD7B1] timestamp_scale: (u64) = 1000000,
         [0x4489] duration: (Option<f64>),     // FIXME: should be float
-        [0x4461] date_utc: (Option<Date>),
+        [0x4461] date_utc: (Option<EbmlDate>),
         [0x7BA9] title: (Option<String>),
         [0x4D80] muxing_app: (String),
         [0x5741] writing_app: (String),
     }
 }
 
+impl Info {
+    /// The DateUTC as nanoseconds since 2001-01-01T00:00:00 UTC.
+    pub fn date_utc_raw(&self) -> Option<i64> {
+        self.date_utc.map(|date| date.0)
+    }
+
+    /// The DateUTC as a [SystemTime](std::time::SystemTime).
+    #[cfg(feature = "std")]
+    pub fn date_utc_as_system_time(&self) -> Option<std::time::SystemTime> {
+        self.date_utc.map(EbmlDate::to_system_time)
+    }
+
+    /// The DateUTC as an [OffsetDateTime](time::OffsetDateTime) in UTC,
+    /// `None` if it is out of its range.
+    #[cfg(feature = "time")]
+    pub fn date_utc_as_offset_date_time(&self) -> Option<time::OffsetDateTime> {
+        let nanos = i128::from(self.date_utc_raw()?) + i128::from(EbmlDate::UNIX_EPOCH_OFFSET_NS);
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanos).ok()
+    }
+}
+
+/// A 128-bit UID, such as a SegmentUID.
+///
+/// It is displayed as hexadecimal bytes separated by spaces, the way
+/// mkvinfo shows them, such as `0xed 0x15 0x72 ...`, and
+/// [from_str](core::str::FromStr::from_str) parses that format back, with
+/// the `0x` prefix of the bytes optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Uuid(pub [u8; 16]);
+
+impl Uuid {
+    /// The UID with all bits set to zero.
+    pub const fn nil() -> Uuid {
+        Uuid([0; 16])
+    }
+
+    pub const fn from_bytes(bytes: [u8; 16]) -> Uuid {
+        Uuid(bytes)
+    }
+
+    /// The UID of the big-endian bytes of `v`.
+    pub const fn from_u128(v: u128) -> Uuid {
+        Uuid(v.to_be_bytes())
+    }
+
+    pub const fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// The big-endian value of the bytes.
+    pub const fn as_u128(&self) -> u128 {
+        u128::from_be_bytes(self.0)
+    }
+}
+
+impl AsRef<[u8]> for Uuid {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<uuid::Uuid> for Uuid {
+    fn from(uid: uuid::Uuid) -> Self {
+        Uuid(uid.into_bytes())
+    }
+}
+
+impl From<Uuid> for uuid::Uuid {
+    fn from(uid: Uuid) -> Self {
+        uuid::Uuid::from_bytes(uid.0)
+    }
+}
+
+impl core::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{b:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::str::FromStr for Uuid {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || crate::Error::InvalidUid(s.to_owned());
+
+        let mut bytes = [0; 16];
+        let mut parts = s.split_whitespace();
+        for byte in &mut bytes {
+            let part = parts.next().ok_or_else(invalid)?;
+            let part = part.strip_prefix("0x").unwrap_or(part);
+            if part.is_empty() || part.len() > 2 || part.starts_with('+') {
+                return Err(invalid());
+            }
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Uuid(bytes))
+    }
+}
+
+/// Formats a UID the way mkvinfo shows it, the same as the [Display]
+/// of [Uuid].
+///
+/// [Display]: core::fmt::Display
+pub fn format_uid(uid: &Uuid) -> String {
+    alloc::format!("{uid}")
+}
+
+/// Parses a UID formatted by [format_uid], the same as the
+/// [FromStr](core::str::FromStr) of [Uuid].
+#[cfg(feature = "std")]
+pub fn parse_uid(s: &str) -> Result<Uuid, crate::Error> {
+    s.parse()
+}
+
+impl<'a> EbmlParsable<'a> for Uuid {
+    fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
+        <[u8; 16] as EbmlParsable>::try_parse(data).map(Uuid)
+    }
+}
+
+// Serialized like uuid::Uuid, as the hyphenated form in human-readable
+// formats.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Uuid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        uuid::Uuid::from(*self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Uuid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        uuid::Uuid::deserialize(deserializer).map(Uuid::from)
+    }
+}
+
+/// Cluster Element (ID 0x1F43B675).
+///
+/// The SimpleBlocks and the BlockGroups are kept apart, `block_order`
+/// tells in which order they are stored, see [Cluster::blocks]. Two
+/// Clusters are equal when they hold the same blocks in the same order.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cluster<'a> {
+    pub timestamp: u64,
+    pub silent_tracks: Option<SilentTracks>,
+    pub position: Option<u64>,
+    pub prev_size: Option<u64>,
+    pub simple_block: Vec<SimpleBlock<'a>>,
+    pub block_group: Vec<BlockGroup<'a>>,
+    /// The kind of each block, in the order they are stored. Left empty,
+    /// the SimpleBlocks come before the BlockGroups.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub block_order: Vec<BlockKind>,
+}
+
+impl PartialEq for Cluster<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.timestamp == other.timestamp
+            && self.silent_tracks == other.silent_tracks
+            && self.position == other.position
+            && self.prev_size == other.prev_size
+            && self.simple_block == other.simple_block
+            && self.block_group == other.block_group
+            && self.blocks().eq(other.blocks())
+    }
+}
+
+impl<'a> EbmlParsable<'a> for Cluster<'a> {
+    // Master Elements can always have a CRC-32 Element
+    fn has_crc() -> bool {
+        true
+    }
+
+    // The Child Elements are parsed one after the other rather than as a
+    // permutation, which keeps the order of the blocks and the blocks
+    // stored after ones of the other kind.
+    fn try_parse(mut input: &'a [u8]) -> Result<Self, ErrorKind> {
+        let mut timestamp = None;
+        let mut cluster = Cluster {
+            timestamp: 0,
+            silent_tracks: None,
+            position: None,
+            prev_size: None,
+            simple_block: Vec::new(),
+            block_group: Vec::new(),
+            block_order: Vec::new(),
+        };
+
+        while let Ok((_, id)) = vid(input) {
+            let res = match id {
+                0xE7 => ebml_element(id)(input).map(|(i, t)| {
+                    timestamp = Some(t);
+                    i
+                }),
+                0x5854 => ebml_element(id)(input).map(|(i, s)| {
+                    cluster.silent_tracks = Some(s);
+                    i
+                }),
+                0xA7 => ebml_element(id)(input).map(|(i, p)| {
+                    cluster.position = Some(p);
+                    i
+                }),
+                0xAB => ebml_element(id)(input).map(|(i, p)| {
+                    cluster.prev_size = Some(p);
+                    i
+                }),
+                0xA3 => ebml_element(id)(input).map(|(i, b)| {
+                    cluster.simple_block.push(b);
+                    cluster.block_order.push(BlockKind::SimpleBlock);
+                    i
+                }),
+                0xA0 => ebml_element(id)(input).map(|(i, g)| {
+                    cluster.block_group.push(g);
+                    cluster.block_order.push(BlockKind::BlockGroup);
+                    i
+                }),
+                0xEC => skip_element(input).map(|(i, _)| i),
+                _ => skip_element(input).map(|(i, id)| {
+                    if let Some(name) = DEPRECATED.get(&id) {
+                        log::warn!("Skipped deprecated Element '{name}' ({id:#0X})");
+                    } else {
+                        log::warn!("Skipped unknown Element {id:#0X}");
+                    }
+                    i
+                }),
+            };
+
+            match res {
+                Ok(i) => input = i,
+                Err(nom::Err::Error(_)) => break,
+                Err(nom::Err::Failure(e)) => return Err(e.kind),
+                Err(nom::Err::Incomplete(_)) => {
+                    return Err(ErrorKind::Nom(nom::error::ErrorKind::Complete))
+                }
+            }
+        }
+
+        if !input.is_empty() {
+            log::warn!("{} unused bytes left after parsing Cluster", input.len());
+        }
+
+        cluster.timestamp = get_required(timestamp, 0xE7)?;
+        Ok(cluster)
+    }
+}
+
+/// The kind of a block of a [Cluster].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlockKind {
+    SimpleBlock,
+    BlockGroup,
+}
+
+/// A SimpleBlock or a BlockGroup of a [Cluster], as returned by
+/// [Cluster::blocks].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterBlock<'c, 'a> {
+    SimpleBlock(&'c SimpleBlock<'a>),
+    BlockGroup(&'c BlockGroup<'a>),
+}
+
+impl<'c, 'a> ClusterBlock<'c, 'a> {
+    pub fn track_number(&self) -> u64 {
+        match self {
+            ClusterBlock::SimpleBlock(b) => b.track_number,
+            ClusterBlock::BlockGroup(g) => g.block.track_number,
+        }
+    }
+
+    /// Timestamp relative to the one of the Cluster, in ticks of the
+    /// TimestampScale.
+    pub fn timestamp(&self) -> i16 {
+        match self {
+            ClusterBlock::SimpleBlock(b) => b.timestamp,
+            ClusterBlock::BlockGroup(g) => g.block.timestamp,
+        }
+    }
+
+    /// The keyframe flag of a SimpleBlock, or whether a BlockGroup has no
+    /// ReferenceBlock.
+    pub fn keyframe(&self) -> bool {
+        match self {
+            ClusterBlock::SimpleBlock(b) => b.keyframe,
+            ClusterBlock::BlockGroup(g) => g.reference_block.is_empty(),
+        }
+    }
+
+    /// The BlockDuration of a BlockGroup, in ticks of the TimestampScale.
+    pub fn duration(&self) -> Option<u64> {
+        match self {
+            ClusterBlock::SimpleBlock(_) => None,
+            ClusterBlock::BlockGroup(g) => g.block_duration,
+        }
+    }
+
+    /// Splits the block payload into its frames, undoing the lacing.
+    pub fn frames(&self) -> Result<Vec<&'c [u8]>, Error> {
+        match self {
+            ClusterBlock::SimpleBlock(b) => b.frames(),
+            ClusterBlock::BlockGroup(g) => g.block.frames(),
+        }
+    }
+}
+
+/// Iterator over the blocks of a Cluster, see [Cluster::blocks].
+pub struct Blocks<'c, 'a> {
+    simple_blocks: core::slice::Iter<'c, SimpleBlock<'a>>,
+    block_groups: core::slice::Iter<'c, BlockGroup<'a>>,
+    order: Option<core::slice::Iter<'c, BlockKind>>,
+}
+
+impl<'c, 'a> Iterator for Blocks<'c, 'a> {
+    type Item = ClusterBlock<'c, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let simple_block = |b| ClusterBlock::SimpleBlock(b);
+        let block_group = |g| ClusterBlock::BlockGroup(g);
+
+        match &mut self.order {
+            Some(order) => match order.next()? {
+                BlockKind::SimpleBlock => self.simple_blocks.next().map(simple_block),
+                BlockKind::BlockGroup => self.block_groups.next().map(block_group),
+            },
+            None => self
+                .simple_blocks
+                .next()
+                .map(simple_block)
+                .or_else(|| self.block_groups.next().map(block_group)),
+        }
+    }
+}
+
 impl_ebml_master! {
-    // Element ID 0x1F43B675
-    #[derive(Debug, Clone, PartialEq)]
-    struct Cluster<'a> {
-        [0xE7] timestamp: (u64),
-        [0xA7] position: (Option<u64>),
-        [0xAB] prev_size: (Option<u64>),
-        [0xA3] simple_block: (Vec<&'a [u8]>) [0..],
-        [0xA0] block_group: (Vec<BlockGroup<'a>>) [0..],
+    // Element ID 0x5854
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct SilentTracks {
+        // Numbers of the tracks without blocks in the Cluster
+        [0x58D7] silent_track_number: (Vec<u64>) [0..],
     }
 }
 
 impl_ebml_master! {
     // Element ID 0xA0
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct BlockGroup<'a> {
-        [0xA1] block: (&'a [u8]),
-        // [0x75A1] block_additions: (Option<BlockAdditions>),
+        [0xA1] block: (Block<'a>),
+        [0x75A1] block_additions: (Option<BlockAdditions>),
         [0x9B] block_duration: (Option<u64>),
         [0xFA] reference_priority: (u64) = 0,
-        [0xFB] reference_block: (Option<u64>),
+        // Timestamps of the referenced blocks, relative to this one
+        [0xFB] reference_block: (Vec<i64>) [0..],
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
         [0xA4] codec_state: (Option<Vec<u8>>),
+        // Nanoseconds of padding to discard after decoding, at the end of
+        // the block when positive (the trimming at the end of Opus
+        // streams) and at its start when negative
         [0x75A2] discard_padding: (Option<i64>),
     }
 }
 
+impl_ebml_master! {
+    // Element ID 0x75A1
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct BlockAdditions {
+        [0xA6] block_more: (Vec<BlockMore>) [1..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0xA6
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct BlockMore {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::bytes"))]
+        [0xA5] block_additional: (Vec<u8>),
+        [0xEE] block_add_id: (u64) = 1,
+    }
+}
+
+impl<'a> Cluster<'a> {
+    /// The timestamp of the Cluster in nanoseconds, for the
+    /// TimestampScale of the Info.
+    ///
+    /// The product is computed on 128 bits, and saturates at [u64::MAX].
+    pub fn timestamp_ns(&self, timestamp_scale: u64) -> u64 {
+        let ns = u128::from(self.timestamp) * u128::from(timestamp_scale);
+        u64::try_from(ns).unwrap_or(u64::MAX)
+    }
+
+    /// The timestamp of a block of the Cluster, from its timestamp
+    /// `relative` to the Cluster, in ticks of the TimestampScale.
+    ///
+    /// The result is clamped between 0 and [u64::MAX].
+    pub fn block_timestamp(&self, relative: i16) -> u64 {
+        self.timestamp.saturating_add_signed(i64::from(relative))
+    }
+
+    /// The timestamp of a block of the Cluster in nanoseconds, see
+    /// [Cluster::block_timestamp].
+    ///
+    /// The result is clamped between 0 and [u64::MAX].
+    pub fn block_timestamp_ns(&self, relative: i16, timestamp_scale: u64) -> u64 {
+        let ns = u128::from(self.block_timestamp(relative)) * u128::from(timestamp_scale);
+        u64::try_from(ns).unwrap_or(u64::MAX)
+    }
+
+    /// Whether the track is listed in the SilentTracks, as having no
+    /// blocks in the Cluster.
+    pub fn track_is_silent(&self, track_number: u64) -> bool {
+        self.silent_tracks
+            .iter()
+            .any(|silent| silent.silent_track_number.contains(&track_number))
+    }
+
+    /// The first SimpleBlock of timestamp `relative_timestamp`, relative to
+    /// the Cluster, found by a binary search.
+    ///
+    /// The SimpleBlocks must be sorted by timestamp as required by the
+    /// specification, see [Cluster::is_timestamp_sorted]. The result is
+    /// unspecified otherwise.
+    pub fn block_at_timestamp(&self, relative_timestamp: i16) -> Option<&SimpleBlock<'a>> {
+        let index = self
+            .simple_block
+            .partition_point(|block| block.timestamp < relative_timestamp);
+        self.simple_block
+            .get(index)
+            .filter(|block| block.timestamp == relative_timestamp)
+    }
+
+    /// Whether the SimpleBlocks are sorted by timestamp, false if the file
+    /// violates the specification.
+    pub fn is_timestamp_sorted(&self) -> bool {
+        self.simple_block
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp)
+    }
+
+    /// The SimpleBlocks and BlockGroups of the Cluster, in the order they
+    /// are stored.
+    ///
+    /// The SimpleBlocks come first when `block_order` does not match the
+    /// blocks, e.g. when it is left empty.
+    pub fn blocks(&self) -> Blocks<'_, 'a> {
+        Blocks {
+            simple_blocks: self.simple_block.iter(),
+            block_groups: self.block_group.iter(),
+            order: self.has_block_order().then(|| self.block_order.iter()),
+        }
+    }
+
+    /// Keeps the blocks of the tracks for which `keep` returns true,
+    /// along with their order.
+    pub fn retain_tracks(&mut self, mut keep: impl FnMut(u64) -> bool) {
+        if self.has_block_order() {
+            let mut simple_blocks = self.simple_block.iter();
+            let mut block_groups = self.block_group.iter();
+            self.block_order.retain(|kind| match kind {
+                BlockKind::SimpleBlock => {
+                    simple_blocks.next().is_some_and(|b| keep(b.track_number))
+                }
+                BlockKind::BlockGroup => block_groups
+                    .next()
+                    .is_some_and(|g| keep(g.block.track_number)),
+            });
+        }
+
+        self.simple_block.retain(|b| keep(b.track_number));
+        self.block_group.retain(|g| keep(g.block.track_number));
+    }
+
+    // Whether `block_order` lists every block
+    fn has_block_order(&self) -> bool {
+        let simple_blocks = self
+            .block_order
+            .iter()
+            .filter(|&&kind| kind == BlockKind::SimpleBlock)
+            .count();
+        simple_blocks == self.simple_block.len()
+            && self.block_order.len() - simple_blocks == self.block_group.len()
+    }
+
+    /// Converts the cluster into one that owns all of its blocks.
+    pub fn into_owned(self) -> Cluster<'static> {
+        Cluster {
+            timestamp: self.timestamp,
+            silent_tracks: self.silent_tracks,
+            position: self.position,
+            prev_size: self.prev_size,
+            simple_block: self
+                .simple_block
+                .into_iter()
+                .map(SimpleBlock::into_owned)
+                .collect(),
+            block_group: self
+                .block_group
+                .into_iter()
+                .map(BlockGroup::into_owned)
+                .collect(),
+            block_order: self.block_order,
+        }
+    }
+}
+
+impl BlockAdditions {
+    /// The BlockAdditional of the BlockMore of ID `id`.
+    pub fn get(&self, id: u64) -> Option<&[u8]> {
+        self.block_more
+            .iter()
+            .find(|more| more.block_add_id == id)
+            .map(|more| more.block_additional.as_slice())
+    }
+}
+
+impl<'a> BlockGroup<'a> {
+    /// The codec-specific data attached to the block.
+    pub fn block_additions(&self) -> Option<&BlockAdditions> {
+        self.block_additions.as_ref()
+    }
+
+    /// The Dolby Vision RPU of the block, stored in the BlockAdditional
+    /// of BlockAddID 1.
+    pub fn dolby_vision_rpu(&self) -> Option<&[u8]> {
+        self.block_additions()?.get(1)
+    }
+
+    /// Whether the group holds a CodecState, the state of the decoder at
+    /// this block, which allows starting to decode from it.
+    pub fn has_codec_state(&self) -> bool {
+        self.codec_state.is_some()
+    }
+
+    /// Converts the block group into one that owns its block.
+    pub fn into_owned(self) -> BlockGroup<'static> {
+        BlockGroup {
+            block: self.block.into_owned(),
+            block_additions: self.block_additions,
+            block_duration: self.block_duration,
+            reference_priority: self.reference_priority,
+            reference_block: self.reference_block,
+            codec_state: self.codec_state,
+            discard_padding: self.discard_padding,
+        }
+    }
+}
+
+/// Block Element (ID 0xA1), the payload of a [BlockGroup].
+///
+/// `data` holds everything after the block header, i.e. the lacing
+/// header (if any) followed by the frames. Use [Block::frames] to
+/// get the individual frames.
+///
+/// The parsers borrow `data` from their input, so that the frames can be
+/// handed to a decoder without being copied. [Block::into_owned] copies
+/// them when the block has to outlive the input.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Block {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Block<'a> {
     pub track_number: u64,
+    /// Timestamp relative to the one of the Cluster, in ticks of the
+    /// TimestampScale, negative for the blocks before the Cluster.
     pub timestamp: i16,
     pub invisible: bool,
     pub lacing: Lacing,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::bytes"))]
+    pub data: Cow<'a, [u8]>,
 }
 
-pub fn block(input: &[u8]) -> EbmlResult<Block> {
+pub fn block(input: &[u8]) -> EbmlResult<'_, Block<'_>> {
     map(
-        tuple((vint, be_i16, map_opt(be_u8, block_flags))),
-        |(track_number, timestamp, flags)| Block {
+        tuple((vint, be_i16, map_opt(be_u8, block_flags), rest)),
+        |(track_number, timestamp, flags, data)| Block {
             track_number,
             timestamp,
             invisible: flags.invisible,
             lacing: flags.lacing,
+            data: Cow::Borrowed(data),
         },
     )(input)
 }
 
+impl<'a> Block<'a> {
+    /// Converts the block into one that owns its data.
+    pub fn into_owned(self) -> Block<'static> {
+        Block {
+            data: Cow::Owned(self.data.into_owned()),
+            ..self
+        }
+    }
+
+    /// Splits the block payload into its frames, undoing the lacing.
+    pub fn frames(&self) -> Result<Vec<&[u8]>, Error> {
+        laced_frames(&self.lacing, &self.data).map_err(|kind| Error { id: 0xA1, kind })
+    }
+}
+
+impl<'a> EbmlParsable<'a> for Block<'a> {
+    fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
+        block(data).map(|(_, b)| b).map_err(block_error_kind)
+    }
+}
+
+/// The flags in the header of a [SimpleBlock] or a [Block]. The keyframe
+/// and discardable flags are only used by the SimpleBlocks.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockFlags {
     pub keyframe: bool,
     pub invisible: bool,
@@ -169,19 +1132,34 @@ pub struct BlockFlags {
     pub discardable: bool,
 }
 
+/// SimpleBlock Element (ID 0xA3).
+///
+/// `data` holds everything after the block header, i.e. the lacing
+/// header (if any) followed by the frames. Use [SimpleBlock::frames] to
+/// get the individual frames.
+///
+/// The parsers borrow `data` from their input, so that the frames can be
+/// handed to a decoder without being copied. [SimpleBlock::into_owned] copies
+/// them when the block has to outlive the input.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SimpleBlock {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimpleBlock<'a> {
     pub track_number: u64,
+    /// Timestamp relative to the one of the Cluster, in ticks of the
+    /// TimestampScale, negative for the blocks before the Cluster.
     pub timestamp: i16,
     pub keyframe: bool,
     pub invisible: bool,
     pub lacing: Lacing,
     pub discardable: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::bytes"))]
+    pub data: Cow<'a, [u8]>,
 }
 
+// The flags are numbered from the most significant bit, see
+// https://www.rfc-editor.org/rfc/rfc9559.html#name-simpleblock-structure
 fn block_flags(data: u8) -> Option<BlockFlags> {
-    let lacing_data = ((data << 6) >> 6) >> 5;
-    let lacing = match lacing_data {
+    let lacing = match (data >> 1) & 0b11 {
         0 => Lacing::None,
         1 => Lacing::Xiph,
         2 => Lacing::FixedSize,
@@ -190,36 +1168,68 @@ fn block_flags(data: u8) -> Option<BlockFlags> {
     };
 
     Some(BlockFlags {
-        keyframe: (data & 1) != 0,
-        invisible: (data & (1 << 4)) != 0,
+        keyframe: (data & 0x80) != 0,
+        invisible: (data & 0x08) != 0,
         lacing,
-        discardable: (data & (1 << 7)) != 0,
+        discardable: (data & 0x01) != 0,
     })
 }
 
-pub fn simple_block(input: &[u8]) -> EbmlResult<SimpleBlock> {
+pub fn simple_block(input: &[u8]) -> EbmlResult<'_, SimpleBlock<'_>> {
     map(
-        tuple((vint, be_i16, map_opt(be_u8, block_flags))),
-        |(track_number, timestamp, flags)| SimpleBlock {
+        tuple((vint, be_i16, map_opt(be_u8, block_flags), rest)),
+        |(track_number, timestamp, flags, data)| SimpleBlock {
             track_number,
             timestamp,
             keyframe: flags.keyframe,
             invisible: flags.invisible,
             lacing: flags.lacing,
             discardable: flags.discardable,
+            data: Cow::Borrowed(data),
         },
     )(input)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct SimpleBlockFlags {
-    pub keyframe: bool,
-    pub invisible: bool,
-    pub lacing: Lacing,
-    pub discardable: bool,
+impl<'a> SimpleBlock<'a> {
+    /// The timestamp of the block in nanoseconds, from the one of its
+    /// Cluster given by [Cluster::timestamp_ns].
+    ///
+    /// The result is clamped between 0 and [u64::MAX].
+    pub fn absolute_timestamp_ns(&self, cluster_timestamp_ns: u64, timestamp_scale: u64) -> u64 {
+        let relative = i128::from(self.timestamp) * i128::from(timestamp_scale);
+        let ns = i128::from(cluster_timestamp_ns) + relative;
+        u64::try_from(ns.max(0)).unwrap_or(u64::MAX)
+    }
+
+    /// Converts the block into one that owns its data.
+    pub fn into_owned(self) -> SimpleBlock<'static> {
+        SimpleBlock {
+            data: Cow::Owned(self.data.into_owned()),
+            ..self
+        }
+    }
+
+    /// Splits the block payload into its frames, undoing the lacing.
+    pub fn frames(&self) -> Result<Vec<&[u8]>, Error> {
+        laced_frames(&self.lacing, &self.data).map_err(|kind| Error { id: 0xA3, kind })
+    }
+}
+
+impl<'a> EbmlParsable<'a> for SimpleBlock<'a> {
+    fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
+        simple_block(data).map(|(_, b)| b).map_err(block_error_kind)
+    }
+}
+
+fn block_error_kind(e: nom::Err<Error>) -> ErrorKind {
+    match e {
+        nom::Err::Failure(e) | nom::Err::Error(e) => e.kind,
+        nom::Err::Incomplete(_) => ErrorKind::Nom(nom::error::ErrorKind::Complete),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Lacing {
     None,
     Xiph,
@@ -228,13 +1238,87 @@ pub enum Lacing {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LacedData {
     pub frame_count: u8,
 }
 
+// https://www.rfc-editor.org/rfc/rfc9559.html#name-block-lacing
+pub(crate) fn laced_frames<'a>(
+    lacing: &Lacing,
+    data: &'a [u8],
+) -> Result<Vec<&'a [u8]>, ErrorKind> {
+    if *lacing == Lacing::None {
+        return Ok(vec![data]);
+    }
+
+    // The first byte is the number of frames minus one
+    let (&count, mut input) = data.split_first().ok_or(ErrorKind::InvalidLacing)?;
+    let count = usize::from(count) + 1;
+
+    // Sizes of all frames except the last one, which fills the rest of the block
+    let mut sizes = Vec::with_capacity(count);
+    match lacing {
+        Lacing::None => unreachable!("handled above"),
+        Lacing::Xiph => {
+            for _ in 1..count {
+                let mut size = 0;
+                loop {
+                    let (&b, i) = input.split_first().ok_or(ErrorKind::InvalidLacing)?;
+                    input = i;
+                    size += usize::from(b);
+                    if b != 0xFF {
+                        break;
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+        Lacing::EBML => {
+            if count > 1 {
+                let (i, first) = vint(input).map_err(|_| ErrorKind::InvalidLacing)?;
+                input = i;
+                let mut size = i64::try_from(first).map_err(|_| ErrorKind::InvalidLacing)?;
+                sizes.push(size as usize);
+
+                // The following sizes are coded as differences to the previous one
+                for _ in 2..count {
+                    let (i, diff) = signed_vint(input).map_err(|_| ErrorKind::InvalidLacing)?;
+                    input = i;
+                    size = size.checked_add(diff).ok_or(ErrorKind::InvalidLacing)?;
+                    if size < 0 {
+                        return Err(ErrorKind::InvalidLacing);
+                    }
+                    sizes.push(size as usize);
+                }
+            }
+        }
+        Lacing::FixedSize => {
+            if input.len() % count != 0 {
+                return Err(ErrorKind::InvalidLacing);
+            }
+            sizes.resize(count - 1, input.len() / count);
+        }
+    }
+
+    let mut frames = Vec::with_capacity(count);
+    for size in sizes {
+        if size > input.len() {
+            return Err(ErrorKind::InvalidLacing);
+        }
+        let (frame, i) = input.split_at(size);
+        frames.push(frame);
+        input = i;
+    }
+    frames.push(input);
+
+    Ok(frames)
+}
+
 impl_ebml_master! {
     // Element ID 0x1654AE6B
     #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Tracks {
         [0xAE] tracks: (Vec<TrackEntry>) [1..],
     }
@@ -278,27 +1362,37 @@ impl From<TrackType> for u64 {
 impl_ebml_master! {
     // Element ID 0xAE
     #[derive(Debug, Clone, PartialEq, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TrackEntry {
         [0xD7] track_number: (u64),
         [0x73C5] track_uid: (u64),
         [0x83] track_type: (u64),
         [0xB9] flag_enabled: (u64) = 1,
         [0x88] flag_default: (u64) = 1,
-        [0x55AA] flag_forced: (u64) = 0,
+        [0x55AA] flag_forced: (bool) = false,
+        [0x55AB] flag_hearing_impaired: (bool) = false,
+        [0x55AC] flag_visual_impaired: (bool) = false,
+        [0x55AD] flag_text_descriptions: (bool) = false,
+        [0x55AE] flag_original: (bool) = false,
+        [0x55AF] flag_commentary: (bool) = false,
         [0x9C] flag_lacing: (u64) = 1,
         [0x23E383] default_duration: (Option<u64>),
         [0x234E7A] default_decoded_field_duration: (Option<u64>),
         // FIXME: reimplement float_or handling
         [0x23314F] track_timestamp_scale: (f64) = 1.0,
         [0x55EE] max_block_addition_id: (u64) = 0,
+        [0x41E4] block_addition_mappings: (Vec<BlockAdditionMapping>) [0..],
         [0x536E] name: (Option<String>),
         [0x22B59C] language: (String) = String::from("eng"),
         [0x22B59D] language_ietf: (Option<String>),
         [0x86] codec_id: (String),
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
         [0x63A2] codec_private: (Option<Vec<u8>>),
         [0x258688] codec_name: (Option<String>),
-        [0x7446] attachment_link: (Option<u64>),
+        [0x7446] attachment_links: (Vec<u64>) [0..],
+        // Nanoseconds to discard from the start of the decoded audio
         [0x56AA] codec_delay: (u64) = 0,
+        // Nanoseconds to decode before the target of a seek
         [0x56BB] seek_pre_roll: (u64) = 0,
         [0xE0] video: (Option<Video>),
         [0xE1] audio: (Option<Audio>),
@@ -312,9 +1406,146 @@ impl_ebml_master! {
     }
 }
 
-impl_ebml_master! {
+impl TrackEntry {
+    /// The encryption settings of the track, if its content is encrypted.
+    pub fn encryption(&self) -> Option<&ContentEncryption> {
+        self.content_encodings
+            .iter()
+            .flat_map(|encodings| &encodings.content_encoding)
+            .filter(|encoding| encoding.encoding_type == 1)
+            .filter_map(|encoding| encoding.encryption.as_ref())
+            .find(|encryption| encryption.enc_algo != 0)
+    }
+
+    /// Whether the content of the track is encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.encryption().is_some()
+    }
+
+    /// The ID of the key needed to decrypt the track.
+    pub fn encryption_key_id(&self) -> Option<&[u8]> {
+        self.encryption()?.enc_key_id.as_deref()
+    }
+
+    /// The language of the track, the BCP 47 LanguageBCP47 if present or
+    /// the ISO 639-2 Language otherwise.
+    pub fn effective_language(&self) -> &str {
+        self.language_ietf.as_deref().unwrap_or(&self.language)
+    }
+
+    /// The BlockAdditionMapping of the BlockAdditions of ID `id`.
+    pub fn addition_mapping_for_id(&self, id: u64) -> Option<&BlockAdditionMapping> {
+        self.block_addition_mappings
+            .iter()
+            .find(|mapping| mapping.id_value == Some(id))
+    }
+
+    /// The attached files used by the track, such as the fonts of a
+    /// subtitle track, in the order of the AttachmentLinks. The links to
+    /// files missing from `attachments` are skipped.
+    pub fn linked_attachments<'a>(&self, attachments: &'a Attachments) -> Vec<&'a AttachedFile> {
+        self.attachment_links
+            .iter()
+            .filter_map(|&uid| attachments.get(uid))
+            .collect()
+    }
+
+    /// How the track is built from other tracks, for a virtual track.
+    pub fn track_operation(&self) -> Option<&TrackOperation> {
+        self.track_operation.as_ref()
+    }
+
+    /// The UID of the track carrying the alpha channel of the video.
+    ///
+    /// Matroska stores the alpha plane in the BlockAdditions of the video
+    /// track itself, so this is the UID of the track when it
+    /// [has an alpha channel](Video::has_alpha).
+    pub fn alpha_plane_track_uid(&self) -> Option<u64> {
+        self.video
+            .as_ref()
+            .filter(|video| video.has_alpha())
+            .map(|_| self.track_uid)
+    }
+
+    /// The CodecPrivate of the track, parsed according to its CodecID.
+    #[cfg(feature = "std")]
+    pub fn parsed_codec_private(&self) -> Result<CodecPrivate, crate::Error> {
+        let data = self
+            .codec_private
+            .as_deref()
+            .ok_or(crate::Error::InvalidCodecPrivate)?;
+
+        let res = match self.codec_id.as_str() {
+            "V_MPEG4/ISO/AVC" => avc_config(data).map(|(_, c)| CodecPrivate::Avc(c)),
+            "V_MPEGH/ISO/HEVC" => hevc_config(data).map(|(_, c)| CodecPrivate::Hevc(c)),
+            "V_AV1" => av1_config(data).map(|(_, c)| CodecPrivate::Av1(c)),
+            "A_OPUS" => opus_head(data).map(|(_, h)| CodecPrivate::Opus(h)),
+            id => return Err(crate::Error::UnsupportedCodec(id.to_owned())),
+        };
+
+        res.map_err(|_| crate::Error::InvalidCodecPrivate)
+    }
+
+    /// The OpusHead of an Opus track, see [TrackEntry::parsed_codec_private].
+    ///
+    /// Fails with [Error::InvalidCodecPrivate](crate::Error::InvalidCodecPrivate)
+    /// for the tracks of other codecs.
+    #[cfg(feature = "std")]
+    pub fn opus_head(&self) -> Result<OpusHead, crate::Error> {
+        match self.parsed_codec_private()? {
+            CodecPrivate::Opus(head) => Ok(head),
+            _ => Err(crate::Error::InvalidCodecPrivate),
+        }
+    }
+
+    /// The number of frames per second of a video track, from its
+    /// DefaultDuration.
+    pub fn frame_rate(&self) -> Option<f64> {
+        let duration = self
+            .video
+            .as_ref()
+            .and(self.default_duration)
+            .filter(|&d| d > 0)?;
+
+        Some(1e9 / duration as f64)
+    }
+
+    /// The number of samples in each frame of an audio track, from its
+    /// DefaultDuration and sampling frequency.
+    pub fn samples_per_frame(&self) -> Option<u64> {
+        let audio = self.audio.as_ref()?;
+        let duration = self.default_duration?;
+
+        Some(round(duration as f64 * audio.sampling_frequency / 1e9) as u64)
+    }
+
+    /// The period between two fields at the output of the decoder of an
+    /// interlaced video track, from its DefaultDecodedFieldDuration.
+    pub fn default_decoded_field_duration(&self) -> Option<core::time::Duration> {
+        self.default_decoded_field_duration
+            .map(core::time::Duration::from_nanos)
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x41E4
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct BlockAdditionMapping {
+        // The BlockAddID of the BlockMore using the mapping
+        [0x41F0] id_value: (Option<u64>),
+        [0x41A4] id_name: (Option<String>),
+        // The registered type of the BlockAdditional data
+        [0x41E7] id_type: (u64) = 0,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
+        [0x41ED] id_extra_data: (Option<Vec<u8>>),
+    }
+}
+
+impl_ebml_master! {
     // Element ID 0xC7
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TrackTranslate {
         [0x66FC] edition_uid: (Vec<u64>) [0..],
         [0x66BF] codec: (u64),
@@ -323,8 +1554,9 @@ impl_ebml_master! {
 }
 
 impl_ebml_master! {
-    // Element ID 0xC4
+    // Element ID 0xE2
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TrackOperation {
         [0xE3] combine_planes: (Option<TrackCombinePlanes>),
         [0xE9] join_blocks: (Option<TrackJoinBlocks>),
@@ -334,6 +1566,7 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0xE3
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TrackCombinePlanes {
         [0xE4] track_planes: (Vec<TrackPlane>) [1..],
     }
@@ -342,15 +1575,69 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0xE4
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TrackPlane {
         [0xE5] uid: (u64),
         [0xE6] plane_type: (u64),
     }
 }
 
+impl TrackCombinePlanes {
+    /// The UID of the track of the plane, such as the two views of a
+    /// stereo pair.
+    pub fn track_uid(&self, plane: TrackPlaneType) -> Option<u64> {
+        self.track_planes
+            .iter()
+            .find(|p| p.plane_type == u64::from(plane))
+            .map(|p| p.uid)
+    }
+}
+
+impl TrackPlane {
+    /// The kind of the plane, `None` for values not defined by the
+    /// specification.
+    pub fn kind(&self) -> Option<TrackPlaneType> {
+        TrackPlaneType::try_from(self.plane_type).ok()
+    }
+}
+
+/// The kind of a plane combined into a video track, see
+/// [TrackPlane::plane_type].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackPlaneType {
+    LeftEye,
+    RightEye,
+    Background,
+}
+
+impl TryFrom<u64> for TrackPlaneType {
+    type Error = u64;
+
+    fn try_from(val: u64) -> Result<Self, Self::Error> {
+        match val {
+            0 => Ok(Self::LeftEye),
+            1 => Ok(Self::RightEye),
+            2 => Ok(Self::Background),
+            val => Err(val),
+        }
+    }
+}
+
+impl From<TrackPlaneType> for u64 {
+    fn from(val: TrackPlaneType) -> Self {
+        match val {
+            TrackPlaneType::LeftEye => 0,
+            TrackPlaneType::RightEye => 1,
+            TrackPlaneType::Background => 2,
+        }
+    }
+}
+
 impl_ebml_master! {
     // Element ID 0xE9
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct TrackJoinBlocks {
         [0xED] uid: (Vec<u64>) [1..],
     }
@@ -359,6 +1646,7 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0x6D80
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct ContentEncodings {
         [0x6240] content_encoding: (Vec<ContentEncoding>) [1..],
     }
@@ -367,6 +1655,7 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0x6240
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct ContentEncoding {
         [0x5031] order: (u64) = 0,
         [0x5032] scope: (u64) = 1,
@@ -379,6 +1668,7 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0x5034
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct ContentCompression {
         [0x4254] algo: (u64) = 0,
         [0x4255] settings: (Option<u64>),
@@ -389,27 +1679,208 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0x5035
     #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct ContentEncryption {
         [0x47E1] enc_algo: (u64) = 0,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
         [0x47E2] enc_key_id: (Option<Vec<u8>>),
+        [0x47E7] aes_settings: (Option<ContentEncAesSettings>),
+        [0x47E3] signature: (Option<ContentSignature>),
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
+        [0x47E4] sig_key_id: (Option<Vec<u8>>),
+        [0x47E5] sig_algo: (u64) = 0,
+        [0x47E6] sig_hash_algo: (u64) = 0,
+    }
+}
+
+impl ContentEncryption {
+    /// The encryption algorithm, `None` for values not defined by the
+    /// specification.
+    pub fn algorithm(&self) -> Option<ContentEncryptionAlgorithm> {
+        ContentEncryptionAlgorithm::try_from(self.enc_algo).ok()
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x47E7
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct ContentEncAesSettings {
+        // 1 is AES-CTR, 2 is AES-CBC
+        [0x47E8] cipher_mode: (u64),
+    }
+}
+
+/// ContentSignature Element (ID 0x47E3), a cryptographic signature of the
+/// contents. Deprecated by the specification, but still parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentSignature(
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::bytes"))] pub Vec<u8>,
+);
+
+impl<'a> EbmlParsable<'a> for ContentSignature {
+    fn try_parse(data: &'a [u8]) -> Result<Self, ErrorKind> {
+        Ok(ContentSignature(data.to_vec()))
+    }
+}
+
+/// The algorithm used to encrypt a track, see [ContentEncryption::enc_algo].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContentEncryptionAlgorithm {
+    NotEncrypted,
+    Des,
+    TripleDes,
+    Twofish,
+    Blowfish,
+    Aes,
+}
+
+impl TryFrom<u64> for ContentEncryptionAlgorithm {
+    type Error = u64;
+
+    fn try_from(val: u64) -> Result<Self, Self::Error> {
+        match val {
+            0 => Ok(Self::NotEncrypted),
+            1 => Ok(Self::Des),
+            2 => Ok(Self::TripleDes),
+            3 => Ok(Self::Twofish),
+            4 => Ok(Self::Blowfish),
+            5 => Ok(Self::Aes),
+            val => Err(val),
+        }
+    }
+}
+
+impl From<ContentEncryptionAlgorithm> for u64 {
+    fn from(val: ContentEncryptionAlgorithm) -> Self {
+        val as u64
     }
 }
 
 impl_ebml_master! {
     // Element ID 0xC6
     #[derive(Debug, Clone, PartialEq, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Audio {
         // FIXME: reimplement float_or handling
         [0xB5] sampling_frequency: (f64) = 5360.0,
-        [0x7885] output_sampling_frequency: (Option<f64>),
+        [0x78B5] output_sampling_frequency: (Option<f64>),
         [0x9F] channels: (u64),
         [0x6264] bit_depth: (Option<u64>),
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
+        [0x7D7B] channel_positions: (Option<Vec<u8>>),
     }
 }
 
+impl Audio {
+    /// The SamplingFrequency, the rate of the encoded audio.
+    pub fn input_sample_rate(&self) -> SampleRate {
+        SampleRate(self.sampling_frequency)
+    }
+
+    /// The OutputSamplingFrequency, the rate of the decoded audio, which
+    /// differs from the input one for SBR. Defaults to the input rate.
+    pub fn output_sample_rate(&self) -> SampleRate {
+        SampleRate(
+            self.output_sampling_frequency
+                .unwrap_or(self.sampling_frequency),
+        )
+    }
+
+    /// The horizontal angle of each channel, stored in ChannelPositions as
+    /// big-endian 64 bits floats.
+    ///
+    /// Returns `None` if the element is missing or its size is not a
+    /// multiple of 8 bytes.
+    pub fn positions(&self) -> Option<Vec<f64>> {
+        let data = self.channel_positions.as_ref()?;
+        if data.len() % 8 != 0 {
+            return None;
+        }
+
+        Some(
+            data.chunks(8)
+                .map(|angle| f64::from_be_bytes(angle.try_into().unwrap()))
+                .collect(),
+        )
+    }
+
+    /// The layout of the channels.
+    ///
+    /// Explicit channel positions are returned as
+    /// [AudioChannelLayout::Custom], otherwise the layout is guessed from
+    /// the number of channels.
+    pub fn layout(&self) -> Option<AudioChannelLayout> {
+        if let Some(positions) = self.positions().filter(|p| p.len() as u64 == self.channels) {
+            return Some(AudioChannelLayout::Custom(positions));
+        }
+
+        match self.channels {
+            1 => Some(AudioChannelLayout::Mono),
+            2 => Some(AudioChannelLayout::Stereo),
+            3 => Some(AudioChannelLayout::Surround),
+            4 => Some(AudioChannelLayout::Quad),
+            6 => Some(AudioChannelLayout::Surround51),
+            8 => Some(AudioChannelLayout::Surround71),
+            _ => None,
+        }
+    }
+}
+
+// `f64::round` is only available with std.
+#[cfg(feature = "std")]
+fn round(x: f64) -> f64 {
+    x.round()
+}
+
+// Rounds half away from zero like `f64::round`, for the values that fit
+// an i64, which covers the sample counts and rates.
+#[cfg(not(feature = "std"))]
+fn round(x: f64) -> f64 {
+    if x.is_nan() || x.abs() >= i64::MAX as f64 {
+        return x;
+    }
+    let t = x as i64 as f64;
+    if (x - t).abs() >= 0.5 {
+        t + x.signum()
+    } else {
+        t
+    }
+}
+
+/// A sampling frequency in Hz, see [Audio::input_sample_rate] and
+/// [Audio::output_sample_rate].
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct SampleRate(pub f64);
+
+impl SampleRate {
+    /// The rate rounded to the nearest Hz.
+    pub fn as_hz(&self) -> u32 {
+        round(self.0) as u32
+    }
+}
+
+/// The layout of the channels of an audio track, see [Audio::layout].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AudioChannelLayout {
+    Mono,
+    Stereo,
+    /// Left, right and center.
+    Surround,
+    Quad,
+    Surround51,
+    Surround71,
+    /// The horizontal angle of each channel, in degrees.
+    Custom(Vec<f64>),
+}
+
 impl_ebml_master! {
     // Element ID 0xC1
     #[derive(Debug, Clone, PartialEq, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Video {
         [0x9A] flag_interlaced: (u64) = 0,
         [0x9D] field_order: (u64) = 2,
@@ -425,15 +1896,127 @@ impl_ebml_master! {
         [0x54B0] display_width: (Option<u64>),
         [0x54BA] display_height: (Option<u64>),
         [0x54B2] display_unit: (u64) = 0,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
         [0x2EB524] colour_space: (Option<Vec<u8>>),
         [0x55B0] colour: (Option<Colour>),
-        [0x55D0] projection: (Option<Projection>),
+        [0x7670] projection: (Option<Projection>),
+    }
+}
+
+impl Video {
+    /// The colour and HDR metadata of the track, if present.
+    pub fn colour(&self) -> Option<&Colour> {
+        self.colour.as_ref()
+    }
+
+    /// The projection of a 360° or spherical video, if present.
+    pub fn projection(&self) -> Option<&Projection> {
+        self.projection.as_ref()
+    }
+
+    /// The stereo-3D layout, `None` for values not defined by the
+    /// specification.
+    pub fn stereo(&self) -> Option<StereoMode> {
+        StereoMode::try_from(self.stereo_mode).ok()
+    }
+
+    /// Whether the track holds the views of both eyes.
+    pub fn is_stereo(&self) -> bool {
+        self.stereo_mode != 0
+    }
+
+    /// Whether the BlockAdditions of the track carry an alpha channel, with
+    /// a BlockAddID of 1. The alpha plane is not a separate track.
+    pub fn has_alpha(&self) -> bool {
+        self.alpha_mode == 1
+    }
+
+    /// The area of the frames to show, as `(left, top, width, height)` in
+    /// pixels, after removing the PixelCrop borders.
+    ///
+    /// When the DisplayUnit is pixels, the DisplayWidth and DisplayHeight
+    /// override the size of the cropped area.
+    pub fn display_rect(&self) -> (u64, u64, u64, u64) {
+        let mut width = self
+            .pixel_width
+            .saturating_sub(self.pixel_crop_left.saturating_add(self.pixel_crop_right));
+        let mut height = self
+            .pixel_height
+            .saturating_sub(self.pixel_crop_top.saturating_add(self.pixel_crop_bottom));
+        if self.display_unit == 0 {
+            width = self.display_width.unwrap_or(width);
+            height = self.display_height.unwrap_or(height);
+        }
+
+        (self.pixel_crop_left, self.pixel_crop_top, width, height)
+    }
+}
+
+/// The stereo-3D layout of a video track, see [Video::stereo_mode].
+///
+/// The variants name the layout followed by the eye coming first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StereoMode {
+    Mono,
+    SideBySideLeft,
+    TopBottomRight,
+    TopBottomLeft,
+    CheckboardRight,
+    CheckboardLeft,
+    RowInterleavedRight,
+    RowInterleavedLeft,
+    ColumnInterleavedRight,
+    ColumnInterleavedLeft,
+    AnaglyphCyanRed,
+    SideBySideRight,
+    AnaglyphGreenMagenta,
+    BothEyesLacedLeft,
+    BothEyesLacedRight,
+}
+
+impl TryFrom<u64> for StereoMode {
+    type Error = u64;
+
+    fn try_from(val: u64) -> Result<Self, Self::Error> {
+        use StereoMode::*;
+
+        const MODES: [StereoMode; 15] = [
+            Mono,
+            SideBySideLeft,
+            TopBottomRight,
+            TopBottomLeft,
+            CheckboardRight,
+            CheckboardLeft,
+            RowInterleavedRight,
+            RowInterleavedLeft,
+            ColumnInterleavedRight,
+            ColumnInterleavedLeft,
+            AnaglyphCyanRed,
+            SideBySideRight,
+            AnaglyphGreenMagenta,
+            BothEyesLacedLeft,
+            BothEyesLacedRight,
+        ];
+
+        usize::try_from(val)
+            .ok()
+            .and_then(|i| MODES.get(i))
+            .copied()
+            .ok_or(val)
+    }
+}
+
+impl From<StereoMode> for u64 {
+    fn from(val: StereoMode) -> Self {
+        val as u64
     }
 }
 
 impl_ebml_master! {
     // Element ID 0x55B0
     #[derive(Debug, Clone, PartialEq, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Colour {
         [0x55B1] matrix_coefficients: (u64) = 2,
         [0x55B2] bits_per_channel: (u64) = 0,
@@ -450,12 +2033,20 @@ impl_ebml_master! {
         [0x55BD] max_fall: (Option<u64>),
         [0x55D0] mastering_metadata: (Option<MasteringMetadata>),
     }
+}
 
+impl Colour {
+    /// Whether the transfer characteristics are the ones of HDR content,
+    /// i.e. SMPTE ST 2084 (PQ) or ARIB STD-B67 (HLG).
+    pub fn is_hdr(&self) -> bool {
+        matches!(self.transfer_characteristics, 16 | 18)
+    }
 }
 
 impl_ebml_master! {
     // Element ID 0x55D0
     #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct MasteringMetadata {
         [0x55D1] primary_r_chromaticity_x: (Option<f64>),
         [0x55D2] primary_r_chromaticity_y: (Option<f64>),
@@ -473,8 +2064,10 @@ impl_ebml_master! {
 impl_ebml_master! {
     // Element ID 0x7670
     #[derive(Debug, Clone, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     struct Projection {
         [0x7671] projection_type: (u64) = 0,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
         [0x7672] projection_private: (Option<Vec<u8>>),
         // FIXME: reimplement float_or handling
         [0x7673] projection_pose_yaw: (f64) = 0.0,
@@ -483,6 +2076,398 @@ impl_ebml_master! {
     }
 }
 
+impl Projection {
+    /// The projection type, `None` for values not defined by the
+    /// specification.
+    pub fn kind(&self) -> Option<ProjectionType> {
+        ProjectionType::try_from(self.projection_type).ok()
+    }
+
+    /// The bounds stored in the ProjectionPrivate of an equirectangular
+    /// projection.
+    pub fn equirectangular_bounds(&self) -> Option<EquirectangularBounds> {
+        if self.kind() != Some(ProjectionType::Equirectangular) {
+            return None;
+        }
+
+        EquirectangularBounds::parse(self.projection_private.as_deref()?)
+    }
+}
+
+/// The type of a [Projection].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ProjectionType {
+    Rectangular,
+    Equirectangular,
+    Cubemap,
+    Mesh,
+}
+
+impl TryFrom<u64> for ProjectionType {
+    type Error = u64;
+
+    fn try_from(val: u64) -> Result<Self, Self::Error> {
+        match val {
+            0 => Ok(Self::Rectangular),
+            1 => Ok(Self::Equirectangular),
+            2 => Ok(Self::Cubemap),
+            3 => Ok(Self::Mesh),
+            val => Err(val),
+        }
+    }
+}
+
+impl From<ProjectionType> for u64 {
+    fn from(val: ProjectionType) -> Self {
+        val as u64
+    }
+}
+
+/// The part of the sphere covered by an equirectangular projection.
+///
+/// The bounds are 0.32 fixed point fractions of the frame size, cropped
+/// from each edge, see the [Spherical Video V2] specification.
+///
+/// [Spherical Video V2]: https://github.com/google/spatial-media/blob/master/docs/spherical-video-v2-rfc.md
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EquirectangularBounds {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl EquirectangularBounds {
+    // The payload of an `equi` box: version and flags, then the bounds
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() != 20 || data[0] != 0 {
+            return None;
+        }
+
+        let bound = |i: usize| u32::from_be_bytes(data[i..i + 4].try_into().unwrap());
+        Some(EquirectangularBounds {
+            top: bound(4),
+            bottom: bound(8),
+            left: bound(12),
+            right: bound(16),
+        })
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x1C53BB6B
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Cues {
+        [0xBB] cue_points: (Vec<CuePoint>) [1..],
+    }
+}
+
+impl Cues {
+    /// Finds the last Cue Point of `track` at or before `timestamp`,
+    /// expressed in Segment Ticks.
+    ///
+    /// Falls back to the first Cue Point of the track if `timestamp`
+    /// comes before all of them. The Cue Points are expected to be
+    /// sorted by time, as mandated by the specification.
+    pub fn lookup(&self, track: u64, timestamp: u64) -> Option<(&CuePoint, &CueTrackPositions)> {
+        let positions = |cp| CuePoint::positions(cp, track).map(|p| (cp, p));
+
+        let end = self.cue_points.partition_point(|cp| cp.time <= timestamp);
+
+        self.cue_points[..end]
+            .iter()
+            .rev()
+            .find_map(positions)
+            .or_else(|| self.cue_points[end..].iter().find_map(positions))
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0xBB
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct CuePoint {
+        [0xB3] time: (u64),
+        [0xB7] track_positions: (Vec<CueTrackPositions>) [1..],
+    }
+}
+
+impl CuePoint {
+    /// The positions of the Cue Point for `track`, if it has any.
+    pub fn positions(&self, track: u64) -> Option<&CueTrackPositions> {
+        self.track_positions.iter().find(|p| p.track == track)
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0xB7
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct CueTrackPositions {
+        [0xF7] track: (u64),
+        // Position of the Cluster, relative to the start of the Segment data
+        [0xF1] cluster_position: (u64),
+        // Position of the Block, relative to the start of the Cluster data
+        [0xF0] relative_position: (Option<u64>),
+        [0xB2] duration: (Option<u64>),
+        [0x5378] block_number: (Option<u64>),
+        [0xEA] codec_state: (u64) = 0,
+        [0xDB] references: (Vec<CueReference>) [0..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0xDB
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct CueReference {
+        [0x96] time: (u64),
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x1941A469
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Attachments {
+        [0x61A7] files: (Vec<AttachedFile>) [1..],
+    }
+}
+
+impl Attachments {
+    /// Finds the attached file with the given UID.
+    pub fn get(&self, uid: u64) -> Option<&AttachedFile> {
+        self.files.iter().find(|file| file.uid == uid)
+    }
+
+    /// Finds the attached file with the given name, ignoring the case.
+    pub fn get_by_name(&self, name: &str) -> Option<&AttachedFile> {
+        let name = name.to_lowercase();
+        self.files
+            .iter()
+            .find(|file| file.name.to_lowercase() == name)
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x61A7
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct AttachedFile {
+        [0x467E] description: (Option<String>),
+        [0x466E] name: (String),
+        [0x4660] media_type: (String),
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::bytes"))]
+        [0x465C] data: (Vec<u8>),
+        [0x46AE] uid: (u64),
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x1043A770
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Chapters {
+        [0x45B9] editions: (Vec<EditionEntry>) [1..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x45B9
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct EditionEntry {
+        [0x45BC] uid: (Option<u64>),
+        [0x45BD] flag_hidden: (bool) = false,
+        [0x45DB] flag_default: (bool) = false,
+        // The chapters are played in their order, skipping the parts of
+        // the Segment outside of them
+        [0x45DD] flag_ordered: (bool) = false,
+        [0xB6] chapters: (Vec<ChapterAtom>) [1..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0xB6
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct ChapterAtom {
+        [0x73C4] uid: (u64),
+        [0x5654] string_uid: (Option<String>),
+        // Timestamps in nanoseconds, not scaled by the TimestampScale
+        [0x91] time_start: (u64),
+        [0x92] time_end: (Option<u64>),
+        // Hidden from the user along with the children
+        [0x98] flag_hidden: (bool) = false,
+        // A disabled chapter is skipped
+        [0x4598] flag_enabled: (bool) = true,
+        [0x6E67] segment_uuid: (Option<Uuid>),
+        [0x6EBC] segment_edition_uid: (Option<u64>),
+        [0x63C3] physical_equiv: (Option<u64>),
+        [0x8F] track: (Option<ChapterTrack>),
+        [0x80] displays: (Vec<ChapterDisplay>) [0..],
+        [0xB6] chapters: (Vec<ChapterAtom>) [0..],
+    }
+}
+
+impl EditionEntry {
+    /// The chapters to show in a chapter list, without the hidden ones
+    /// and their children.
+    pub fn visible_chapters(&self) -> impl Iterator<Item = &ChapterAtom> {
+        self.chapters.iter().filter(|chapter| !chapter.flag_hidden)
+    }
+}
+
+impl ChapterAtom {
+    /// The title of the chapter in `language`, or in the first language
+    /// available if there is no translation for it.
+    pub fn title(&self, language: &str) -> Option<&str> {
+        self.displays
+            .iter()
+            .find(|display| display.has_language(language))
+            .or_else(|| self.displays.first())
+            .map(|display| display.string.as_str())
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x8F
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct ChapterTrack {
+        [0x89] track_uid: (Vec<u64>) [1..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x80
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct ChapterDisplay {
+        [0x85] string: (String),
+        // An empty list means "eng"
+        [0x437C] language: (Vec<String>) [0..],
+        [0x437D] language_bcp47: (Vec<String>) [0..],
+        [0x437E] country: (Vec<String>) [0..],
+    }
+}
+
+impl ChapterDisplay {
+    /// Checks if the display is in `language`, given as an ISO 639-2
+    /// or a BCP 47 code.
+    pub fn has_language(&self, language: &str) -> bool {
+        if self.language.is_empty() && language == "eng" {
+            return true;
+        }
+
+        self.language
+            .iter()
+            .chain(&self.language_bcp47)
+            .any(|l| l == language)
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x1254C367
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Tags {
+        [0x7373] tags: (Vec<Tag>) [1..],
+    }
+}
+
+impl Tags {
+    /// Finds the SimpleTags named `name`, ignoring case, at any nesting level.
+    pub fn find(&self, name: &str) -> Vec<&SimpleTag> {
+        let mut found = Vec::new();
+        for tag in &self.tags {
+            for simple_tag in &tag.simple_tags {
+                simple_tag.find_into(name, &mut found);
+            }
+        }
+
+        found
+    }
+
+    /// The Tags targeting the track of UID `uid`.
+    ///
+    /// The Tags without a TagTrackUID apply to the whole Segment and
+    /// are not returned.
+    pub fn for_track(&self, uid: u64) -> Vec<&Tag> {
+        self.tags
+            .iter()
+            .filter(|tag| tag.targets.track_uid.contains(&uid))
+            .collect()
+    }
+
+    /// The Tags targeting the chapter of UID `uid`.
+    ///
+    /// The Tags without a TagChapterUID apply to the whole Segment and
+    /// are not returned.
+    pub fn for_chapter(&self, uid: u64) -> Vec<&Tag> {
+        self.tags
+            .iter()
+            .filter(|tag| tag.targets.chapter_uid.contains(&uid))
+            .collect()
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x7373
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Tag {
+        [0x63C0] targets: (Targets),
+        [0x67C8] simple_tags: (Vec<SimpleTag>) [1..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x63C0
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Targets {
+        [0x68CA] target_type_value: (u64) = 50,
+        [0x63CA] target_type: (Option<String>),
+        [0x63C5] track_uid: (Vec<u64>) [0..],
+        [0x63C9] edition_uid: (Vec<u64>) [0..],
+        [0x63C4] chapter_uid: (Vec<u64>) [0..],
+        [0x63C6] attachment_uid: (Vec<u64>) [0..],
+    }
+}
+
+impl_ebml_master! {
+    // Element ID 0x67C8
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct SimpleTag {
+        [0x45A3] name: (String),
+        [0x447A] language: (String) = String::from("und"),
+        [0x447B] language_bcp47: (Option<String>),
+        [0x4484] default: (u64) = 1,
+        // Only one of string and binary is set
+        [0x4487] string: (Option<String>),
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::opt_bytes"))]
+        [0x4485] binary: (Option<Vec<u8>>),
+        [0x67C8] simple_tags: (Vec<SimpleTag>) [0..],
+    }
+}
+
+impl SimpleTag {
+    fn find_into<'a>(&'a self, name: &str, found: &mut Vec<&'a SimpleTag>) {
+        if self.name.eq_ignore_ascii_case(name) {
+            found.push(self);
+        }
+
+        for simple_tag in &self.simple_tags {
+            simple_tag.find_into(name, found);
+        }
+    }
+}
+
 #[cfg(test)]
 #[allow(non_upper_case_globals)]
 mod tests {
@@ -507,6 +2492,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cluster_block_order() {
+        use crate::serializer::ebml::encode_element;
+
+        let block = |track: u8, timestamp: u8| [0x80 | track, 0, timestamp, 0x80, track];
+        let mut data = encode_element(0xE7, &[10]).unwrap();
+        data.extend(encode_element(0xA3, &block(1, 0)).unwrap());
+        let group = encode_element(0xA1, &block(2, 5)).unwrap();
+        data.extend(encode_element(0xA0, &group).unwrap());
+        data.extend(encode_element(0xA3, &block(1, 10)).unwrap());
+        let data = encode_element(0x1F43B675, &data).unwrap();
+
+        let Ok((_, SegmentElement::Cluster(mut cluster))) = segment_element(&data) else {
+            panic!("not a Cluster");
+        };
+        let blocks = |cluster: &Cluster| {
+            cluster
+                .blocks()
+                .map(|b| (b.track_number(), b.timestamp()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(blocks(&cluster), [(1, 0), (2, 5), (1, 10)]);
+
+        // Without the order, the SimpleBlocks come first
+        let mut unordered = cluster.clone();
+        unordered.block_order.clear();
+        assert_eq!(blocks(&unordered), [(1, 0), (1, 10), (2, 5)]);
+        assert_ne!(unordered, cluster);
+
+        cluster.retain_tracks(|track| track == 2);
+        assert_eq!(blocks(&cluster), [(2, 5)]);
+        assert_eq!(cluster.block_order, [BlockKind::BlockGroup]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let mut input = &mkv[59..];
+        let mut count = 0;
+
+        while let Ok((i, element)) = segment_element(input) {
+            let json = serde_json::to_string(&element).unwrap();
+            let parsed: SegmentElement = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, element);
+
+            input = i;
+            count += 1;
+        }
+        assert!(count > 3);
+
+        let info = Info {
+            segment_uid: Some(Uuid::from_u128(0xed157223369df02dcf5076a5fea70034)),
+            date_utc: Some(EbmlDate(86_400_000_000_000)),
+            ..Info::default()
+        };
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["segment_uid"], "ed157223-369d-f02d-cf50-76a5fea70034");
+        assert_eq!(json["date_utc"], "2001-01-02T00:00:00Z");
+        assert_eq!(serde_json::from_value::<Info>(json).unwrap(), info);
+
+        let block = SimpleBlock {
+            track_number: 1,
+            timestamp: 0,
+            keyframe: true,
+            invisible: false,
+            lacing: Lacing::None,
+            discardable: false,
+            data: Cow::Borrowed(b"frame"),
+        };
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["data"], "ZnJhbWU=");
+        assert_eq!(serde_json::from_value::<SimpleBlock>(json).unwrap(), block);
+    }
+
     #[test]
     fn mkv_segment_elements() {
         let mut index: usize = 59;
@@ -539,6 +2598,273 @@ mod tests {
         }
     }
 
+    #[test]
+    fn date_utc() {
+        let mut info = Info::default();
+        assert_eq!(info.date_utc_raw(), None);
+        assert_eq!(info.date_utc_as_system_time(), None);
+
+        // 2010-08-21T07:23:03.5 UTC
+        info.date_utc = Some(EbmlDate(304_068_183_500_000_000));
+        assert_eq!(info.date_utc_raw(), Some(304_068_183_500_000_000));
+        let time = info.date_utc_as_system_time().unwrap();
+        let since_epoch = time.duration_since(std::time::UNIX_EPOCH).unwrap();
+        assert_eq!(since_epoch.as_millis(), 1_282_375_383_500);
+
+        info.date_utc = Some(EbmlDate(-1_000_000_000));
+        let time = info.date_utc_as_system_time().unwrap();
+        let since_epoch = time.duration_since(std::time::UNIX_EPOCH).unwrap();
+        assert_eq!(since_epoch.as_secs(), 978_307_199);
+
+        #[cfg(feature = "time")]
+        {
+            let date = info.date_utc_as_offset_date_time().unwrap();
+            assert_eq!(date.unix_timestamp(), 978_307_199);
+            assert_eq!(date.year(), 2000);
+        }
+    }
+
+    #[test]
+    fn timestamps_ns() {
+        let mut cluster = Cluster {
+            timestamp: 1042,
+            silent_tracks: None,
+            position: None,
+            prev_size: None,
+            simple_block: Vec::new(),
+            block_group: Vec::new(),
+            block_order: Vec::new(),
+        };
+        assert_eq!(cluster.timestamp_ns(1_000_000), 1_042_000_000);
+
+        // A 48 bits timestamp overflows u64 once scaled
+        cluster.timestamp = (1 << 48) - 1;
+        assert_eq!(cluster.timestamp_ns(1_000_000), u64::MAX);
+        assert_eq!(cluster.timestamp_ns(1), (1 << 48) - 1);
+
+        assert_eq!(cluster.block_timestamp(-10), (1 << 48) - 11);
+        cluster.timestamp = u64::MAX - 1;
+        assert_eq!(cluster.block_timestamp(10), u64::MAX);
+        assert_eq!(cluster.block_timestamp_ns(-10, 1), u64::MAX - 11);
+        assert_eq!(cluster.block_timestamp_ns(-10, 1_000_000), u64::MAX);
+        cluster.timestamp = 5;
+        assert_eq!(cluster.block_timestamp(-10), 0);
+
+        // track 1, timestamp -10
+        let (_, mut block) = simple_block(&[0x81, 0xFF, 0xF6, 0x80, 0x00]).unwrap();
+        assert_eq!(
+            block.absolute_timestamp_ns(1_042_000_000, 1_000_000),
+            1_032_000_000
+        );
+        assert_eq!(block.absolute_timestamp_ns(5_000_000, 1_000_000), 0);
+        block.timestamp = i16::MAX;
+        assert_eq!(
+            block.absolute_timestamp_ns(u64::MAX - 1, 1_000_000),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn negative_block_timestamp() {
+        // track 1, timestamp -500, keyframe
+        let data = [0x81, 0xFE, 0x0C, 0x80, 0x00];
+        let (_, simple) = simple_block(&data).unwrap();
+        assert_eq!(simple.timestamp, -500);
+        let (_, block) = block(&data).unwrap();
+        assert_eq!(block.timestamp, -500);
+
+        // The block is decoded half a second before its Cluster
+        assert_eq!(
+            simple.absolute_timestamp_ns(1_000_000_000, 1_000_000),
+            500_000_000
+        );
+        assert_eq!(simple.absolute_timestamp_ns(1_000_000_000, 1), 999_999_500);
+    }
+
+    #[test]
+    fn block_at_timestamp() {
+        let block = |timestamp: i16| {
+            let [high, low] = timestamp.to_be_bytes();
+            let data = vec![0x81, high, low, 0x80, timestamp as u8];
+            simple_block(&data).unwrap().1.into_owned()
+        };
+        let mut cluster = Cluster {
+            timestamp: 1000,
+            silent_tracks: None,
+            position: None,
+            prev_size: None,
+            simple_block: [-20, 0, 0, 40, 80].into_iter().map(block).collect(),
+            block_group: Vec::new(),
+            block_order: Vec::new(),
+        };
+        assert!(cluster.is_timestamp_sorted());
+        assert_eq!(cluster.block_at_timestamp(-20).unwrap().timestamp, -20);
+        assert_eq!(
+            cluster.block_at_timestamp(0),
+            Some(&cluster.simple_block[1])
+        );
+        assert_eq!(cluster.block_at_timestamp(80).unwrap().timestamp, 80);
+        assert_eq!(cluster.block_at_timestamp(20), None);
+        assert_eq!(cluster.block_at_timestamp(100), None);
+
+        cluster.simple_block.swap(0, 4);
+        assert!(!cluster.is_timestamp_sorted());
+    }
+
+    #[test]
+    fn simple_block_lacing() {
+        // track 1, timestamp 0, keyframe, Xiph lacing with frames of 2, 300 and 1 bytes
+        let mut data = vec![0x81, 0x00, 0x00, 0x82, 0x02, 0x02, 0xFF, 0x2D];
+        data.extend([1; 2]);
+        data.extend([2; 300]);
+        data.extend([3; 1]);
+
+        let (_, block) = simple_block(&data).unwrap();
+        assert_eq!(block.track_number, 1);
+        assert!(block.keyframe);
+        assert_eq!(block.lacing, Lacing::Xiph);
+        assert_eq!(
+            block.frames().unwrap(),
+            vec![&[1; 2][..], &[2; 300][..], &[3; 1][..]]
+        );
+
+        // EBML lacing with frames of 800, 500 and 1000 bytes
+        let mut data = vec![0x81, 0x00, 0x00, 0x86, 0x02, 0x43, 0x20, 0x5E, 0xD3];
+        data.extend([1; 800]);
+        data.extend([2; 500]);
+        data.extend([3; 1000]);
+
+        let (_, block) = simple_block(&data).unwrap();
+        assert_eq!(block.lacing, Lacing::EBML);
+        assert_eq!(
+            block.frames().unwrap(),
+            vec![&[1; 800][..], &[2; 500][..], &[3; 1000][..]]
+        );
+
+        // Fixed-size lacing with 4 frames of 3 bytes
+        let mut data = vec![0x81, 0x00, 0x00, 0x84, 0x03];
+        data.extend([7; 12]);
+
+        let (_, block) = simple_block(&data).unwrap();
+        assert_eq!(block.lacing, Lacing::FixedSize);
+        assert_eq!(block.frames().unwrap(), vec![&[7; 3][..]; 4]);
+
+        // No lacing
+        let data = [0x82, 0xFF, 0xFE, 0x00, 0xAB, 0xCD];
+        let (_, block) = simple_block(&data).unwrap();
+        assert_eq!(block.track_number, 2);
+        assert_eq!(block.timestamp, -2);
+        assert!(!block.keyframe);
+        assert_eq!(block.frames().unwrap(), vec![&[0xAB, 0xCD][..]]);
+    }
+
+    #[test]
+    fn blocks_borrow_input() {
+        let data = include_bytes!("../assets/single_stream.mkv");
+        let (i, _) = crate::ebml::ebml_header(data).unwrap();
+        let (mut i, _) = segment(i).unwrap();
+        let cluster = loop {
+            match segment_element(i).unwrap() {
+                (_, SegmentElement::Cluster(cluster)) => break cluster,
+                (rest, _) => i = rest,
+            }
+        };
+
+        let input = data.as_ptr_range();
+        for block in &cluster.simple_block {
+            assert!(matches!(block.data, Cow::Borrowed(_)));
+            for frame in block.frames().unwrap() {
+                assert!(input.contains(&frame.as_ptr()));
+            }
+        }
+
+        let owned = cluster.simple_block[0].clone().into_owned();
+        assert!(matches!(owned.data, Cow::Owned(_)));
+        assert_eq!(owned, cluster.simple_block[0]);
+    }
+
+    #[test]
+    fn simple_block_lacing_errors() {
+        let frame_sizes = |data: &[u8]| {
+            let (_, block) = simple_block(data).unwrap();
+            block
+                .frames()
+                .map(|frames| frames.iter().map(|f| f.len()).collect::<Vec<_>>())
+        };
+
+        // EBML lacing with frames of 500, 300 and 400 bytes, the deltas
+        // are -200 and +100
+        let mut data = vec![
+            0x81, 0x00, 0x00, 0x86, 0x03, 0x41, 0xF4, 0x5F, 0x37, 0x60, 0x63,
+        ];
+        data.extend([1; 500]);
+        data.extend([2; 300]);
+        data.extend([3; 400]);
+        data.extend([4; 10]);
+        assert_eq!(frame_sizes(&data).unwrap(), vec![500, 300, 400, 10]);
+
+        // EBML lacing with a delta making the second frame size negative
+        let data = [0x81, 0x00, 0x00, 0x86, 0x02, 0x8A, 0xAB, 0x00, 0x00];
+        assert!(frame_sizes(&data).is_err());
+
+        // Xiph lacing where the size bytes never end
+        let data = [0x81, 0x00, 0x00, 0x82, 0x01, 0xFF, 0xFF, 0xFF];
+        assert!(frame_sizes(&data).is_err());
+
+        // Xiph lacing with a first frame of 265 bytes in a 20 bytes payload
+        let mut data = vec![0x81, 0x00, 0x00, 0x82, 0x01, 0xFF, 0x0A];
+        data.extend([0; 20]);
+        assert!(frame_sizes(&data).is_err());
+
+        // Fixed-size lacing of 3 frames over 10 bytes
+        let mut data = vec![0x81, 0x00, 0x00, 0x84, 0x02];
+        data.extend([0; 10]);
+        assert!(frame_sizes(&data).is_err());
+
+        // Lacing without the frame count
+        let data = [0x81, 0x00, 0x00, 0x84];
+        assert!(frame_sizes(&data).is_err());
+
+        let err = simple_block(&[0x81, 0x00, 0x00, 0x84])
+            .unwrap()
+            .1
+            .frames()
+            .unwrap_err();
+        assert_eq!(err.id, 0xA3);
+        assert_eq!(err.kind, ErrorKind::InvalidLacing);
+    }
+
+    #[test]
+    fn laced_cluster_frames() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets")
+            .join("matroska_test_w1_1")
+            .join("test5.mkv");
+        let data = std::fs::read(path).expect("can read file");
+
+        let (i, _) = crate::ebml::ebml_header(&data).unwrap();
+        let (mut i, _) = segment(i).unwrap();
+
+        let mut laced = 0;
+        while let Ok((rest, element)) = segment_element(i) {
+            if let SegmentElement::Cluster(c) = element {
+                for block in c.simple_block.iter() {
+                    let frames = block.frames().unwrap();
+                    let total: usize = frames.iter().map(|f| f.len()).sum();
+                    assert!(total <= block.data.len());
+
+                    if block.lacing != Lacing::None {
+                        assert!(frames.len() > 1);
+                        laced += 1;
+                    }
+                }
+            }
+            i = rest;
+        }
+
+        assert!(laced > 0);
+    }
+
     #[test]
     fn webm_segment_root() {
         let res = segment(&webm[40..100]);
@@ -582,4 +2908,577 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn silent_tracks() {
+        let data = [
+            0x1F, 0x43, 0xB6, 0x75, 0x8E, 0xE7, 0x81, 0x00, 0x58, 0x54, 0x88, 0x58, 0xD7, 0x81,
+            0x02, 0x58, 0xD7, 0x81, 0x03,
+        ];
+        let cluster = match segment_element(&data) {
+            Ok((_, SegmentElement::Cluster(cluster))) => cluster,
+            e => panic!("expected a Cluster, got {e:?}"),
+        };
+
+        assert_eq!(
+            cluster.silent_tracks.as_ref().unwrap().silent_track_number,
+            [2, 3]
+        );
+        assert!(cluster.track_is_silent(2));
+        assert!(cluster.track_is_silent(3));
+        assert!(!cluster.track_is_silent(1));
+
+        let (_, cluster) =
+            segment_element(&[0x1F, 0x43, 0xB6, 0x75, 0x83, 0xE7, 0x81, 0x00]).unwrap();
+        let SegmentElement::Cluster(cluster) = cluster else {
+            panic!("expected a Cluster");
+        };
+        assert!(!cluster.track_is_silent(2));
+    }
+
+    #[test]
+    fn block_group() {
+        let data = [
+            0xA0, 0xA7, 0xA1, 0x85, 0x81, 0x00, 0x0A, 0x00, b'x', 0x9B, 0x81, 0x28, 0xFB, 0x81,
+            0xEC, 0xFB, 0x82, 0xFF, 0x9C, 0x75, 0xA1, 0x8A, 0xA6, 0x88, 0xEE, 0x81, 0x02, 0xA5,
+            0x83, b'a', b'd', b'd', 0x75, 0xA2, 0x81, 0x05, 0xA4, 0x83, 0x01, 0x02, 0x03,
+        ];
+        let (_, group) = crate::ebml::ebml_element::<BlockGroup>(0xA0)(&data).unwrap();
+
+        assert_eq!(group.block.track_number, 1);
+        assert_eq!(group.block.timestamp, 10);
+        assert_eq!(group.block.frames().unwrap(), vec![b"x"]);
+        assert_eq!(group.block_duration, Some(40));
+        assert_eq!(group.reference_block, vec![-20, -100]);
+        assert_eq!(group.discard_padding, Some(5));
+        assert_eq!(group.codec_state.as_deref(), Some(&[1, 2, 3][..]));
+        assert!(group.has_codec_state());
+
+        let additions = group.block_additions().unwrap();
+        assert_eq!(additions.block_more.len(), 1);
+        assert_eq!(additions.block_more[0].block_add_id, 2);
+        assert_eq!(additions.get(2), Some(&b"add"[..]));
+        assert_eq!(additions.get(1), None);
+        assert_eq!(group.dolby_vision_rpu(), None);
+
+        let mut group = group.clone();
+        group.block_additions.as_mut().unwrap().block_more[0].block_add_id = 1;
+        assert_eq!(group.dolby_vision_rpu(), Some(&b"add"[..]));
+        group.block_additions = None;
+        assert_eq!(group.dolby_vision_rpu(), None);
+        group.codec_state = None;
+        assert!(!group.has_codec_state());
+    }
+
+    #[test]
+    fn audio_layout() {
+        let mut audio = Audio {
+            channels: 6,
+            ..Default::default()
+        };
+        assert_eq!(audio.positions(), None);
+        assert_eq!(audio.layout(), Some(AudioChannelLayout::Surround51));
+
+        let angles = [-30.0, 30.0];
+        audio.channel_positions = Some(angles.iter().flat_map(|a: &f64| a.to_be_bytes()).collect());
+        audio.channels = 2;
+        assert_eq!(audio.positions().as_deref(), Some(&angles[..]));
+        assert_eq!(
+            audio.layout(),
+            Some(AudioChannelLayout::Custom(angles.to_vec()))
+        );
+
+        // Positions not matching the number of channels are ignored
+        audio.channels = 8;
+        assert_eq!(audio.layout(), Some(AudioChannelLayout::Surround71));
+        audio.channel_positions = Some(vec![0; 7]);
+        assert_eq!(audio.positions(), None);
+        audio.channels = 5;
+        assert_eq!(audio.layout(), None);
+    }
+
+    #[test]
+    fn sample_rates() {
+        // HE-AAC, the SBR doubling the rate of the AAC core
+        let mut audio = Audio {
+            sampling_frequency: 24000.0,
+            output_sampling_frequency: Some(48000.0),
+            ..Default::default()
+        };
+        assert_eq!(audio.input_sample_rate(), SampleRate(24000.0));
+        assert_eq!(audio.output_sample_rate().as_hz(), 48000);
+
+        audio.output_sampling_frequency = None;
+        audio.sampling_frequency = 44099.6;
+        assert_eq!(audio.output_sample_rate(), audio.input_sample_rate());
+        assert_eq!(audio.output_sample_rate().as_hz(), 44100);
+
+        // SamplingFrequency, OutputSamplingFrequency and Channels
+        let mut data = vec![0xE1, 0x98, 0xB5, 0x88];
+        data.extend_from_slice(&24000f64.to_be_bytes());
+        data.extend_from_slice(&[0x78, 0xB5, 0x88]);
+        data.extend_from_slice(&48000f64.to_be_bytes());
+        data.extend_from_slice(&[0x9F, 0x81, 0x02]);
+        let (rest, audio) = crate::ebml::ebml_element::<Audio>(0xE1)(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(audio.sampling_frequency, 24000.0);
+        assert_eq!(audio.output_sampling_frequency, Some(48000.0));
+        assert_eq!(audio.channels, 2);
+    }
+
+    #[test]
+    fn parsed_codec_private() {
+        let mut track = TrackEntry {
+            codec_id: String::from("V_MPEG4/ISO/AVC"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            track.parsed_codec_private(),
+            Err(crate::Error::InvalidCodecPrivate)
+        ));
+
+        track.codec_private = Some(vec![1, 0x4D, 0x40, 0x1F, 0xFF, 0xE0, 0]);
+        match track.parsed_codec_private() {
+            Ok(CodecPrivate::Avc(config)) => assert_eq!(config.profile, 77),
+            res => panic!("unexpected {res:?}"),
+        }
+
+        track.codec_id = String::from("V_AV1");
+        assert!(matches!(
+            track.parsed_codec_private(),
+            Err(crate::Error::InvalidCodecPrivate)
+        ));
+
+        assert!(matches!(
+            track.opus_head(),
+            Err(crate::Error::InvalidCodecPrivate)
+        ));
+
+        track.codec_id = String::from("A_OPUS");
+        track.codec_private = Some(b"OpusHead\x01\x02\x38\x01\x80\xbb\0\0\0\0\0".to_vec());
+        let head = track.opus_head().unwrap();
+        assert_eq!((head.channel_count, head.pre_skip), (2, 312));
+
+        track.codec_id = String::from("V_VP9");
+        assert!(
+            matches!(track.parsed_codec_private(), Err(crate::Error::UnsupportedCodec(id)) if id == "V_VP9")
+        );
+    }
+
+    #[test]
+    fn seek_element_ids() {
+        let seek_head = SeekHead {
+            positions: vec![
+                Seek {
+                    id: [0x15, 0x49, 0xA9, 0x66],
+                    position: 100,
+                },
+                Seek {
+                    id: [0x1F, 0x43, 0xB6, 0x75],
+                    position: 200,
+                },
+                Seek {
+                    id: [0x1F, 0x43, 0xB6, 0x75],
+                    position: 300,
+                },
+            ],
+        };
+
+        assert_eq!(seek_head.positions[0].element_id(), ElementId::INFO);
+        assert_eq!(
+            seek_head.position_for_element(ElementId::CLUSTER),
+            Some(200)
+        );
+        assert_eq!(seek_head.position_for_element(ElementId::CUES), None);
+
+        assert_eq!(ElementId::INFO.name(), "Info");
+        assert_eq!(ElementId(0x73C5).name(), "TrackUID");
+        assert_eq!(ElementId(0x4487).name(), "TagString");
+        assert_eq!(ElementId(0x1234).name(), "Unknown");
+    }
+
+    #[test]
+    fn accessibility_flags() {
+        // Forced subtitles for the hearing impaired
+        let mut data = vec![
+            0xAE, 0x99, 0xD7, 0x81, 0x03, 0x73, 0xC5, 0x81, 0x03, 0x83, 0x81, 0x11, 0x86, 0x85,
+        ];
+        data.extend_from_slice(b"S_ASS");
+        data.extend([0x55, 0xAA, 0x81, 0x01, 0x55, 0xAB, 0x81, 0x01]);
+        let (_, track) = crate::ebml::ebml_element::<TrackEntry>(0xAE)(&data).unwrap();
+
+        assert!(track.flag_forced);
+        assert!(track.flag_hearing_impaired);
+        assert!(!track.flag_visual_impaired);
+        assert!(!track.flag_text_descriptions);
+        assert!(!track.flag_original);
+        assert!(!track.flag_commentary);
+    }
+
+    #[test]
+    fn default_duration_helpers() {
+        let mut track = TrackEntry {
+            default_duration: Some(41_708_333),
+            video: Some(Video::default()),
+            ..Default::default()
+        };
+        assert!((track.frame_rate().unwrap() - 23.976).abs() < 0.001);
+        assert_eq!(track.samples_per_frame(), None);
+
+        track.default_duration = Some(0);
+        assert_eq!(track.frame_rate(), None);
+
+        assert_eq!(track.default_decoded_field_duration(), None);
+        track.default_decoded_field_duration = Some(20_854_166);
+        assert_eq!(
+            track.default_decoded_field_duration(),
+            Some(core::time::Duration::from_nanos(20_854_166))
+        );
+
+        // 20 ms Opus frames at 48 kHz
+        let track = TrackEntry {
+            default_duration: Some(20_000_000),
+            audio: Some(Audio {
+                sampling_frequency: 48000.0,
+                channels: 2,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(track.samples_per_frame(), Some(960));
+        assert_eq!(track.frame_rate(), None);
+    }
+
+    #[test]
+    fn stereo_and_alpha() {
+        let mut track = TrackEntry {
+            track_uid: 42,
+            video: Some(Video::default()),
+            ..Default::default()
+        };
+        let video = track.video.as_mut().unwrap();
+        assert!(!video.is_stereo());
+        assert_eq!(video.stereo(), Some(StereoMode::Mono));
+
+        video.stereo_mode = 11;
+        assert!(video.is_stereo());
+        assert_eq!(video.stereo(), Some(StereoMode::SideBySideRight));
+        video.stereo_mode = 15;
+        assert_eq!(video.stereo(), None);
+        assert_eq!(u64::from(StereoMode::BothEyesLacedRight), 14);
+
+        assert!(!video.has_alpha());
+        assert_eq!(track.alpha_plane_track_uid(), None);
+        track.video.as_mut().unwrap().alpha_mode = 1;
+        assert!(track.video.as_ref().unwrap().has_alpha());
+        assert_eq!(track.alpha_plane_track_uid(), Some(42));
+    }
+
+    #[test]
+    fn display_rect() {
+        // 1080p encoded as 1920x1088
+        let mut video = Video {
+            pixel_width: 1920,
+            pixel_height: 1088,
+            pixel_crop_bottom: 8,
+            ..Default::default()
+        };
+        assert_eq!(video.display_rect(), (0, 0, 1920, 1080));
+
+        video.pixel_crop_left = 240;
+        video.pixel_crop_right = 240;
+        assert_eq!(video.display_rect(), (240, 0, 1440, 1080));
+
+        video.display_width = Some(1920);
+        assert_eq!(video.display_rect(), (240, 0, 1920, 1080));
+        // The display size is an aspect ratio for the other units
+        video.display_unit = 3;
+        video.display_height = Some(1080);
+        assert_eq!(video.display_rect(), (240, 0, 1440, 1080));
+    }
+
+    #[test]
+    fn track_operation() {
+        // A virtual track combining the left eye from track UID 1 and the
+        // right eye from track UID 2
+        let data = [
+            0xAE, 0xA5, 0xD7, 0x81, 0x03, 0x73, 0xC5, 0x81, 0x03, 0x83, 0x81, 0x01, 0x86, 0x85,
+            0x56, 0x5F, 0x4D, 0x56, 0x43, 0xE2, 0x92, 0xE3, 0x90, 0xE4, 0x86, 0xE5, 0x81, 0x01,
+            0xE6, 0x81, 0x00, 0xE4, 0x86, 0xE5, 0x81, 0x02, 0xE6, 0x81, 0x01,
+        ];
+        let (_, track) = crate::ebml::ebml_element::<TrackEntry>(0xAE)(&data).unwrap();
+        assert_eq!(track.track_number, 3);
+
+        let planes = track
+            .track_operation()
+            .and_then(|op| op.combine_planes.as_ref())
+            .unwrap();
+        assert_eq!(planes.track_planes.len(), 2);
+        assert_eq!(
+            planes.track_planes[1].kind(),
+            Some(TrackPlaneType::RightEye)
+        );
+        assert_eq!(planes.track_uid(TrackPlaneType::LeftEye), Some(1));
+        assert_eq!(planes.track_uid(TrackPlaneType::RightEye), Some(2));
+        assert_eq!(planes.track_uid(TrackPlaneType::Background), None);
+
+        assert_eq!(TrackEntry::default().track_operation(), None);
+    }
+
+    #[test]
+    fn effective_language() {
+        // Portuguese subtitles, with and without the region
+        let data = [
+            0xAE, 0xA2, 0xD7, 0x81, 0x03, 0x73, 0xC5, 0x81, 0x03, 0x83, 0x81, 0x11, 0x86, 0x86,
+            0x53, 0x5F, 0x54, 0x45, 0x58, 0x54, 0x22, 0xB5, 0x9C, 0x83, 0x70, 0x6F, 0x72, 0x22,
+            0xB5, 0x9D, 0x85, 0x70, 0x74, 0x2D, 0x42, 0x52,
+        ];
+        let (_, mut track) = crate::ebml::ebml_element::<TrackEntry>(0xAE)(&data).unwrap();
+        assert_eq!(track.language, "por");
+        assert_eq!(track.language_ietf.as_deref(), Some("pt-BR"));
+        assert_eq!(track.effective_language(), "pt-BR");
+
+        track.language_ietf = None;
+        assert_eq!(track.effective_language(), "por");
+    }
+
+    #[test]
+    fn block_addition_mapping() {
+        // An AV1 track with the Dolby Vision configuration of profile 10,
+        // the RPUs being stored in the BlockAdditionals of ID 1. The mapping
+        // comes after other elements, as written by the muxers
+        let data = [
+            0xAE, 0xBE, 0xD7, 0x81, 0x01, 0x73, 0xC5, 0x81, 0x01, 0x83, 0x81, 0x01, 0x86, 0x85,
+            0x56, 0x5F, 0x41, 0x56, 0x31, 0x55, 0xEE, 0x81, 0x01, 0x41, 0xE4, 0xA6, 0x41, 0xF0,
+            0x81, 0x01, 0x41, 0xE7, 0x84, 0x64, 0x76, 0x63, 0x43, 0x41, 0xED, 0x98, 0x01, 0x00,
+            0x14, 0x4D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let (_, track) = crate::ebml::ebml_element::<TrackEntry>(0xAE)(&data).unwrap();
+        assert_eq!(track.codec_id, "V_AV1");
+        assert_eq!(track.max_block_addition_id, 1);
+
+        let mapping = track.addition_mapping_for_id(1).unwrap();
+        assert_eq!(mapping.id_name, None);
+        assert_eq!(mapping.id_type, u64::from_be_bytes(*b"\0\0\0\0dvcC"));
+        let extra_data = mapping.id_extra_data.as_deref().unwrap();
+        assert_eq!(extra_data.len(), 24);
+        assert_eq!(extra_data[..4], [0x01, 0x00, 0x14, 0x4D]);
+
+        assert_eq!(track.addition_mapping_for_id(2), None);
+        assert_eq!(TrackEntry::default().addition_mapping_for_id(1), None);
+    }
+
+    #[test]
+    fn linked_attachments() {
+        // An ASS subtitle track using the fonts of UIDs 2, 1 and 9
+        let data = [
+            0xAE, 0xA2, 0xD7, 0x81, 0x01, 0x73, 0xC5, 0x81, 0x01, 0x83, 0x81, 0x11, 0x86, 0x8A,
+            0x53, 0x5F, 0x54, 0x45, 0x58, 0x54, 0x2F, 0x41, 0x53, 0x53, 0x74, 0x46, 0x81, 0x02,
+            0x74, 0x46, 0x81, 0x01, 0x74, 0x46, 0x81, 0x09,
+        ];
+        let (_, track) = crate::ebml::ebml_element::<TrackEntry>(0xAE)(&data).unwrap();
+        assert_eq!(track.attachment_links, [2, 1, 9]);
+
+        let font = |uid, name: &str| AttachedFile {
+            description: None,
+            name: String::from(name),
+            media_type: String::from("font/ttf"),
+            data: vec![uid as u8],
+            uid,
+        };
+        let attachments = Attachments {
+            files: vec![font(1, "a.ttf"), font(2, "b.ttf"), font(3, "c.ttf")],
+        };
+        let names: Vec<_> = track
+            .linked_attachments(&attachments)
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect();
+        assert_eq!(names, ["b.ttf", "a.ttf"]);
+        assert!(TrackEntry::default()
+            .linked_attachments(&attachments)
+            .is_empty());
+    }
+
+    #[test]
+    fn content_encryption() {
+        let data = [
+            0x6D, 0x80, 0x9C, 0x62, 0x40, 0x99, 0x50, 0x33, 0x81, 0x01, 0x50, 0x35, 0x92, 0x47,
+            0xE1, 0x81, 0x05, 0x47, 0xE2, 0x84, b'k', b'e', b'y', b'1', 0x47, 0xE7, 0x84, 0x47,
+            0xE8, 0x81, 0x01,
+        ];
+        let (_, encodings) = crate::ebml::ebml_element::<ContentEncodings>(0x6D80)(&data).unwrap();
+        let encryption = encodings.content_encoding[0].encryption.as_ref().unwrap();
+        assert_eq!(
+            encryption.algorithm(),
+            Some(ContentEncryptionAlgorithm::Aes)
+        );
+        assert_eq!(encryption.aes_settings.as_ref().unwrap().cipher_mode, 1);
+        assert_eq!(encryption.signature, None);
+
+        let mut track = TrackEntry::default();
+        assert!(!track.is_encrypted());
+
+        track.content_encodings = Some(encodings);
+        assert!(track.is_encrypted());
+        assert_eq!(track.encryption_key_id(), Some(&b"key1"[..]));
+    }
+
+    #[test]
+    fn cues_lookup() {
+        // Two Cue Points from test1.mkv, at 0 and 1042, for track 1
+        let data = [
+            0x1C, 0x53, 0xBB, 0x6B, 0x9E, 0xBB, 0x8C, 0xB3, 0x81, 0x00, 0xB7, 0x87, 0xF7, 0x81,
+            0x01, 0xF1, 0x82, 0x03, 0x03, 0xBB, 0x8E, 0xB3, 0x82, 0x04, 0x12, 0xB7, 0x88, 0xF7,
+            0x81, 0x01, 0xF1, 0x83, 0x02, 0x38, 0xAE,
+        ];
+
+        let cues = match segment_element(&data) {
+            Ok((_, SegmentElement::Cues(cues))) => cues,
+            res => panic!("res: {res:?}"),
+        };
+        assert_eq!(cues.cue_points.len(), 2);
+
+        let (cue_point, positions) = cues.lookup(1, 5000).unwrap();
+        assert_eq!(cue_point.time, 1042);
+        assert_eq!(positions.cluster_position, 0x0238AE);
+
+        let (cue_point, positions) = cues.lookup(1, 0).unwrap();
+        assert_eq!(cue_point.time, 0);
+        assert_eq!(positions.cluster_position, 0x0303);
+
+        assert!(cues.lookup(2, 0).is_none());
+    }
+
+    #[test]
+    fn uid_format() {
+        let uid = Uuid::from_u128(0xed157223369df02dcf5076a5fea70034);
+        let formatted = uid.to_string();
+        assert_eq!(
+            formatted,
+            "0xed 0x15 0x72 0x23 0x36 0x9d 0xf0 0x2d 0xcf 0x50 0x76 0xa5 0xfe 0xa7 0x0 0x34"
+        );
+        assert_eq!(formatted.parse::<Uuid>().unwrap(), uid);
+        assert_eq!(format_uid(&uid), formatted);
+        assert_eq!(parse_uid(&formatted).unwrap(), uid);
+        assert_eq!(
+            "ed 15 72 23 36 9d f0 2d cf 50 76 a5 fe a7 0 34"
+                .parse::<Uuid>()
+                .unwrap(),
+            uid
+        );
+
+        let hyphenated = uuid::Uuid::from(uid);
+        assert_eq!(
+            hyphenated.to_string(),
+            "ed157223-369d-f02d-cf50-76a5fea70034"
+        );
+        assert_eq!(Uuid::from(hyphenated), uid);
+
+        for s in [
+            "",
+            "0xed 0x15",
+            "0x100 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0",
+            "0x 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0",
+            "0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0",
+            "ed157223-369d-f02d-cf50-76a5fea70034",
+        ] {
+            assert!(
+                matches!(s.parse::<Uuid>(), Err(crate::Error::InvalidUid(_))),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn tags_find() {
+        let tags = match segment_element(&mkv[497..]) {
+            Ok((_, SegmentElement::Tags(tags))) => tags,
+            res => panic!("res: {res:?}"),
+        };
+        assert_eq!(tags.tags.len(), 2);
+        assert_eq!(tags.tags[1].targets.track_uid, vec![1]);
+
+        let encoders = tags.find("encoder");
+        assert_eq!(encoders.len(), 2);
+        assert_eq!(encoders[0].string.as_deref(), Some("Lavf57.10.0"));
+        assert_eq!(encoders[1].language, "und");
+
+        let track_tags = tags.for_track(1);
+        assert_eq!(track_tags.len(), 1);
+        assert_eq!(track_tags[0], &tags.tags[1]);
+        assert!(tags.for_track(2).is_empty());
+        assert!(tags.for_chapter(1).is_empty());
+
+        let mut chapter_tags = tags.clone();
+        chapter_tags.tags[0].targets.chapter_uid = vec![7, 8];
+        assert_eq!(chapter_tags.for_chapter(8), [&chapter_tags.tags[0]]);
+
+        // ARTIST holding a nested URL
+        let data = [
+            0x12, 0x54, 0xC3, 0x67, 0xA7, 0x73, 0x73, 0xA4, 0x63, 0xC0, 0x80, 0x67, 0xC8, 0x9E,
+            0x45, 0xA3, 0x86, b'A', b'R', b'T', b'I', b'S', b'T', 0x44, 0x87, 0x83, b'F', b'o',
+            b'o', 0x67, 0xC8, 0x8C, 0x45, 0xA3, 0x83, b'U', b'R', b'L', 0x44, 0x87, 0x83, b'a',
+            b'b', b'c',
+        ];
+        let tags = match segment_element(&data) {
+            Ok((_, SegmentElement::Tags(tags))) => tags,
+            res => panic!("res: {res:?}"),
+        };
+        assert_eq!(tags.tags[0].targets.target_type_value, 50);
+        assert_eq!(tags.find("Artist")[0].string.as_deref(), Some("Foo"));
+        assert_eq!(tags.find("url")[0].string.as_deref(), Some("abc"));
+        assert!(tags.find("title").is_empty());
+    }
+
+    #[test]
+    fn chapters() {
+        let data = [
+            0x10, 0x43, 0xA7, 0x70, 0xEB, 0x45, 0xB9, 0xE8, 0x45, 0xBC, 0x81, 0x01, 0xB6, 0x9D,
+            0x73, 0xC4, 0x81, 0x0A, 0x91, 0x81, 0x00, 0x92, 0x85, 0x01, 0x2A, 0x05, 0xF2, 0x00,
+            0x80, 0x8D, 0x85, 0x85, 0x49, 0x6E, 0x74, 0x72, 0x6F, 0x43, 0x7C, 0x83, 0x65, 0x6E,
+            0x67, 0xB6, 0xC3, 0x73, 0xC4, 0x81, 0x0B, 0x91, 0x85, 0x01, 0x2A, 0x05, 0xF2, 0x00,
+            0x80, 0x8E, 0x85, 0x86, 0x50, 0x61, 0x72, 0x74, 0x20, 0x31, 0x43, 0x7C, 0x83, 0x65,
+            0x6E, 0x67, 0x80, 0x90, 0x85, 0x88, 0x50, 0x61, 0x72, 0x74, 0x69, 0x65, 0x20, 0x31,
+            0x43, 0x7C, 0x83, 0x66, 0x72, 0x61, 0xB6, 0x94, 0x73, 0xC4, 0x81, 0x0C, 0x91, 0x85,
+            0x01, 0x83, 0x6E, 0x21, 0x00, 0x80, 0x87, 0x85, 0x85, 0x53, 0x63, 0x65, 0x6E, 0x65,
+        ];
+
+        let chapters = match segment_element(&data) {
+            Ok((_, SegmentElement::Chapters(chapters))) => chapters,
+            res => panic!("res: {res:?}"),
+        };
+
+        let edition = &chapters.editions[0];
+        assert_eq!(edition.uid, Some(1));
+        assert_eq!(edition.chapters.len(), 2);
+
+        let intro = &edition.chapters[0];
+        assert_eq!(intro.time_start, 0);
+        assert_eq!(intro.time_end, Some(5_000_000_000));
+        assert_eq!(intro.title("fra"), Some("Intro"));
+
+        let part = &edition.chapters[1];
+        assert_eq!(part.time_start, 5_000_000_000);
+        assert_eq!(part.title("eng"), Some("Part 1"));
+        assert_eq!(part.title("fra"), Some("Partie 1"));
+
+        // Nested chapter, without language
+        let scene = &part.chapters[0];
+        assert_eq!(scene.uid, 12);
+        assert_eq!(scene.time_start, 6_500_000_000);
+        assert!(scene.displays[0].has_language("eng"));
+        assert_eq!(scene.title("eng"), Some("Scene"));
+
+        // Default flags
+        assert!(!edition.flag_hidden && !edition.flag_default && !edition.flag_ordered);
+        assert!(!intro.flag_hidden && intro.flag_enabled);
+        assert_eq!(edition.visible_chapters().count(), 2);
+
+        let mut edition = edition.clone();
+        edition.chapters[0].flag_hidden = true;
+        let visible: Vec<_> = edition.visible_chapters().map(|c| c.uid).collect();
+        assert_eq!(visible, [11]);
+    }
 }
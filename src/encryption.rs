@@ -0,0 +1,142 @@
+//! Decryption of the blocks of encrypted tracks, available with the
+//! `encryption` feature.
+//!
+//! The block payloads are laid out as described by the [WebM Encryption]
+//! specification: a signal byte, an 8 bytes IV and optional partitions,
+//! followed by the data encrypted with AES-128 in CTR mode.
+//!
+//! [WebM Encryption]: https://www.webmproject.org/docs/webm-encryption/
+
+use std::borrow::Cow;
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+
+use crate::elements::Cluster;
+use crate::Error;
+
+type Aes128Ctr = ctr::Ctr64BE<aes::Aes128>;
+
+/// The size of an AES-128 key.
+pub const KEY_SIZE: usize = 16;
+
+const SIGNAL_ENCRYPTED: u8 = 0x01;
+const SIGNAL_PARTITIONED: u8 = 0x02;
+const IV_SIZE: usize = 8;
+
+/// Decrypts the payload of a block, returning the frame data without the
+/// encryption header.
+pub(crate) fn decrypt_block(key: &[u8; KEY_SIZE], data: &[u8]) -> Result<Vec<u8>, Error> {
+    let (&signal, data) = data.split_first().ok_or(Error::Decryption)?;
+    if signal & SIGNAL_ENCRYPTED == 0 {
+        return Ok(data.to_vec());
+    }
+
+    if data.len() < IV_SIZE {
+        return Err(Error::Decryption);
+    }
+    let (iv, mut data) = data.split_at(IV_SIZE);
+
+    // The partitions alternate between clear and encrypted data,
+    // starting with clear data
+    let mut offsets = Vec::new();
+    if signal & SIGNAL_PARTITIONED != 0 {
+        let (&count, rest) = data.split_first().ok_or(Error::Decryption)?;
+        let size = usize::from(count) * 4;
+        if rest.len() < size {
+            return Err(Error::Decryption);
+        }
+
+        offsets = rest[..size]
+            .chunks(4)
+            .map(|offset| u32::from_be_bytes(offset.try_into().unwrap()) as usize)
+            .collect();
+        data = &rest[size..];
+
+        if offsets.windows(2).any(|w| w[0] > w[1]) || offsets.last() > Some(&data.len()) {
+            return Err(Error::Decryption);
+        }
+    } else {
+        offsets.push(0);
+    }
+    offsets.push(data.len());
+
+    let mut counter = [0; 16];
+    counter[..IV_SIZE].copy_from_slice(iv);
+    let mut cipher = Aes128Ctr::new(key.into(), &counter.into());
+
+    let mut frame = data.to_vec();
+    for encrypted in offsets.windows(2).step_by(2) {
+        cipher.apply_keystream(&mut frame[encrypted[0]..encrypted[1]]);
+    }
+
+    Ok(frame)
+}
+
+/// Decrypts the blocks of the cluster whose track has a key.
+pub(crate) fn decrypt_cluster<'k>(
+    cluster: &mut Cluster<'_>,
+    key: impl Fn(u64) -> Option<&'k [u8; KEY_SIZE]>,
+) -> Result<(), Error> {
+    for block in &mut cluster.simple_block {
+        if let Some(key) = key(block.track_number) {
+            block.data = Cow::Owned(decrypt_block(key, &block.data)?);
+        }
+    }
+
+    for group in &mut cluster.block_group {
+        if let Some(key) = key(group.block.track_number) {
+            group.block.data = Cow::Owned(decrypt_block(key, &group.block.data)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const KEY: &[u8; KEY_SIZE] = b"0123456789abcdef";
+
+    pub(crate) fn encrypt(iv: [u8; IV_SIZE], data: &[u8]) -> Vec<u8> {
+        let mut counter = [0; 16];
+        counter[..IV_SIZE].copy_from_slice(&iv);
+
+        let mut block = vec![SIGNAL_ENCRYPTED];
+        block.extend(iv);
+        block.extend(data);
+        Aes128Ctr::new(KEY.into(), &counter.into()).apply_keystream(&mut block[1 + IV_SIZE..]);
+        block
+    }
+
+    #[test]
+    fn decrypt() {
+        let frame = b"a frame longer than one AES block";
+        let block = encrypt([1, 2, 3, 4, 5, 6, 7, 8], frame);
+        assert_ne!(&block[1 + IV_SIZE..], frame);
+        assert_eq!(decrypt_block(KEY, &block).unwrap(), frame);
+
+        // Clear frame
+        assert_eq!(decrypt_block(KEY, b"\x00frame").unwrap(), b"frame");
+
+        // Clear data, then the encrypted data of the first frame
+        let mut partitioned = vec![SIGNAL_ENCRYPTED | SIGNAL_PARTITIONED];
+        partitioned.extend(&block[1..1 + IV_SIZE]);
+        partitioned.extend([1, 0, 0, 0, 5]);
+        partitioned.extend(b"clear");
+        partitioned.extend(&block[1 + IV_SIZE..]);
+        let mut expected = b"clear".to_vec();
+        expected.extend(frame);
+        assert_eq!(decrypt_block(KEY, &partitioned).unwrap(), expected);
+
+        assert!(matches!(decrypt_block(KEY, b""), Err(Error::Decryption)));
+        assert!(matches!(
+            decrypt_block(KEY, b"\x01iv"),
+            Err(Error::Decryption)
+        ));
+        assert!(matches!(
+            decrypt_block(KEY, b"\x03iviviviv\x01\x00\x00\x00\x10data"),
+            Err(Error::Decryption)
+        ));
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::io;
+
+use cookie_factory::GenError;
+
+use crate::ebml;
+use crate::elements::Uuid;
+use crate::webm::WebmViolation;
+
+/// Errors returned by the high-level APIs such as
+/// [MatroskaReader](crate::reader::MatroskaReader) and
+/// [MatroskaWriter](crate::writer::MatroskaWriter).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading from the underlying source failed.
+    Io(io::Error),
+
+    /// The data could not be parsed, see [ebml::Error].
+    Ebml(ebml::Error),
+
+    /// The source ended in the middle of an element.
+    UnexpectedEof,
+
+    /// An element of the enclosed size does not fit in the internal buffer.
+    BufferTooSmall(usize),
+
+    /// The file has no Cues, or they do not reference the requested track.
+    MissingCues,
+
+    /// An element could not be serialized.
+    Serialize(GenError),
+
+    /// The CRC-32 of an element does not match its data. `expected` is
+    /// the checksum stored in the file, `actual` the one of the data.
+    CrcMismatch {
+        element_id: u32,
+        expected: u32,
+        actual: u32,
+    },
+
+    /// A decryption key does not have the enclosed size of an AES-128 key.
+    InvalidKeyLength(usize),
+
+    /// The encryption header of a block is malformed.
+    Decryption,
+
+    /// The Segment has neither a Duration nor blocks to compute it from.
+    DurationUnknown,
+
+    /// The file has no video track.
+    NoVideoTrack,
+
+    /// The file has no audio track.
+    NoAudioTrack,
+
+    /// The file has no chapters.
+    NoChapters,
+
+    /// The CodecPrivate of the track is missing, or does not match the
+    /// layout expected for its codec.
+    InvalidCodecPrivate,
+
+    /// There is no parser for the CodecPrivate of the enclosed CodecID.
+    UnsupportedCodec(String),
+
+    /// No file in the directory holds the linked Segment of the enclosed
+    /// UID.
+    MissingLinkedSegment(Uuid),
+
+    /// The linked Segments lack a SegmentUID, or their PrevUID and NextUID
+    /// do not chain them one after the other.
+    InvalidSegmentChain,
+
+    /// The enclosed string is not a UID in the format of the
+    /// [Display](std::fmt::Display) of [Uuid].
+    InvalidUid(String),
+
+    /// A timestamp does not fit in 64 bits once converted to another
+    /// TimestampScale.
+    TimestampOverflow,
+
+    /// The file does not follow the WebM profile.
+    InvalidWebm(Vec<WebmViolation>),
+
+    /// A track was added to a [MatroskaEncoder](crate::writer::MatroskaEncoder)
+    /// after its Tracks were written.
+    TracksWritten,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            Error::Ebml(e) => write!(f, "could not parse element {:#0X}: {:?}", e.id, e.kind),
+            Error::UnexpectedEof => write!(f, "unexpected end of file"),
+            Error::BufferTooSmall(size) => {
+                write!(f, "element of {size} bytes does not fit in the buffer")
+            }
+            Error::MissingCues => write!(f, "no cue point found for the track"),
+            Error::Serialize(e) => write!(f, "could not serialize element: {e:?}"),
+            Error::CrcMismatch {
+                element_id,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "CRC-32 mismatch in element {element_id:#0X}: expected {expected:#010X}, got {actual:#010X}"
+            ),
+            Error::InvalidKeyLength(len) => {
+                write!(f, "a key of {len} bytes is not a valid AES-128 key")
+            }
+            Error::Decryption => write!(f, "could not decrypt the block"),
+            Error::DurationUnknown => write!(f, "the duration of the segment is unknown"),
+            Error::NoVideoTrack => write!(f, "no video track found"),
+            Error::NoAudioTrack => write!(f, "no audio track found"),
+            Error::NoChapters => write!(f, "no chapters found"),
+            Error::InvalidCodecPrivate => write!(f, "missing or invalid codec private data"),
+            Error::UnsupportedCodec(id) => write!(f, "unsupported codec {id}"),
+            Error::MissingLinkedSegment(uid) => write!(f, "linked segment {uid} not found"),
+            Error::InvalidSegmentChain => write!(f, "the segments are not linked in a chain"),
+            Error::InvalidUid(s) => write!(f, "invalid UID {s:?}"),
+            Error::TimestampOverflow => write!(f, "the timestamp overflows"),
+            Error::InvalidWebm(violations) => {
+                write!(f, "not a WebM file:")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    let sep = if i == 0 { " " } else { ", " };
+                    write!(f, "{sep}{violation}")?;
+                }
+                Ok(())
+            }
+            Error::TracksWritten => write!(f, "the tracks were already written"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<ebml::Error> for Error {
+    fn from(e: ebml::Error) -> Self {
+        match e.kind {
+            ebml::ErrorKind::Crc32Mismatch { expected, actual } => Error::CrcMismatch {
+                element_id: e.id,
+                expected,
+                actual,
+            },
+            _ => Error::Ebml(e),
+        }
+    }
+}
@@ -0,0 +1,671 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use crate::ebml::EbmlHeader;
+use crate::elements::{Attachments, Chapters, Cues, Info, Tags, Tracks, Uuid};
+pub use crate::reader::Frame;
+use crate::reader::{keep, MatroskaReader};
+use crate::Error;
+
+/// A Matroska file whose Top-Level Elements, except the Clusters, have
+/// been read into memory.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::file::MatroskaFile;
+///
+/// let file = MatroskaFile::new(File::open("video.mkv")?)?;
+/// for attachment in file.attachments().iter().flat_map(|a| &a.files) {
+///     println!("{} ({} bytes)", attachment.name, attachment.data.len());
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaFile<R> {
+    reader: MatroskaReader<R>,
+    tags: Vec<Tags>,
+    attachments: Option<Attachments>,
+}
+
+impl MatroskaFile<File> {
+    /// Opens the file at `path`, see [MatroskaFile::from_seekable].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_seekable(File::open(path)?)
+    }
+}
+
+impl<R: Read> MatroskaFile<R> {
+    /// Reads the whole Segment from `source`, skipping over the Clusters.
+    pub fn new(source: R) -> Result<Self, Error> {
+        let mut reader = MatroskaReader::new(source)?;
+        let mut tags = Vec::new();
+        let mut attachments = None;
+
+        while let Some(element) = reader.next_element()? {
+            keep(element, &mut tags, &mut attachments);
+        }
+
+        Ok(MatroskaFile {
+            reader,
+            tags,
+            attachments,
+        })
+    }
+
+    /// The EBML Header of the file.
+    pub fn header(&self) -> &EbmlHeader {
+        self.reader.header()
+    }
+
+    /// The Info element, if the file has one.
+    pub fn info(&self) -> Option<&Info> {
+        self.reader.info()
+    }
+
+    /// The Tracks element, if the file has one.
+    pub fn tracks(&self) -> Option<&Tracks> {
+        self.reader.tracks()
+    }
+
+    /// The Cues element, if the file has one.
+    pub fn cues(&self) -> Option<&Cues> {
+        self.reader.cues()
+    }
+
+    /// The Chapters element, if the file has one.
+    pub fn chapters(&self) -> Option<&Chapters> {
+        self.reader.chapters()
+    }
+
+    /// All the Tags elements, a Segment can contain several of them.
+    pub fn tags(&self) -> &[Tags] {
+        &self.tags
+    }
+
+    pub(crate) fn reader_mut(&mut self) -> &mut MatroskaReader<R> {
+        &mut self.reader
+    }
+
+    /// The Attachments element, if the file has one.
+    pub fn attachments(&self) -> Option<&Attachments> {
+        self.attachments.as_ref()
+    }
+
+    /// The data of the attached file with the given UID.
+    pub fn extract_attachment(&self, uid: u64) -> Option<&[u8]> {
+        self.attachments
+            .as_ref()
+            .and_then(|attachments| attachments.get(uid))
+            .map(|file| file.data.as_slice())
+    }
+
+    /// The cover art of the file.
+    ///
+    /// It is the attached file named `cover.jpg`, `cover.png`,
+    /// `small_cover.jpg` or `small_cover.png`, in that order, ignoring the
+    /// case. Otherwise it is the binary value of a `PICTURE` or `COVER_ART`
+    /// SimpleTag, whose media type is guessed from its data.
+    pub fn cover_art(&self) -> Option<CoverArt> {
+        const NAMES: [(&str, bool); 4] = [
+            ("cover.jpg", false),
+            ("cover.png", false),
+            ("small_cover.jpg", true),
+            ("small_cover.png", true),
+        ];
+
+        let attached = self.attachments.as_ref().and_then(|attachments| {
+            NAMES.iter().find_map(|&(name, is_small)| {
+                let file = attachments.get_by_name(name)?;
+                Some(CoverArt {
+                    data: file.data.clone(),
+                    mime_type: file.media_type.clone(),
+                    is_small,
+                })
+            })
+        });
+
+        attached.or_else(|| {
+            let data = ["PICTURE", "COVER_ART"].iter().find_map(|name| {
+                self.tags
+                    .iter()
+                    .flat_map(|tags| tags.find(name))
+                    .find_map(|tag| tag.binary.as_ref())
+            })?;
+            let mime_type = if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+                "image/jpeg"
+            } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+                "image/png"
+            } else {
+                "application/octet-stream"
+            };
+
+            Some(CoverArt {
+                data: data.clone(),
+                mime_type: String::from(mime_type),
+                is_small: false,
+            })
+        })
+    }
+}
+
+impl<R: Read + Seek> MatroskaFile<R> {
+    /// Reads the Top-Level Elements found before the first Cluster, then
+    /// the ones the SeekHead points to, without reading the Clusters.
+    pub fn from_seekable(source: R) -> Result<Self, Error> {
+        let mut reader = MatroskaReader::new(source)?;
+        let metadata = reader.metadata_only()?;
+
+        Ok(MatroskaFile {
+            reader,
+            tags: metadata.tags,
+            attachments: metadata.attachments,
+        })
+    }
+
+    /// An identifier of the content of the file, independent of its name.
+    ///
+    /// It is the SegmentUID, or a hash of the first Cluster when the file
+    /// has none.
+    pub fn fingerprint(&mut self) -> Result<Fingerprint, Error> {
+        let info = self.info();
+        let prev_uid = info.and_then(|info| info.prev_uid);
+        let next_uid = info.and_then(|info| info.next_uid);
+
+        let (uid, hashed) = match info.and_then(|info| info.segment_uid) {
+            Some(uid) => (uid, false),
+            None => {
+                let data = self.reader.first_cluster_bytes()?.unwrap_or_default();
+                (Uuid::from_u128(fnv1a_128(&data)), true)
+            }
+        };
+
+        Ok(Fingerprint {
+            uid,
+            hashed,
+            prev_uid,
+            next_uid,
+        })
+    }
+
+    /// Checks that the entries of the SeekHead point to the elements they
+    /// claim, returning the ones that do not.
+    ///
+    /// The position of the reader is preserved.
+    pub fn verify_seek_head(&mut self) -> Result<Vec<SeekHeadError>, Error> {
+        let entries = self.reader.seek_head().map_or(Vec::new(), |seek_head| {
+            seek_head
+                .positions
+                .iter()
+                .map(|seek| (u32::from_be_bytes(seek.id), seek.position))
+                .collect()
+        });
+
+        let mut errors = Vec::new();
+        for (claimed_id, offset) in entries {
+            match self.reader.element_id_at(offset)? {
+                Some(actual_id) if actual_id == claimed_id => {}
+                Some(actual_id) => errors.push(SeekHeadError::WrongElementAt {
+                    claimed_id,
+                    actual_id,
+                    offset,
+                }),
+                None => errors.push(SeekHeadError::NoElementAt { claimed_id, offset }),
+            }
+        }
+
+        Ok(errors)
+    }
+
+    /// Reads the frames of the first video track needed to display the
+    /// frame at `timestamp_ns`: the closest keyframe at or before it,
+    /// followed by the delta frames up to the frame of the latest
+    /// presentation timestamp not after `timestamp_ns`.
+    ///
+    /// The frames are returned in decoding order, which differs from the
+    /// presentation order with B-frames: the frames are read up to the
+    /// next keyframe, so that a frame stored after one presented later is
+    /// still found. A single [Frame] would not be enough, as the delta
+    /// frames can only be decoded after the frames they depend on.
+    ///
+    /// The Cues are used to find the Cluster of the keyframe.
+    pub fn read_video_frame_at(&mut self, timestamp_ns: u64) -> Result<Vec<Frame>, Error> {
+        let track_number = self
+            .tracks()
+            .and_then(|tracks| tracks.tracks.iter().find(|t| t.track_type == 1))
+            .map(|track| track.track_number)
+            .ok_or(Error::NoVideoTrack)?;
+
+        self.reader.seek_to_timestamp(track_number, timestamp_ns)?;
+
+        let mut frames: Vec<Frame> = Vec::new();
+        for frame in self.reader.frames(track_number) {
+            let frame = frame?;
+            if frame.keyframe {
+                if frame.timestamp_ns > timestamp_ns {
+                    break;
+                }
+                frames.clear();
+            } else if frames.is_empty() {
+                // Delta frames before the first keyframe cannot be decoded
+                continue;
+            }
+            frames.push(frame);
+        }
+
+        // The frames decoded after the one displayed are not needed
+        let displayed = frames
+            .iter()
+            .enumerate()
+            .filter(|(_, frame)| frame.timestamp_ns <= timestamp_ns)
+            .max_by_key(|(_, frame)| frame.timestamp_ns)
+            .map_or(0, |(i, _)| i + 1);
+        frames.truncate(displayed);
+
+        Ok(frames)
+    }
+
+    /// Reads the frames of the first audio track needed to decode the audio
+    /// at `timestamp_ns`: since decoders like Opus have to converge first,
+    /// the frames start SeekPreRoll nanoseconds before the timestamp, up to
+    /// the frame holding it.
+    ///
+    /// Seeking uses the Cues of the audio track if it has some, otherwise
+    /// those of the first track listed, as Clusters interleave the blocks
+    /// of all the tracks.
+    pub fn read_audio_frame_at(&mut self, timestamp_ns: u64) -> Result<Vec<Frame>, Error> {
+        let (track_number, seek_pre_roll) = self
+            .tracks()
+            .and_then(|tracks| tracks.tracks.iter().find(|t| t.track_type == 2))
+            .map(|track| (track.track_number, track.seek_pre_roll))
+            .ok_or(Error::NoAudioTrack)?;
+        let start = timestamp_ns.saturating_sub(seek_pre_roll);
+
+        let cue_track = self
+            .cues()
+            .and_then(|cues| cues.cue_points.first())
+            .and_then(|cue_point| cue_point.track_positions.first())
+            .map(|positions| positions.track);
+        match (
+            self.reader.seek_to_timestamp(track_number, start),
+            cue_track,
+        ) {
+            (Err(Error::MissingCues), Some(cue_track)) => {
+                self.reader.seek_to_timestamp(cue_track, start)?;
+            }
+            (res, _) => {
+                res?;
+            }
+        }
+
+        let mut frames: Vec<Frame> = Vec::new();
+        for frame in self.reader.frames(track_number) {
+            let frame = frame?;
+            if frame.timestamp_ns > timestamp_ns {
+                break;
+            }
+
+            // The frame holding the start replaces the previous ones
+            if frame.timestamp_ns <= start {
+                frames.clear();
+            }
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+}
+
+/// The cover art of a file, see [MatroskaFile::cover_art].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverArt {
+    pub data: Vec<u8>,
+    pub mime_type: String,
+    /// Whether it is the small version of the cover, of 120 pixels at most.
+    pub is_small: bool,
+}
+
+/// The identifier of the content of a file, see [MatroskaFile::fingerprint].
+///
+/// The [Display](fmt::Display) is the primary UID in lowercase
+/// hexadecimal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    /// The SegmentUID, or the hash of the first Cluster.
+    pub uid: Uuid,
+    /// Whether the UID is the hash of the first Cluster.
+    pub hashed: bool,
+    /// The UID of the previous linked Segment.
+    pub prev_uid: Option<Uuid>,
+    /// The UID of the next linked Segment.
+    pub next_uid: Option<Uuid>,
+}
+
+impl Fingerprint {
+    /// Whether the primary UID is one of `uids`.
+    pub fn matches_any(&self, uids: &[Uuid]) -> bool {
+        uids.contains(&self.uid)
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.uid.as_u128())
+    }
+}
+
+// 128-bit FNV-1a, stable across platforms and releases unlike the hashers
+// of the standard library.
+pub(crate) fn fnv1a_128(data: &[u8]) -> u128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    data.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u128::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// A SeekHead entry not matching the file, see
+/// [MatroskaFile::verify_seek_head].
+///
+/// The offsets are relative to the start of the Segment data, as in the
+/// SeekHead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeekHeadError {
+    /// Another element is at the position of the entry.
+    WrongElementAt {
+        claimed_id: u32,
+        actual_id: u32,
+        offset: u64,
+    },
+    /// The position of the entry is past the end of the file or not at the
+    /// start of an element.
+    NoElementAt { claimed_id: u32, offset: u64 },
+}
+
+impl fmt::Display for SeekHeadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeekHeadError::WrongElementAt {
+                claimed_id,
+                actual_id,
+                offset,
+            } => write!(
+                f,
+                "expected the element {claimed_id:#X} at {offset}, found {actual_id:#X}"
+            ),
+            SeekHeadError::NoElementAt { claimed_id, offset } => {
+                write!(
+                    f,
+                    "expected the element {claimed_id:#X} at {offset}, found none"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_upper_case_globals)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::SimpleTag;
+    use crate::test_utils;
+
+    const mkv: &[u8] = include_bytes!("../assets/single_stream_cover.mkv");
+
+    #[test]
+    fn extract_cover_attachment() {
+        let file = MatroskaFile::new(Cursor::new(mkv)).unwrap();
+
+        assert!(file.info().is_some());
+        assert_eq!(file.tracks().unwrap().tracks.len(), 1);
+        assert_eq!(file.tags().len(), 1);
+
+        let attachments = file.attachments().unwrap();
+        assert_eq!(attachments.files.len(), 1);
+
+        let cover = &attachments.files[0];
+        assert_eq!(cover.name, "cover.png");
+        assert_eq!(cover.media_type, "image/png");
+        assert_eq!(cover.description.as_deref(), Some("Cover"));
+
+        let data = file.extract_attachment(cover.uid).unwrap();
+        assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(data.len(), 73);
+
+        assert!(file.extract_attachment(cover.uid + 1).is_none());
+    }
+
+    #[test]
+    fn cover_art() {
+        let mut file = MatroskaFile::new(Cursor::new(mkv)).unwrap();
+        let cover = file.cover_art().unwrap();
+        assert_eq!(cover.mime_type, "image/png");
+        assert_eq!(cover.data.len(), 73);
+        assert!(!cover.is_small);
+
+        // The large cover comes first
+        let attachments = file.attachments.as_mut().unwrap();
+        let mut small = attachments.files[0].clone();
+        attachments.files[0].name = String::from("Small_Cover.png");
+        small.data.truncate(8);
+        attachments.files.push(small);
+        let cover = file.cover_art().unwrap();
+        assert_eq!(cover.data.len(), 8);
+        assert!(!cover.is_small);
+
+        file.attachments.as_mut().unwrap().files.pop();
+        assert!(file.cover_art().unwrap().is_small);
+
+        file.attachments = None;
+        assert_eq!(file.cover_art(), None);
+
+        file.tags[0].tags[0].simple_tags.push(SimpleTag {
+            name: String::from("COVER_ART"),
+            language: String::from("und"),
+            language_bcp47: None,
+            default: 1,
+            string: None,
+            binary: Some(vec![0xFF, 0xD8, 0xFF, 0xE0]),
+            simple_tags: Vec::new(),
+        });
+        let cover = file.cover_art().unwrap();
+        assert_eq!(cover.mime_type, "image/jpeg");
+        assert_eq!(cover.data, [0xFF, 0xD8, 0xFF, 0xE0]);
+    }
+
+    #[test]
+    fn seekable_metadata() {
+        let file = MatroskaFile::from_seekable(Cursor::new(mkv)).unwrap();
+        let streamed = MatroskaFile::new(Cursor::new(mkv)).unwrap();
+
+        assert_eq!(file.info(), streamed.info());
+        assert_eq!(file.tracks(), streamed.tracks());
+        assert_eq!(file.tags(), streamed.tags());
+        assert_eq!(file.attachments(), streamed.attachments());
+        assert!(file.attachments().is_some());
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut file = MatroskaFile::from_seekable(Cursor::new(mkv)).unwrap();
+        let fingerprint = file.fingerprint().unwrap();
+        let uid = file.info().unwrap().segment_uid.unwrap();
+        assert_eq!(fingerprint.uid, uid);
+        assert!(!fingerprint.hashed);
+        assert_eq!(fingerprint.to_string(), format!("{:032x}", uid.as_u128()));
+        assert!(fingerprint.matches_any(&[Uuid::nil(), uid]));
+        assert!(!fingerprint.matches_any(&[Uuid::nil()]));
+
+        // Without a SegmentUID, the first Cluster is hashed
+        let fingerprint = |frame: &[u8]| {
+            let data = encode_audio(&[frame]);
+            let mut file = MatroskaFile::from_seekable(Cursor::new(data)).unwrap();
+            assert_eq!(file.info().unwrap().segment_uid, None);
+            file.fingerprint().unwrap()
+        };
+        assert!(fingerprint(b"frame").hashed);
+        assert_eq!(fingerprint(b"frame"), fingerprint(b"frame"));
+        assert_ne!(fingerprint(b"frame").uid, fingerprint(b"frames").uid);
+
+        assert_eq!(fnv1a_128(b""), 0x6c62272e07bb014262b821756295c58d);
+        assert_eq!(fnv1a_128(b"a"), 0xd228cb696f1a8caf78912b704e4a8964);
+    }
+
+    #[test]
+    fn verify_seek_head() {
+        let mut file = MatroskaFile::open("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        assert_eq!(file.verify_seek_head().unwrap(), []);
+
+        // Points the entry of the Tracks to the Info, and drops the Cues
+        // at the end of the file
+        let mut data = std::fs::read("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        let seek_head = file.reader.seek_head().unwrap().clone();
+        let position = |id| {
+            let seek = seek_head.positions.iter().find(|s| s.id == id);
+            seek.unwrap().position
+        };
+        let info_position = position([0x15, 0x49, 0xA9, 0x66]);
+        let cues_position = position([0x1C, 0x53, 0xBB, 0x6B]);
+        patch_seek_position(
+            &mut data,
+            [0x16, 0x54, 0xAE, 0x6B],
+            position([0x16, 0x54, 0xAE, 0x6B]),
+            info_position,
+        );
+        // The Segment data starts with the SeekHead
+        let segment_start = data.windows(4).position(|w| w == [0x11, 0x4D, 0x9B, 0x74]);
+        data.truncate(segment_start.unwrap() + cues_position as usize);
+
+        let mut file = MatroskaFile::new(Cursor::new(data)).unwrap();
+        assert_eq!(
+            file.verify_seek_head().unwrap(),
+            [
+                SeekHeadError::WrongElementAt {
+                    claimed_id: 0x1654AE6B,
+                    actual_id: 0x1549A966,
+                    offset: info_position,
+                },
+                SeekHeadError::NoElementAt {
+                    claimed_id: 0x1C53BB6B,
+                    offset: cues_position,
+                },
+            ]
+        );
+    }
+
+    // A file with an Opus track, one frame every 20 ms.
+    fn encode_audio(frames: &[&[u8]]) -> Vec<u8> {
+        use crate::elements::TrackEntry;
+        use crate::writer::MatroskaEncoder;
+
+        let track = TrackEntry {
+            seek_pre_roll: 80_000_000,
+            ..test_utils::track(1, 2, "A_OPUS")
+        };
+        let mut encoder = MatroskaEncoder::new(Vec::new(), test_utils::info(), vec![track])
+            .unwrap()
+            .with_max_cluster_duration(200_000_000);
+        for (i, frame) in frames.iter().enumerate() {
+            encoder
+                .push_frame(1, i as u64 * 20_000_000, true, frame)
+                .unwrap();
+        }
+        encoder.finish().unwrap()
+    }
+
+    // Rewrites the SeekPosition following the SeekID `id` in place, the new
+    // position having to fit in the same width.
+    fn patch_seek_position(data: &mut [u8], id: [u8; 4], old: u64, new: u64) {
+        let mut entry = vec![0x53, 0xAB, 0x84];
+        entry.extend(id);
+        let start = data.windows(7).position(|w| w == entry).unwrap() + 7;
+        assert_eq!(&data[start..start + 2], [0x53, 0xAC]);
+
+        let len = usize::from(data[start + 2] & 0x7F);
+        let value = &mut data[start + 3..start + 3 + len];
+        assert_eq!(value.iter().fold(0, |v, &b| v << 8 | u64::from(b)), old);
+        assert!(new < 1 << (8 * len));
+        value.copy_from_slice(&new.to_be_bytes()[8 - len..]);
+    }
+
+    #[test]
+    fn read_video_frame_at() {
+        let mut file = MatroskaFile::open("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        let frames = file.read_video_frame_at(2_000_000_000).unwrap();
+
+        assert_eq!(frames.len(), 19);
+        assert!(frames[0].keyframe);
+        assert_eq!(frames[0].timestamp_ns, 1_250_000_000);
+        assert!(frames[1..]
+            .iter()
+            .all(|f| !f.keyframe && f.track_number == 1));
+        assert_eq!(frames[18].timestamp_ns, 2_000_000_000);
+    }
+
+    #[test]
+    fn read_video_frame_at_b_frames() {
+        let block = |timestamp, keyframe| test_utils::block(1, timestamp, keyframe);
+
+        // Stored in decoding order: I0 P120 B40 B80 I160
+        let data = test_utils::segment(
+            vec![test_utils::track(1, 1, "V_MPEG4/ISO/AVC")],
+            &[(
+                0,
+                &[
+                    block(0, true),
+                    block(120, false),
+                    block(40, false),
+                    block(80, false),
+                    block(160, true),
+                ],
+            )],
+        );
+
+        let mut file = MatroskaFile::new(Cursor::new(data)).unwrap();
+        let mut timestamps = |timestamp_ns| -> Vec<u64> {
+            let frames = file.read_video_frame_at(timestamp_ns).unwrap();
+            frames.iter().map(|f| f.timestamp_ns / 1_000_000).collect()
+        };
+        assert_eq!(timestamps(90_000_000), [0, 120, 40, 80]);
+        assert_eq!(timestamps(50_000_000), [0, 120, 40]);
+        assert_eq!(timestamps(130_000_000), [0, 120]);
+        assert_eq!(timestamps(20_000_000), [0]);
+        assert_eq!(timestamps(200_000_000), [160]);
+    }
+
+    #[test]
+    fn read_audio_frame_at() {
+        let frames: Vec<[u8; 1]> = (0..50).map(|i| [i]).collect();
+        let data = encode_audio(&frames.iter().map(|f| &f[..]).collect::<Vec<_>>());
+
+        let mut file = MatroskaFile::new(Cursor::new(data)).unwrap();
+        let timestamps = |frames: Vec<Frame>| -> Vec<u64> {
+            frames.iter().map(|f| f.timestamp_ns / 1_000_000).collect()
+        };
+        assert_eq!(
+            timestamps(file.read_audio_frame_at(510_000_000).unwrap()),
+            [420, 440, 460, 480, 500]
+        );
+        assert_eq!(
+            timestamps(file.read_audio_frame_at(50_000_000).unwrap()),
+            [0, 20, 40]
+        );
+
+        // Only the video track has Cues, and the SeekPreRoll is not set
+        let mut file = MatroskaFile::open("assets/bbb-vp9-opus.webm").unwrap();
+        let frames = file.read_audio_frame_at(500_000_000).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].track_number, 2);
+        assert!(frames[0].timestamp_ns <= 500_000_000);
+
+        let mut file = MatroskaFile::new(Cursor::new(mkv)).unwrap();
+        assert!(matches!(
+            file.read_audio_frame_at(0),
+            Err(Error::NoAudioTrack)
+        ));
+    }
+}
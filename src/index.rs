@@ -0,0 +1,333 @@
+//! An index of the Clusters of a file, for seeking in files without Cues.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::Path;
+
+use crate::ebml::{self, ErrorKind};
+use crate::elements::{segment_element, SegmentElement};
+use crate::Error;
+
+/// The timestamp and position of every Cluster of a Segment.
+///
+/// The index is built by a linear scan of the file reading only the
+/// element headers, the Info and the Timestamp of each Cluster: the
+/// blocks are skipped over without being read.
+///
+/// ```no_run
+/// use matroska::index::MatroskaIndex;
+/// use matroska::reader::MatroskaReader;
+///
+/// let index = MatroskaIndex::build_from_file("video.mkv")?;
+/// if let Some(offset) = index.cluster_before(60_000_000_000) {
+///     let mut reader = MatroskaReader::new(std::fs::File::open("video.mkv")?)?;
+///     reader.seek_to_byte_offset(offset)?;
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatroskaIndex {
+    // (timestamp in nanoseconds, position in the Segment), by timestamp
+    clusters: Vec<(u64, u64)>,
+}
+
+impl MatroskaIndex {
+    /// Builds the index of the file at `path`.
+    pub fn build_from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::build(File::open(path)?)
+    }
+
+    /// Builds the index of the Segment read from `source`, which is read
+    /// from its start.
+    pub fn build<R: Read + Seek>(source: R) -> Result<Self, Error> {
+        let mut source = BufReader::new(source);
+
+        let header = read_element_header(&mut source)?.ok_or(Error::UnexpectedEof)?;
+        expect_id(header.id, 0x1A45DFA3)?;
+        source.seek_relative(header.size.ok_or(Error::UnexpectedEof)? as i64)?;
+
+        let segment = read_element_header(&mut source)?.ok_or(Error::UnexpectedEof)?;
+        expect_id(segment.id, 0x18538067)?;
+        let segment_size = segment.size;
+
+        let mut timestamp_scale = 1_000_000;
+        let mut clusters = Vec::new();
+        // Position relative to the Segment data
+        let mut position = 0;
+        // Header of a Top-Level Element read while scanning a Cluster of
+        // unknown size
+        let mut next = None;
+
+        while segment_size.is_none_or(|size| position < size) {
+            let header = match next.take() {
+                Some(header) => header,
+                None => match read_element_header(&mut source)? {
+                    Some(header) => header,
+                    None => break,
+                },
+            };
+            let start = position;
+            position += header.bytes.len() as u64;
+
+            match (header.id, header.size) {
+                (0x1F43B675, size) => {
+                    let end = size.map(|size| position + size);
+                    let mut timestamp = None;
+
+                    // Children have IDs of one byte, the Top-Level
+                    // Elements of four
+                    while end.is_none_or(|end| position < end) {
+                        let Some(child) = read_element_header(&mut source)? else {
+                            break;
+                        };
+                        if vint_len(child.bytes[0]) < 4 {
+                            let size = child.size.ok_or(Error::UnexpectedEof)?;
+                            position += child.bytes.len() as u64;
+                            if child.id == 0xE7 && timestamp.is_none() {
+                                timestamp = Some(read_uint(&mut source, size)?);
+                                position += size;
+                                // The rest of a Cluster of known size is skipped
+                                if let Some(end) = end {
+                                    // The Timestamp may run past the Cluster
+                                    let rest =
+                                        end.checked_sub(position).ok_or(Error::UnexpectedEof)?;
+                                    source.seek_relative(rest as i64)?;
+                                    position = end;
+                                }
+                            } else {
+                                source.seek_relative(size as i64)?;
+                                position += size;
+                            }
+                        } else {
+                            next = Some(child);
+                            break;
+                        }
+                    }
+
+                    if let Some(timestamp) = timestamp {
+                        clusters.push((timestamp, start));
+                    }
+                }
+                (0x1549A966, Some(size)) => {
+                    // The size is not trusted for the allocation
+                    let mut data = header.bytes;
+                    let len = data.len() as u64;
+                    (&mut source).take(size).read_to_end(&mut data)?;
+                    if data.len() as u64 != len + size {
+                        return Err(Error::UnexpectedEof);
+                    }
+                    position += size;
+                    let (_, element) = segment_element(&data).map_err(|e| match e {
+                        nom::Err::Error(e) | nom::Err::Failure(e) => Error::from(e),
+                        nom::Err::Incomplete(_) => Error::UnexpectedEof,
+                    })?;
+                    if let SegmentElement::Info(info) = element {
+                        if info.timestamp_scale > 0 {
+                            timestamp_scale = info.timestamp_scale;
+                        }
+                    }
+                }
+                (_, Some(size)) => {
+                    source.seek_relative(size as i64)?;
+                    position += size;
+                }
+                // Only Clusters can have an unknown size
+                (_, None) => break,
+            }
+        }
+
+        let mut clusters: Vec<_> = clusters
+            .into_iter()
+            .map(|(timestamp, position)| (timestamp.saturating_mul(timestamp_scale), position))
+            .collect();
+        clusters.sort_unstable();
+
+        Ok(MatroskaIndex { clusters })
+    }
+
+    /// The timestamp in nanoseconds and the position of the Clusters,
+    /// sorted by timestamp.
+    ///
+    /// The positions are relative to the start of the Segment data, as
+    /// expected by [MatroskaReader::seek_to_byte_offset](crate::reader::MatroskaReader::seek_to_byte_offset).
+    pub fn clusters(&self) -> &[(u64, u64)] {
+        &self.clusters
+    }
+
+    /// The position of the last Cluster starting at or before
+    /// `timestamp_ns`.
+    pub fn cluster_before(&self, timestamp_ns: u64) -> Option<u64> {
+        let count = self
+            .clusters
+            .partition_point(|&(timestamp, _)| timestamp <= timestamp_ns);
+        count.checked_sub(1).map(|i| self.clusters[i].1)
+    }
+}
+
+fn expect_id(id: u32, expected: u32) -> Result<(), Error> {
+    if id == expected {
+        Ok(())
+    } else {
+        Err(Error::Ebml(ebml::Error {
+            id: expected,
+            kind: ErrorKind::MissingElement,
+        }))
+    }
+}
+
+fn vint_len(first: u8) -> usize {
+    first.leading_zeros() as usize + 1
+}
+
+struct ElementHeader {
+    id: u32,
+    // `None` when unknown
+    size: Option<u64>,
+    bytes: Vec<u8>,
+}
+
+// Reads the ID and the data size of an element, `None` at the end of the
+// source.
+fn read_element_header<R: Read>(source: &mut R) -> Result<Option<ElementHeader>, Error> {
+    let mut header = vec![0];
+    if source.read(&mut header)? == 0 {
+        return Ok(None);
+    }
+
+    let id_len = vint_len(header[0]);
+    if id_len > 4 {
+        return Err(Error::Ebml(ebml::Error {
+            id: 0,
+            kind: ErrorKind::IDTooWide,
+        }));
+    }
+    header.resize(id_len + 1, 0);
+    read_exact(source, &mut header[1..])?;
+
+    let size_len = vint_len(header[id_len]);
+    if size_len > 8 {
+        return Err(Error::Ebml(ebml::Error {
+            id: 0,
+            kind: ErrorKind::VintTooWide,
+        }));
+    }
+    header.resize(id_len + size_len, 0);
+    read_exact(source, &mut header[id_len + 1..])?;
+
+    let id = header[..id_len]
+        .iter()
+        .fold(0, |id, &b| (id << 8) | u32::from(b));
+    let size = header[id_len + 1..].iter().fold(
+        u64::from(header[id_len] & 0xFF_u8.checked_shr(size_len as u32).unwrap_or(0)),
+        |size, &b| (size << 8) | u64::from(b),
+    );
+    let unknown = size == (1 << (7 * size_len)) - 1;
+
+    Ok(Some(ElementHeader {
+        id,
+        size: (!unknown).then_some(size),
+        bytes: header,
+    }))
+}
+
+fn read_uint<R: Read>(source: &mut R, size: u64) -> Result<u64, Error> {
+    if size > 8 {
+        return Err(Error::Ebml(ebml::Error {
+            id: 0xE7,
+            kind: ErrorKind::UintTooWide,
+        }));
+    }
+
+    let mut data = [0; 8];
+    read_exact(source, &mut data[8 - size as usize..])?;
+    Ok(u64::from_be_bytes(data))
+}
+
+fn read_exact<R: Read>(source: &mut R, data: &mut [u8]) -> Result<(), Error> {
+    source.read_exact(data).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+        _ => Error::Io(e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::reader::MatroskaReader;
+
+    #[test]
+    fn build() {
+        for path in [
+            "assets/matroska_test_w1_1/test1.mkv",
+            "assets/matroska_test_w1_1/test5.mkv",
+            "assets/bbb-vp9-opus.webm",
+        ] {
+            let index = MatroskaIndex::build_from_file(path).unwrap();
+
+            let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+            let mut expected = Vec::new();
+            loop {
+                let position = reader.next_element_position();
+                let scale = reader.info().map_or(1_000_000, |info| info.timestamp_scale);
+                match reader.next_element().unwrap() {
+                    Some(SegmentElement::Cluster(cluster)) => {
+                        expected.push((cluster.timestamp * scale, position))
+                    }
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+            expected.sort_unstable();
+
+            assert!(!expected.is_empty());
+            assert_eq!(index.clusters(), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn cluster_before() {
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let index = MatroskaIndex::build_from_file(path).unwrap();
+        let clusters = index.clusters();
+
+        assert_eq!(index.cluster_before(0), Some(clusters[0].1));
+        let (timestamp, position) = clusters[3];
+        assert_eq!(index.cluster_before(timestamp), Some(position));
+        assert_eq!(index.cluster_before(timestamp - 1), Some(clusters[2].1));
+        assert_eq!(
+            index.cluster_before(u64::MAX),
+            Some(clusters.last().unwrap().1)
+        );
+        assert_eq!(MatroskaIndex::default().cluster_before(0), None);
+
+        let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        reader.seek_to_byte_offset(position).unwrap();
+        match reader.next_element().unwrap() {
+            Some(SegmentElement::Cluster(cluster)) => {
+                assert_eq!(cluster.timestamp * 1_000_000, timestamp)
+            }
+            e => panic!("expected a Cluster, got {e:?}"),
+        }
+
+        assert!(matches!(
+            MatroskaIndex::build(Cursor::new(b"not a matroska file")),
+            Err(Error::Ebml(_))
+        ));
+    }
+
+    #[test]
+    fn timestamp_past_cluster() {
+        let data = [
+            0x1A, 0x45, 0xDF, 0xA3, 0x80, // EBML Header
+            0x18, 0x53, 0x80, 0x67, 0xFF, // Segment of unknown size
+            0x1F, 0x43, 0xB6, 0x75, 0x82, // Cluster of 2 bytes
+            0xE7, 0x84, 0, 0, 0, 1, // Timestamp of 6 bytes
+        ];
+        assert!(matches!(
+            MatroskaIndex::build(Cursor::new(data)),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+}
@@ -1,6 +1,68 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "tokio")]
+pub mod async_reader;
+#[cfg(feature = "std")]
+pub mod chapters;
+#[cfg(feature = "std")]
+pub mod codec_private;
+#[cfg(feature = "std")]
 pub mod demuxer;
+#[cfg(feature = "std")]
+mod display;
 pub mod ebml;
 pub mod elements;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "std")]
+mod error;
+#[cfg(feature = "std")]
+pub mod file;
+#[cfg(feature = "std")]
+pub mod index;
+#[cfg(feature = "std")]
+pub mod linked;
+#[cfg(feature = "std")]
+pub mod merger;
+#[cfg(feature = "std")]
 pub mod muxer;
 pub mod permutation;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "std")]
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod progress;
+#[cfg(feature = "std")]
+pub mod reader;
+#[cfg(feature = "std")]
+pub mod reorder;
+#[cfg(feature = "std")]
+pub mod rewriter;
+#[cfg(feature = "serde")]
+mod serde_utils;
+#[cfg(feature = "std")]
 pub mod serializer;
+#[cfg(feature = "std")]
+pub mod splitter;
+pub mod state_machine;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "symphonia")]
+mod symphonia;
+#[cfg(all(test, feature = "std"))]
+mod test_utils;
+#[cfg(feature = "std")]
+pub mod validator;
+#[cfg(feature = "std")]
+pub mod webm;
+#[cfg(feature = "std")]
+pub mod writer;
+
+#[cfg(feature = "std")]
+pub use elements::parse_uid;
+pub use elements::{format_uid, Uuid};
+#[cfg(feature = "std")]
+pub use error::Error;
@@ -0,0 +1,527 @@
+//! Reading of hard-linked Segments, stored in several files chained by the
+//! PrevUID and NextUID of their Info.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::elements::{SegmentElement, Uuid};
+use crate::file::MatroskaFile;
+use crate::reader::{Frame, MatroskaReader};
+use crate::Error;
+
+/// Iterator over the frames of a track across linked Segments.
+///
+/// When the Segment ends and its Info has a NextUID, the file holding the
+/// Segment of that UID is looked up in the directory, trying the
+/// NextFilename first, and reading continues there. The timestamps of
+/// each Segment are shifted by the durations of the previous ones, so that
+/// the frames form a single stream.
+///
+/// ```no_run
+/// use matroska::linked::MatroskaLinkedReader;
+///
+/// for frame in MatroskaLinkedReader::open("part1.mkv", ".", 1)? {
+///     let frame = frame?;
+///     println!("{} ({} bytes)", frame.timestamp_ns, frame.data.len());
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaLinkedReader {
+    directory: PathBuf,
+    reader: MatroskaReader<File>,
+    track_number: u64,
+    // Sum of the durations of the previous Segments, in nanoseconds
+    offset: u64,
+    // UIDs of the Segments read so far, to stop on loops
+    segments: Vec<Uuid>,
+    pending: VecDeque<Frame>,
+}
+
+impl MatroskaLinkedReader {
+    /// Opens the file at `path` and reads the frames of `track_number`
+    /// from it, then from the Segments linked after it, which are searched
+    /// for in `directory`.
+    pub fn open(
+        path: impl AsRef<Path>,
+        directory: impl AsRef<Path>,
+        track_number: u64,
+    ) -> Result<Self, Error> {
+        let reader = open_segment(path.as_ref())?;
+        let segments = reader
+            .info()
+            .and_then(|info| info.segment_uid)
+            .into_iter()
+            .collect();
+
+        Ok(MatroskaLinkedReader {
+            directory: directory.as_ref().to_path_buf(),
+            reader,
+            track_number,
+            offset: 0,
+            segments,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// The reader of the Segment being read.
+    pub fn reader(&self) -> &MatroskaReader<File> {
+        &self.reader
+    }
+
+    // Moves to the Segment linked after the current one, returns false if
+    // there is none.
+    fn next_segment(&mut self) -> Result<bool, Error> {
+        let Some(info) = self.reader.info() else {
+            return Ok(false);
+        };
+        let Some(uid) = info.next_uid else {
+            return Ok(false);
+        };
+        if self.segments.contains(&uid) {
+            return Ok(false);
+        }
+
+        let mut candidates = Vec::new();
+        if let Some(filename) = &info.next_filename {
+            candidates.push(self.directory.join(filename));
+        }
+        let mut entries = fs::read_dir(&self.directory)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        candidates.extend(entries.into_iter().filter(|path| path.is_file()));
+
+        // Files that are not Matroska are skipped
+        let reader = candidates
+            .iter()
+            .filter_map(|path| open_segment(path).ok())
+            .find(|reader| reader.info().and_then(|info| info.segment_uid) == Some(uid))
+            .ok_or(Error::MissingLinkedSegment(uid))?;
+
+        let duration = self.reader.calculate_duration()?.as_nanos();
+        self.offset = self
+            .offset
+            .saturating_add(u64::try_from(duration).unwrap_or(u64::MAX));
+        self.segments.push(uid);
+        self.reader = reader;
+
+        Ok(true)
+    }
+}
+
+impl Iterator for MatroskaLinkedReader {
+    type Item = Result<Frame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let res = match self
+                .reader
+                .read_frames(Some(self.track_number), &mut self.pending)
+            {
+                Ok(false) => self.next_segment(),
+                res => res,
+            };
+            match res {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        self.pending.pop_front().map(|mut frame| {
+            // The timestamps of the reader saturate as well
+            frame.timestamp_ns = frame.timestamp_ns.saturating_add(self.offset);
+            Ok(frame)
+        })
+    }
+}
+
+/// Linked Segments opened beforehand, put in the order of their PrevUID and
+/// NextUID.
+///
+/// Unlike [MatroskaLinkedReader], the files are not searched for, which
+/// allows reading Segments from any source.
+///
+/// ```no_run
+/// use matroska::file::MatroskaFile;
+/// use matroska::linked::MatroskaTimeline;
+///
+/// let segments = vec![MatroskaFile::open("part2.mkv")?, MatroskaFile::open("part1.mkv")?];
+/// let mut timeline = MatroskaTimeline::from_segments(segments)?;
+/// for frame in timeline.frames(1) {
+///     let frame = frame?;
+///     println!("{} ({} bytes)", frame.timestamp_ns, frame.data.len());
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaTimeline<R> {
+    segments: Vec<MatroskaFile<R>>,
+}
+
+impl<R: Read + Seek> MatroskaTimeline<R> {
+    /// Orders `segments` from the first one of the chain to the last one.
+    ///
+    /// Fails with [Error::InvalidSegmentChain] if a Segment has no
+    /// SegmentUID, or if the Segments do not follow each other without
+    /// gaps. The first Segment may link to a previous one, and the last
+    /// one to a next one, that are not given.
+    pub fn from_segments(segments: Vec<MatroskaFile<R>>) -> Result<Self, Error> {
+        let links = segments
+            .iter()
+            .map(|segment| {
+                let info = segment.info().ok_or(Error::InvalidSegmentChain)?;
+                let uid = info.segment_uid.ok_or(Error::InvalidSegmentChain)?;
+                Ok((uid, info.prev_uid, info.next_uid))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let index = |uid: Option<Uuid>| links.iter().position(|link| Some(link.0) == uid);
+
+        // The first Segment is not linked from another one given
+        let mut first = links
+            .iter()
+            .enumerate()
+            .filter(|(_, link)| index(link.1).is_none());
+        let (Some((mut current, _)), None) = (first.next(), first.next()) else {
+            return Err(Error::InvalidSegmentChain);
+        };
+
+        let mut order = vec![current];
+        while let Some(next) = index(links[current].2) {
+            if order.contains(&next) || links[next].1 != Some(links[current].0) {
+                return Err(Error::InvalidSegmentChain);
+            }
+            order.push(next);
+            current = next;
+        }
+        if order.len() != segments.len() {
+            return Err(Error::InvalidSegmentChain);
+        }
+
+        let mut segments: Vec<_> = segments.into_iter().map(Some).collect();
+        let segments = order
+            .into_iter()
+            .map(|i| segments[i].take().expect("the Segments are ordered once"))
+            .collect();
+
+        Ok(MatroskaTimeline { segments })
+    }
+
+    /// The Segments, in the order of the chain.
+    pub fn segments(&self) -> &[MatroskaFile<R>] {
+        &self.segments
+    }
+
+    /// Returns an iterator over the frames of `track_number` in all the
+    /// Segments, from the start of the first one.
+    ///
+    /// The timestamps of each Segment are shifted by the durations of the
+    /// previous ones, so that the frames form a single stream.
+    pub fn frames(&mut self, track_number: u64) -> TimelineFrames<'_, R> {
+        TimelineFrames {
+            timeline: self,
+            track_number,
+            segment: 0,
+            started: false,
+            offset: 0,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// Iterator over the frames of a track in a [MatroskaTimeline], see
+/// [MatroskaTimeline::frames].
+pub struct TimelineFrames<'t, R> {
+    timeline: &'t mut MatroskaTimeline<R>,
+    track_number: u64,
+    // Index of the Segment being read
+    segment: usize,
+    started: bool,
+    // Sum of the durations of the previous Segments, in nanoseconds
+    offset: u64,
+    pending: VecDeque<Frame>,
+}
+
+impl<R: Read + Seek> TimelineFrames<'_, R> {
+    // Reads the frames of the next Cluster, moving to the next Segment at
+    // the end of one. Returns false after the last Segment.
+    fn read_frames(&mut self) -> Result<bool, Error> {
+        loop {
+            let Some(segment) = self.timeline.segments.get_mut(self.segment) else {
+                return Ok(false);
+            };
+            let reader = segment.reader_mut();
+            if !self.started {
+                reader.seek_to_byte_offset(0)?;
+                self.started = true;
+            }
+            if reader.read_frames(Some(self.track_number), &mut self.pending)? {
+                return Ok(true);
+            }
+
+            let duration = reader.calculate_duration()?.as_nanos();
+            self.offset = self
+                .offset
+                .saturating_add(u64::try_from(duration).unwrap_or(u64::MAX));
+            self.segment += 1;
+            self.started = false;
+        }
+    }
+}
+
+impl<R: Read + Seek> Iterator for TimelineFrames<'_, R> {
+    type Item = Result<Frame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            match self.read_frames() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        self.pending.pop_front().map(|mut frame| {
+            frame.timestamp_ns = frame.timestamp_ns.saturating_add(self.offset);
+            Ok(frame)
+        })
+    }
+}
+
+// Opens the file at `path` and reads its Segment up to the Info, then
+// moves back to the start of the Segment.
+fn open_segment(path: &Path) -> Result<MatroskaReader<File>, Error> {
+    let mut reader = MatroskaReader::new(File::open(path)?)?;
+    while reader.info().is_none() {
+        match reader.next_element()? {
+            Some(SegmentElement::Cluster(_)) | None => break,
+            Some(_) => {}
+        }
+    }
+    reader.seek_to_byte_offset(0)?;
+
+    Ok(reader)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::Info;
+    use crate::test_utils;
+    use crate::writer::{MatroskaWriter, RawBlock};
+
+    // A Segment of 100 ms with two frames holding `data`
+    fn segment(info: Info, data: u8) -> Vec<u8> {
+        let track = test_utils::track(1, 2, "A_OPUS");
+        let info = Info {
+            duration: Some(100.0),
+            ..info
+        };
+        let out = Cursor::new(Vec::new());
+        let mut writer = MatroskaWriter::new(out, info, vec![track]).unwrap();
+        for timestamp in [0, 50] {
+            let block = RawBlock {
+                track_number: 1,
+                timestamp: 0,
+                keyframe: true,
+                data: &[data],
+            };
+            writer.write_cluster(timestamp, &[block]).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn timeline() {
+        let uids = [1, 2, 3].map(Uuid::from_u128);
+        let info = |i: usize| Info {
+            segment_uid: Some(uids[i]),
+            prev_uid: i.checked_sub(1).map(|p| uids[p]),
+            next_uid: uids.get(i + 1).copied(),
+            ..test_utils::info()
+        };
+        let open = |data: &[u8]| MatroskaFile::from_seekable(Cursor::new(data.to_vec())).unwrap();
+        let parts: Vec<_> = (0..3).map(|i| segment(info(i), i as u8)).collect();
+
+        let segments = vec![open(&parts[2]), open(&parts[0]), open(&parts[1])];
+        let mut timeline = MatroskaTimeline::from_segments(segments).unwrap();
+        let uid = |segment: &MatroskaFile<_>| segment.info().unwrap().segment_uid.unwrap();
+        let order: Vec<_> = timeline.segments().iter().map(uid).collect();
+        assert_eq!(order, uids);
+
+        let frames = timeline.frames(1).collect::<Result<Vec<_>, _>>().unwrap();
+        let frames: Vec<_> = frames.iter().map(|f| (f.timestamp_ns, f.data[0])).collect();
+        assert_eq!(
+            frames,
+            [
+                (0, 0),
+                (50_000_000, 0),
+                (100_000_000, 1),
+                (150_000_000, 1),
+                (200_000_000, 2),
+                (250_000_000, 2),
+            ]
+        );
+        // The frames can be read again
+        assert_eq!(timeline.frames(1).count(), 6);
+        assert_eq!(timeline.frames(2).count(), 0);
+
+        // The Segments may continue a longer chain
+        let segments = vec![open(&parts[2]), open(&parts[1])];
+        assert!(MatroskaTimeline::from_segments(segments).is_ok());
+
+        // A gap in the chain
+        let segments = vec![open(&parts[2]), open(&parts[0])];
+        assert!(matches!(
+            MatroskaTimeline::from_segments(segments),
+            Err(Error::InvalidSegmentChain)
+        ));
+        // A Segment without a UID
+        let segments = vec![open(&parts[0]), open(&segment(test_utils::info(), 0))];
+        assert!(matches!(
+            MatroskaTimeline::from_segments(segments),
+            Err(Error::InvalidSegmentChain)
+        ));
+    }
+
+    #[test]
+    fn timeline_saturated() {
+        let uids = [1, 2].map(Uuid::from_u128);
+        let info = Info {
+            segment_uid: Some(uids[0]),
+            next_uid: Some(uids[1]),
+            ..test_utils::info()
+        };
+        let first = segment(info, 0);
+
+        // The timestamp of the frame of the second Segment saturates
+        let track = test_utils::track(1, 2, "A_OPUS");
+        let info = Info {
+            segment_uid: Some(uids[1]),
+            prev_uid: Some(uids[0]),
+            ..test_utils::info()
+        };
+        let mut writer = MatroskaWriter::new(Cursor::new(Vec::new()), info, vec![track]).unwrap();
+        let block = RawBlock {
+            track_number: 1,
+            timestamp: 100,
+            keyframe: true,
+            data: &[1],
+        };
+        writer.write_cluster(u64::MAX - 10, &[block]).unwrap();
+        let second = writer.finish().unwrap().into_inner();
+
+        let segments = [first, second]
+            .map(|data| MatroskaFile::from_seekable(Cursor::new(data)).unwrap())
+            .into();
+        let mut timeline = MatroskaTimeline::from_segments(segments).unwrap();
+        let frames = timeline.frames(1).collect::<Result<Vec<_>, _>>().unwrap();
+        let timestamps: Vec<_> = frames.iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, [0, 50_000_000, u64::MAX]);
+    }
+
+    #[test]
+    fn linked_segments() {
+        let directory =
+            std::env::temp_dir().join(format!("matroska-linked-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let uids = [1, 2, 3].map(Uuid::from_u128);
+        let track = test_utils::track(1, 2, "A_OPUS");
+        // The second file is only found by its UID
+        for (i, name) in ["part1.mkv", "other.mkv", "part3.mkv"].iter().enumerate() {
+            let info = Info {
+                duration: Some(100.0),
+                segment_uid: Some(uids[i]),
+                prev_uid: i.checked_sub(1).map(|p| uids[p]),
+                next_uid: uids.get(i + 1).copied(),
+                next_filename: Some(format!("part{}.mkv", i + 2)),
+                ..test_utils::info()
+            };
+            let file = File::create(directory.join(name)).unwrap();
+            let mut writer = MatroskaWriter::new(file, info, vec![track.clone()]).unwrap();
+            for timestamp in [0, 50] {
+                let block = RawBlock {
+                    track_number: 1,
+                    timestamp: 0,
+                    keyframe: true,
+                    data: &[i as u8],
+                };
+                writer.write_cluster(timestamp, &[block]).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        fs::write(directory.join("notes.txt"), "not a Matroska file").unwrap();
+
+        let frames = MatroskaLinkedReader::open(directory.join("part1.mkv"), &directory, 1)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let frames: Vec<_> = frames.iter().map(|f| (f.timestamp_ns, f.data[0])).collect();
+        assert_eq!(
+            frames,
+            [
+                (0, 0),
+                (50_000_000, 0),
+                (100_000_000, 1),
+                (150_000_000, 1),
+                (200_000_000, 2),
+                (250_000_000, 2),
+            ]
+        );
+
+        // The first Segment links to a missing one
+        fs::remove_file(directory.join("other.mkv")).unwrap();
+        let res = MatroskaLinkedReader::open(directory.join("part1.mkv"), &directory, 1)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>();
+        assert!(matches!(res, Err(Error::MissingLinkedSegment(uid)) if uid == uids[1]));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn linked_segments_saturated() {
+        let directory =
+            std::env::temp_dir().join(format!("matroska-saturated-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let uids = [1, 2].map(Uuid::from_u128);
+        let info = Info {
+            segment_uid: Some(uids[0]),
+            next_uid: Some(uids[1]),
+            ..test_utils::info()
+        };
+        fs::write(directory.join("part1.mkv"), segment(info, 0)).unwrap();
+
+        // The timestamp of the frame of the second Segment saturates
+        let track = test_utils::track(1, 2, "A_OPUS");
+        let info = Info {
+            segment_uid: Some(uids[1]),
+            prev_uid: Some(uids[0]),
+            ..test_utils::info()
+        };
+        let file = File::create(directory.join("part2.mkv")).unwrap();
+        let mut writer = MatroskaWriter::new(file, info, vec![track]).unwrap();
+        let block = RawBlock {
+            track_number: 1,
+            timestamp: 100,
+            keyframe: true,
+            data: &[1],
+        };
+        writer.write_cluster(u64::MAX - 10, &[block]).unwrap();
+        writer.finish().unwrap();
+
+        let frames = MatroskaLinkedReader::open(directory.join("part1.mkv"), &directory, 1)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let timestamps: Vec<_> = frames.iter().map(|f| f.timestamp_ns).collect();
+        assert_eq!(timestamps, [0, 50_000_000, u64::MAX]);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
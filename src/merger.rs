@@ -0,0 +1,632 @@
+//! Concatenation of several files into a single Segment.
+
+use std::borrow::Cow;
+use std::io::{Read, Seek, Write};
+
+use crate::elements::{
+    Block, BlockGroup, BlockKind, Cluster, ClusterBlock, Info, Lacing, SegmentElement,
+    SilentTracks, SimpleBlock, Tags, TrackEntry,
+};
+use crate::file::MatroskaFile;
+use crate::writer::MatroskaWriter;
+use crate::Error;
+
+/// Appends files one after the other into a single file, like
+/// `mkvmerge a.mkv + b.mkv`.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::file::MatroskaFile;
+/// use matroska::merger::MatroskaMerger;
+///
+/// let files = vec![
+///     MatroskaFile::open("episode1.mkv")?,
+///     MatroskaFile::open("episode2.mkv")?,
+/// ];
+/// MatroskaMerger::merge(files, File::create("episodes.mkv")?)?;
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaMerger;
+
+impl MatroskaMerger {
+    /// Writes the Clusters of `files` one after the other to `dst`, and
+    /// returns the destination.
+    ///
+    /// The tracks of the files are matched by type, CodecID, CodecPrivate,
+    /// language and audio or video parameters, and numbered in the order they first
+    /// appear. The timestamps of each file are shifted by the durations of
+    /// the previous ones. The Clusters of a file lacking some of the
+    /// tracks list them as SilentTracks.
+    ///
+    /// The Info is the one of the first file, without its links to other
+    /// Segments. The SeekHead and the Cues are written anew, and the Tags
+    /// of all the files are merged, targeting the tracks they are matched
+    /// to. The Chapters and the Attachments are not copied.
+    ///
+    /// The SimpleBlocks and BlockGroups are written in the order they are
+    /// stored, the BlockGroups keeping their BlockDuration and
+    /// ReferenceBlocks. The laced blocks are written as a block per frame,
+    /// each frame timestamped from the BlockDuration or the DefaultDuration
+    /// of its track. A new Cluster starts whenever a frame is too far from
+    /// the timestamp of the current one.
+    ///
+    /// `dst` is a destination rather than a [MatroskaWriter], because the
+    /// writer starts with the Info and the Tracks, which are only known
+    /// once the tracks of all the files are matched.
+    pub fn merge<R: Read + Seek, W: Write + Seek>(
+        mut files: Vec<MatroskaFile<R>>,
+        dst: W,
+    ) -> Result<W, Error> {
+        // The tracks of the output, and the number and UID of the tracks of
+        // each file with the number of the output track they are matched to
+        let mut tracks: Vec<TrackEntry> = Vec::new();
+        let mut mappings = Vec::new();
+        for file in &files {
+            let mut mapping: Vec<(u64, u64, u64)> = Vec::new();
+            for track in file.tracks().map_or(&[][..], |t| &t.tracks) {
+                let matching = tracks.iter().find(|t| {
+                    same_track(t, track) && !mapping.iter().any(|m| m.1 == t.track_number)
+                });
+                let number = match matching {
+                    Some(t) => t.track_number,
+                    None => {
+                        let mut uid = track.track_uid;
+                        while tracks.iter().any(|t| t.track_uid == uid) {
+                            uid = uid.wrapping_add(1);
+                        }
+                        let mut track = track.clone();
+                        track.track_number = tracks.len() as u64 + 1;
+                        track.track_uid = uid;
+                        tracks.push(track);
+                        tracks.len() as u64
+                    }
+                };
+                mapping.push((track.track_number, number, track.track_uid));
+            }
+            mappings.push(mapping);
+        }
+
+        let mut info = files
+            .first()
+            .and_then(|file| file.info().cloned())
+            .unwrap_or_else(|| Info {
+                timestamp_scale: 1_000_000,
+                ..Default::default()
+            });
+        if info.timestamp_scale == 0 {
+            info.timestamp_scale = 1_000_000;
+        }
+        let scale = info.timestamp_scale;
+
+        // Each file starts at the end of the previous one
+        let mut offsets = Vec::new();
+        let mut total = 0;
+        for file in &mut files {
+            offsets.push(total);
+            total += file.reader_mut().calculate_duration()?.as_nanos() as u64;
+        }
+
+        info.segment_uid = None;
+        info.segment_filename = None;
+        info.prev_uid = None;
+        info.prev_filename = None;
+        info.next_uid = None;
+        info.next_filename = None;
+        info.duration = Some(total as f64 / scale as f64);
+
+        let mut writer = MatroskaWriter::new(dst, info, tracks.clone())?;
+        let mut tags = Vec::new();
+
+        for ((file, mapping), offset) in files.iter_mut().zip(&mappings).zip(offsets) {
+            let silent_tracks: Vec<u64> = tracks
+                .iter()
+                .map(|track| track.track_number)
+                .filter(|&number| !mapping.iter().any(|m| m.1 == number))
+                .collect();
+
+            for tag in file.tags().iter().flat_map(|t| &t.tags) {
+                let mut tag = tag.clone();
+                for uid in &mut tag.targets.track_uid {
+                    if let Some(m) = mapping.iter().find(|m| m.2 == *uid) {
+                        *uid = tracks[m.1 as usize - 1].track_uid;
+                    }
+                }
+                tags.push(tag);
+            }
+
+            let default_durations: Vec<_> = file
+                .tracks()
+                .map_or(&[][..], |t| &t.tracks)
+                .iter()
+                .map(|track| (track.track_number, track.default_duration))
+                .collect();
+            let default_duration = |track_number| {
+                default_durations
+                    .iter()
+                    .find(|d| d.0 == track_number)
+                    .and_then(|d| d.1)
+            };
+
+            let reader = file.reader_mut();
+            reader.seek_to_byte_offset(0)?;
+            loop {
+                let file_scale = u128::from(reader.timestamp_scale());
+                let cluster = match reader.next_element()? {
+                    Some(SegmentElement::Cluster(cluster)) => cluster,
+                    Some(_) => continue,
+                    None => break,
+                };
+
+                // Nanoseconds of the file to ticks of the output
+                let to_output = |ns: u128| {
+                    i64::try_from((ns + u128::from(offset)) / u128::from(scale)).unwrap_or(i64::MAX)
+                };
+                let new_cluster = |timestamp: i64| Cluster {
+                    timestamp: timestamp as u64,
+                    silent_tracks: (!silent_tracks.is_empty()).then(|| SilentTracks {
+                        silent_track_number: silent_tracks.clone(),
+                    }),
+                    position: None,
+                    prev_size: None,
+                    simple_block: Vec::new(),
+                    block_group: Vec::new(),
+                    block_order: Vec::new(),
+                };
+                let mut output = new_cluster(to_output(u128::from(cluster.timestamp) * file_scale));
+
+                for block in cluster.blocks() {
+                    let Some(m) = mapping.iter().find(|m| m.0 == block.track_number()) else {
+                        continue;
+                    };
+                    let frames = block.frames()?;
+                    let start = u128::from(cluster.block_timestamp(block.timestamp())) * file_scale;
+                    let frame_duration = match block.duration() {
+                        Some(duration) => {
+                            Some(u128::from(duration) * file_scale / frames.len().max(1) as u128)
+                        }
+                        None => default_duration(block.track_number()).map(u128::from),
+                    };
+
+                    for (i, &data) in frames.iter().enumerate() {
+                        let absolute = to_output(start + frame_duration.unwrap_or(0) * i as u128);
+
+                        // A new Cluster starts when the frame is too far from
+                        // the timestamp of this one for the output scale
+                        if i16::try_from(absolute - output.timestamp as i64).is_err() {
+                            if !output.block_order.is_empty() {
+                                writer.write_cluster_element(&output)?;
+                            }
+                            output = new_cluster(absolute);
+                        }
+                        let relative = (absolute - output.timestamp as i64) as i16;
+
+                        match block {
+                            ClusterBlock::SimpleBlock(b) => {
+                                output.simple_block.push(SimpleBlock {
+                                    track_number: m.1,
+                                    timestamp: relative,
+                                    keyframe: b.keyframe,
+                                    invisible: b.invisible,
+                                    lacing: Lacing::None,
+                                    discardable: b.discardable,
+                                    data: Cow::Borrowed(data),
+                                });
+                                output.block_order.push(BlockKind::SimpleBlock);
+                            }
+                            ClusterBlock::BlockGroup(g) => {
+                                // The referenced blocks are moved to the
+                                // output scale, relative to this frame
+                                let reference_block = g
+                                    .reference_block
+                                    .iter()
+                                    .map(|&reference| {
+                                        let referenced = cluster
+                                            .block_timestamp(g.block.timestamp)
+                                            .saturating_add_signed(reference);
+                                        to_output(u128::from(referenced) * file_scale) - absolute
+                                    })
+                                    .collect();
+                                let first = i == 0;
+                                let last = i + 1 == frames.len();
+
+                                output.block_group.push(BlockGroup {
+                                    block: Block {
+                                        track_number: m.1,
+                                        timestamp: relative,
+                                        invisible: g.block.invisible,
+                                        lacing: Lacing::None,
+                                        data: Cow::Borrowed(data),
+                                    },
+                                    block_additions: g.block_additions.clone().filter(|_| first),
+                                    block_duration: g.block_duration.and(frame_duration).map(
+                                        |duration| {
+                                            u64::try_from(duration / u128::from(scale))
+                                                .unwrap_or(u64::MAX)
+                                        },
+                                    ),
+                                    reference_priority: g.reference_priority,
+                                    reference_block,
+                                    codec_state: g.codec_state.clone().filter(|_| first),
+                                    // Padding at the start of the block
+                                    // when negative, at its end otherwise
+                                    discard_padding: g.discard_padding.filter(|&padding| {
+                                        if padding < 0 {
+                                            first
+                                        } else {
+                                            last
+                                        }
+                                    }),
+                                });
+                                output.block_order.push(BlockKind::BlockGroup);
+                            }
+                        }
+                    }
+                }
+
+                if !output.block_order.is_empty() {
+                    writer.write_cluster_element(&output)?;
+                }
+            }
+        }
+
+        if !tags.is_empty() {
+            writer.write_tags(&Tags { tags })?;
+        }
+
+        writer.finish()
+    }
+}
+
+// Whether a track of a file carries the same stream as a track of another
+fn same_track(a: &TrackEntry, b: &TrackEntry) -> bool {
+    let audio = |t: &TrackEntry| {
+        t.audio
+            .as_ref()
+            .map(|a| (a.sampling_frequency.to_bits(), a.channels, a.bit_depth))
+    };
+    let video = |t: &TrackEntry| t.video.as_ref().map(|v| (v.pixel_width, v.pixel_height));
+
+    a.track_type == b.track_type
+        && a.codec_id == b.codec_id
+        && a.codec_private == b.codec_private
+        && a.effective_language() == b.effective_language()
+        && audio(a) == audio(b)
+        && video(a) == video(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::{Audio, SimpleTag, Tag, Targets};
+    use crate::reader::MatroskaReader;
+    use crate::test_utils;
+    use crate::writer::RawBlock;
+
+    #[test]
+    fn merge() {
+        let video = TrackEntry {
+            track_uid: 10,
+            ..test_utils::track(1, 1, "V_VP9")
+        };
+        let audio = |track_number, track_uid| TrackEntry {
+            track_uid,
+            audio: Some(Audio {
+                sampling_frequency: 48000.0,
+                channels: 2,
+                ..Default::default()
+            }),
+            ..test_utils::track(track_number, 2, "A_OPUS")
+        };
+        let info = Info {
+            duration: Some(1000.0),
+            ..test_utils::info()
+        };
+        let block = |track_number, timestamp| test_utils::block(track_number, timestamp, true);
+
+        // The second file only has the audio track, with another number
+        // and UID, and a Tag targeting it
+        let mut first = MatroskaWriter::new(
+            Cursor::new(Vec::new()),
+            info.clone(),
+            vec![video, audio(2, 20)],
+        )
+        .unwrap();
+        first
+            .write_cluster(0, &[block(1, 0), block(2, 0), block(2, 500)])
+            .unwrap();
+        let first = Cursor::new(first.finish().unwrap().into_inner());
+
+        let mut second =
+            MatroskaWriter::new(Cursor::new(Vec::new()), info, vec![audio(5, 50)]).unwrap();
+        second
+            .write_cluster(0, &[block(5, 0), block(5, 900)])
+            .unwrap();
+        second
+            .write_tags(&Tags {
+                tags: vec![Tag {
+                    targets: Targets {
+                        target_type_value: 50,
+                        target_type: None,
+                        track_uid: vec![50],
+                        edition_uid: Vec::new(),
+                        chapter_uid: Vec::new(),
+                        attachment_uid: Vec::new(),
+                    },
+                    simple_tags: vec![SimpleTag {
+                        name: String::from("TITLE"),
+                        language: String::from("und"),
+                        language_bcp47: None,
+                        default: 1,
+                        string: Some(String::from("Episode 2")),
+                        binary: None,
+                        simple_tags: Vec::new(),
+                    }],
+                }],
+            })
+            .unwrap();
+        let second = Cursor::new(second.finish().unwrap().into_inner());
+
+        let files = vec![
+            MatroskaFile::from_seekable(first).unwrap(),
+            MatroskaFile::from_seekable(second).unwrap(),
+        ];
+        let data = MatroskaMerger::merge(files, Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner();
+
+        let file = MatroskaFile::from_seekable(Cursor::new(&data)).unwrap();
+        let tracks = &file.tracks().unwrap().tracks;
+        assert_eq!(tracks.len(), 2);
+        assert_eq!((tracks[1].track_number, tracks[1].track_uid), (2, 20));
+        assert_eq!(file.info().unwrap().duration, Some(2000.0));
+        assert_eq!(file.tags()[0].for_track(20).len(), 1);
+        assert_eq!(
+            file.tags()[0].find("title")[0].string.as_deref(),
+            Some("Episode 2")
+        );
+        assert_eq!(file.cues().unwrap().cue_points.len(), 3);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let audio: Vec<_> = reader
+            .frames(2)
+            .map(|frame| frame.unwrap().timestamp_ns / 1_000_000)
+            .collect();
+        assert_eq!(audio, [0, 500, 1000, 1900]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let silent: Vec<_> = reader
+            .clusters()
+            .map(|cluster| cluster.unwrap().cluster.silent_tracks)
+            .collect();
+        assert_eq!(silent[0], None);
+        assert_eq!(silent[1].as_ref().unwrap().silent_track_number, [1]);
+    }
+
+    #[test]
+    fn merge_long_cluster() {
+        let track = TrackEntry {
+            track_uid: 10,
+            ..test_utils::track(1, 17, "S_TEXT/UTF8")
+        };
+        let block = |timestamp| RawBlock {
+            track_number: 1,
+            timestamp,
+            keyframe: true,
+            data: b"text",
+        };
+
+        // The blocks of the second file are 50 seconds apart, which do not
+        // fit in a Cluster at the millisecond scale of the first file
+        let mut first = MatroskaWriter::new(
+            Cursor::new(Vec::new()),
+            Info {
+                duration: Some(1000.0),
+                ..test_utils::info()
+            },
+            vec![track.clone()],
+        )
+        .unwrap();
+        first.write_cluster(0, &[block(0)]).unwrap();
+        let first = Cursor::new(first.finish().unwrap().into_inner());
+
+        let mut second = MatroskaWriter::new(
+            Cursor::new(Vec::new()),
+            Info {
+                timestamp_scale: 10_000_000,
+                duration: Some(6000.0),
+                ..Default::default()
+            },
+            vec![track],
+        )
+        .unwrap();
+        second.write_cluster(0, &[block(0), block(5000)]).unwrap();
+        let second = Cursor::new(second.finish().unwrap().into_inner());
+
+        let files = vec![
+            MatroskaFile::from_seekable(first).unwrap(),
+            MatroskaFile::from_seekable(second).unwrap(),
+        ];
+        let data = MatroskaMerger::merge(files, Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let timestamps: Vec<_> = reader
+            .frames(1)
+            .map(|frame| frame.unwrap().timestamp_ns / 1_000_000)
+            .collect();
+        assert_eq!(timestamps, [0, 1000, 51000]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(reader.clusters().count(), 3);
+    }
+
+    #[test]
+    fn merge_languages() {
+        let audio = |track_number, language: &str| TrackEntry {
+            language: String::from(language),
+            ..test_utils::track(track_number, 2, "A_OPUS")
+        };
+        let info = Info {
+            duration: Some(1000.0),
+            ..test_utils::info()
+        };
+
+        // The French track of the second file does not continue the English
+        // one of the first file, and the empty Cluster is dropped
+        let mut first = MatroskaWriter::new(
+            Cursor::new(Vec::new()),
+            info.clone(),
+            vec![audio(1, "eng"), audio(2, "fre")],
+        )
+        .unwrap();
+        first
+            .write_cluster(0, &[test_utils::block(1, 0, true)])
+            .unwrap();
+        first.write_cluster(500, &[]).unwrap();
+        let first = Cursor::new(first.finish().unwrap().into_inner());
+
+        let mut second =
+            MatroskaWriter::new(Cursor::new(Vec::new()), info, vec![audio(1, "fre")]).unwrap();
+        second
+            .write_cluster(0, &[test_utils::block(1, 0, true)])
+            .unwrap();
+        let second = Cursor::new(second.finish().unwrap().into_inner());
+
+        let files = vec![
+            MatroskaFile::from_seekable(first).unwrap(),
+            MatroskaFile::from_seekable(second).unwrap(),
+        ];
+        let data = MatroskaMerger::merge(files, Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner();
+
+        let file = MatroskaFile::from_seekable(Cursor::new(&data)).unwrap();
+        let languages: Vec<_> = file
+            .tracks()
+            .unwrap()
+            .tracks
+            .iter()
+            .map(|track| track.language.as_str())
+            .collect();
+        assert_eq!(languages, ["eng", "fre"]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let clusters: Vec<_> = reader
+            .clusters()
+            .map(|cluster| {
+                let cluster = cluster.unwrap().cluster;
+                let tracks: Vec<_> = cluster.blocks().map(|b| b.track_number()).collect();
+                (cluster.timestamp, tracks)
+            })
+            .collect();
+        assert_eq!(clusters, [(0, vec![1]), (1000, vec![2])]);
+    }
+
+    #[test]
+    fn merge_block_groups() {
+        let video = TrackEntry {
+            track_uid: 10,
+            ..test_utils::track(1, 1, "V_MPEG4/ISO/AVC")
+        };
+        let audio = TrackEntry {
+            track_uid: 20,
+            default_duration: Some(10_000_000),
+            ..test_utils::track(2, 2, "A_AAC")
+        };
+        let info = |duration| Info {
+            duration: Some(duration),
+            ..test_utils::info()
+        };
+        let simple_block = |track_number, timestamp, lacing, data: &'static [u8]| SimpleBlock {
+            track_number,
+            timestamp,
+            keyframe: true,
+            invisible: false,
+            lacing,
+            discardable: false,
+            data: data.into(),
+        };
+
+        let first = MatroskaWriter::new(
+            Cursor::new(Vec::new()),
+            info(100.0),
+            vec![video.clone(), audio.clone()],
+        )
+        .unwrap();
+        let first = Cursor::new(first.finish().unwrap().into_inner());
+
+        // A keyframe, a laced audio block of 3 frames, then a BlockGroup
+        // referencing the keyframe
+        let mut second =
+            MatroskaWriter::new(Cursor::new(Vec::new()), info(100.0), vec![video, audio]).unwrap();
+        second
+            .write_cluster_element(&Cluster {
+                timestamp: 0,
+                silent_tracks: None,
+                position: None,
+                prev_size: None,
+                simple_block: vec![
+                    simple_block(1, 0, Lacing::None, &[1]),
+                    simple_block(2, 0, Lacing::FixedSize, &[2, 2, 3, 4]),
+                ],
+                block_group: vec![BlockGroup {
+                    block: Block {
+                        track_number: 1,
+                        timestamp: 40,
+                        invisible: false,
+                        lacing: Lacing::None,
+                        data: (&[5][..]).into(),
+                    },
+                    block_additions: None,
+                    block_duration: Some(40),
+                    reference_priority: 0,
+                    reference_block: vec![-40],
+                    codec_state: None,
+                    discard_padding: None,
+                }],
+                block_order: vec![
+                    BlockKind::SimpleBlock,
+                    BlockKind::BlockGroup,
+                    BlockKind::SimpleBlock,
+                ],
+            })
+            .unwrap();
+        let second = Cursor::new(second.finish().unwrap().into_inner());
+
+        let files = vec![
+            MatroskaFile::from_seekable(first).unwrap(),
+            MatroskaFile::from_seekable(second).unwrap(),
+        ];
+        let data = MatroskaMerger::merge(files, Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let cluster = reader
+            .clusters()
+            .map(|cluster| cluster.unwrap().cluster)
+            .find(|cluster| !cluster.block_order.is_empty())
+            .unwrap();
+        assert_eq!(cluster.timestamp, 100);
+        let blocks: Vec<_> = cluster
+            .blocks()
+            .map(|block| (block.track_number(), block.timestamp(), block.duration()))
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                (1, 0, None),
+                (1, 40, Some(40)),
+                (2, 0, None),
+                (2, 10, None),
+                (2, 20, None)
+            ]
+        );
+        assert_eq!(cluster.block_group[0].reference_block, [-40]);
+        assert_eq!(cluster.simple_block[3].data, &[4][..]);
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::io::Write;
 use std::sync::Arc;
 
@@ -17,8 +18,7 @@ use crate::{
         cookie_utils::tuple,
         ebml::{gen_ebml_header, EbmlSize},
         elements::{
-            gen_cluster, gen_info, gen_seek_head, gen_segment_header_unknown_size,
-            gen_simple_block_header, gen_tracks,
+            gen_cluster, gen_info, gen_seek_head, gen_segment_header_unknown_size, gen_tracks,
         },
     },
 };
@@ -29,7 +29,7 @@ pub struct MkvMuxer {
     seek_head: SeekHead,
     info: Option<Info>,
     tracks: Option<Tracks>,
-    blocks: Vec<Vec<u8>>,
+    blocks: Vec<SimpleBlock<'static>>,
     blocks_len: usize,
     timestamp: Option<u64>,
 }
@@ -292,8 +292,6 @@ impl Muxer for MkvMuxer {
     }
 
     fn write_packet<W: Write>(&mut self, out: &mut Writer<W>, pkt: Arc<Packet>) -> Result<()> {
-        let mut v = Vec::with_capacity(16);
-
         let s = SimpleBlock {
             track_number: pkt.stream_index as u64 + 1,
             timestamp: pkt.t.pts.or(pkt.t.dts).unwrap_or(0) as i16,
@@ -301,38 +299,11 @@ impl Muxer for MkvMuxer {
             invisible: false,
             lacing: Lacing::None,
             discardable: false,
+            data: Cow::Owned(pkt.data.clone()),
         };
 
-        let mut origin = (v).as_ptr() as usize;
-        let mut needed = 0usize;
-        let offset;
-        loop {
-            if needed > 0 {
-                let len = needed + v.len();
-                v.resize(len, 0);
-                origin = (v).as_ptr() as usize;
-            }
-
-            match gen_simple_block_header(&s)((&mut v, 0)) {
-                Err(GenError::BufferTooSmall(sz)) => {
-                    needed = sz;
-                }
-                Err(e) => {
-                    error!("{:?}", e);
-                    return Err(Error::InvalidData);
-                }
-                Ok((sl, sz)) => {
-                    offset = sl.as_ptr() as usize + sz - origin;
-                    break;
-                }
-            };
-        }
-        v.truncate(offset);
-
-        v.extend(pkt.data.iter());
-        let len = v.len();
-        self.blocks.push(v);
-        self.blocks_len += len;
+        self.blocks_len += s.size(0xA3);
+        self.blocks.push(s);
 
         self.timestamp = if self.timestamp.is_none() {
             Some(pkt.t.pts.or(pkt.t.dts).unwrap_or(0) as u64)
@@ -342,14 +313,14 @@ impl Muxer for MkvMuxer {
 
         if pkt.is_key || self.blocks_len >= 5242880 {
             {
-                let simple_blocks: Vec<&[u8]> = self.blocks.iter().map(|v| &v[..]).collect();
-
                 let cluster = Cluster {
                     timestamp: self.timestamp.take().unwrap(),
+                    silent_tracks: None,
                     position: None,
                     prev_size: None,
-                    simple_block: simple_blocks,
+                    simple_block: std::mem::take(&mut self.blocks),
                     block_group: Vec::new(),
+                    block_order: Vec::new(),
                 };
 
                 let mut buf: Vec<u8> = vec![0; cluster.size(0x1F43B675)];
@@ -381,7 +352,6 @@ impl Muxer for MkvMuxer {
                 out.write_all(&buf).unwrap();
             }
 
-            self.blocks.truncate(0);
             self.blocks_len = 0;
         }
 
@@ -392,14 +362,14 @@ impl Muxer for MkvMuxer {
         let nb = self.blocks.len();
 
         if nb > 0 {
-            let simple_blocks: Vec<&[u8]> = self.blocks.iter().map(|v| &v[..]).collect();
-
             let cluster = Cluster {
                 timestamp: self.timestamp.take().unwrap(),
+                silent_tracks: None,
                 position: None,
                 prev_size: None,
-                simple_block: simple_blocks,
+                simple_block: std::mem::take(&mut self.blocks),
                 block_group: Vec::new(),
+                block_order: Vec::new(),
             };
 
             let mut buf: Vec<u8> = vec![0; cluster.size(0x1F43B675)];
@@ -0,0 +1,219 @@
+//! A demuxer interface independent of any media framework, so that the
+//! Matroska demuxer can fill a type-erased demuxer slot of a pipeline.
+//!
+//! See [demuxer](crate::demuxer) for the implementation of the
+//! `av-format` demuxer.
+
+use std::collections::VecDeque;
+use std::io::{Read, Seek};
+
+use crate::elements::TrackEntry;
+use crate::file::MatroskaFile;
+use crate::reader::Frame;
+use crate::Error;
+
+/// The packets returned by [Demuxer::read_packet], the frames of the
+/// tracks.
+pub type Packet = Frame;
+
+/// The description of the media, returned by [Demuxer::open].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DemuxInfo {
+    /// The duration in nanoseconds, if stored in the file.
+    pub duration: Option<u64>,
+    pub tracks: Vec<TrackEntry>,
+}
+
+/// How [Demuxer::seek] positions the demuxer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeekFlags {
+    /// Start at the first packet at or after the timestamp, instead of
+    /// the keyframe before it. The packets returned may then not be
+    /// decodable on their own.
+    pub any: bool,
+}
+
+/// A demuxer returning the packets of all the tracks in storage order.
+pub trait Demuxer {
+    /// Reads the headers of the media.
+    fn open(&mut self) -> Result<DemuxInfo, Error>;
+
+    /// Returns the next packet, or `None` at the end of the media.
+    fn read_packet(&mut self) -> Result<Option<Packet>, Error>;
+
+    /// Moves to `timestamp_ns` and returns the timestamp of the next packet.
+    fn seek(&mut self, timestamp_ns: u64, flags: SeekFlags) -> Result<u64, Error>;
+}
+
+/// The [Demuxer] of Matroska files, backed by a [MatroskaFile].
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::pipeline::{Demuxer, MatroskaDemuxer, SeekFlags};
+///
+/// let mut demuxer: Box<dyn Demuxer> = Box::new(MatroskaDemuxer::new(File::open("video.mkv")?));
+/// let info = demuxer.open()?;
+/// println!("{} tracks", info.tracks.len());
+///
+/// demuxer.seek(10_000_000_000, SeekFlags::default())?;
+/// while let Some(packet) = demuxer.read_packet()? {
+///     println!("{} {}", packet.track_number, packet.timestamp_ns);
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaDemuxer<R> {
+    source: Option<R>,
+    file: Option<MatroskaFile<R>>,
+    // Packets of the last Cluster read, not returned yet
+    pending: VecDeque<Packet>,
+}
+
+impl<R: Read + Seek> MatroskaDemuxer<R> {
+    /// Creates a demuxer reading from `source` once opened.
+    pub fn new(source: R) -> Self {
+        MatroskaDemuxer {
+            source: Some(source),
+            file: None,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // The file, read on first use.
+    fn file(&mut self) -> Result<&mut MatroskaFile<R>, Error> {
+        if let Some(source) = self.source.take() {
+            // Reading the elements listed in the SeekHead moved the reader
+            let mut file = MatroskaFile::from_seekable(source)?;
+            file.reader_mut().seek_to_byte_offset(0)?;
+            self.file = Some(file);
+        }
+
+        Ok(self.file.as_mut().expect("the source is read on first use"))
+    }
+
+    fn peek_packet(&mut self) -> Result<Option<&Packet>, Error> {
+        while self.pending.is_empty() {
+            let reader = self.file.as_mut().expect("opened").reader_mut();
+            if !reader.read_frames(None, &mut self.pending)? {
+                return Ok(None);
+            }
+        }
+
+        Ok(self.pending.front())
+    }
+}
+
+impl<R: Read + Seek> Demuxer for MatroskaDemuxer<R> {
+    fn open(&mut self) -> Result<DemuxInfo, Error> {
+        let file = self.file()?;
+        let duration = file.info().and_then(|info| {
+            let scale = match info.timestamp_scale {
+                0 => 1_000_000,
+                scale => scale,
+            };
+            info.duration
+                .map(|duration| (duration * scale as f64) as u64)
+        });
+
+        Ok(DemuxInfo {
+            duration,
+            tracks: file.tracks().map_or(Vec::new(), |t| t.tracks.clone()),
+        })
+    }
+
+    fn read_packet(&mut self) -> Result<Option<Packet>, Error> {
+        self.file()?;
+        self.peek_packet()?;
+
+        Ok(self.pending.pop_front())
+    }
+
+    /// Seeks with the Cues of the first video track, or of the first track
+    /// if there is no video. Without Cues, reading starts again from the
+    /// first Cluster.
+    fn seek(&mut self, timestamp_ns: u64, flags: SeekFlags) -> Result<u64, Error> {
+        let file = self.file()?;
+        let tracks = file.tracks().map_or(&[][..], |t| &t.tracks);
+        let track_number = tracks
+            .iter()
+            .find(|t| t.track_type == 1)
+            .or(tracks.first())
+            .map(|t| t.track_number);
+
+        let reader = file.reader_mut();
+        let res = match track_number {
+            Some(track_number) => reader.seek_to_timestamp(track_number, timestamp_ns),
+            None => Err(Error::MissingCues),
+        };
+        match res {
+            Err(Error::MissingCues) => reader.seek_to_byte_offset(0)?,
+            res => {
+                res?;
+            }
+        }
+        self.pending.clear();
+
+        loop {
+            let Some(packet) = self.peek_packet()? else {
+                return Ok(timestamp_ns);
+            };
+            if !flags.any || packet.timestamp_ns >= timestamp_ns {
+                return Ok(packet.timestamp_ns);
+            }
+            self.pending.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    #[test]
+    fn demux() {
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut demuxer: Box<dyn Demuxer> =
+            Box::new(MatroskaDemuxer::new(File::open(path).unwrap()));
+
+        let info = demuxer.open().unwrap();
+        assert_eq!(info.tracks.len(), 2);
+        assert_eq!(info.duration, Some(87_336_000_000));
+
+        let packets: Vec<_> = (0..20)
+            .map(|_| demuxer.read_packet().unwrap().unwrap())
+            .collect();
+        assert!(packets[0].keyframe && packets[0].timestamp_ns == 0);
+        assert!(packets.iter().any(|p| p.track_number == 1));
+        assert!(packets.iter().any(|p| p.track_number == 2));
+
+        // The Cues of the video track lead to the Cluster of its keyframe
+        // at 1.042 s, starting with audio
+        let timestamp = demuxer.seek(10_000_000_000, SeekFlags::default()).unwrap();
+        assert_eq!(timestamp, 1_032_000_000);
+        let packet = demuxer.read_packet().unwrap().unwrap();
+        assert_eq!((packet.track_number, packet.timestamp_ns), (2, timestamp));
+        let video = std::iter::from_fn(|| demuxer.read_packet().unwrap())
+            .find(|p| p.track_number == 1)
+            .unwrap();
+        assert!(video.keyframe);
+        assert_eq!(video.timestamp_ns, 1_042_000_000);
+
+        let timestamp = demuxer
+            .seek(10_000_000_000, SeekFlags { any: true })
+            .unwrap();
+        assert!((10_000_000_000..10_100_000_000).contains(&timestamp));
+        assert_eq!(
+            demuxer.read_packet().unwrap().unwrap().timestamp_ns,
+            timestamp
+        );
+
+        let mut count = 0;
+        while demuxer.read_packet().unwrap().is_some() {
+            count += 1;
+        }
+        assert!(count > 0);
+        assert!(demuxer.read_packet().unwrap().is_none());
+    }
+}
@@ -0,0 +1,26 @@
+//! The commonly used types and functions, to import them all at once.
+//!
+//! ```no_run
+//! use std::fs::File;
+//!
+//! use matroska::prelude::*;
+//!
+//! let mut reader = MatroskaReader::new(File::open("video.mkv")?)?;
+//! while let Some(element) = reader.next_element()? {
+//!     if let SegmentElement::Cluster(cluster) = element {
+//!         println!("{} blocks", cluster.simple_block.len());
+//!     }
+//! }
+//! # Ok::<(), Error>(())
+//! ```
+
+pub use crate::ebml::{ebml_header, EbmlHeader};
+pub use crate::elements::{
+    segment, segment_element, Audio, Block, BlockGroup, Cluster, ElementId, Info, SegmentElement,
+    SimpleBlock, TrackEntry, Tracks, Video,
+};
+pub use crate::file::MatroskaFile;
+pub use crate::reader::{Frame, MatroskaReader, MatroskaReaderBuilder};
+pub use crate::serializer::ebml::EbmlSize;
+pub use crate::writer::{MatroskaWriter, RawBlock};
+pub use crate::Error;
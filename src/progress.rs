@@ -0,0 +1,353 @@
+//! Progress reporting while a source is read, see [ProgressReader].
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::ebml::{vid, vint};
+
+/// Wraps a source and reports the progress through it once per Cluster.
+///
+/// The reader follows the element headers of the bytes read through it,
+/// and calls `F` with the number of bytes read and the total size of the
+/// source on the `read` calls that reach the end of a Cluster, so a
+/// [MatroskaReader](crate::reader::MatroskaReader) reading ahead of the
+/// Clusters it returns reports them as they are buffered. The total is
+/// `None` for the sources created with [ProgressReader::new], and the
+/// length of the source for the ones created with
+/// [ProgressReader::with_total].
+///
+/// After a seek, the headers are followed again from the new position,
+/// which has to be the start of a Top-Level Element for the Clusters to be
+/// found.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::progress::ProgressReader;
+/// use matroska::reader::MatroskaReader;
+///
+/// let source = ProgressReader::with_total(File::open("video.mkv")?, |read, total| {
+///     if let Some(total) = total {
+///         println!("{}%", read * 100 / total);
+///     }
+/// })?;
+/// for element in MatroskaReader::new(source)? {
+///     element?;
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct ProgressReader<R, F> {
+    inner: R,
+    on_progress: F,
+    total: Option<u64>,
+    // Offset in the source of the next byte read
+    position: u64,
+    // Offset up to which the headers were followed
+    parsed: u64,
+    // Offset of the next element header, and its bytes read so far
+    next: u64,
+    header: Vec<u8>,
+    // End of the Cluster being read if its size is known, or whether a
+    // Cluster of unknown size is being read
+    cluster_end: Option<u64>,
+    in_unknown_cluster: bool,
+    // Set on invalid data, the headers are no longer followed
+    lost: bool,
+}
+
+// IDs of the Top-Level Elements, which end a Cluster of unknown size, and
+// of the EBML Header, which starts a chained Segment.
+const TOP_LEVEL_IDS: [u32; 9] = [
+    0x1A45DFA3, 0x114D9B74, 0x1549A966, 0x1654AE6B, 0x1F43B675, 0x1C53BB6B, 0x1941A469, 0x1043A770,
+    0x1254C367,
+];
+
+impl<R: Read, F: FnMut(u64, Option<u64>)> ProgressReader<R, F> {
+    /// Wraps a source whose size is unknown, read from its start.
+    pub fn new(inner: R, on_progress: F) -> Self {
+        ProgressReader {
+            inner,
+            on_progress,
+            total: None,
+            position: 0,
+            parsed: 0,
+            next: 0,
+            header: Vec::new(),
+            cluster_end: None,
+            in_unknown_cluster: false,
+            lost: false,
+        }
+    }
+
+    /// The number of bytes read from the source, or its position after a
+    /// seek.
+    pub fn bytes_read(&self) -> u64 {
+        self.position
+    }
+
+    /// The wrapped source.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    // Follows the headers of `buf`, read at `self.position`, and returns
+    // whether the end of a Cluster was reached.
+    fn follow(&mut self, buf: &[u8]) -> bool {
+        if self.parsed != self.position {
+            self.restart_at(self.position);
+        }
+
+        let start = self.position;
+        let end = start + buf.len() as u64;
+        self.parsed = end;
+        if self.lost {
+            return false;
+        }
+
+        let mut crossed = false;
+        loop {
+            if self
+                .cluster_end
+                .is_some_and(|cluster_end| cluster_end <= end)
+            {
+                self.cluster_end = None;
+                crossed = true;
+            }
+
+            // Bytes of the next header in this buffer
+            let from = self.next + self.header.len() as u64;
+            if from >= end {
+                break;
+            }
+            let from = (from.max(start) - start) as usize;
+            let until = buf.len().min(from + 12 - self.header.len());
+            self.header.extend_from_slice(&buf[from..until]);
+
+            let (id, size, header_len) = match header(&self.header) {
+                Ok(Some(header)) => header,
+                Ok(None) => break,
+                Err(()) => {
+                    self.lost = true;
+                    break;
+                }
+            };
+            self.header.clear();
+            let data = self.next + header_len;
+
+            if self.in_unknown_cluster && TOP_LEVEL_IDS.contains(&id) {
+                self.in_unknown_cluster = false;
+                crossed = true;
+            }
+            self.next = match (id, size) {
+                // The children of the Segment and of the Clusters of unknown
+                // size are followed
+                (0x18538067, _) => data,
+                (0x1F43B675, None) => {
+                    self.in_unknown_cluster = true;
+                    data
+                }
+                (0x1F43B675, Some(size)) => {
+                    self.cluster_end = Some(data.saturating_add(size));
+                    data.saturating_add(size)
+                }
+                (_, Some(size)) => data.saturating_add(size),
+                (_, None) => {
+                    self.lost = true;
+                    break;
+                }
+            };
+        }
+
+        crossed
+    }
+
+    // Follows the headers again from `position`.
+    fn restart_at(&mut self, position: u64) {
+        self.parsed = position;
+        self.next = position;
+        self.header.clear();
+        self.cluster_end = None;
+        self.in_unknown_cluster = false;
+        self.lost = false;
+    }
+}
+
+impl<R: Read + Seek, F: FnMut(u64, Option<u64>)> ProgressReader<R, F> {
+    /// Wraps a source whose total is its length, read from its current
+    /// position.
+    pub fn with_total(mut inner: R, on_progress: F) -> io::Result<Self> {
+        let position = inner.stream_position()?;
+        let total = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(position))?;
+
+        let mut reader = ProgressReader::new(inner, on_progress);
+        reader.total = Some(total);
+        reader.restart_at(position);
+        reader.position = position;
+        Ok(reader)
+    }
+}
+
+impl<R: Read, F: FnMut(u64, Option<u64>)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        let mut crossed = self.follow(&buf[..n]);
+        // The end of the source ends a Cluster of unknown size
+        if n == 0 && !buf.is_empty() && self.in_unknown_cluster {
+            self.in_unknown_cluster = false;
+            crossed = true;
+        }
+        self.position += n as u64;
+
+        if crossed {
+            (self.on_progress)(self.position, self.total);
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek, F: FnMut(u64, Option<u64>)> Seek for ProgressReader<R, F> {
+    // The headers are followed from the new position on the next read, so
+    // seeking back to the current position, as when the length of the
+    // source is looked up, keeps them.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+// The ID, the size, `None` if unknown, and the length of the element
+// header at the start of `data`, `None` if it is incomplete.
+fn header(data: &[u8]) -> Result<Option<(u32, Option<u64>, u64)>, ()> {
+    match vid(data).and_then(|(i, id)| vint(i).map(|(rest, size)| (rest, id, size))) {
+        Ok((rest, id, size)) => {
+            let header_len = data.len() - rest.len();
+            let id_len = 1 + data[0].leading_zeros() as usize;
+            let vint_len = header_len - id_len;
+            let unknown = size == (1 << (7 * vint_len)) - 1;
+            Ok(Some((id, (!unknown).then_some(size), header_len as u64)))
+        }
+        Err(nom::Err::Incomplete(_)) if data.len() < 12 => Ok(None),
+        Err(_) => Err(()),
+    }
+}
+
+#[cfg(test)]
+#[allow(non_upper_case_globals)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::SegmentElement;
+    use crate::reader::MatroskaReader;
+    use crate::serializer::ebml::{encode_element, encode_master};
+
+    const webm: &[u8] = include_bytes!("../assets/bbb-vp9-opus.webm");
+
+    // Offsets in the source of the ends of the Clusters.
+    fn cluster_ends(data: &[u8]) -> Vec<u64> {
+        let mut reader = MatroskaReader::new(Cursor::new(data)).unwrap();
+        let mut ends = Vec::new();
+        while let Some(element) = reader.next_element().unwrap() {
+            if let SegmentElement::Cluster(_) = element {
+                ends.push(reader.bytes_read());
+            }
+        }
+        ends
+    }
+
+    #[test]
+    fn known_sizes() {
+        let mut progress = Vec::new();
+        let mut read = Vec::new();
+        ProgressReader::with_total(Cursor::new(webm), |read, total| {
+            progress.push((read, total))
+        })
+        .unwrap()
+        .read_to_end(&mut read)
+        .unwrap();
+        assert_eq!(read, webm);
+
+        // Each read reaching the end of a Cluster is reported once
+        let total = Some(webm.len() as u64);
+        assert!(!progress.is_empty());
+        assert!(progress.iter().all(|&(_, t)| t == total));
+
+        // Whatever the size of the reads
+        let ends = cluster_ends(webm);
+        let mut progress = Vec::new();
+        let mut reader = ProgressReader::new(webm, |read, total| progress.push((read, total)));
+        let mut buf = [0; 7];
+        while reader.read(&mut buf).unwrap() > 0 {}
+        drop(reader);
+        let expected: Vec<_> = ends
+            .iter()
+            .map(|&end| (end.div_ceil(7) * 7, None))
+            .collect();
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(read, t)| (read.min(webm.len() as u64), t))
+            .collect();
+        assert_eq!(progress, expected);
+    }
+
+    #[test]
+    fn unknown_sizes() {
+        let (rest, _) = crate::ebml::ebml_header(webm).unwrap();
+        let mut data = webm[..webm.len() - rest.len()].to_vec();
+        let mut info = encode_element(0x4D80, b"test").unwrap();
+        info.extend(encode_element(0x5741, b"test").unwrap());
+        data.extend(encode_master(0x18538067, None).unwrap());
+        data.extend(encode_element(0x1549A966, &info).unwrap());
+        let mut ends = Vec::new();
+        for timestamp in [0u8, 10] {
+            data.extend(encode_master(0x1F43B675, None).unwrap());
+            data.extend(encode_element(0xE7, &[timestamp]).unwrap());
+            data.extend(encode_element(0xA3, &[0x81, 0, 0, 0x80, 1, 2, 3]).unwrap());
+            ends.push(data.len() as u64);
+        }
+        data.extend(encode_element(0x1654AE6B, &[]).unwrap());
+        data.extend(encode_master(0x1F43B675, None).unwrap());
+        data.extend(encode_element(0xE7, &[20]).unwrap());
+        ends.push(data.len() as u64);
+
+        let mut progress = Vec::new();
+        let mut reader = ProgressReader::new(&data[..], |read, _| progress.push(read));
+        let mut buf = [0; 1];
+        while reader.read(&mut buf).unwrap() > 0 {}
+        drop(reader);
+
+        // A Cluster of unknown size ends at the next Top-Level Element, once
+        // its header is read, or at the end of the source
+        assert_eq!(progress, [ends[0] + 12, ends[1] + 5, ends[2]]);
+    }
+
+    #[test]
+    fn with_matroska_reader() {
+        let mut progress = Vec::new();
+        let source = ProgressReader::with_total(Cursor::new(webm), |read, total| {
+            progress.push((read, total))
+        })
+        .unwrap();
+        let mut reader = MatroskaReader::new(source).unwrap();
+        let clusters = cluster_ends(webm).len();
+        let mut read = 0;
+        while let Some(element) = reader.next_element().unwrap() {
+            read += matches!(element, SegmentElement::Cluster(_)) as usize;
+        }
+        assert_eq!(read, clusters);
+
+        // The reader seeks back to the start, the Clusters are reported
+        // again while they are read
+        reader.seek_to_byte_offset(0).unwrap();
+        while reader.next_element().unwrap().is_some() {}
+        drop(reader);
+
+        assert!(!progress.is_empty());
+        assert!(progress
+            .iter()
+            .all(|&(read, total)| total == Some(webm.len() as u64) && read <= webm.len() as u64));
+        let restarts = progress.windows(2).filter(|w| w[1].0 <= w[0].0).count();
+        assert_eq!(restarts, 1);
+    }
+}
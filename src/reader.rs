@@ -0,0 +1,2662 @@
+use std::cmp::min;
+#[cfg(feature = "encryption")]
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use circular::Buffer;
+use nom::{sequence::pair, Offset};
+
+use crate::ebml::{
+    ebml_header, elem_size, element_size, is_unknown_size, vid, vint, with_crc_validation,
+    EbmlHeader, EbmlResult,
+};
+use crate::elements::{
+    segment, segment_element, Attachments, Chapters, Cluster, Cues, ElementId, Info, SeekHead,
+    SegmentElement, Tags, TrackEntry, Tracks,
+};
+#[cfg(feature = "encryption")]
+use crate::encryption::{decrypt_cluster, KEY_SIZE};
+use crate::webm::{self, WebmViolation};
+use crate::Error;
+
+/// Default capacity of the internal buffer, 5 MiB.
+pub const DEFAULT_BUFFER_SIZE: usize = 5_242_880;
+
+/// Smallest capacity accepted by [MatroskaReaderBuilder::with_buffer_size],
+/// 64 KiB.
+pub const MIN_BUFFER_SIZE: usize = 65_536;
+
+/// Reads the elements of a Matroska Segment from any [Read] source.
+///
+/// The EBML Header and the Segment header are parsed when the reader is
+/// created, the Top-Level Elements can then be read one by one with
+/// [MatroskaReader::next_element], or by iterating over the reader.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::elements::SegmentElement;
+/// use matroska::reader::MatroskaReader;
+///
+/// let mut reader = MatroskaReader::new(File::open("video.mkv")?)?;
+/// println!("doc type: {}", reader.header().doc_type);
+///
+/// while let Some(element) = reader.next_element()? {
+///     if let SegmentElement::Cluster(cluster) = element {
+///         println!("cluster at {}", cluster.timestamp);
+///     }
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaReader<R> {
+    source: R,
+    buffer: Buffer,
+    header: EbmlHeader,
+    segment_size: Option<u64>,
+    // Offset in the source of the first byte of the Segment data
+    segment_start: u64,
+    // Offset in the source of the first byte of `buffer.data()`
+    position: u64,
+    // Size of the element returned by the last `next_element` call,
+    // which is released on the next call
+    pending: usize,
+    seek_head: Option<SeekHead>,
+    info: Option<Info>,
+    tracks: Option<Tracks>,
+    cues: Option<Cues>,
+    chapters: Option<Chapters>,
+    crc_validation: bool,
+    selected_tracks: Option<Vec<u64>>,
+    duration: Option<Duration>,
+    error_recovery: bool,
+    on_error: Option<Box<ErrorCallback>>,
+    // Sizes of the Clusters read, and of the blocks of each track, to
+    // estimate the bitrates. The Clusters before `counted_end` are not
+    // counted again when read after a seek.
+    cluster_bytes: u64,
+    track_bytes: Vec<(u64, u64)>,
+    counted_end: u64,
+    // Entries of the Tracks, once searched for by `track_list`
+    track_list: Option<Vec<TrackEntry>>,
+    // Decryption keys by Track UID
+    #[cfg(feature = "encryption")]
+    keys: HashMap<u64, [u8; KEY_SIZE]>,
+}
+
+type ErrorCallback = dyn FnMut(u64, &Error) -> RecoveryAction + Send;
+
+/// What [MatroskaReader::next_element] does on an error when the recovery
+/// is enabled with [MatroskaReader::with_error_recovery].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Skips the given number of bytes and parses the element there.
+    /// `Skip(0)` skips 1 byte, as parsing again at the same position would
+    /// fail again.
+    Skip(u64),
+    /// Skips to the next Cluster ID, `0x1F43B675`, after the start of the
+    /// failing element.
+    Resync,
+    /// Returns the error.
+    Abort,
+}
+
+/// Configures and creates a [MatroskaReader].
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::reader::MatroskaReaderBuilder;
+///
+/// // Only keep the blocks of the track 2
+/// let reader = MatroskaReaderBuilder::new()
+///     .with_crc_validation(true)
+///     .select_tracks(&[2])
+///     .build(File::open("video.mkv")?)?;
+/// # Ok::<(), matroska::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MatroskaReaderBuilder {
+    crc_validation: bool,
+    error_recovery: bool,
+    selected_tracks: Option<Vec<u64>>,
+    buffer_size: Option<usize>,
+}
+
+impl MatroskaReaderBuilder {
+    /// Creates a builder with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables the validation of the CRC-32 Elements, see
+    /// [MatroskaReader::with_crc_validation].
+    pub fn with_crc_validation(mut self, enabled: bool) -> Self {
+        self.crc_validation = enabled;
+        self
+    }
+
+    /// Enables the recovery from errors, see
+    /// [MatroskaReader::with_error_recovery].
+    pub fn with_error_recovery(mut self, enabled: bool) -> Self {
+        self.error_recovery = enabled;
+        self
+    }
+
+    /// Only keeps the blocks of the given tracks in the Clusters returned
+    /// by the reader, the blocks of the other tracks are dropped when the
+    /// Cluster is parsed, before their payload is copied or decrypted.
+    pub fn select_tracks(mut self, track_numbers: &[u64]) -> Self {
+        self.selected_tracks = Some(track_numbers.to_vec());
+        self
+    }
+
+    /// Sets the capacity of the internal buffer, [DEFAULT_BUFFER_SIZE] by
+    /// default.
+    ///
+    /// The master elements other than the Segment are read whole into the
+    /// buffer, so it must be larger than the largest Cluster, Cues or
+    /// Attachments of the file, or reading them fails with
+    /// [Error::BufferTooSmall].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is less than [MIN_BUFFER_SIZE].
+    pub fn with_buffer_size(mut self, bytes: usize) -> Self {
+        assert!(
+            bytes >= MIN_BUFFER_SIZE,
+            "the buffer size must be at least {MIN_BUFFER_SIZE} bytes"
+        );
+        self.buffer_size = Some(bytes);
+        self
+    }
+
+    /// Creates the reader and parses the EBML Header and the Segment header.
+    pub fn build<R: Read>(self, source: R) -> Result<MatroskaReader<R>, Error> {
+        let mut reader = MatroskaReader {
+            source,
+            buffer: Buffer::with_capacity(self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE)),
+            header: placeholder_header(),
+            segment_size: None,
+            segment_start: 0,
+            position: 0,
+            pending: 0,
+            seek_head: None,
+            info: None,
+            tracks: None,
+            cues: None,
+            chapters: None,
+            crc_validation: self.crc_validation,
+            selected_tracks: self.selected_tracks,
+            duration: None,
+            error_recovery: self.error_recovery,
+            on_error: None,
+            cluster_bytes: 0,
+            track_bytes: Vec::new(),
+            counted_end: 0,
+            track_list: None,
+            #[cfg(feature = "encryption")]
+            keys: HashMap::new(),
+        };
+
+        reader.fill_element()?.ok_or(Error::UnexpectedEof)?;
+        reader.header = reader.parse(ebml_header)?;
+
+        let start = reader.position;
+        let (_, size) = reader.parse(segment)?;
+        reader.segment_start = reader.position;
+        reader.segment_size = size.filter(|&size| !is_unknown_size(size, reader.position - start));
+
+        Ok(reader)
+    }
+}
+
+impl MatroskaReader<&[u8]> {
+    /// Checks if `data`, the start of a file, holds the EBML Header of a
+    /// Matroska or WebM file, without allocating.
+    ///
+    /// Returns false if the DocType is not within `data`.
+    ///
+    /// ```
+    /// use matroska::reader::MatroskaReader;
+    ///
+    /// let data = std::fs::read("assets/bbb-vp9-opus.webm")?;
+    /// assert!(MatroskaReader::probe(&data[..64]));
+    /// assert!(!MatroskaReader::probe(b"RIFF\x24\x08\x00\x00WAVE"));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn probe(data: &[u8]) -> bool {
+        let Ok((mut input, (0x1A45DFA3, size))) = pair(vid, vint)(data) else {
+            return false;
+        };
+        if let Ok(size) = usize::try_from(size) {
+            input = &input[..min(size, input.len())];
+        }
+
+        while let Ok((i, (id, size))) = pair(vid, vint)(input) {
+            let Some(value) = usize::try_from(size).ok().and_then(|size| i.get(..size)) else {
+                return false;
+            };
+            if id == 0x4282 {
+                // Strings can be padded with zeros
+                let end = value.iter().rposition(|&b| b != 0).map_or(0, |p| p + 1);
+                return matches!(&value[..end], b"matroska" | b"webm");
+            }
+            input = &i[value.len()..];
+        }
+
+        false
+    }
+}
+
+impl<R: Read> MatroskaReader<R> {
+    /// Creates a reader and parses the EBML Header and the Segment header.
+    ///
+    /// See [MatroskaReaderBuilder] for the available options.
+    pub fn new(source: R) -> Result<Self, Error> {
+        MatroskaReaderBuilder::new().build(source)
+    }
+
+    /// Enables the validation of the CRC-32 Elements.
+    ///
+    /// The checksums are not verified by default, as computing them means
+    /// reading every byte of the Clusters. When enabled, reading an
+    /// element whose CRC-32 does not match returns [Error::CrcMismatch].
+    pub fn with_crc_validation(mut self, enabled: bool) -> Self {
+        self.crc_validation = enabled;
+        self
+    }
+
+    /// Enables the recovery from the errors of corrupted or truncated
+    /// files in [MatroskaReader::next_element].
+    ///
+    /// When an element cannot be read, the callback set with
+    /// [MatroskaReader::on_error] picks the [RecoveryAction]. Without one,
+    /// the reader resyncs: it searches for the next Cluster ID, as the
+    /// Clusters hold most of the data and the Top-Level Elements of 4
+    /// bytes IDs rarely occur in the block payloads by chance. Errors of
+    /// the source are always returned.
+    ///
+    /// In this mode the returned elements are copied out of the internal
+    /// buffer.
+    pub fn with_error_recovery(mut self, enabled: bool) -> Self {
+        self.error_recovery = enabled;
+        self
+    }
+
+    /// Sets the callback deciding how to recover from an error, and enables
+    /// the recovery. It gets the position, relative to the Segment data,
+    /// of the element that could not be read.
+    pub fn on_error(
+        mut self,
+        on_error: impl FnMut(u64, &Error) -> RecoveryAction + Send + 'static,
+    ) -> Self {
+        self.error_recovery = true;
+        self.on_error = Some(Box::new(on_error));
+        self
+    }
+
+    /// The EBML Header of the file.
+    pub fn header(&self) -> &EbmlHeader {
+        &self.header
+    }
+
+    /// Checks the DocType, and the Tracks and Chapters read so far,
+    /// against the WebM profile: VP8, VP9 or AV1 video, Vorbis or Opus
+    /// audio, WebVTT text, no compression and no ordered editions.
+    ///
+    /// See [WebmReader](crate::webm::WebmReader) to also reject the
+    /// Attachments.
+    pub fn validate_webm_profile(&self) -> Result<(), Vec<WebmViolation>> {
+        let mut violations = Vec::new();
+        if self.header.doc_type != "webm" {
+            violations.push(WebmViolation::DocType(self.header.doc_type.clone()));
+        }
+        if let Some(tracks) = &self.tracks {
+            violations.extend(webm::check_tracks(tracks));
+        }
+        if let Some(chapters) = &self.chapters {
+            violations.extend(webm::check_chapters(chapters));
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(violations),
+        }
+    }
+
+    /// The offset in the source of the end of the last element returned
+    /// by [MatroskaReader::next_element], the bytes read so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.position + self.pending as u64
+    }
+
+    /// The size of the Segment data, or `None` if it is unknown
+    /// (e.g. for live streams).
+    pub fn segment_size(&self) -> Option<u64> {
+        self.segment_size
+    }
+
+    /// The SeekHead element, once it has been read.
+    ///
+    /// The entries of the SeekHeads read afterwards are merged into it,
+    /// see [MatroskaReader::load_seek_heads] to read the secondary
+    /// SeekHeads it points to.
+    pub fn seek_head(&self) -> Option<&SeekHead> {
+        self.seek_head.as_ref()
+    }
+
+    /// The Info element, once it has been read.
+    pub fn info(&self) -> Option<&Info> {
+        self.info.as_ref()
+    }
+
+    /// The Tracks element, once it has been read.
+    pub fn tracks(&self) -> Option<&Tracks> {
+        self.tracks.as_ref()
+    }
+
+    /// The Cues element, once it has been read.
+    pub fn cues(&self) -> Option<&Cues> {
+        self.cues.as_ref()
+    }
+
+    /// The Chapters element, once it has been read.
+    pub fn chapters(&self) -> Option<&Chapters> {
+        self.chapters.as_ref()
+    }
+
+    /// The average bitrate of the Segment in bits per second, from the
+    /// Duration of the Info.
+    ///
+    /// The size is the one of the Clusters read so far, or the size of the
+    /// Segment before the first Cluster is read. Returns `None` without a
+    /// Duration.
+    pub fn total_bitrate(&self) -> Option<u64> {
+        let bytes = match self.cluster_bytes {
+            0 => self.segment_size?,
+            bytes => bytes,
+        };
+        self.bitrate(bytes)
+    }
+
+    /// The average bitrate of a track in bits per second, from the Duration
+    /// of the Info and the size of the blocks of the track read so far.
+    /// Returns `None` without a Duration.
+    pub fn track_bitrate(&self, track_number: u64) -> Option<u64> {
+        let bytes = self
+            .track_bytes
+            .iter()
+            .find(|t| t.0 == track_number)
+            .map_or(0, |t| t.1);
+        self.bitrate(bytes)
+    }
+
+    fn bitrate(&self, bytes: u64) -> Option<u64> {
+        let duration = self.info.as_ref()?.duration? * self.timestamp_scale() as f64;
+        if duration.is_nan() || duration <= 0.0 {
+            return None;
+        }
+        Some((bytes as f64 * 8.0 * 1e9 / duration).round() as u64)
+    }
+
+    /// A rough number of frames of the track, for progress reporting.
+    ///
+    /// It is the Duration of the Info divided by the DefaultDuration of
+    /// the track, or at least the number of Cue Points of the track, which
+    /// usually index its keyframes. Returns `None` without the Duration
+    /// and the Cues read so far.
+    pub fn packet_count_estimate(&self, track: u64) -> Option<u64> {
+        let cue_points = self.cues.as_ref().map(|cues| {
+            cues.cue_points
+                .iter()
+                .filter(|point| point.track_positions.iter().any(|p| p.track == track))
+                .count() as u64
+        });
+
+        let default_duration = self
+            .tracks()?
+            .tracks
+            .iter()
+            .find(|t| t.track_number == track)?
+            .default_duration
+            .filter(|&d| d > 0);
+        let duration = self.info.as_ref().and_then(|info| info.duration);
+        let from_duration = match (duration, default_duration) {
+            (Some(duration), Some(default_duration)) => {
+                let ns = duration * self.timestamp_scale() as f64;
+                Some((ns / default_duration as f64).ceil() as u64)
+            }
+            _ => None,
+        };
+
+        match (from_duration, cue_points) {
+            (Some(count), cue_points) => Some(count.max(cue_points.unwrap_or(0))),
+            (None, cue_points) => cue_points,
+        }
+    }
+
+    /// Sets the AES-128 key used to decrypt the blocks of the track with
+    /// the given UID, see [TrackEntry::encryption_key_id] for the ID of
+    /// the key a track needs.
+    ///
+    /// The blocks of the Clusters returned by the reader are then decrypted,
+    /// once the Tracks element has been read. Available with the
+    /// `encryption` feature.
+    ///
+    /// [TrackEntry::encryption_key_id]: crate::elements::TrackEntry::encryption_key_id
+    #[cfg(feature = "encryption")]
+    pub fn set_decryption_key(&mut self, track_uid: u64, key: &[u8]) -> Result<(), Error> {
+        let key = key
+            .try_into()
+            .map_err(|_| Error::InvalidKeyLength(key.len()))?;
+        self.keys.insert(track_uid, key);
+        Ok(())
+    }
+
+    /// Reads the next Top-Level Element of the Segment.
+    ///
+    /// Returns `Ok(None)` at the end of the Segment. The returned element
+    /// borrows the internal buffer, see the [Iterator] implementation for
+    /// owned elements. The SeekHead, Info, Tracks, Cues and Chapters
+    /// elements are kept by the reader as they are read.
+    pub fn next_element(&mut self) -> Result<Option<SegmentElement<'_>>, Error> {
+        if !self.error_recovery {
+            return self.read_element();
+        }
+
+        loop {
+            // Owned elements end the borrow of the buffer before recovering
+            let position = self.next_element_position();
+            match self
+                .read_element()
+                .map(|e| e.map(SegmentElement::into_owned))
+            {
+                Err(Error::Io(e)) => return Err(Error::Io(e)),
+                Err(e) => self.recover(position, e)?,
+                res => return res,
+            }
+        }
+    }
+
+    fn read_element(&mut self) -> Result<Option<SegmentElement<'_>>, Error> {
+        self.consume(self.pending);
+        self.pending = 0;
+
+        if let Some(size) = self.segment_size {
+            if self.position >= self.segment_start + size {
+                return Ok(None);
+            }
+        }
+
+        let size = match self.fill_element()? {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+
+        let data = &self.buffer.data()[..size];
+        match with_crc_validation(self.crc_validation, || segment_element(data)) {
+            Ok((_, mut element)) => {
+                match &mut element {
+                    SegmentElement::SeekHead(s) => match &mut self.seek_head {
+                        // A secondary SeekHead lists the elements missing
+                        // from the first one
+                        Some(seek_head) => {
+                            for seek in &s.positions {
+                                if !seek_head.positions.contains(seek) {
+                                    seek_head.positions.push(seek.clone());
+                                }
+                            }
+                        }
+                        None => self.seek_head = Some(s.clone()),
+                    },
+                    SegmentElement::Info(i) => self.info = Some(i.clone()),
+                    SegmentElement::Tracks(t) => self.tracks = Some(t.clone()),
+                    SegmentElement::Cues(c) => self.cues = Some(c.clone()),
+                    SegmentElement::Chapters(c) => self.chapters = Some(c.clone()),
+                    SegmentElement::Cluster(c) => {
+                        if let Some(selected) = &self.selected_tracks {
+                            c.retain_tracks(|track_number| selected.contains(&track_number));
+                        }
+
+                        #[cfg(feature = "encryption")]
+                        if !self.keys.is_empty() {
+                            let tracks = self.tracks.as_ref().map_or(&[][..], |t| &t.tracks);
+                            decrypt_cluster(c, |track_number| {
+                                let track =
+                                    tracks.iter().find(|t| t.track_number == track_number)?;
+                                self.keys.get(&track.track_uid)
+                            })?;
+                        }
+
+                        if self.position >= self.counted_end {
+                            self.counted_end = self.position + size as u64;
+                            self.cluster_bytes += size as u64;
+
+                            let blocks = c.simple_block.iter().map(|b| (b.track_number, &b.data));
+                            let groups = c
+                                .block_group
+                                .iter()
+                                .map(|g| (g.block.track_number, &g.block.data));
+                            for (track_number, data) in blocks.chain(groups) {
+                                match self.track_bytes.iter_mut().find(|t| t.0 == track_number) {
+                                    Some((_, bytes)) => *bytes += data.len() as u64,
+                                    None => {
+                                        self.track_bytes.push((track_number, data.len() as u64))
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                self.pending = size;
+                Ok(Some(element))
+            }
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Err(e.into()),
+            Err(nom::Err::Incomplete(_)) => Err(Error::UnexpectedEof),
+        }
+    }
+
+    pub(crate) fn timestamp_scale(&self) -> u64 {
+        match self.info.as_ref() {
+            Some(info) if info.timestamp_scale > 0 => info.timestamp_scale,
+            _ => 1_000_000,
+        }
+    }
+
+    // Position relative to the Segment data of the element the next
+    // `next_element` call returns
+    pub(crate) fn next_element_position(&self) -> u64 {
+        self.position + self.pending as u64 - self.segment_start
+    }
+
+    /// Iterates over the frames of a track from the current position.
+    ///
+    /// The frames come in the order they are stored in, i.e. in decoding
+    /// order. The frames of a laced block follow each other: each one
+    /// starts at the end of the previous one, from the DefaultDuration of
+    /// the track or the BlockDuration split between the frames.
+    pub fn frames(&mut self, track_number: u64) -> Frames<'_, R> {
+        Frames {
+            reader: self,
+            track_number,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Iterates over the packets of a subtitle track from the current
+    /// position.
+    ///
+    /// The packets are the frames of the track, with the duration of their
+    /// BlockGroup. The text formats, such as SRT, ASS and WebVTT, hold UTF-8
+    /// text, while HDMV PGS packets hold images.
+    pub fn subtitle_packets(&mut self, track_number: u64) -> SubtitlePackets<'_, R> {
+        SubtitlePackets {
+            frames: self.frames(track_number),
+        }
+    }
+
+    /// Iterates over the Clusters from the current position, skipping the
+    /// other elements.
+    ///
+    /// The Info read before the first Cluster gives the TimestampScale of
+    /// their timestamps in nanoseconds.
+    pub fn clusters(&mut self) -> Clusters<'_, R> {
+        Clusters { reader: self }
+    }
+
+    // Queues the frames of the track, or of all the tracks, from the next
+    // element if it is a Cluster, returns false at the end of the Segment.
+    pub(crate) fn read_frames(
+        &mut self,
+        track_number: Option<u64>,
+        pending: &mut VecDeque<Frame>,
+    ) -> Result<bool, Error> {
+        let scale = self.timestamp_scale();
+        let default_durations: Vec<_> = self
+            .tracks()
+            .map_or(&[][..], |tracks| &tracks.tracks)
+            .iter()
+            .map(|track| (track.track_number, track.default_duration))
+            .collect();
+        let default_duration = |track_number| {
+            default_durations
+                .iter()
+                .find(|&&(number, _)| number == track_number)
+                .and_then(|&(_, duration)| duration)
+        };
+        let selected = |number| track_number.is_none_or(|track_number| track_number == number);
+
+        let cluster = match self.next_element()? {
+            Some(SegmentElement::Cluster(cluster)) => cluster,
+            Some(_) => return Ok(true),
+            None => return Ok(false),
+        };
+
+        for block in cluster.blocks() {
+            let track_number = block.track_number();
+            if !selected(track_number) {
+                continue;
+            }
+
+            let frames = block.frames()?;
+            let timestamp = cluster.block_timestamp_ns(block.timestamp(), scale);
+            // The frames of a laced block follow each other, the
+            // BlockDuration covering all of them
+            let duration = match block.duration() {
+                Some(duration) => Some(duration.saturating_mul(scale) / frames.len().max(1) as u64),
+                None => default_duration(track_number),
+            };
+            let keyframe = block.keyframe();
+
+            pending.extend(frames.into_iter().enumerate().map(|(i, data)| Frame {
+                track_number,
+                timestamp_ns: duration.map_or(timestamp, |duration| {
+                    timestamp.saturating_add(duration.saturating_mul(i as u64))
+                }),
+                duration_ns: duration,
+                keyframe,
+                data: data.to_vec(),
+            }));
+        }
+
+        Ok(true)
+    }
+
+    // Makes sure the whole element at the start of the buffer is available
+    // and returns its size, or `None` if the source ended between elements.
+    fn fill_element(&mut self) -> Result<Option<usize>, Error> {
+        let size = loop {
+            match element_size(self.buffer.data()) {
+                Ok((_, size)) => break size,
+                Err(nom::Err::Incomplete(_)) => {
+                    if self.fill()? == 0 {
+                        return if self.buffer.empty() {
+                            Ok(None)
+                        } else {
+                            Err(Error::UnexpectedEof)
+                        };
+                    }
+                }
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => return Err(e.into()),
+            }
+        };
+
+        if size > self.buffer.capacity() {
+            return Err(Error::BufferTooSmall(size));
+        }
+
+        while self.buffer.available_data() < size {
+            if self.fill()? == 0 {
+                return Err(Error::UnexpectedEof);
+            }
+        }
+
+        Ok(Some(size))
+    }
+
+    // Runs a parser returning owned data on the buffer, refilling it as needed.
+    fn parse<O>(&mut self, parser: impl Fn(&[u8]) -> EbmlResult<'_, O>) -> Result<O, Error> {
+        loop {
+            match with_crc_validation(self.crc_validation, || parser(self.buffer.data())) {
+                Ok((i, o)) => {
+                    let consumed = self.buffer.data().offset(i);
+                    self.consume(consumed);
+                    return Ok(o);
+                }
+                Err(nom::Err::Incomplete(_)) => {
+                    if self.fill()? == 0 {
+                        return Err(Error::UnexpectedEof);
+                    }
+                }
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    // Applies the action picked for the error of the element at the start
+    // of the buffer.
+    fn recover(&mut self, position: u64, error: Error) -> Result<(), Error> {
+        let action = match &mut self.on_error {
+            Some(on_error) => on_error(position, &error),
+            None => RecoveryAction::Resync,
+        };
+        log::warn!("could not read the element at {position}: {error}, {action:?}");
+
+        match action {
+            RecoveryAction::Skip(count) => self.skip(count.max(1)),
+            RecoveryAction::Resync => {
+                self.skip(1)?;
+                self.resync()
+            }
+            RecoveryAction::Abort => Err(error),
+        }
+    }
+
+    // Skips `count` bytes, or up to the end of the source.
+    fn skip(&mut self, mut count: u64) -> Result<(), Error> {
+        loop {
+            let available = min(count, self.buffer.available_data() as u64);
+            self.consume(available as usize);
+            count -= available;
+
+            if count == 0 || self.fill()? == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    // Skips to the next Cluster ID, or to the end of the source.
+    fn resync(&mut self) -> Result<(), Error> {
+        const CLUSTER_ID: [u8; 4] = [0x1F, 0x43, 0xB6, 0x75];
+
+        loop {
+            let data = self.buffer.data();
+            if let Some(offset) = data.windows(4).position(|w| w == CLUSTER_ID) {
+                self.consume(offset);
+                return Ok(());
+            }
+
+            // The end of the data can start an ID
+            let count = data.len().saturating_sub(3);
+            self.consume(count);
+            if self.fill()? == 0 {
+                let count = self.buffer.available_data();
+                self.consume(count);
+                return Ok(());
+            }
+        }
+    }
+
+    fn fill(&mut self) -> Result<usize, Error> {
+        if self.buffer.available_space() == 0 {
+            self.buffer.shift();
+        }
+
+        let sz = self.source.read(self.buffer.space())?;
+        self.buffer.fill(sz);
+
+        Ok(sz)
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.position += self.buffer.consume(count) as u64;
+    }
+}
+
+impl<R: Read + Seek> MatroskaReader<R> {
+    /// The fraction of the file read so far, from 0.0 to 1.0, for progress
+    /// reporting.
+    ///
+    /// The size of the file is the end of the Segment, or the length of
+    /// the source when the Segment size is unknown. It is `None` if the
+    /// length of the source cannot be found.
+    pub fn estimated_position(&mut self) -> Option<f64> {
+        let total = match self.segment_size {
+            Some(size) => self.segment_start + size,
+            None => self.source_len().ok()?,
+        };
+        Some((self.bytes_read() as f64 / total as f64).min(1.0))
+    }
+
+    // The length of the source, seeking back to the current position of
+    // the source afterwards.
+    fn source_len(&mut self) -> std::io::Result<u64> {
+        let current = self.source.stream_position()?;
+        let len = self.source.seek(SeekFrom::End(0))?;
+        if len != current {
+            self.source.seek(SeekFrom::Start(current))?;
+        }
+        Ok(len)
+    }
+
+    /// Moves the reader to the Cluster holding the closest keyframe of
+    /// `track` at or before `timestamp_ns`, and returns its offset in
+    /// the source.
+    ///
+    /// The Cues are located through the SeekHead if they were not read
+    /// yet. The next call to [MatroskaReader::next_element] returns
+    /// the Cluster.
+    pub fn seek_to_timestamp(&mut self, track: u64, timestamp_ns: u64) -> Result<u64, Error> {
+        if self.cues.is_none() {
+            self.load_cues()?;
+        }
+
+        let timestamp_scale = self.timestamp_scale();
+        let (_, positions) = self
+            .cues
+            .as_ref()
+            .and_then(|cues| cues.lookup(track, timestamp_ns / timestamp_scale))
+            .ok_or(Error::MissingCues)?;
+
+        let offset = self.segment_start + positions.cluster_position;
+        self.seek(offset)?;
+
+        Ok(offset)
+    }
+
+    /// Finds the first keyframe of `track` after `after_timestamp_ns`, and
+    /// returns it with its absolute timestamp.
+    ///
+    /// The scan starts at the Cluster of the last cue point of the track at
+    /// or before `after_timestamp_ns`, so that the keyframes that are not
+    /// indexed are found as well, or at the first Cluster if the Cues do
+    /// not reference the track. The reader is left after the Cluster
+    /// holding the keyframe. Returns `None` if the track has no keyframe
+    /// after the timestamp.
+    pub fn next_keyframe(
+        &mut self,
+        track: u64,
+        after_timestamp_ns: u64,
+    ) -> Result<Option<Frame>, Error> {
+        if self.cues.is_none() {
+            self.load_cues()?;
+        }
+
+        let timestamp_scale = self.timestamp_scale();
+        let position = self
+            .cues
+            .as_ref()
+            .and_then(|cues| cues.lookup(track, after_timestamp_ns / timestamp_scale))
+            .map(|(_, positions)| positions.cluster_position);
+        match position {
+            Some(position) => self.seek_in_segment(position)?,
+            None => self.seek(self.segment_start)?,
+        }
+
+        let mut pending = VecDeque::new();
+        while self.read_frames(Some(track), &mut pending)? {
+            if let Some(frame) = pending
+                .drain(..)
+                .find(|frame| frame.keyframe && frame.timestamp_ns > after_timestamp_ns)
+            {
+                return Ok(Some(frame));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The duration of the Segment.
+    ///
+    /// It comes from the Info element when it has a Duration, otherwise
+    /// the whole Segment is scanned for the end of the last block: its
+    /// timestamp plus its BlockDuration, or the DefaultDuration of its
+    /// track for each of its frames. The result is cached, and the
+    /// position of the reader is preserved.
+    pub fn calculate_duration(&mut self) -> Result<Duration, Error> {
+        if let Some(duration) = self.duration {
+            return Ok(duration);
+        }
+
+        let resume = self.position + self.pending as u64;
+        let found = self.find_duration();
+        self.seek(resume)?;
+        let duration = found?;
+        self.duration = Some(duration);
+
+        Ok(duration)
+    }
+
+    fn find_duration(&mut self) -> Result<Duration, Error> {
+        if self.info.is_none() {
+            self.find_info()?;
+        }
+        if let Some(ticks) = self.info.as_ref().and_then(|info| info.duration) {
+            return Ok(Duration::from_nanos(
+                (ticks * self.timestamp_scale() as f64) as u64,
+            ));
+        }
+
+        self.seek(self.segment_start)?;
+        let mut end = None;
+        loop {
+            let scale = self.timestamp_scale();
+            let default_durations: Vec<_> = self
+                .tracks()
+                .map_or(&[][..], |tracks| &tracks.tracks)
+                .iter()
+                .map(|track| (track.track_number, track.default_duration))
+                .collect();
+            let default_duration = |track_number| {
+                default_durations
+                    .iter()
+                    .find(|&&(number, _)| number == track_number)
+                    .and_then(|&(_, duration)| duration)
+            };
+
+            let cluster = match self.next_element()? {
+                Some(SegmentElement::Cluster(cluster)) => cluster,
+                Some(_) => continue,
+                None => break,
+            };
+            for block in cluster.blocks() {
+                let duration = match block.duration() {
+                    Some(duration) => duration.saturating_mul(scale),
+                    None => default_duration(block.track_number()).map_or(0, |duration| {
+                        let frames = block.frames().map_or(1, |frames| frames.len());
+                        duration.saturating_mul(frames as u64)
+                    }),
+                };
+                let start = cluster.block_timestamp_ns(block.timestamp(), scale);
+                end = end.max(Some(start.saturating_add(duration)));
+            }
+        }
+
+        end.map(Duration::from_nanos).ok_or(Error::DurationUnknown)
+    }
+
+    fn find_info(&mut self) -> Result<(), Error> {
+        self.seek(self.segment_start)?;
+        while let Some(element) = self.next_element()? {
+            match element {
+                SegmentElement::Info(_) => return Ok(()),
+                SegmentElement::Cluster(_) => break,
+                _ => {}
+            }
+        }
+
+        self.load_seek_heads()?;
+        let position = self
+            .seek_head
+            .as_ref()
+            .and_then(|seek_head| seek_head.position_for_element(ElementId::INFO));
+        if let Some(position) = position {
+            self.seek_in_segment(position)?;
+            self.next_element()?;
+        }
+
+        Ok(())
+    }
+
+    // Moves the reader to a position relative to the Segment data,
+    // as found in the SeekHead and the Cues
+    pub(crate) fn seek_in_segment(&mut self, position: u64) -> Result<(), Error> {
+        self.seek(self.segment_start + position)
+    }
+
+    /// Moves the reader to `offset`, relative to the start of the
+    /// Segment data, and resumes parsing there.
+    ///
+    /// This allows recovering from a SeekHead with wrong positions. The
+    /// caller is responsible for landing on the start of an EBML element:
+    /// otherwise the next call to [MatroskaReader::next_element] fails or
+    /// returns garbage.
+    pub fn seek_to_byte_offset(&mut self, offset: u64) -> Result<(), Error> {
+        self.seek_in_segment(offset)
+    }
+
+    /// Moves the reader to the first Cluster, without parsing the elements
+    /// before it.
+    ///
+    /// The Cluster is located with the Cues or the SeekHead read so far,
+    /// otherwise the elements from the current position are skipped using
+    /// their size until a Cluster starts. The next call to [MatroskaReader::next_element] returns the
+    /// Cluster, or `None` if the Segment has none.
+    pub fn skip_to_clusters(&mut self) -> Result<(), Error> {
+        const CLUSTER_ID: u32 = 0x1F43B675;
+
+        let position = self
+            .cues
+            .iter()
+            .flat_map(|cues| &cues.cue_points)
+            .flat_map(|point| &point.track_positions)
+            .map(|positions| positions.cluster_position)
+            .min()
+            .or_else(|| {
+                self.seek_head
+                    .as_ref()
+                    .and_then(|seek_head| seek_head.position_for_element(ElementId::CLUSTER))
+            });
+        if let Some(position) = position {
+            return self.seek_in_segment(position);
+        }
+
+        self.consume(self.pending);
+        self.pending = 0;
+        loop {
+            if let Some(size) = self.segment_size {
+                if self.position >= self.segment_start + size {
+                    return Ok(());
+                }
+            }
+
+            let (id, size) = loop {
+                let data = self.buffer.data();
+                let header = pair(vid, elem_size)(data)
+                    .map(|(i, (id, size))| (id, data.offset(i) as u64 + size as u64));
+                match header {
+                    Ok(header) => break header,
+                    Err(nom::Err::Incomplete(_)) => {
+                        if self.fill()? == 0 {
+                            return Ok(());
+                        }
+                    }
+                    Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => return Err(e.into()),
+                }
+            };
+            if id == CLUSTER_ID {
+                return Ok(());
+            }
+
+            self.seek(self.position.saturating_add(size))?;
+        }
+    }
+
+    // The ID of the element at `position` in the Segment, `None` if there
+    // is no valid ID there. The position of the reader is restored.
+    pub(crate) fn element_id_at(&mut self, position: u64) -> Result<Option<u32>, Error> {
+        let resume = self.position + self.pending as u64;
+        self.seek_in_segment(position)?;
+
+        let id = loop {
+            match vid(self.buffer.data()) {
+                Ok((_, id)) => break Some(id),
+                Err(nom::Err::Incomplete(_)) => {
+                    if self.fill()? == 0 {
+                        break None;
+                    }
+                }
+                Err(_) => break None,
+            }
+        };
+
+        self.seek(resume)?;
+        Ok(id)
+    }
+
+    // The bytes of the first Cluster, header included. The position of the
+    // reader is restored.
+    pub(crate) fn first_cluster_bytes(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let resume = self.position + self.pending as u64;
+        self.seek(self.segment_start)?;
+        self.skip_to_clusters()?;
+
+        let data = match self.fill_element()? {
+            Some(size) if self.buffer.data().starts_with(&[0x1F, 0x43, 0xB6, 0x75]) => {
+                Some(self.buffer.data()[..size].to_vec())
+            }
+            _ => None,
+        };
+
+        self.seek(resume)?;
+        Ok(data)
+    }
+
+    /// Reads the secondary SeekHeads listed in the SeekHead, so that
+    /// [MatroskaReader::seek_head] holds the entries of all of them.
+    ///
+    /// The position of the reader is restored afterwards.
+    pub fn load_seek_heads(&mut self) -> Result<(), Error> {
+        let resume = self.position + self.pending as u64;
+
+        let mut read = Vec::new();
+        loop {
+            let position = self.seek_head.as_ref().and_then(|seek_head| {
+                seek_head
+                    .positions
+                    .iter()
+                    .find(|seek| {
+                        seek.element_id() == ElementId::SEEK_HEAD && !read.contains(&seek.position)
+                    })
+                    .map(|seek| seek.position)
+            });
+            let Some(position) = position else {
+                break;
+            };
+
+            read.push(position);
+            self.seek_in_segment(position)?;
+            self.next_element()?;
+        }
+
+        self.seek(resume)
+    }
+
+    /// The entries of the Tracks, which is read first if it was not yet.
+    ///
+    /// The Tracks is searched for among the elements before the first
+    /// Cluster, then through the SeekHead, and the position of the reader is
+    /// restored afterwards. The result is cached, so the next calls return
+    /// right away, with an empty slice if the file has no Tracks.
+    pub fn track_list(&mut self) -> Result<&[TrackEntry], Error> {
+        if self.track_list.is_none() {
+            if self.tracks.is_none() {
+                let resume = self.position + self.pending as u64;
+                let found = self.find_tracks();
+                self.seek(resume)?;
+                found?;
+            }
+
+            let tracks = self.tracks.as_ref().map(|t| t.tracks.clone());
+            self.track_list = Some(tracks.unwrap_or_default());
+        }
+
+        Ok(self.track_list.as_deref().unwrap_or_default())
+    }
+
+    fn find_tracks(&mut self) -> Result<(), Error> {
+        self.seek(self.segment_start)?;
+        while let Some(element) = self.next_element()? {
+            match element {
+                SegmentElement::Tracks(_) => return Ok(()),
+                SegmentElement::Cluster(_) => break,
+                _ => {}
+            }
+        }
+
+        self.load_seek_heads()?;
+        let position = self
+            .seek_head
+            .as_ref()
+            .and_then(|seek_head| seek_head.position_for_element(ElementId::TRACKS));
+        if let Some(position) = position {
+            self.seek_in_segment(position)?;
+            self.next_element()?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the data of the attached file named `name`, ignoring the case,
+    /// or returns `None` if there is no such file.
+    ///
+    /// The Attachments are located through the SeekHead, or found among the
+    /// elements before the first Cluster if no SeekHead was read. They are
+    /// not kept by the reader, so they are read again on every call. The
+    /// position of the reader is restored afterwards.
+    pub fn read_attachment_by_name(&mut self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let resume = self.position + self.pending as u64;
+        let data = self.find_attachment(name);
+        self.seek(resume)?;
+        data
+    }
+
+    /// Reads the metadata of the file without the Clusters: the elements
+    /// before the first Cluster, then the ones the SeekHead points to.
+    ///
+    /// The Cues are read as well, and kept by the reader. The position of
+    /// the reader is restored afterwards.
+    pub fn metadata_only(&mut self) -> Result<MatroskaMetadata, Error> {
+        let resume = self.position + self.pending as u64;
+        let metadata = self.read_metadata();
+        self.seek(resume)?;
+        metadata
+    }
+
+    fn read_metadata(&mut self) -> Result<MatroskaMetadata, Error> {
+        let mut tags = Vec::new();
+        let mut attachments = None;
+        let mut read = Vec::new();
+        self.seek(self.segment_start)?;
+        while let Some(element) = self.next_element()? {
+            match element {
+                SegmentElement::Cluster(_) => break,
+                SegmentElement::Void(_) | SegmentElement::Unknown(..) => continue,
+                _ => read.push(element.id()),
+            }
+            keep(element, &mut tags, &mut attachments);
+        }
+
+        self.load_seek_heads()?;
+        // The SeekHeads are merged when read and the Clusters skipped
+        read.extend([0x114D9B74, 0x1F43B675]);
+
+        let positions = self.seek_head.as_ref().map_or(Vec::new(), |seek_head| {
+            seek_head
+                .positions
+                .iter()
+                .filter(|seek| !read.contains(&u32::from_be_bytes(seek.id)))
+                .map(|seek| seek.position)
+                .collect()
+        });
+        for position in positions {
+            self.seek_in_segment(position)?;
+            if let Some(element) = self.next_element()? {
+                keep(element, &mut tags, &mut attachments);
+            }
+        }
+
+        Ok(MatroskaMetadata {
+            info: self.info.clone(),
+            tracks: self.tracks.clone(),
+            chapters: self.chapters.clone(),
+            tags,
+            attachments,
+        })
+    }
+
+    fn find_attachment(&mut self, name: &str) -> Result<Option<Vec<u8>>, Error> {
+        let data =
+            |attachments: &Attachments| attachments.get_by_name(name).map(|file| file.data.clone());
+
+        if self.seek_head.is_none() {
+            self.seek(self.segment_start)?;
+            while let Some(element) = self.next_element()? {
+                match element {
+                    SegmentElement::Attachments(attachments) => return Ok(data(&attachments)),
+                    SegmentElement::Cluster(_) => break,
+                    _ => {}
+                }
+            }
+        }
+
+        self.load_seek_heads()?;
+
+        let position = self
+            .seek_head
+            .as_ref()
+            .and_then(|seek_head| seek_head.position_for_element(ElementId::ATTACHMENTS));
+        let Some(position) = position else {
+            return Ok(None);
+        };
+
+        self.seek_in_segment(position)?;
+        match self.next_element()? {
+            Some(SegmentElement::Attachments(attachments)) => Ok(data(&attachments)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads all the Clusters and checks the CueClusterPosition and the
+    /// CueTime of every Cue Point against them.
+    ///
+    /// Each position must be the start of a Cluster holding a block of the
+    /// track at the CueTime. Fails with [Error::MissingCues] if the Segment
+    /// has no Cues. The position of the reader is restored afterwards.
+    pub fn verify_index(&mut self) -> Result<Vec<IndexError>, Error> {
+        let resume = self.position + self.pending as u64;
+        let clusters = self.scan_clusters();
+        self.seek(resume)?;
+        let clusters = clusters?;
+
+        let cues = self.cues.as_ref().ok_or(Error::MissingCues)?;
+        let mut errors = Vec::new();
+        for point in &cues.cue_points {
+            for positions in &point.track_positions {
+                let cue_block = (positions.track, point.time);
+                let offset = positions.cluster_position;
+
+                let Some(cluster) = clusters.iter().find(|c| c.position == offset) else {
+                    let actual = clusters
+                        .iter()
+                        .find(|c| c.blocks.contains(&cue_block))
+                        .or_else(|| clusters.iter().rev().find(|c| c.position < offset))
+                        .map_or(0, |c| c.position);
+                    errors.push(IndexError::OffsetMismatch {
+                        expected: offset,
+                        actual,
+                    });
+                    continue;
+                };
+
+                if !cluster.blocks.contains(&cue_block) {
+                    let actual = cluster
+                        .blocks
+                        .iter()
+                        .find(|(track, _)| *track == positions.track)
+                        .map_or(cluster.timestamp, |(_, timestamp)| *timestamp);
+                    errors.push(IndexError::TimestampMismatch {
+                        expected: point.time,
+                        actual,
+                        cue_offset: offset,
+                    });
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+
+    // The position and the blocks of every Cluster, from the start of the
+    // Segment
+    fn scan_clusters(&mut self) -> Result<Vec<ScannedCluster>, Error> {
+        let mut clusters = Vec::new();
+        self.seek(self.segment_start)?;
+        loop {
+            let position = self.next_element_position();
+            match self.next_element()? {
+                Some(SegmentElement::Cluster(cluster)) => {
+                    let blocks = cluster
+                        .simple_block
+                        .iter()
+                        .map(|b| (b.track_number, b.timestamp))
+                        .chain(
+                            cluster
+                                .block_group
+                                .iter()
+                                .map(|g| (g.block.track_number, g.block.timestamp)),
+                        )
+                        .map(|(track, relative)| (track, cluster.block_timestamp(relative)))
+                        .collect();
+                    clusters.push(ScannedCluster {
+                        position,
+                        timestamp: cluster.timestamp,
+                        blocks,
+                    });
+                }
+                Some(_) => {}
+                None => return Ok(clusters),
+            }
+        }
+    }
+
+    fn load_cues(&mut self) -> Result<(), Error> {
+        let resume = self.position + self.pending as u64;
+
+        // The SeekHead and the Info come before the first Cluster
+        if self.seek_head.is_none() || self.info.is_none() {
+            self.seek(self.segment_start)?;
+            while let Some(element) = self.next_element()? {
+                if let SegmentElement::Cluster(_) = element {
+                    break;
+                }
+            }
+        }
+
+        self.load_seek_heads()?;
+
+        let position = self
+            .seek_head
+            .as_ref()
+            .and_then(|seek_head| seek_head.position_for_element(ElementId::CUES));
+
+        if let (None, Some(position)) = (&self.cues, position) {
+            self.seek_in_segment(position)?;
+            self.next_element()?;
+        }
+
+        self.seek(resume)
+    }
+
+    fn seek(&mut self, offset: u64) -> Result<(), Error> {
+        self.source.seek(SeekFrom::Start(offset))?;
+        self.buffer.reset();
+        self.position = offset;
+        self.pending = 0;
+
+        Ok(())
+    }
+}
+
+// The reader keeps the other elements itself
+pub(crate) fn keep(
+    element: SegmentElement<'_>,
+    tags: &mut Vec<Tags>,
+    attachments: &mut Option<Attachments>,
+) {
+    match element {
+        SegmentElement::Tags(t) => tags.push(t),
+        SegmentElement::Attachments(a) => *attachments = Some(a),
+        _ => {}
+    }
+}
+
+/// The elements of a file describing its content, as read by
+/// [MatroskaReader::metadata_only].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatroskaMetadata {
+    pub info: Option<Info>,
+    pub tracks: Option<Tracks>,
+    pub chapters: Option<Chapters>,
+    pub tags: Vec<Tags>,
+    pub attachments: Option<Attachments>,
+}
+
+// A Cluster as checked by MatroskaReader::verify_index, with the track
+// and the timestamp of its blocks
+struct ScannedCluster {
+    position: u64,
+    timestamp: u64,
+    blocks: Vec<(u64, u64)>,
+}
+
+/// A Cue Point not matching the Clusters, see
+/// [MatroskaReader::verify_index].
+///
+/// The offsets are relative to the start of the Segment data and the
+/// timestamps are in TimestampScale units, as in the Cues.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexError {
+    /// The Cluster at the CueClusterPosition has no block of the track at
+    /// the CueTime. `actual` is the timestamp of the first block of the
+    /// track in the Cluster, or the Cluster Timestamp if it has none.
+    TimestampMismatch {
+        expected: u64,
+        actual: u64,
+        cue_offset: u64,
+    },
+    /// No Cluster starts at the CueClusterPosition. `actual` is the position
+    /// of the Cluster holding the block at the CueTime, otherwise of the
+    /// Cluster the position falls in.
+    OffsetMismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::TimestampMismatch {
+                expected,
+                actual,
+                cue_offset,
+            } => write!(
+                f,
+                "expected a block at {expected} in the Cluster at {cue_offset}, found {actual}"
+            ),
+            IndexError::OffsetMismatch { expected, actual } => {
+                write!(f, "expected a Cluster at {expected}, found one at {actual}")
+            }
+        }
+    }
+}
+
+/// A frame of a track, as returned by [MatroskaReader::frames] and
+/// [MatroskaFile::read_video_frame_at](crate::file::MatroskaFile::read_video_frame_at).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub track_number: u64,
+    /// The presentation timestamp, in nanoseconds.
+    pub timestamp_ns: u64,
+    /// The duration in nanoseconds, from the BlockDuration divided by the
+    /// number of laced frames, or the DefaultDuration of the track.
+    pub duration_ns: Option<u64>,
+    pub keyframe: bool,
+    pub data: Vec<u8>,
+}
+
+/// Iterator over the frames of a track, see [MatroskaReader::frames].
+pub struct Frames<'r, R> {
+    reader: &'r mut MatroskaReader<R>,
+    track_number: u64,
+    // Frames of the last Cluster read, not returned yet
+    pending: VecDeque<Frame>,
+}
+
+impl<R: Read> Iterator for Frames<'_, R> {
+    type Item = Result<Frame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            match self
+                .reader
+                .read_frames(Some(self.track_number), &mut self.pending)
+            {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// A packet of a subtitle track, as returned by
+/// [MatroskaReader::subtitle_packets].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitlePacket {
+    pub timestamp_ns: u64,
+    /// The time the subtitle is displayed, from the BlockDuration or the
+    /// default duration of the track, 0 if none is set.
+    pub duration_ns: u64,
+    pub data: Vec<u8>,
+}
+
+impl SubtitlePacket {
+    /// The text of the packet, for the text formats.
+    pub fn as_str(&self) -> Result<&str, std::str::Utf8Error> {
+        std::str::from_utf8(&self.data)
+    }
+}
+
+/// Iterator over the packets of a subtitle track, see
+/// [MatroskaReader::subtitle_packets].
+pub struct SubtitlePackets<'r, R> {
+    frames: Frames<'r, R>,
+}
+
+impl<R: Read> Iterator for SubtitlePackets<'_, R> {
+    type Item = Result<SubtitlePacket, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let packet = self.frames.next()?.map(|frame| SubtitlePacket {
+            timestamp_ns: frame.timestamp_ns,
+            duration_ns: frame.duration_ns.unwrap_or(0),
+            data: frame.data,
+        });
+        Some(packet)
+    }
+}
+
+/// A Cluster with its timestamp in nanoseconds, as returned by
+/// [MatroskaReader::clusters].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampedCluster {
+    pub cluster: Cluster<'static>,
+    /// The timestamp of the Cluster, in nanoseconds.
+    pub timestamp_ns: u64,
+    /// The TimestampScale of the Segment.
+    pub scale: u64,
+}
+
+/// Iterator over the Clusters, see [MatroskaReader::clusters].
+pub struct Clusters<'r, R> {
+    reader: &'r mut MatroskaReader<R>,
+}
+
+impl<R: Read> Iterator for Clusters<'_, R> {
+    type Item = Result<TimestampedCluster, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let scale = self.reader.timestamp_scale();
+            match self.reader.next_element() {
+                Ok(Some(SegmentElement::Cluster(cluster))) => {
+                    return Some(Ok(TimestampedCluster {
+                        timestamp_ns: cluster.timestamp_ns(scale),
+                        cluster: cluster.into_owned(),
+                        scale,
+                    }))
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for MatroskaReader<R> {
+    type Item = Result<SegmentElement<'static>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_element()
+            .map(|element| element.map(SegmentElement::into_owned))
+            .transpose()
+    }
+}
+
+// Stands for the EBML Header until it is parsed.
+pub(crate) fn placeholder_header() -> EbmlHeader {
+    EbmlHeader {
+        version: 1,
+        read_version: 1,
+        max_id_length: 4,
+        max_size_length: 8,
+        doc_type: String::new(),
+        doc_type_version: 1,
+        doc_type_read_version: 1,
+    }
+}
+
+#[cfg(test)]
+#[allow(non_upper_case_globals)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_utils;
+
+    const webm: &[u8] = include_bytes!("../assets/bbb-vp9-opus.webm");
+
+    #[test]
+    fn read_elements() {
+        let mut reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+
+        assert_eq!(reader.header().doc_type, "webm");
+        assert_eq!(reader.segment_size(), Some(23293));
+
+        let mut ids = Vec::new();
+        while let Some(element) = reader.next_element().unwrap() {
+            let id = match element {
+                SegmentElement::SeekHead(_) => 0x114D9B74,
+                SegmentElement::Info(_) => 0x1549A966,
+                SegmentElement::Tracks(_) => 0x1654AE6B,
+                SegmentElement::Cues(_) => 0x1C53BB6B,
+                SegmentElement::Chapters(_) => 0x1043A770,
+                SegmentElement::Attachments(_) => 0x1941A469,
+                SegmentElement::Tags(_) => 0x1254C367,
+                SegmentElement::Cluster(_) => 0x1F43B675,
+                SegmentElement::Void(_) => 0xEC,
+                SegmentElement::Unknown(id, _) => id,
+            };
+            ids.push(id);
+        }
+
+        ids.retain(|&id| id != 0xEC);
+        assert_eq!(&ids[..3], &[0x114D9B74, 0x1549A966, 0x1654AE6B]);
+        assert!(ids.contains(&0x1F43B675));
+        assert!(reader.next_element().unwrap().is_none());
+    }
+
+    #[test]
+    fn probe() {
+        assert!(MatroskaReader::probe(webm));
+        let mkv = std::fs::read("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        assert!(MatroskaReader::probe(&mkv));
+
+        // The DocType of the webm file ends at byte 35
+        assert!(MatroskaReader::probe(&webm[..35]));
+        for len in [0, 4, 5, 34] {
+            assert!(!MatroskaReader::probe(&webm[..len]), "{len}");
+        }
+
+        let mut other = webm[..35].to_vec();
+        other[31..35].copy_from_slice(b"mp4a");
+        assert!(!MatroskaReader::probe(&other));
+        assert!(!MatroskaReader::probe(b"\x00\x00\x00\x20ftypisom"));
+    }
+
+    #[test]
+    fn iterate_owned_elements() {
+        let reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+
+        let clusters: Vec<_> = reader
+            .filter_map(|element| match element.unwrap() {
+                SegmentElement::Cluster(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+
+        assert!(!clusters.is_empty());
+        assert!(clusters[0].simple_block[0].keyframe);
+    }
+
+    #[test]
+    fn unknown_segment_size() {
+        // Mark the 8 bytes Segment size as unknown, like a live stream
+        let mut live = webm.to_vec();
+        live[47..55].copy_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+        let reader = MatroskaReader::new(Cursor::new(live)).unwrap();
+        assert_eq!(reader.segment_size(), None);
+
+        let elements = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert!(elements
+            .iter()
+            .any(|e| matches!(e, SegmentElement::Cluster(_))));
+    }
+
+    #[test]
+    fn select_tracks() {
+        let count_blocks = |reader: MatroskaReader<_>| {
+            let mut counts = [0; 3];
+            for element in reader {
+                if let SegmentElement::Cluster(c) = element.unwrap() {
+                    for block in &c.simple_block {
+                        counts[block.track_number as usize] += 1;
+                    }
+                    for group in &c.block_group {
+                        counts[group.block.track_number as usize] += 1;
+                    }
+                }
+            }
+            counts
+        };
+
+        let all = count_blocks(MatroskaReader::new(Cursor::new(webm)).unwrap());
+        assert!(all[1] > 0 && all[2] > 0);
+
+        let reader = MatroskaReaderBuilder::new()
+            .select_tracks(&[2])
+            .build(Cursor::new(webm))
+            .unwrap();
+        assert_eq!(count_blocks(reader), [0, 0, all[2]]);
+    }
+
+    #[test]
+    fn buffer_size() {
+        use crate::writer::RawBlock;
+
+        let frame = vec![0; 2 * MIN_BUFFER_SIZE];
+        let block = RawBlock {
+            track_number: 1,
+            timestamp: 0,
+            keyframe: true,
+            data: &frame,
+        };
+        let tracks = vec![test_utils::track(1, 1, "V_VP9")];
+        let data = test_utils::segment(tracks, &[(0, &[block])]);
+
+        let cluster = |reader: &mut MatroskaReader<_>| loop {
+            match reader.next_element() {
+                Ok(Some(SegmentElement::Cluster(_))) => break Ok(()),
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("no Cluster"),
+                Err(e) => break Err(e),
+            }
+        };
+
+        let mut reader = MatroskaReaderBuilder::new()
+            .with_buffer_size(MIN_BUFFER_SIZE)
+            .build(Cursor::new(&data))
+            .unwrap();
+        assert!(matches!(
+            cluster(&mut reader),
+            Err(Error::BufferTooSmall(_))
+        ));
+
+        let mut reader = MatroskaReaderBuilder::new()
+            .with_buffer_size(4 * MIN_BUFFER_SIZE)
+            .build(Cursor::new(&data))
+            .unwrap();
+        assert!(cluster(&mut reader).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 65536 bytes")]
+    fn buffer_size_minimum() {
+        MatroskaReaderBuilder::new().with_buffer_size(4096);
+    }
+
+    #[test]
+    fn huge_cluster_timestamp() {
+        let block = |timestamp| test_utils::block(1, timestamp, true);
+        let data = test_utils::segment(
+            vec![test_utils::track(1, 1, "V_VP9")],
+            &[(u64::MAX - 10, &[block(-500), block(100)])],
+        );
+
+        // The timestamps saturate instead of overflowing
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let timestamps: Vec<_> = reader
+            .frames(1)
+            .map(|frame| frame.unwrap().timestamp_ns)
+            .collect();
+        assert_eq!(timestamps, [u64::MAX, u64::MAX]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(
+            reader.calculate_duration().unwrap(),
+            Duration::from_nanos(u64::MAX)
+        );
+        assert!(reader.verify_index().is_ok());
+    }
+
+    #[test]
+    fn negative_block_timestamp() {
+        let block = |timestamp| test_utils::block(1, timestamp, true);
+        let data = test_utils::segment(
+            vec![test_utils::track(1, 1, "V_VP9")],
+            &[(1000, &[block(-500), block(0)])],
+        );
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let timestamps: Vec<_> = reader
+            .frames(1)
+            .map(|frame| frame.unwrap().timestamp_ns)
+            .collect();
+        assert_eq!(timestamps, [500_000_000, 1_000_000_000]);
+    }
+
+    #[test]
+    fn next_keyframe() {
+        let block = |timestamp, keyframe| test_utils::block(1, timestamp, keyframe);
+
+        // Keyframes at 0, 1000, 1500 and 3000 ms, the one at 1500 ms is not
+        // the first block of its Cluster
+        let data = test_utils::segment(
+            vec![test_utils::track(1, 1, "V_VP9")],
+            &[
+                (0, &[block(0, true), block(500, false)]),
+                (1000, &[block(0, true), block(250, false), block(500, true)]),
+                (2000, &[block(0, false), block(1000, true)]),
+            ],
+        );
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let mut next = |after_ms: u64| {
+            reader
+                .next_keyframe(1, after_ms * 1_000_000)
+                .unwrap()
+                .map(|frame| frame.timestamp_ns / 1_000_000)
+        };
+        assert_eq!(next(0), Some(1000));
+        assert_eq!(next(1000), Some(1500));
+        assert_eq!(next(1200), Some(1500));
+        assert_eq!(next(1600), Some(3000));
+        assert_eq!(next(3000), None);
+
+        // Without Cues, the Segment is scanned from the start. The ID of the
+        // Cues is replaced by an unknown one
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let position = loop {
+            let position = reader.next_element_position();
+            if let Some(SegmentElement::Cues(_)) = reader.next_element().unwrap() {
+                break (reader.segment_start + position) as usize;
+            }
+        };
+        let mut no_cues = data.clone();
+        no_cues[position..position + 4].copy_from_slice(&[0x10, 0x00, 0x00, 0x01]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&no_cues)).unwrap();
+        assert_eq!(
+            reader.next_keyframe(1, 1_200_000_000).unwrap(),
+            Some(Frame {
+                track_number: 1,
+                timestamp_ns: 1_500_000_000,
+                duration_ns: None,
+                keyframe: true,
+                data: vec![0; 4],
+            })
+        );
+        assert!(reader.cues().is_none());
+        assert_eq!(reader.next_keyframe(2, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn crc_validation() {
+        // Segment of unknown size holding an Info element with a CRC-32
+        let info = [
+            0x2A, 0xD7, 0xB1, 0x83, 0x0F, 0x42, 0x40, 0x4D, 0x80, 0x81, b'a', 0x57, 0x41, 0x81,
+            b'b',
+        ];
+        let crc = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC).checksum(&info);
+        let mut data = webm[..47].to_vec();
+        data.extend([0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        data.extend([
+            0x15,
+            0x49,
+            0xA9,
+            0x66,
+            0x80 | (info.len() as u8 + 6),
+            0xBF,
+            0x84,
+        ]);
+        data.extend(crc.to_le_bytes());
+        data.extend(info);
+
+        let read_info = |data: &[u8], validate| {
+            let reader = MatroskaReader::new(Cursor::new(data)).unwrap();
+            reader.with_crc_validation(validate).next().unwrap()
+        };
+        assert!(matches!(
+            read_info(&data, true),
+            Ok(SegmentElement::Info(_))
+        ));
+
+        let corrupted = data.len() - 1;
+        data[corrupted] = b'c';
+        assert!(matches!(
+            read_info(&data, false),
+            Ok(SegmentElement::Info(_))
+        ));
+        match read_info(&data, true) {
+            Err(Error::CrcMismatch {
+                element_id,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(element_id, 0x1549A966);
+                assert_eq!(expected, crc);
+                assert_ne!(actual, crc);
+            }
+            res => panic!("res: {res:?}"),
+        }
+    }
+
+    #[cfg(feature = "encryption")]
+    #[test]
+    fn decrypt_blocks() {
+        use crate::encryption::tests::{encrypt, KEY};
+        use crate::writer::RawBlock;
+
+        let tracks = (1..=2)
+            .map(|n| TrackEntry {
+                track_uid: n * 100,
+                ..test_utils::track(n, 1, "V_VP9")
+            })
+            .collect();
+        let encrypted = encrypt([7; 8], b"secret frame");
+        let blocks = [(1, &encrypted[..]), (2, &b"clear frame"[..])].map(|(n, data)| RawBlock {
+            track_number: n,
+            timestamp: 0,
+            keyframe: true,
+            data,
+        });
+        let data = test_utils::segment(tracks, &[(0, &blocks)]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert!(matches!(
+            reader.set_decryption_key(100, &KEY[1..]),
+            Err(Error::InvalidKeyLength(15))
+        ));
+        reader.set_decryption_key(100, KEY).unwrap();
+
+        let cluster = reader
+            .find_map(|e| match e.unwrap() {
+                SegmentElement::Cluster(c) => Some(c),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(&cluster.simple_block[0].data[..], b"secret frame");
+        assert_eq!(&cluster.simple_block[1].data[..], b"clear frame");
+    }
+
+    #[test]
+    fn calculate_duration() {
+        use crate::writer::MatroskaEncoder;
+
+        let file = std::fs::File::open("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        let mut reader = MatroskaReader::new(file).unwrap();
+        let duration = reader.calculate_duration().unwrap();
+        let info = reader.info().unwrap();
+        assert_eq!(
+            duration.as_nanos(),
+            (info.duration.unwrap() * info.timestamp_scale as f64) as u128
+        );
+
+        // Without a Duration in the Info, the clusters are scanned
+        let block = |timestamp| test_utils::block(1, timestamp, true);
+        let tracks = vec![test_utils::track(1, 2, "A_OPUS")];
+        let data = test_utils::segment(
+            tracks.clone(),
+            &[(0, &[block(0), block(20)]), (1000, &[block(60), block(40)])],
+        );
+
+        let second = MatroskaReader::new(Cursor::new(&data)).unwrap().nth(1);
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        reader.next_element().unwrap();
+        assert_eq!(
+            reader.calculate_duration().unwrap(),
+            Duration::from_millis(1060)
+        );
+        assert_eq!(
+            reader.calculate_duration().unwrap(),
+            Duration::from_millis(1060)
+        );
+        assert_eq!(
+            reader.next().map(Result::unwrap),
+            second.map(Result::unwrap)
+        );
+
+        // The last block lasts for the DefaultDuration of its track, or
+        // for its BlockDuration
+        let mut with_default_duration = tracks.clone();
+        with_default_duration[0].default_duration = Some(20_000_000);
+        let data = test_utils::segment(with_default_duration, &[(1000, &[block(60), block(40)])]);
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(
+            reader.calculate_duration().unwrap(),
+            Duration::from_millis(1080)
+        );
+
+        let mut encoder =
+            MatroskaEncoder::new(Vec::new(), test_utils::info(), tracks.clone()).unwrap();
+        encoder.push_frame(1, 0, true, &[0]).unwrap();
+        encoder
+            .push_frame_with_duration(1, 500_000_000, 300_000_000, true, &[0])
+            .unwrap();
+        let data = encoder.finish().unwrap();
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(
+            reader.calculate_duration().unwrap(),
+            Duration::from_millis(800)
+        );
+
+        let data = test_utils::segment(tracks, &[]);
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert!(matches!(
+            reader.calculate_duration(),
+            Err(Error::DurationUnknown)
+        ));
+    }
+
+    #[test]
+    fn seek_to_timestamp() {
+        let file = std::fs::File::open("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        let mut reader = MatroskaReader::new(file).unwrap();
+
+        let offset = reader.seek_to_timestamp(1, 10_000_000_000).unwrap();
+        assert!(reader.cues().is_some());
+
+        let scale = reader.info().unwrap().timestamp_scale;
+        let cluster = match reader.next_element().unwrap() {
+            Some(SegmentElement::Cluster(c)) => c,
+            e => panic!("expected a Cluster at {offset}, got {e:?}"),
+        };
+        // Cue Points are at 0s, 1.042s and 11.667s
+        assert_eq!(scale, 1_000_000);
+        assert_eq!(cluster.timestamp, 1042);
+
+        let first = reader.seek_to_timestamp(1, 0).unwrap();
+        assert!(first < offset);
+
+        assert!(matches!(
+            reader.seek_to_timestamp(42, 0),
+            Err(Error::MissingCues)
+        ));
+    }
+
+    #[test]
+    fn seek_to_byte_offset() {
+        let mut reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        reader.next_element().unwrap();
+        let seek = reader.seek_head().unwrap().positions.clone();
+
+        for seek in seek.iter().rev() {
+            reader.seek_to_byte_offset(seek.position).unwrap();
+            let element = reader.next_element().unwrap().unwrap();
+            assert_eq!(element.id().to_be_bytes(), seek.id);
+        }
+
+        // Parsing continues with the following elements
+        reader.seek_to_byte_offset(300).unwrap();
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::Tracks(_))
+        ));
+        assert_eq!(reader.next_element_position(), 473);
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::Cluster(_))
+        ));
+    }
+
+    #[test]
+    fn discard_padding() {
+        use crate::elements::BlockGroup;
+        use crate::writer::MatroskaEncoder;
+
+        let track = test_utils::track(1, 2, "A_OPUS");
+        let mut encoder =
+            MatroskaEncoder::new(Vec::new(), test_utils::info(), vec![track]).unwrap();
+        encoder.push_frame(1, 0, true, &[0xFC; 3]).unwrap();
+        let mut data = encoder.finish().unwrap();
+
+        // The last packet is in a BlockGroup trimming 6.5 ms at its end
+        data.extend([
+            0x1F, 0x43, 0xB6, 0x75, 0x95, 0xE7, 0x82, 0x03, 0xE8, 0xA0, 0x8F, 0xA1, 0x87, 0x81,
+            0x00, 0x00, 0x00, 0xFC, 0xFF, 0xFE, 0x75, 0xA2, 0x83, 0x63, 0x2E, 0xA0,
+        ]);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let mut last_group = None;
+        while let Some(element) = reader.next_element().unwrap() {
+            if let SegmentElement::Cluster(mut cluster) = element {
+                last_group = cluster
+                    .block_group
+                    .pop()
+                    .map(BlockGroup::into_owned)
+                    .or(last_group);
+            }
+        }
+
+        let group = last_group.unwrap();
+        assert_eq!(group.block.data, &[0xFC, 0xFF, 0xFE][..]);
+        assert_eq!(group.discard_padding, Some(6_500_000));
+    }
+
+    #[test]
+    fn error_recovery() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::writer::MatroskaEncoder;
+
+        let track = test_utils::track(1, 2, "A_OPUS");
+        let mut encoder = MatroskaEncoder::new(Vec::new(), test_utils::info(), vec![track])
+            .unwrap()
+            .with_max_cluster_duration(200_000_000);
+        for i in 0..10u64 {
+            encoder
+                .push_frame(1, i * 100_000_000, true, &[0; 8])
+                .unwrap();
+        }
+        let mut data = encoder.finish().unwrap();
+
+        fn timestamps<R: Read>(reader: MatroskaReader<R>) -> Result<Vec<u64>, Error> {
+            let mut timestamps = Vec::new();
+            for element in reader {
+                if let SegmentElement::Cluster(cluster) = element? {
+                    timestamps.push(cluster.timestamp);
+                }
+            }
+            Ok(timestamps)
+        }
+        let mut expected = timestamps(MatroskaReader::new(Cursor::new(&data)).unwrap()).unwrap();
+        assert!(expected.len() > 3);
+
+        // Invalid size of the third Cluster
+        let clusters: Vec<_> = data
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == [0x1F, 0x43, 0xB6, 0x75])
+            .map(|(offset, _)| offset)
+            .collect();
+        data[clusters[2] + 4] = 0;
+        expected.remove(2);
+
+        let reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert!(matches!(timestamps(reader), Err(Error::Ebml(_))));
+
+        let reader = MatroskaReader::new(Cursor::new(&data))
+            .unwrap()
+            .with_error_recovery(true);
+        assert_eq!(timestamps(reader).unwrap(), expected);
+
+        // The callback gets the position of the Cluster
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let skip = (clusters[3] - clusters[2]) as u64;
+        let reader = MatroskaReader::new(Cursor::new(&data)).unwrap().on_error({
+            let errors = errors.clone();
+            move |position, _| {
+                errors.lock().unwrap().push(position);
+                RecoveryAction::Skip(skip)
+            }
+        });
+        assert_eq!(timestamps(reader).unwrap(), expected);
+        let segment_start = MatroskaReader::new(Cursor::new(&data))
+            .unwrap()
+            .segment_start;
+        assert_eq!(
+            *errors.lock().unwrap(),
+            [clusters[2] as u64 - segment_start]
+        );
+
+        let reader = MatroskaReader::new(Cursor::new(&data))
+            .unwrap()
+            .on_error(|_, _| RecoveryAction::Abort);
+        assert!(matches!(timestamps(reader), Err(Error::Ebml(_))));
+
+        // Skipping 0 bytes moves on instead of failing at the same position
+        // forever
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let reader = MatroskaReader::new(Cursor::new(&data)).unwrap().on_error({
+            let errors = errors.clone();
+            move |position, _| {
+                errors.lock().unwrap().push(position);
+                RecoveryAction::Skip(0)
+            }
+        });
+        let found = timestamps(reader).unwrap();
+        assert_eq!(found[..2], expected[..2]);
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors[0], clusters[2] as u64 - segment_start);
+        assert!(errors.windows(2).all(|w| w[0] < w[1]));
+
+        // Truncated files end at the last complete element
+        let reader = MatroskaReader::new(Cursor::new(&webm[..2000]))
+            .unwrap()
+            .with_error_recovery(true);
+        assert!(timestamps(reader).unwrap().is_empty());
+    }
+
+    #[test]
+    fn truncated_input() {
+        let mut reader = MatroskaReader::new(Cursor::new(&webm[..2000])).unwrap();
+
+        let res = loop {
+            match reader.next_element() {
+                Ok(Some(_)) => continue,
+                res => break res.map(|_| ()),
+            }
+        };
+        assert!(matches!(res, Err(Error::UnexpectedEof)));
+    }
+
+    #[test]
+    fn frames() {
+        let path = "assets/matroska_test_w1_1/test5.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+
+        // Subtitles stored in BlockGroups with a BlockDuration
+        let subtitles: Vec<_> = reader.frames(3).take(2).map(Result::unwrap).collect();
+        assert_eq!(subtitles[0].timestamp_ns, 3_549_000_000);
+        assert_eq!(subtitles[0].duration_ns, Some(1_741_000_000));
+        assert!(subtitles[0].keyframe);
+        assert_eq!(subtitles[0].data, b"...the colossus of Rhodes!");
+        assert_eq!(subtitles[1].data, b"No!");
+
+        // SimpleBlocks, using the default duration of the track, including
+        // the ones stored after the BlockGroups of the subtitles
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        let frames = reader.frames(1).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(frames.len(), 1104);
+        assert!(frames[0].keyframe && !frames[1].keyframe);
+        // In decoding order, the P-frame before the B-frame
+        assert_eq!(frames[1].timestamp_ns, 83_000_000);
+        assert_eq!(frames[2].timestamp_ns, 42_000_000);
+        assert!(frames
+            .iter()
+            .all(|f| f.track_number == 1 && f.duration_ns == Some(41_666_665)));
+        assert!(reader.next_element().unwrap().is_none());
+    }
+
+    #[test]
+    fn laced_frames() {
+        // MP3 frames laced by 8, with a DefaultDuration of 24 ms
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        let frames = reader.frames(2).collect::<Result<Vec<_>, _>>().unwrap();
+        assert!(frames.iter().all(|f| f.duration_ns == Some(24_000_000)));
+        assert_eq!(frames[1].timestamp_ns - frames[0].timestamp_ns, 24_000_000);
+        assert_eq!(frames[7].timestamp_ns - frames[0].timestamp_ns, 168_000_000);
+        assert!(frames
+            .windows(2)
+            .all(|w| w[0].timestamp_ns < w[1].timestamp_ns));
+    }
+
+    #[test]
+    fn subtitle_packets() {
+        let path = "assets/matroska_test_w1_1/test5.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        let packets = reader
+            .subtitle_packets(3)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let codec_id = &reader.tracks().unwrap().tracks[2].codec_id;
+        assert_eq!(codec_id, "S_TEXT/UTF8");
+
+        assert_eq!(packets[0].timestamp_ns, 3_549_000_000);
+        assert_eq!(packets[0].duration_ns, 1_741_000_000);
+        assert_eq!(packets[0].as_str(), Ok("...the colossus of Rhodes!"));
+        assert_eq!(packets[1].as_str(), Ok("No!"));
+        assert!(packets.iter().all(|packet| packet.duration_ns > 0));
+        assert!(packets
+            .windows(2)
+            .all(|w| w[0].timestamp_ns < w[1].timestamp_ns));
+
+        let packet = SubtitlePacket {
+            timestamp_ns: 0,
+            duration_ns: 0,
+            data: vec![0xFF, 0xFE],
+        };
+        assert!(packet.as_str().is_err());
+    }
+
+    #[test]
+    fn bitrates() {
+        let mut reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        assert_eq!(reader.total_bitrate(), None);
+        while reader.tracks().is_none() {
+            reader.next_element().unwrap();
+        }
+
+        // 1.02 s, from the Segment size before the Clusters
+        let segment_size = reader.segment_size().unwrap();
+        let bitrate = (segment_size as f64 * 8.0 / 1.02).round() as u64;
+        assert_eq!(reader.total_bitrate(), Some(bitrate));
+        assert_eq!(reader.track_bitrate(1), Some(0));
+
+        let mut clusters = 0;
+        let mut bytes = [0, 0];
+        while let Some(element) = reader.next_element().unwrap() {
+            if let SegmentElement::Cluster(cluster) = element {
+                clusters += 1;
+                for block in &cluster.simple_block {
+                    bytes[block.track_number as usize - 1] += block.data.len();
+                }
+            }
+        }
+        assert!(clusters > 0);
+        let total = reader.total_bitrate().unwrap();
+        assert!(total < bitrate);
+        for (track, bytes) in [(1, bytes[0]), (2, bytes[1])] {
+            let bitrate = (bytes as f64 * 8.0 / 1.02).round() as u64;
+            assert_eq!(reader.track_bitrate(track), Some(bitrate));
+        }
+        assert_eq!(reader.track_bitrate(3), Some(0));
+
+        // The Clusters are counted once
+        reader.seek_to_byte_offset(0).unwrap();
+        while reader.next_element().unwrap().is_some() {}
+        assert_eq!(reader.total_bitrate(), Some(total));
+    }
+
+    #[test]
+    fn packet_count_estimate() {
+        let path = "assets/matroska_test_w1_1/test5.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        while reader.cues().is_none() {
+            reader.next_element().unwrap();
+        }
+
+        // 46.665 s of frames of 41.7 ms for the video
+        assert_eq!(reader.packet_count_estimate(1), Some(1120));
+        // The subtitles have no DefaultDuration nor Cues
+        assert_eq!(reader.packet_count_estimate(3), Some(0));
+        assert_eq!(reader.packet_count_estimate(42), None);
+
+        let reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        assert_eq!(reader.packet_count_estimate(1), None);
+    }
+
+    #[test]
+    fn track_list() {
+        let mut reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        let tracks = reader.track_list().unwrap().to_vec();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].codec_id, "V_VP9");
+
+        // The position is kept, and the tracks come from the cache
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::SeekHead(_))
+        ));
+        assert_eq!(reader.track_list().unwrap(), tracks);
+
+        // A Segment without Tracks
+        use crate::serializer::ebml::{encode_element, encode_master};
+        let (rest, _) = ebml_header(webm).unwrap();
+        let mut data = webm[..webm.len() - rest.len()].to_vec();
+        let mut info = encode_element(0x4D80, b"test").unwrap();
+        info.extend(encode_element(0x5741, b"test").unwrap());
+        data.extend(encode_master(0x18538067, None).unwrap());
+        data.extend(encode_element(0x1549A966, &info).unwrap());
+        let mut reader = MatroskaReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(reader.track_list().unwrap(), []);
+    }
+
+    #[test]
+    fn estimated_position() {
+        let mut reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        let start = reader.bytes_read();
+        assert!(start > 0);
+
+        let mut positions = vec![reader.estimated_position().unwrap()];
+        while reader.next_element().unwrap().is_some() {
+            positions.push(reader.estimated_position().unwrap());
+        }
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(positions.last(), Some(&1.0));
+        assert_eq!(reader.bytes_read(), webm.len() as u64);
+
+        // A Segment of unknown size, the position is the fraction of the
+        // source read
+        use crate::serializer::ebml::encode_master;
+        let (rest, _) = ebml_header(webm).unwrap();
+        let (body, _) = segment(rest).unwrap();
+        let mut data = webm[..webm.len() - rest.len()].to_vec();
+        data.extend(encode_master(0x18538067, None).unwrap());
+        data.extend_from_slice(body);
+        let len = data.len() as f64;
+
+        let mut expected = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        let mut reader = MatroskaReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(reader.segment_size(), None);
+        let mut positions = vec![reader.estimated_position().unwrap()];
+        while let Some(element) = reader.next_element().unwrap() {
+            assert_eq!(Some(element), expected.next_element().unwrap());
+            let position = reader.estimated_position().unwrap();
+            assert_eq!(position, reader.bytes_read() as f64 / len);
+            positions.push(position);
+        }
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(positions.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn clusters() {
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+
+        let clusters = reader.clusters().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(clusters.len(), 11);
+        assert!(clusters.iter().all(|c| c.scale == 1_000_000));
+        assert_eq!(clusters[0].timestamp_ns, 0);
+        assert_eq!(
+            clusters[1].timestamp_ns,
+            clusters[1].cluster.timestamp * 1_000_000
+        );
+        assert!(clusters
+            .windows(2)
+            .all(|c| c[0].timestamp_ns < c[1].timestamp_ns));
+        assert!(reader.next_element().unwrap().is_none());
+    }
+
+    #[test]
+    fn skip_to_clusters() {
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+
+        // Without Cues nor SeekHead, the elements are skipped
+        reader.skip_to_clusters().unwrap();
+        let first = match reader.next_element().unwrap() {
+            Some(SegmentElement::Cluster(cluster)) => cluster.into_owned(),
+            element => panic!("expected a Cluster, got {element:?}"),
+        };
+        assert!(reader.info().is_none() && reader.tracks().is_none());
+
+        // The Cues give the position of the first Cluster
+        reader.seek_to_timestamp(1, 0).unwrap();
+        assert!(reader.cues().is_some());
+        reader.seek_to_byte_offset(0).unwrap();
+        reader.skip_to_clusters().unwrap();
+        match reader.next_element().unwrap() {
+            Some(SegmentElement::Cluster(cluster)) => assert_eq!(cluster, first),
+            element => panic!("expected a Cluster, got {element:?}"),
+        }
+
+        // The data up to the first Cluster
+        let end = webm.windows(4).position(|w| w == [0x1F, 0x43, 0xB6, 0x75]);
+        let mut reader = MatroskaReader::new(Cursor::new(&webm[..end.unwrap()])).unwrap();
+        reader.skip_to_clusters().unwrap();
+        assert!(reader.next_element().unwrap().is_none());
+    }
+
+    #[test]
+    fn read_attachment_by_name() {
+        let mkv = include_bytes!("../assets/single_stream_cover.mkv");
+        let mut reader = MatroskaReader::new(Cursor::new(mkv)).unwrap();
+        let data = reader
+            .read_attachment_by_name("Cover.PNG")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(reader.read_attachment_by_name("cover.jpg").unwrap(), None);
+
+        // The position is restored
+        let Some(SegmentElement::SeekHead(_)) = reader.next_element().unwrap() else {
+            panic!("expected the SeekHead");
+        };
+        assert!(reader
+            .read_attachment_by_name("cover.png")
+            .unwrap()
+            .is_some());
+        assert!(!matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::SeekHead(_))
+        ));
+
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        assert_eq!(reader.read_attachment_by_name("cover.png").unwrap(), None);
+    }
+
+    #[test]
+    fn secondary_seek_head() {
+        use std::borrow::Cow;
+
+        use crate::elements::{Cluster, Lacing, Seek, SeekHead, SimpleBlock, Tracks};
+        use crate::serializer::ebml::{gen_ebml_header, EbmlSize};
+        use crate::serializer::elements::{
+            gen_cluster, gen_info, gen_seek_head, gen_segment_header, gen_tracks,
+        };
+        use crate::writer::serialize;
+
+        let info = test_utils::info();
+        let tracks = Tracks {
+            tracks: vec![test_utils::track(1, 2, "A_OPUS")],
+        };
+        let cluster = |timestamp| Cluster {
+            timestamp,
+            silent_tracks: None,
+            position: None,
+            prev_size: None,
+            simple_block: vec![SimpleBlock {
+                track_number: 1,
+                timestamp: 0,
+                keyframe: true,
+                invisible: false,
+                lacing: Lacing::None,
+                discardable: false,
+                data: Cow::Borrowed(&[1, 2, 3]),
+            }],
+            block_group: Vec::new(),
+            block_order: Vec::new(),
+        };
+
+        // Info, Tracks and two Clusters, followed by the SeekHead listing them
+        let mut body = vec![0; info.size(0x1549A966)];
+        serialize(&mut body, gen_info(&info)).unwrap();
+        let mut elements = vec![(0x1549A966u32, 0)];
+        let mut append = |id, data: Vec<u8>| {
+            elements.push((id, body.len()));
+            body.extend(data);
+        };
+        let mut data = vec![0; tracks.size(0x1654AE6B)];
+        serialize(&mut data, gen_tracks(&tracks)).unwrap();
+        append(0x1654AE6B, data);
+        for timestamp in [0, 1000] {
+            let cluster = cluster(timestamp);
+            let mut data = vec![0; cluster.size(0x1F43B675)];
+            serialize(&mut data, gen_cluster(&cluster)).unwrap();
+            append(0x1F43B675, data);
+        }
+
+        // The first SeekHead only points to the secondary one, whose
+        // position depends on the size of the first
+        let mut first = SeekHead {
+            positions: vec![Seek {
+                id: 0x114D9B74u32.to_be_bytes(),
+                position: 0,
+            }],
+        };
+        let first_size = loop {
+            let size = first.size(0x114D9B74) as u64;
+            if first.positions[0].position == size + body.len() as u64 {
+                break size;
+            }
+            first.positions[0].position = size + body.len() as u64;
+        };
+        let secondary = SeekHead {
+            positions: elements
+                .iter()
+                .map(|&(id, position)| Seek {
+                    id: id.to_be_bytes(),
+                    position: first_size + position as u64,
+                })
+                .collect(),
+        };
+
+        let header = placeholder_header();
+        let mut file = vec![0; header.size(0x1A45DFA3) + 12];
+        let len = serialize(&mut file, gen_ebml_header(&header)).unwrap();
+        serialize(&mut file[len..], gen_segment_header(None)).unwrap();
+        for seek_head in [&first, &secondary] {
+            let mut data = vec![0; seek_head.size(0x114D9B74)];
+            serialize(&mut data, gen_seek_head(seek_head)).unwrap();
+            file.extend(data);
+            if seek_head == &first {
+                file.extend(&body);
+            }
+        }
+
+        let mut reader = MatroskaReader::new(Cursor::new(&file)).unwrap();
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::SeekHead(_))
+        ));
+        assert_eq!(reader.seek_head(), Some(&first));
+
+        reader.load_seek_heads().unwrap();
+        let seek_head = reader.seek_head().unwrap().clone();
+        assert_eq!(seek_head.positions.len(), 5);
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::Info(_))
+        ));
+
+        let clusters: Vec<_> = seek_head
+            .positions
+            .iter()
+            .filter(|seek| seek.element_id() == ElementId::CLUSTER)
+            .map(|seek| seek.position)
+            .collect();
+        assert_eq!(clusters.len(), 2);
+        for (position, timestamp) in clusters.into_iter().zip([0, 1000]) {
+            reader.seek_in_segment(position).unwrap();
+            match reader.next_element().unwrap() {
+                Some(SegmentElement::Cluster(c)) => assert_eq!(c.timestamp, timestamp),
+                e => panic!("expected a Cluster at {position}, got {e:?}"),
+            }
+        }
+
+        // Reading the whole Segment merges the SeekHeads as well
+        let mut reader = MatroskaReader::new(Cursor::new(&file)).unwrap();
+        while reader.next_element().unwrap().is_some() {}
+        assert_eq!(reader.seek_head().unwrap().positions, seek_head.positions);
+    }
+
+    #[test]
+    fn verify_index() {
+        let mut reader = MatroskaReader::new(Cursor::new(webm)).unwrap();
+        reader.next_element().unwrap();
+        assert_eq!(reader.verify_index().unwrap(), []);
+        // The position was restored
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::Void(_))
+        ));
+
+        let offset = reader.cues().unwrap().cue_points[0].track_positions[0].cluster_position;
+
+        // The file ends with the Cues, holding a single Cue Point of
+        // CueTime 0 and CueClusterPosition 473
+        let mut moved = webm.to_vec();
+        *moved.last_mut().unwrap() += 1;
+        let mut reader = MatroskaReader::new(Cursor::new(&moved)).unwrap();
+        assert_eq!(
+            reader.verify_index().unwrap(),
+            [IndexError::OffsetMismatch {
+                expected: offset + 1,
+                actual: offset,
+            }]
+        );
+
+        let mut late = webm.to_vec();
+        let time = late.len() - 10;
+        late[time] = 1;
+        let mut reader = MatroskaReader::new(Cursor::new(&late)).unwrap();
+        assert_eq!(
+            reader.verify_index().unwrap(),
+            [IndexError::TimestampMismatch {
+                expected: 1,
+                actual: 0,
+                cue_offset: offset,
+            }]
+        );
+
+        let mut reader = MatroskaReader::new(Cursor::new(&webm[..webm.len() - 26])).unwrap();
+        assert!(matches!(reader.verify_index(), Err(Error::MissingCues)));
+    }
+
+    #[test]
+    fn metadata_only() {
+        let mkv = std::fs::read("assets/single_stream_cover.mkv").unwrap();
+        let mut reader = MatroskaReader::new(Cursor::new(&mkv)).unwrap();
+        let position = reader.next_element_position();
+
+        let metadata = reader.metadata_only().unwrap();
+        assert!(metadata.info.is_some());
+        assert_eq!(metadata.tracks.unwrap().tracks.len(), 1);
+        assert_eq!(metadata.chapters, None);
+        assert_eq!(metadata.tags.len(), 1);
+        assert_eq!(metadata.attachments.unwrap().files[0].name, "cover.png");
+        assert_eq!(reader.next_element_position(), position);
+
+        // Without SeekHead, the Cues after the Clusters are not found
+        let mut data = webm.to_vec();
+        data[55..61].copy_from_slice(&[0xEC, 0x08, 0x00, 0x00, 0x00, 0x2C]);
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let metadata = reader.metadata_only().unwrap();
+        assert_eq!(metadata.tracks.unwrap().tracks.len(), 2);
+        assert!(reader.seek_head().is_none());
+        assert!(reader.cues().is_none());
+    }
+}
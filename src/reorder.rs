@@ -0,0 +1,154 @@
+//! Reordering of frames from decoding order to presentation order.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use crate::reader::Frame;
+use crate::Error;
+
+/// Default number of frames [MatroskaFrameReorderer] holds, the largest
+/// reordering allowed by H.264 and HEVC.
+pub const DEFAULT_REORDER_DEPTH: usize = 16;
+
+/// Reorders the frames of an iterator, such as [Frames](crate::reader::Frames),
+/// by presentation timestamp.
+///
+/// The blocks of a Matroska track are stored in decoding order with their
+/// presentation timestamps, so the frames of a codec with B-frames come
+/// out of order. The reorderer holds up to `depth` frames and returns the
+/// earliest one as the next frame comes in, which sorts the frames as long
+/// as none is more than `depth` frames away from its place. Frames of the
+/// same timestamp keep their order, and errors are returned right away.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::reader::MatroskaReader;
+/// use matroska::reorder::MatroskaFrameReorderer;
+///
+/// let mut reader = MatroskaReader::new(File::open("video.mkv")?)?;
+/// for frame in MatroskaFrameReorderer::new(reader.frames(1)) {
+///     println!("{}", frame?.timestamp_ns);
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaFrameReorderer<I> {
+    frames: I,
+    depth: usize,
+    pending: BinaryHeap<Reverse<Pending>>,
+    // Number of frames received, to keep the order of equal timestamps
+    count: u64,
+    done: bool,
+}
+
+impl<I: Iterator<Item = Result<Frame, Error>>> MatroskaFrameReorderer<I> {
+    /// Reorders `frames`, holding [DEFAULT_REORDER_DEPTH] frames.
+    pub fn new(frames: I) -> Self {
+        MatroskaFrameReorderer {
+            frames,
+            depth: DEFAULT_REORDER_DEPTH,
+            pending: BinaryHeap::new(),
+            count: 0,
+            done: false,
+        }
+    }
+
+    /// Sets the number of frames held, at least 1.
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+}
+
+impl<I: Iterator<Item = Result<Frame, Error>>> Iterator for MatroskaFrameReorderer<I> {
+    type Item = Result<Frame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done && self.pending.len() < self.depth {
+            match self.frames.next() {
+                Some(Ok(frame)) => {
+                    self.pending.push(Reverse(Pending(frame, self.count)));
+                    self.count += 1;
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => self.done = true,
+            }
+        }
+
+        self.pending
+            .pop()
+            .map(|Reverse(Pending(frame, _))| Ok(frame))
+    }
+}
+
+// A frame held, ordered by timestamp then by arrival
+struct Pending(Frame, u64);
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.0.timestamp_ns, self.1).cmp(&(other.0.timestamp_ns, other.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(timestamp: u64, data: u8) -> Result<Frame, Error> {
+        Ok(Frame {
+            track_number: 1,
+            timestamp_ns: timestamp,
+            duration_ns: None,
+            keyframe: data == 0,
+            data: vec![data],
+        })
+    }
+
+    #[test]
+    fn reorder() {
+        // An I-frame and a P-frame with the pyramid of B-frames between
+        // them, in decoding order
+        let stored = [(0, 0), (160, 1), (80, 2), (40, 3), (120, 4), (120, 5)];
+        let timestamps = |depth| {
+            let frames = stored
+                .iter()
+                .map(|&(timestamp, data)| frame(timestamp, data));
+            MatroskaFrameReorderer::new(frames)
+                .with_depth(depth)
+                .map(|frame| frame.map(|f| (f.timestamp_ns, f.data[0])))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+
+        let sorted = [(0, 0), (40, 3), (80, 2), (120, 4), (120, 5), (160, 1)];
+        assert_eq!(timestamps(DEFAULT_REORDER_DEPTH), sorted);
+        assert_eq!(timestamps(3), sorted);
+        // Not enough frames held to move the P-frame after the B-frames
+        assert_eq!(
+            timestamps(2),
+            [(0, 0), (80, 2), (40, 3), (120, 4), (120, 5), (160, 1)]
+        );
+        assert_eq!(timestamps(0), stored);
+
+        let frames = vec![frame(40, 1), Err(Error::UnexpectedEof), frame(0, 0)];
+        let mut reorderer = MatroskaFrameReorderer::new(frames.into_iter());
+        assert!(matches!(reorderer.next(), Some(Err(Error::UnexpectedEof))));
+        assert_eq!(reorderer.next().unwrap().unwrap().timestamp_ns, 0);
+        assert_eq!(reorderer.next().unwrap().unwrap().timestamp_ns, 40);
+        assert!(reorderer.next().is_none());
+    }
+}
@@ -0,0 +1,388 @@
+//! Copy of a Matroska file without re-encoding, changing some of its
+//! elements on the way.
+
+use std::io::{Read, Seek, Write};
+
+use crate::elements::{Cluster, Info, SegmentElement, TrackEntry};
+use crate::reader::MatroskaReader;
+use crate::writer::{MatroskaWriter, RawBlock};
+use crate::Error;
+
+type InfoCallback = dyn FnMut(&mut Info);
+type TrackCallback = dyn FnMut(&mut TrackEntry);
+type TrackFilter = dyn Fn(&TrackEntry) -> bool;
+
+/// Copies the Segment read by a [MatroskaReader] to a destination with a
+/// [MatroskaWriter], calling the registered callbacks to change the Info
+/// and the tracks, and to drop tracks.
+///
+/// The blocks are copied as they are, the frames of laced blocks being
+/// written as separate SimpleBlocks. The Cues and the SeekHead are
+/// rebuilt for the new positions, while the Chapters, Tags and
+/// Attachments are not copied.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::reader::MatroskaReader;
+/// use matroska::rewriter::MatroskaRewriter;
+///
+/// let reader = MatroskaReader::new(File::open("video.mkv")?)?;
+/// MatroskaRewriter::new(reader, File::create("video-only.mkv")?)
+///     .on_segment_info(|info| info.writing_app = String::from("example"))
+///     .filter_tracks(|track| track.track_type == 1)
+///     .run()?;
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaRewriter<R, W> {
+    src: MatroskaReader<R>,
+    dst: W,
+    on_segment_info: Vec<Box<InfoCallback>>,
+    on_track: Vec<Box<TrackCallback>>,
+    filters: Vec<Box<TrackFilter>>,
+    // TimestampScale to convert the timestamps to
+    timestamp_scale: Option<u64>,
+}
+
+impl<R: Read, W: Write + Seek> MatroskaRewriter<R, W> {
+    /// Creates a rewriter copying the Segment read by `src` to `dst`.
+    pub fn new(src: MatroskaReader<R>, dst: W) -> Self {
+        MatroskaRewriter {
+            src,
+            dst,
+            on_segment_info: Vec::new(),
+            on_track: Vec::new(),
+            filters: Vec::new(),
+            timestamp_scale: None,
+        }
+    }
+
+    /// Calls `cb` on the Info before writing it.
+    ///
+    /// The TimestampScale must not be changed, as the timestamps of the
+    /// blocks are copied unchanged.
+    pub fn on_segment_info(mut self, cb: impl FnMut(&mut Info) + 'static) -> Self {
+        self.on_segment_info.push(Box::new(cb));
+        self
+    }
+
+    /// Calls `cb` on every track kept, before writing the Tracks.
+    pub fn on_track(mut self, cb: impl FnMut(&mut TrackEntry) + 'static) -> Self {
+        self.on_track.push(Box::new(cb));
+        self
+    }
+
+    /// Only keeps the tracks for which `pred` returns true, along with
+    /// their blocks.
+    pub fn filter_tracks(mut self, pred: impl Fn(&TrackEntry) -> bool + 'static) -> Self {
+        self.filters.push(Box::new(pred));
+        self
+    }
+
+    /// Copies the Segment and returns the destination.
+    pub fn run(mut self) -> Result<W, Error> {
+        let mut info = None;
+        let mut tracks = None;
+        let mut dst = Some(self.dst);
+        let mut writer = None;
+        // Numbers of the tracks kept
+        let mut kept = Vec::new();
+        // The TimestampScale of the source and of the destination
+        let mut scales = (1, 1);
+
+        loop {
+            match self.src.next_element()? {
+                Some(SegmentElement::Info(i)) => info = Some(i),
+                Some(SegmentElement::Tracks(t)) => tracks = Some(t.tracks),
+                Some(SegmentElement::Cluster(cluster)) => {
+                    let writer = match &mut writer {
+                        Some(writer) => writer,
+                        None => writer.insert(open_writer(
+                            dst.take().expect("the writer is created once"),
+                            info.take().unwrap_or_default(),
+                            tracks.take().unwrap_or_default(),
+                            &mut self.on_segment_info,
+                            &mut self.on_track,
+                            &self.filters,
+                            self.timestamp_scale,
+                            &mut kept,
+                            &mut scales,
+                        )?),
+                    };
+                    write_cluster(writer, &cluster, &kept, scales)?;
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        let writer = match writer {
+            Some(writer) => writer,
+            None => open_writer(
+                dst.take().expect("the writer is created once"),
+                info.unwrap_or_default(),
+                tracks.unwrap_or_default(),
+                &mut self.on_segment_info,
+                &mut self.on_track,
+                &self.filters,
+                self.timestamp_scale,
+                &mut kept,
+                &mut scales,
+            )?,
+        };
+
+        writer.finish()
+    }
+}
+
+/// Converts the timestamps of a Segment to another TimestampScale.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::reader::MatroskaReader;
+/// use matroska::rewriter::MatroskaRemuxer;
+///
+/// // Ticks of 1/90000 second, the clock of MPEG-TS
+/// let reader = MatroskaReader::new(File::open("video.mkv")?)?;
+/// MatroskaRemuxer::change_timestamp_scale(reader, File::create("90khz.mkv")?, 11_111)?;
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaRemuxer;
+
+impl MatroskaRemuxer {
+    /// Copies the Segment read by `src` to `dst` like a [MatroskaRewriter],
+    /// with the TimestampScale set to `new_scale_ns` nanoseconds, at least
+    /// 1, and returns the destination.
+    ///
+    /// The timestamps of the Clusters and blocks, and the Duration, are
+    /// converted as `ticks * old_scale / new_scale`, rounding down, and the
+    /// Cues are rebuilt from them. The Clusters are split where the
+    /// timestamps of their blocks no longer fit relative to the Cluster.
+    /// Fails with [Error::TimestampOverflow] if a timestamp in nanoseconds
+    /// does not fit in 64 bits.
+    pub fn change_timestamp_scale<R: Read, W: Write + Seek>(
+        src: MatroskaReader<R>,
+        dst: W,
+        new_scale_ns: u64,
+    ) -> Result<W, Error> {
+        let mut rewriter = MatroskaRewriter::new(src, dst);
+        rewriter.timestamp_scale = Some(new_scale_ns.max(1));
+        rewriter.run()
+    }
+}
+
+// Applies the callbacks to the Info and the tracks and writes them,
+// listing the numbers of the tracks kept in `kept`, and the TimestampScale
+// of the source and of the destination in `scales`.
+#[allow(clippy::too_many_arguments)]
+fn open_writer<W: Write + Seek>(
+    dst: W,
+    mut info: Info,
+    mut tracks: Vec<TrackEntry>,
+    on_segment_info: &mut [Box<InfoCallback>],
+    on_track: &mut [Box<TrackCallback>],
+    filters: &[Box<TrackFilter>],
+    timestamp_scale: Option<u64>,
+    kept: &mut Vec<u64>,
+    scales: &mut (u64, u64),
+) -> Result<MatroskaWriter<W>, Error> {
+    for cb in on_segment_info {
+        cb(&mut info);
+    }
+
+    if info.timestamp_scale == 0 {
+        info.timestamp_scale = 1_000_000;
+    }
+    *scales = (info.timestamp_scale, info.timestamp_scale);
+    if let Some(scale) = timestamp_scale {
+        info.duration = info
+            .duration
+            .map(|d| d * info.timestamp_scale as f64 / scale as f64);
+        info.timestamp_scale = scale;
+        scales.1 = scale;
+    }
+
+    tracks.retain(|track| filters.iter().all(|pred| pred(track)));
+    for track in &mut tracks {
+        for cb in on_track.iter_mut() {
+            cb(track);
+        }
+    }
+    kept.extend(tracks.iter().map(|track| track.track_number));
+
+    MatroskaWriter::new(dst, info, tracks)
+}
+
+// Writes the blocks of the tracks kept, in the order of their timestamps,
+// converting them from the TimestampScale `scales.0` to `scales.1`.
+fn write_cluster<W: Write + Seek>(
+    writer: &mut MatroskaWriter<W>,
+    cluster: &Cluster<'_>,
+    kept: &[u64],
+    scales: (u64, u64),
+) -> Result<(), Error> {
+    let mut blocks = Vec::new();
+    for block in &cluster.simple_block {
+        if kept.contains(&block.track_number) {
+            for data in block.frames()? {
+                blocks.push(RawBlock {
+                    track_number: block.track_number,
+                    timestamp: block.timestamp,
+                    keyframe: block.keyframe,
+                    data,
+                });
+            }
+        }
+    }
+    for group in &cluster.block_group {
+        if kept.contains(&group.block.track_number) {
+            for data in group.block.frames()? {
+                blocks.push(RawBlock {
+                    track_number: group.block.track_number,
+                    timestamp: group.block.timestamp,
+                    keyframe: group.reference_block.is_empty(),
+                    data,
+                });
+            }
+        }
+    }
+    blocks.sort_by_key(|block| block.timestamp);
+
+    if blocks.is_empty() {
+        return Ok(());
+    }
+    if scales.0 == scales.1 {
+        return writer.write_cluster(cluster.timestamp, &blocks);
+    }
+
+    // With a finer scale the relative timestamps may not fit in 16 bits
+    // anymore, so that the blocks are split into several Clusters
+    let rescale = |relative: i16| {
+        cluster
+            .block_timestamp(relative)
+            .checked_mul(scales.0)
+            .map(|ns| ns / scales.1)
+            .ok_or(Error::TimestampOverflow)
+    };
+    let mut start = 0;
+    while start < blocks.len() {
+        let timestamp = rescale(blocks[start].timestamp)?;
+        let mut rescaled = Vec::new();
+        for block in &blocks[start..] {
+            let relative = rescale(block.timestamp)? - timestamp;
+            let Ok(relative) = i16::try_from(relative) else {
+                break;
+            };
+            rescaled.push(RawBlock {
+                timestamp: relative,
+                ..*block
+            });
+        }
+
+        start += rescaled.len();
+        writer.write_cluster(timestamp, &rescaled)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::file::MatroskaFile;
+
+    #[test]
+    fn rewrite() {
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut expected = Vec::new();
+        let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        for frame in reader.frames(1) {
+            let frame = frame.unwrap();
+            expected.push((frame.timestamp_ns, frame.data));
+        }
+
+        let reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        let out = MatroskaRewriter::new(reader, Cursor::new(Vec::new()))
+            .on_segment_info(|info| info.writing_app = String::from("rewriter"))
+            .filter_tracks(|track| track.track_type == 1)
+            .on_track(|track| track.name = Some(String::from("video")))
+            .run()
+            .unwrap();
+
+        let mut file = MatroskaFile::from_seekable(Cursor::new(out.into_inner())).unwrap();
+        assert_eq!(file.info().unwrap().writing_app, "rewriter");
+        let tracks = &file.tracks().unwrap().tracks;
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].track_number, 1);
+        assert_eq!(tracks[0].name.as_deref(), Some("video"));
+        assert!(!file.cues().unwrap().cue_points.is_empty());
+
+        file.reader_mut().seek_to_byte_offset(0).unwrap();
+        let frames: Vec<_> = file
+            .reader_mut()
+            .frames(1)
+            .map(|frame| frame.map(|f| (f.timestamp_ns, f.data)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(frames, expected);
+        file.reader_mut().seek_to_byte_offset(0).unwrap();
+        assert_eq!(file.reader_mut().frames(2).count(), 0);
+    }
+
+    #[test]
+    fn change_timestamp_scale() {
+        let path = "assets/bbb-vp9-opus.webm";
+        fn frames<R: Read>(reader: &mut MatroskaReader<R>) -> Vec<(u64, Vec<u8>)> {
+            reader
+                .frames(1)
+                .map(|frame| frame.map(|f| (f.timestamp_ns, f.data)))
+                .collect::<Result<_, _>>()
+                .unwrap()
+        }
+        let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        let expected = frames(&mut reader);
+        let duration = reader.info().unwrap().duration.unwrap();
+
+        let reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        let out = MatroskaRemuxer::change_timestamp_scale(reader, Cursor::new(Vec::new()), 11_111)
+            .unwrap()
+            .into_inner();
+
+        let file = MatroskaFile::from_seekable(Cursor::new(&out)).unwrap();
+        let info = file.info().unwrap();
+        assert_eq!(info.timestamp_scale, 11_111);
+        assert!((info.duration.unwrap() - duration * 1_000_000.0 / 11_111.0).abs() < 1e-6);
+
+        // The Clusters of 1 second are split at 32767 ticks of 11 µs
+        let mut reader = MatroskaReader::new(Cursor::new(&out)).unwrap();
+        let rescaled = frames(&mut reader);
+        assert_eq!(rescaled.len(), expected.len());
+        for ((timestamp, data), (expected_timestamp, expected_data)) in
+            rescaled.iter().zip(&expected)
+        {
+            assert!(expected_timestamp - timestamp < 11_111);
+            assert_eq!(data, expected_data);
+        }
+
+        let cues = &file.cues().unwrap().cue_points;
+        let mut reader = MatroskaReader::new(Cursor::new(&out)).unwrap();
+        for cue_point in cues {
+            let position = cue_point.track_positions[0].cluster_position;
+            reader.seek_to_byte_offset(position).unwrap();
+            let Some(SegmentElement::Cluster(cluster)) = reader.next_element().unwrap() else {
+                panic!("no Cluster at {position}");
+            };
+            let track = cue_point.track_positions[0].track;
+            assert!(cluster
+                .simple_block
+                .iter()
+                .any(|block| block.track_number == track
+                    && block.keyframe
+                    && cluster.timestamp + block.timestamp as u64 == cue_point.time));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Helpers for the `serde` feature.
+//!
+//! Binary data is represented as base64 strings and [EbmlDate] as an
+//! RFC 3339 timestamp, so that the output stays readable in text formats
+//! such as JSON.
+
+use std::fmt::Write;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::{self, Deserializer};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::ebml::{civil_from_days, EbmlDate, EPOCH_DAYS};
+
+/// `#[serde(with)]` module for `Vec<u8>` and `Cow<[u8]>` fields.
+pub(crate) mod bytes {
+    use super::*;
+
+    pub(crate) fn serialize<T: AsRef<[u8]>, S: Serializer>(
+        data: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub(crate) fn deserialize<'de, T: From<Vec<u8>>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        STANDARD.decode(s).map(T::from).map_err(de::Error::custom)
+    }
+}
+
+/// `#[serde(with)]` module for `Option<Vec<u8>>` fields.
+pub(crate) mod opt_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        data: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        data.as_ref()
+            .map(|d| STANDARD.encode(d))
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| STANDARD.decode(s).map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// The reverse of civil_from_days, from the same algorithms
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+fn digits(s: &str) -> Option<i64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+impl EbmlDate {
+    fn to_rfc3339(self) -> String {
+        let seconds = self.0.div_euclid(NANOS_PER_SECOND);
+        let nanos = self.0.rem_euclid(NANOS_PER_SECOND);
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY) + EPOCH_DAYS);
+
+        let mut s = format!(
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        );
+        if nanos != 0 {
+            write!(s, ".{nanos:09}").unwrap();
+        }
+        s.push('Z');
+        s
+    }
+
+    fn from_rfc3339(s: &str) -> Option<EbmlDate> {
+        let number = |range: std::ops::Range<usize>| s.get(range).and_then(digits);
+        let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if separators
+            .iter()
+            .any(|&(i, c)| s.as_bytes().get(i) != Some(&c))
+            || !matches!(s.as_bytes().get(10), Some(b'T' | b't' | b' '))
+        {
+            return None;
+        }
+
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+            return None;
+        }
+
+        let mut rest = &s[19..];
+        let mut nanos = 0;
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            // Digits past the nanosecond are dropped
+            let fraction_digits = len.min(9);
+            nanos = digits(&fraction[..fraction_digits])? * 10_i64.pow(9 - fraction_digits as u32);
+            rest = &fraction[len..];
+        }
+
+        let offset = match rest.as_bytes() {
+            [b'Z' | b'z'] => 0,
+            [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+                let offset = digits(&rest[1..3])? * 3600 + digits(&rest[4..6])? * 60;
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => return None,
+        };
+
+        let days = days_from_civil(year, month, day) - EPOCH_DAYS;
+        let seconds = days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second - offset;
+
+        seconds
+            .checked_mul(NANOS_PER_SECOND)?
+            .checked_add(nanos)
+            .map(EbmlDate)
+    }
+}
+
+impl Serialize for EbmlDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_rfc3339())
+    }
+}
+
+impl<'de> Deserialize<'de> for EbmlDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        EbmlDate::from_rfc3339(&s)
+            .ok_or_else(|| de::Error::custom(format!("invalid RFC 3339 date: {s}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_rfc3339() {
+        let dates = [
+            (0, "2001-01-01T00:00:00Z"),
+            (-1, "2000-12-31T23:59:59.999999999Z"),
+            (1_500, "2001-01-01T00:00:00.000001500Z"),
+            (730_857_600 * NANOS_PER_SECOND, "2024-02-29T00:00:00Z"),
+            (-978_307_200 * NANOS_PER_SECOND, "1970-01-01T00:00:00Z"),
+        ];
+
+        for (nanos, s) in dates {
+            assert_eq!(EbmlDate(nanos).to_rfc3339(), s);
+            assert_eq!(EbmlDate::from_rfc3339(s), Some(EbmlDate(nanos)));
+        }
+
+        assert_eq!(
+            EbmlDate::from_rfc3339("2001-01-01T02:00:00.5+02:00"),
+            Some(EbmlDate(NANOS_PER_SECOND / 2))
+        );
+        assert_eq!(EbmlDate::from_rfc3339("2001-01-01"), None);
+        assert_eq!(EbmlDate::from_rfc3339("2001-13-01T00:00:00Z"), None);
+        assert_eq!(EbmlDate::from_rfc3339("2001-01-01T00:00:00"), None);
+    }
+}
@@ -19,12 +19,12 @@ where
     }
 }
 
-pub(crate) fn gen_opt<'a, 'b, T, G: 'a, H>(
+pub(crate) fn gen_opt<'a, 'b, T, G, H>(
     val: Option<&'a T>,
     f: G,
 ) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a
 where
-    G: Fn(&'a T) -> H,
+    G: Fn(&'a T) -> H + 'a,
     H: Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a,
 {
     move |input| {
@@ -36,12 +36,12 @@ where
     }
 }
 
-pub(crate) fn gen_opt_copy<'a, 'b, T: Copy + 'a, G: 'a, H>(
+pub(crate) fn gen_opt_copy<'a, 'b, T: Copy + 'a, G, H>(
     val: Option<T>,
     f: G,
 ) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a
 where
-    G: Fn(T) -> H,
+    G: Fn(T) -> H + 'a,
     H: Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a,
 {
     move |input| {
@@ -53,20 +53,15 @@ where
     }
 }
 
-pub(crate) fn gen_many<'a, 'b, T: IntoIterator + Copy + 'a, G: 'a, H>(
+pub(crate) fn gen_many<'a, 'b, T: IntoIterator + Copy + 'a, G, H>(
     list: T,
     f: G,
 ) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a
 where
-    G: Fn(<T as IntoIterator>::Item) -> H,
+    G: Fn(<T as IntoIterator>::Item) -> H + 'a,
     H: Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a,
 {
-    move |input| {
-        list.into_iter().fold(Ok(input), |r, v| match r {
-            Err(e) => Err(e),
-            Ok(x) => f(v)(x),
-        })
-    }
+    move |input| list.into_iter().try_fold(input, |x, v| f(v)(x))
 }
 
 pub(crate) fn tuple<'a, List: tuple::Tuple<'a>>(
@@ -1,10 +1,12 @@
+use std::io::Write;
+
 use cookie_factory::gen::set_be_u8;
 use cookie_factory::gen_slice;
 use cookie_factory::GenError;
-use nom::AsBytes;
 
-use crate::ebml::{Date, EbmlHeader};
+use crate::ebml::{EbmlDate, EbmlHeader};
 use crate::serializer::cookie_utils::{gen_at_offset, gen_skip, gen_slice, set_be_f64, tuple};
+use crate::Error;
 
 const ALLOWED_ID_VALUES: u64 = (1u64 << 56) - 1;
 
@@ -25,6 +27,23 @@ pub(crate) fn vint_size(i: u64) -> Result<u8, GenError> {
     Ok(val)
 }
 
+// Number of bytes of the shortest big-endian representation of an
+// unsigned integer, at least one.
+pub(crate) fn uint_size(i: u64) -> u8 {
+    8 - (i.leading_zeros() / 8).min(7) as u8
+}
+
+// Number of bytes of the shortest big-endian two's complement
+// representation of a signed integer, at least one.
+pub(crate) fn int_size(i: i64) -> u8 {
+    let sign_bits = if i < 0 {
+        i.leading_ones()
+    } else {
+        i.leading_zeros()
+    };
+    8 - ((sign_bits - 1) / 8).min(7) as u8
+}
+
 // Will return zero if i == 0.
 pub(crate) fn vid_size(i: u32) -> u8 {
     4 - (i.leading_zeros() / 8) as u8
@@ -57,6 +76,74 @@ pub(crate) fn gen_vint(
     }
 }
 
+/// Encodes `value` as an EBML variable-size integer, using the shortest
+/// length whose VINT_DATA bits are not all ones, as that value is reserved.
+///
+/// Values from 2^56 - 1 cannot be encoded. [vint](crate::ebml::vint) is
+/// the decoder.
+pub fn encode_vint(value: u64) -> Result<Vec<u8>, Error> {
+    let mut buf = vec![0; vint_size(value).map_err(Error::Serialize)?.into()];
+    gen_vint(value)((&mut buf, 0)).map_err(Error::Serialize)?;
+
+    Ok(buf)
+}
+
+/// Encodes `value` as the Element Data Size and data of a Float Element of
+/// 4 bytes. [ebml_float](crate::ebml::ebml_float) is the decoder.
+pub fn encode_float32(value: f32) -> Vec<u8> {
+    [&[0x84][..], &value.to_be_bytes()].concat()
+}
+
+/// Encodes `value` as the Element Data Size and data of a Float Element of
+/// 8 bytes. [ebml_float](crate::ebml::ebml_float) is the decoder.
+pub fn encode_float64(value: f64) -> Vec<u8> {
+    [&[0x88][..], &value.to_be_bytes()].concat()
+}
+
+/// Encodes an Element ID, which keeps its VINT_MARKER, to its bytes.
+///
+/// The width of the ID is given by its marker, and has to be the shortest
+/// one able to hold its VINT_DATA, which cannot be all zeros or all ones:
+/// 0x4001 has to be written 0x81 and is refused. [vid](crate::ebml::vid)
+/// is the decoder.
+pub fn encode_element_id(id: u32) -> Result<Vec<u8>, Error> {
+    let len = vid_size(id);
+    let first = (id >> ((len.max(1) - 1) * 8)) as u8;
+    if len == 0 || first.leading_zeros() + 1 != u32::from(len) {
+        return Err(Error::Serialize(GenError::CustomError(0)));
+    }
+
+    let data = id & !(1 << (len * 7));
+    if data == 0 || vint_size(u64::from(data)).ok() != Some(len) {
+        return Err(Error::Serialize(GenError::CustomError(0)));
+    }
+
+    Ok(id.to_be_bytes()[4 - len as usize..].to_vec())
+}
+
+/// Encodes the header of a Master Element: its ID and `inner_size`, the
+/// size of its data, with the shortest width.
+///
+/// `None` writes the 8 bytes unknown size, `0x01FFFFFFFFFFFFFF`, for an
+/// element whose size is not known yet while streaming.
+pub fn encode_master(id: u32, inner_size: Option<u64>) -> Result<Vec<u8>, Error> {
+    let size = match inner_size {
+        Some(size) => encode_vint(size)?,
+        None => (u64::MAX >> 7).to_be_bytes().to_vec(),
+    };
+
+    Ok([encode_element_id(id)?, size].concat())
+}
+
+/// Encodes an element of ID `id` holding `data`, with the shortest Element
+/// Data Size.
+pub fn encode_element(id: u32, data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut element = encode_master(id, Some(data.len() as u64))?;
+    element.extend_from_slice(data);
+
+    Ok(element)
+}
+
 pub(crate) fn gen_vid(
     num: u32,
 ) -> impl Fn((&mut [u8], usize)) -> Result<(&mut [u8], usize), GenError> {
@@ -70,7 +157,7 @@ pub(crate) fn gen_uint(
     num: u64,
 ) -> impl Fn((&mut [u8], usize)) -> Result<(&mut [u8], usize), GenError> {
     move |mut input| {
-        let needed_bytes = vint_size(num)?;
+        let needed_bytes = uint_size(num);
 
         let mut i = needed_bytes - 1;
         loop {
@@ -168,7 +255,20 @@ pub(crate) fn gen_ebml_uint(
     id: u32,
     num: u64,
 ) -> impl Fn((&mut [u8], usize)) -> Result<(&mut [u8], usize), GenError> {
-    gen_ebml_uint_l(id, num, move || vint_size(num))
+    gen_ebml_uint_l(id, num, move || Ok(uint_size(num)))
+}
+
+pub(crate) fn gen_ebml_int(
+    id: u32,
+    num: i64,
+) -> impl Fn((&mut [u8], usize)) -> Result<(&mut [u8], usize), GenError> {
+    move |input| {
+        let needed_bytes = int_size(num) as usize;
+
+        let (buf, ofs) = gen_vid(id)(input)?;
+        let (buf, ofs) = gen_vint(needed_bytes as u64)((buf, ofs))?;
+        gen_slice(&num.to_be_bytes()[8 - needed_bytes..])((buf, ofs))
+    }
 }
 
 pub(crate) fn gen_ebml_str<'a, 'b>(
@@ -235,6 +335,18 @@ pub(crate) fn gen_ebml_header<'a, 'b>(
     }
 }
 
+impl EbmlHeader {
+    /// Writes the header with the shortest encoding of every element and
+    /// returns the number of bytes written.
+    pub fn write<W: Write>(&self, mut w: W) -> Result<usize, Error> {
+        let mut buf = vec![0; self.size(0x1A45DFA3)];
+        gen_ebml_header(self)((&mut buf, 0)).map_err(Error::Serialize)?;
+        w.write_all(&buf)?;
+
+        Ok(buf.len())
+    }
+}
+
 pub trait EbmlSize {
     fn capacity(&self) -> usize;
 
@@ -245,6 +357,22 @@ pub trait EbmlSize {
 
         id_size as usize + size_tag_size as usize + self_size
     }
+
+    /// Encodes the header of a Master Element, see [encode_master].
+    fn encode_master(id: u32, inner_size: Option<u64>) -> Result<Vec<u8>, Error>
+    where
+        Self: Sized,
+    {
+        encode_master(id, inner_size)
+    }
+
+    /// Encodes an element holding `data`, see [encode_element].
+    fn encode_element(id: u32, data: &[u8]) -> Result<Vec<u8>, Error>
+    where
+        Self: Sized,
+    {
+        encode_element(id, data)
+    }
 }
 
 impl EbmlSize for u32 {
@@ -255,13 +383,19 @@ impl EbmlSize for u32 {
 
 impl EbmlSize for u64 {
     fn capacity(&self) -> usize {
-        vint_size(*self).unwrap_or(0) as usize
+        uint_size(*self) as usize
     }
 }
 
 impl EbmlSize for i64 {
     fn capacity(&self) -> usize {
-        vint_size(*self as u64).unwrap_or(0) as usize
+        int_size(*self) as usize
+    }
+}
+
+impl EbmlSize for bool {
+    fn capacity(&self) -> usize {
+        1
     }
 }
 
@@ -272,7 +406,7 @@ impl EbmlSize for f64 {
     }
 }
 
-impl EbmlSize for Date {
+impl EbmlSize for EbmlDate {
     fn capacity(&self) -> usize {
         // FIXME: Handle zero-sized date
         8
@@ -303,13 +437,13 @@ impl<T: EbmlSize> EbmlSize for Option<T> {
 
 impl EbmlSize for String {
     fn capacity(&self) -> usize {
-        self.as_bytes().len()
+        self.len()
     }
 }
 
 impl EbmlSize for Vec<u8> {
     fn capacity(&self) -> usize {
-        (*self).as_bytes().len()
+        self.len()
     }
 }
 
@@ -319,13 +453,13 @@ impl<const N: usize> EbmlSize for [u8; N] {
     }
 }
 
-impl<'a> EbmlSize for &'a [u8] {
+impl EbmlSize for &[u8] {
     fn capacity(&self) -> usize {
         self.len()
     }
 }
 
-impl<'a> EbmlSize for Vec<&'a [u8]> {
+impl EbmlSize for Vec<&[u8]> {
     fn capacity(&self) -> usize {
         self.iter().fold(0, |acc, sl| acc + sl.len())
     }
@@ -337,7 +471,7 @@ impl EbmlSize for Vec<u64> {
     }
 }
 
-impl EbmlSize for uuid::Uuid {
+impl EbmlSize for crate::elements::Uuid {
     fn capacity(&self) -> usize {
         16
     }
@@ -409,6 +543,138 @@ mod tests {
         test_vint_serializer(2100000);
     }
 
+    #[test]
+    fn encode_vint_edge_cases() {
+        let cases: [(u64, &[u8]); 9] = [
+            (0, &[0x80]),
+            (126, &[0xFE]),
+            (127, &[0x40, 0x7F]),
+            (128, &[0x40, 0x80]),
+            ((1 << 14) - 2, &[0x7F, 0xFE]),
+            ((1 << 14) - 1, &[0x20, 0x3F, 0xFF]),
+            (1 << 14, &[0x20, 0x40, 0x00]),
+            ((1 << 21) - 1, &[0x10, 0x1F, 0xFF, 0xFF]),
+            (
+                (1 << 56) - 2,
+                &[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE],
+            ),
+        ];
+        for (value, encoded) in cases {
+            assert_eq!(encode_vint(value).unwrap(), encoded, "{value}");
+            assert_eq!(crate::ebml::vint(encoded), Ok((&[][..], value)));
+        }
+
+        assert!(matches!(
+            encode_vint((1 << 56) - 1),
+            Err(Error::Serialize(_))
+        ));
+        assert!(encode_vint(u64::MAX).is_err());
+    }
+
+    fn test_int_serializer(i: i64) -> bool {
+        let mut data = vec![0; i.size(0xFB)];
+        gen_ebml_int(0xFB, i)((&mut data[..], 0)).unwrap();
+
+        assert_eq!(
+            crate::ebml::ebml_element::<i64>(0xFB)(&data),
+            Ok((&[][..], i))
+        );
+        true
+    }
+
+    quickcheck! {
+      fn test_int(i: i64) -> bool {
+        test_int_serializer(i)
+      }
+    }
+
+    #[test]
+    fn int() {
+        for (value, size) in [
+            (0, 1),
+            (127, 1),
+            (128, 2),
+            (-1, 1),
+            (-128, 1),
+            (-129, 2),
+            (i64::MAX, 8),
+            (i64::MIN, 8),
+        ] {
+            assert_eq!(int_size(value), size, "{value}");
+            test_int_serializer(value);
+        }
+    }
+
+    #[test]
+    fn encode_floats() {
+        use crate::ebml::ebml_float;
+
+        // The Durations and SamplingFrequencies of the Matroska test files
+        // and of the WebM assets
+        let floats: [(f32, &[u8]); 3] = [
+            (87336.0, &[0x84, 0x47, 0xAA, 0x94, 0x00]),
+            (48000.0, &[0x84, 0x47, 0x3B, 0x80, 0x00]),
+            (44100.0, &[0x84, 0x47, 0x2C, 0x44, 0x00]),
+        ];
+        for (value, encoded) in floats {
+            assert_eq!(encode_float32(value), encoded, "{value}");
+            assert_eq!(ebml_float(encoded), Ok((&[][..], f64::from(value))));
+        }
+
+        let doubles: [(f64, &[u8]); 3] = [
+            (1020.0, &[0x88, 0x40, 0x8F, 0xE0, 0, 0, 0, 0, 0]),
+            (48000.0, &[0x88, 0x40, 0xE7, 0x70, 0, 0, 0, 0, 0]),
+            (
+                -0.1,
+                &[0x88, 0xBF, 0xB9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9A],
+            ),
+        ];
+        for (value, encoded) in doubles {
+            assert_eq!(encode_float64(value), encoded, "{value}");
+            assert_eq!(ebml_float(encoded), Ok((&[][..], value)));
+        }
+
+        // A float of 4 bytes keeps its value, not its shortest decimal
+        let data = encode_float32(0.1);
+        assert_eq!(ebml_float(&data), Ok((&[][..], f64::from(0.1f32))));
+
+        for data in [&[0x80][..], &[0x82, 0x47, 0xAA], &[0x83, 0x47, 0xAA, 0x94]] {
+            assert!(ebml_float(data).is_err());
+        }
+        assert!(matches!(
+            ebml_float(&[0x84, 0x47]),
+            Err(nom::Err::Incomplete(_))
+        ));
+    }
+
+    #[test]
+    fn encode_element_id_widths() {
+        let cases: [(u32, &[u8]); 6] = [
+            (0x81, &[0x81]),
+            (0xEC, &[0xEC]),
+            (0x407F, &[0x40, 0x7F]),
+            (0x4286, &[0x42, 0x86]),
+            (0x2AD7B1, &[0x2A, 0xD7, 0xB1]),
+            (0x1A45DFA3, &[0x1A, 0x45, 0xDF, 0xA3]),
+        ];
+        for (id, encoded) in cases {
+            assert_eq!(encode_element_id(id).unwrap(), encoded, "{id:#X}");
+            let data = [encoded, &[0x80]].concat();
+            assert_eq!(crate::ebml::vid(&data), Ok((&[0x80][..], id)));
+        }
+
+        // No marker, marker not matching the width, all zeros or all ones,
+        // not the shortest width
+        for id in [
+            0, 0x01, 0x7F, 0x8086, 0x80, 0xFF, 0x4000, 0x7FFF, 0x4001, 0x201FFF, 0x10000001,
+        ] {
+            assert!(
+                matches!(encode_element_id(id), Err(Error::Serialize(_))),
+                "{id:#X}"
+            );
+        }
+    }
+
     fn test_vid_serializer(id: u32) -> bool {
         println!("\ntesting for id={id}");
 
@@ -431,6 +697,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn encode_elements() {
+        assert_eq!(
+            encode_master(0x1F43B675, Some(5)).unwrap(),
+            [0x1F, 0x43, 0xB6, 0x75, 0x85]
+        );
+        assert_eq!(
+            encode_master(0x1F43B675, Some(127)).unwrap(),
+            [0x1F, 0x43, 0xB6, 0x75, 0x40, 0x7F]
+        );
+        assert_eq!(
+            encode_master(0x18538067, None).unwrap(),
+            [0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        assert!(encode_master(0x4001, Some(0)).is_err());
+
+        let element = encode_element(0x4282, b"webm").unwrap();
+        assert_eq!(element, [0x42, 0x82, 0x84, b'w', b'e', b'b', b'm']);
+        let (_, doc_type) = crate::ebml::ebml_element::<String>(0x4282)(&element).unwrap();
+        assert_eq!(doc_type, "webm");
+        assert_eq!(encode_element(0xEC, &[]).unwrap(), [0xEC, 0x80]);
+        assert_eq!(
+            <u64 as EbmlSize>::encode_master(0x1F43B675, Some(5)).unwrap(),
+            encode_master(0x1F43B675, Some(5)).unwrap()
+        );
+        assert_eq!(
+            <u64 as EbmlSize>::encode_element(0x4282, b"webm").unwrap(),
+            element
+        );
+    }
+
     #[test]
     fn vid() {
         test_vid_serializer(128);
@@ -537,4 +834,64 @@ mod tests {
         }
       }
     }
+
+    proptest::proptest! {
+        #[test]
+        fn ebml_header_round_trip(
+            version: u32,
+            read_version: u32,
+            max_id_length: u32,
+            max_size_length: u32,
+            doc_type: String,
+            doc_type_version: u32,
+            doc_type_read_version: u32,
+        ) {
+            let header = EbmlHeader {
+                version,
+                read_version,
+                max_id_length,
+                max_size_length,
+                doc_type,
+                doc_type_version,
+                doc_type_read_version,
+            };
+
+            let mut data = Vec::new();
+            let len = header.write(&mut data).unwrap();
+            proptest::prop_assert_eq!(len, data.len());
+
+            let (rest, parsed) = crate::ebml::ebml_header(&data).unwrap();
+            proptest::prop_assert!(rest.is_empty());
+            proptest::prop_assert_eq!(&parsed, &header);
+
+            let mut rewritten = Vec::new();
+            parsed.write(&mut rewritten).unwrap();
+            proptest::prop_assert_eq!(rewritten, data);
+        }
+    }
+
+    #[test]
+    fn write_canonical_ebml_header() {
+        // The header of this file has an 8 bytes size
+        let data = include_bytes!("../../assets/bbb-vp9-opus.webm");
+        let (rest, header) = crate::ebml::ebml_header(data).unwrap();
+        let children = &data[12..data.len() - rest.len()];
+
+        let mut written = Vec::new();
+        assert_eq!(header.write(&mut written).unwrap(), 5 + children.len());
+        assert_eq!(
+            written[..5],
+            [0x1A, 0x45, 0xDF, 0xA3, 0x80 | children.len() as u8]
+        );
+        assert_eq!(&written[5..], children);
+
+        // 255 fits in one byte
+        let header = EbmlHeader {
+            doc_type_version: 255,
+            ..header
+        };
+        written.clear();
+        header.write(&mut written).unwrap();
+        assert_eq!(written.len(), 5 + children.len());
+    }
 }
@@ -3,14 +3,19 @@ use cookie_factory::GenError;
 
 use crate::{
     elements::{
-        Audio, Cluster, Colour, Info, Lacing, MasteringMetadata, Projection, Seek, SeekHead,
-        SimpleBlock, TrackEntry, Tracks, Video,
+        Audio, Block, BlockAdditions, BlockGroup, BlockMore, Cluster, ClusterBlock, Colour,
+        CuePoint, CueReference, CueTrackPositions, Cues, Info, Lacing, MasteringMetadata,
+        Projection, Seek, SeekHead, SilentTracks, SimpleBlock, SimpleTag, Tag, Tags, Targets,
+        TrackEntry, Tracks, Video, Void,
+    },
+    serializer::cookie_utils::{
+        gen_many, gen_opt, gen_opt_copy, gen_skip, gen_slice, set_be_i16, tuple,
     },
-    serializer::cookie_utils::{gen_many, gen_opt, gen_opt_copy, set_be_i16, tuple},
     serializer::ebml::{
-        gen_ebml_binary, gen_ebml_master, gen_ebml_str, gen_ebml_uint, gen_ebml_uint_l, gen_f64,
-        gen_vid, gen_vint, vint_size, EbmlSize,
+        gen_ebml_binary, gen_ebml_int, gen_ebml_master, gen_ebml_str, gen_ebml_uint,
+        gen_ebml_uint_l, gen_f64, gen_vid, gen_vint, vint_size, EbmlSize,
     },
+    Error,
 };
 
 pub(crate) fn gen_segment_header_unknown_size(
@@ -18,6 +23,64 @@ pub(crate) fn gen_segment_header_unknown_size(
     move |input| tuple((gen_vid(0x18538067), |i| set_be_u8(i, 0xFF)))(input)
 }
 
+/// Segment header with an 8 bytes size, so that it can be rewritten
+/// in place once the size is known. `None` writes an unknown size.
+pub(crate) fn gen_segment_header(
+    size: Option<u64>,
+) -> impl Fn((&mut [u8], usize)) -> Result<(&mut [u8], usize), GenError> {
+    move |input| {
+        let size = match size {
+            Some(size) if size >= (1 << 56) - 1 => return Err(GenError::CustomError(0)),
+            Some(size) => size | 1 << 56,
+            None => u64::MAX >> 7,
+        };
+
+        tuple((gen_vid(0x18538067), gen_slice(&size.to_be_bytes())))(input)
+    }
+}
+
+/// Void Element taking exactly `size` bytes, including its header.
+pub(crate) fn gen_void(
+    size: usize,
+) -> impl Fn((&mut [u8], usize)) -> Result<(&mut [u8], usize), GenError> {
+    move |input| {
+        // Use an 8 bytes Element Data Size for anything that doesn't fit in one
+        let (data_size, size_length) = match size {
+            0 | 1 => return Err(GenError::CustomError(0)),
+            2..=128 => (size - 2, 1),
+            _ => (size - 9, 8),
+        };
+
+        let data_size = (data_size as u64 | 1 << (7 * size_length)).to_be_bytes();
+
+        let input = gen_vid(0xEC)(input)?;
+        let input = gen_slice(&data_size[8 - size_length..])(input)?;
+        gen_skip(size - 1 - size_length)(input)
+    }
+}
+
+impl Void {
+    /// The smallest Void Element takes 2 bytes: its ID and a 1 byte size.
+    pub fn min_overhead() -> usize {
+        2
+    }
+
+    /// The header of the Void Elements written by [Void::encode] takes at
+    /// most 9 bytes: its ID and an 8 bytes size.
+    pub fn max_overhead() -> usize {
+        9
+    }
+
+    /// Encodes a Void Element taking exactly `target_total_bytes` bytes,
+    /// header included, e.g. to fill the space left by a rewritten element.
+    pub fn encode(target_total_bytes: usize) -> Result<Vec<u8>, Error> {
+        let mut data = vec![0; target_total_bytes];
+        gen_void(target_total_bytes)((&mut data, 0)).map_err(Error::Serialize)?;
+
+        Ok(data)
+    }
+}
+
 impl EbmlSize for Seek {
     fn capacity(&self) -> usize {
         self.id.size(0x53AB) + self.position.size(0x53AC)
@@ -127,6 +190,11 @@ impl EbmlSize for TrackEntry {
             + self.flag_enabled.size(0xB9)
             + self.flag_default.size(0x88)
             + self.flag_forced.size(0x55AA)
+            + set_flag(self.flag_hearing_impaired).size(0x55AB)
+            + set_flag(self.flag_visual_impaired).size(0x55AC)
+            + set_flag(self.flag_text_descriptions).size(0x55AD)
+            + set_flag(self.flag_original).size(0x55AE)
+            + set_flag(self.flag_commentary).size(0x55AF)
             + self.flag_lacing.size(0x9C)
             + self.default_duration.size(0x23E383)
             + self.default_decoded_field_duration.size(0x234E7A)
@@ -138,7 +206,10 @@ impl EbmlSize for TrackEntry {
             + self.codec_id.size(0x86)
             + self.codec_private.size(0x63A2)
             + self.codec_name.size(0x258688)
-            + self.attachment_link.size(0x7446)
+            + self
+                .attachment_links
+                .iter()
+                .fold(0, |acc, uid| acc + uid.size(0x7446))
             + self.codec_delay.size(0x56AA)
             + self.seek_pre_roll.size(0x56BB)
             + self.video.size(0xE0)
@@ -146,6 +217,12 @@ impl EbmlSize for TrackEntry {
     }
 }
 
+// The accessibility flags have no default value, they are only written
+// when set
+fn set_flag(flag: bool) -> Option<u64> {
+    flag.then_some(1)
+}
+
 fn gen_track_entry<'a, 'b>(
     t: &'a TrackEntry,
 ) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
@@ -162,7 +239,18 @@ fn gen_track_entry<'a, 'b>(
                 gen_ebml_uint(0x83, t.track_type),
                 gen_ebml_uint(0xB9, t.flag_enabled),
                 gen_ebml_uint(0x88, t.flag_default),
-                gen_ebml_uint(0x55AA, t.flag_forced),
+                gen_ebml_uint(0x55AA, t.flag_forced.into()),
+                gen_opt_copy(set_flag(t.flag_hearing_impaired), |v| {
+                    gen_ebml_uint(0x55AB, v)
+                }),
+                gen_opt_copy(set_flag(t.flag_visual_impaired), |v| {
+                    gen_ebml_uint(0x55AC, v)
+                }),
+                gen_opt_copy(set_flag(t.flag_text_descriptions), |v| {
+                    gen_ebml_uint(0x55AD, v)
+                }),
+                gen_opt_copy(set_flag(t.flag_original), |v| gen_ebml_uint(0x55AE, v)),
+                gen_opt_copy(set_flag(t.flag_commentary), |v| gen_ebml_uint(0x55AF, v)),
                 gen_ebml_uint(0x9C, t.flag_lacing),
                 gen_opt_copy(t.default_duration, |v| gen_ebml_uint(0x23E383, v)),
                 gen_opt_copy(t.default_decoded_field_duration, |v| {
@@ -176,7 +264,7 @@ fn gen_track_entry<'a, 'b>(
                 gen_ebml_str(0x86, &t.codec_id),
                 gen_opt(t.codec_private.as_ref(), |v| gen_ebml_binary(0x63A2, v)),
                 gen_opt(t.codec_name.as_ref(), |v| gen_ebml_str(0x258688, v)),
-                gen_opt_copy(t.attachment_link, |v| gen_ebml_uint(0x7446, v)),
+                gen_many(&t.attachment_links, |&v| gen_ebml_uint(0x7446, v)),
                 gen_ebml_uint(0x56AA, t.codec_delay),
                 gen_ebml_uint(0x56BB, t.seek_pre_roll),
                 gen_opt(t.video.as_ref(), gen_track_entry_video),
@@ -192,6 +280,7 @@ impl EbmlSize for Audio {
             + self.output_sampling_frequency.size(0x78B5)
             + self.channels.size(0x9F)
             + self.bit_depth.size(0x6264)
+            + self.channel_positions.size(0x7D7B)
     }
 }
 
@@ -209,6 +298,7 @@ fn gen_track_entry_audio<'a, 'b>(
                 gen_opt_copy(a.output_sampling_frequency, |v| gen_f64(0x78B5, v)),
                 gen_ebml_uint(0x9F, a.channels),
                 gen_opt_copy(a.bit_depth, |v| gen_ebml_uint(0x6264, v)),
+                gen_opt(a.channel_positions.as_ref(), |v| gen_ebml_binary(0x7D7B, v)),
             )),
         )(input)
     }
@@ -351,7 +441,7 @@ fn gen_track_entry_video_colour_mastering_metadata<'a, 'b>(
                 gen_opt_copy(m.primary_g_chromaticity_y, |v| gen_f64(0x55D4, v)),
                 gen_opt_copy(m.primary_b_chromaticity_x, |v| gen_f64(0x55D5, v)),
                 gen_opt_copy(m.primary_b_chromaticity_y, |v| gen_f64(0x55D6, v)),
-                gen_opt_copy(m.white_point_chromaticity_y, |v| gen_f64(0x55D7, v)),
+                gen_opt_copy(m.white_point_chromaticity_x, |v| gen_f64(0x55D7, v)),
                 gen_opt_copy(m.white_point_chromaticity_y, |v| gen_f64(0x55D8, v)),
                 gen_opt_copy(m.luminance_max, |v| gen_f64(0x55D9, v)),
                 gen_opt_copy(m.luminance_min, |v| gen_f64(0x55DA, v)),
@@ -395,11 +485,17 @@ fn gen_track_entry_video_projection<'a, 'b>(
 impl<'a> EbmlSize for Cluster<'a> {
     fn capacity(&self) -> usize {
         self.timestamp.size(0xE7)
+            + self.silent_tracks.size(0x5854)
             + self.position.size(0xA7)
             + self.prev_size.size(0xAB)
-            + self.simple_block.size(0xA3)
-        // TODO: implement for BlockGroup
-        // + self.block_group.size(0xA0)
+            + self
+                .simple_block
+                .iter()
+                .fold(0, |acc, block| acc + block.size(0xA3))
+            + self
+                .block_group
+                .iter()
+                .fold(0, |acc, group| acc + group.size(0xA0))
     }
 }
 
@@ -413,16 +509,68 @@ pub(crate) fn gen_cluster<'a, 'b>(
             byte_capacity,
             tuple((
                 gen_ebml_uint(0xE7, c.timestamp),
+                gen_opt(c.silent_tracks.as_ref(), gen_silent_tracks),
                 gen_opt_copy(c.position, |v| gen_ebml_uint(0xA7, v)),
                 gen_opt_copy(c.prev_size, |v| gen_ebml_uint(0xAB, v)),
-                gen_many(&c.simple_block, |v| gen_ebml_binary(0xA3, v)),
-                // TODO: implement for BlockGroup
-                // gen_many(&c.block_group, gen_block_group)
+                gen_blocks(c),
             )),
         )(input)
     }
 }
 
+// The SimpleBlocks and BlockGroups of a Cluster, in their order
+fn gen_blocks<'a, 'b>(
+    c: &'a Cluster,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        c.blocks().try_fold(input, |input, block| match block {
+            ClusterBlock::SimpleBlock(b) => gen_simple_block(b)(input),
+            ClusterBlock::BlockGroup(g) => gen_block_group(g)(input),
+        })
+    }
+}
+
+impl EbmlSize for SilentTracks {
+    fn capacity(&self) -> usize {
+        self.silent_track_number
+            .iter()
+            .fold(0, |acc, number| acc + number.size(0x58D7))
+    }
+}
+
+fn gen_silent_tracks<'a, 'b>(
+    s: &'a SilentTracks,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(s.capacity() as u64)?;
+        gen_ebml_master(
+            0x5854,
+            byte_capacity,
+            gen_many(&s.silent_track_number, |&v| gen_ebml_uint(0x58D7, v)),
+        )(input)
+    }
+}
+
+impl<'a> EbmlSize for SimpleBlock<'a> {
+    fn capacity(&self) -> usize {
+        // track number, 2 bytes of timestamp and 1 byte of flags
+        vint_size(self.track_number).unwrap_or(0) as usize + 3 + self.data.len()
+    }
+}
+
+fn gen_simple_block<'a, 'b>(
+    s: &'a SimpleBlock,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(s.capacity() as u64)?;
+        gen_ebml_master(
+            0xA3,
+            byte_capacity,
+            tuple((gen_simple_block_header(s), gen_slice(&s.data))),
+        )(input)
+    }
+}
+
 pub(crate) fn gen_simple_block_header<'a, 'b>(
     s: &'a SimpleBlock,
 ) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
@@ -430,22 +578,17 @@ pub(crate) fn gen_simple_block_header<'a, 'b>(
         let mut flags = 0u8;
 
         if s.keyframe {
-            flags |= 0b0001u8;
+            flags |= 0b10000000u8;
         }
 
         if s.invisible {
-            flags |= 0b00010000u8;
+            flags |= 0b00001000u8;
         }
 
-        flags |= match s.lacing {
-            Lacing::None => 0u8,
-            Lacing::Xiph => 0b00100000u8,
-            Lacing::FixedSize => 0b01000000u8,
-            Lacing::EBML => 0b01100000u8,
-        };
+        flags |= lacing_flags(&s.lacing);
 
         if s.discardable {
-            flags |= 0b10000000u8;
+            flags |= 0b00000001u8;
         }
 
         set_be_u8(
@@ -455,6 +598,333 @@ pub(crate) fn gen_simple_block_header<'a, 'b>(
     }
 }
 
+fn lacing_flags(lacing: &Lacing) -> u8 {
+    match lacing {
+        Lacing::None => 0u8,
+        Lacing::Xiph => 0b00000010u8,
+        Lacing::FixedSize => 0b00000100u8,
+        Lacing::EBML => 0b00000110u8,
+    }
+}
+
+impl<'a> EbmlSize for BlockGroup<'a> {
+    fn capacity(&self) -> usize {
+        self.block.size(0xA1)
+            + self.block_additions.size(0x75A1)
+            + self.block_duration.size(0x9B)
+            + self.reference_priority.size(0xFA)
+            + self
+                .reference_block
+                .iter()
+                .fold(0, |acc, reference| acc + reference.size(0xFB))
+            + self.codec_state.size(0xA4)
+            + self.discard_padding.size(0x75A2)
+    }
+}
+
+fn gen_block_group<'a, 'b>(
+    g: &'a BlockGroup,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(g.capacity() as u64)?;
+        gen_ebml_master(
+            0xA0,
+            byte_capacity,
+            tuple((
+                gen_block(&g.block),
+                gen_opt(g.block_additions.as_ref(), gen_block_additions),
+                gen_opt_copy(g.block_duration, |v| gen_ebml_uint(0x9B, v)),
+                gen_ebml_uint(0xFA, g.reference_priority),
+                gen_many(&g.reference_block, |v| gen_ebml_int(0xFB, *v)),
+                gen_opt(g.codec_state.as_ref(), |v| gen_ebml_binary(0xA4, v)),
+                gen_opt_copy(g.discard_padding, |v| gen_ebml_int(0x75A2, v)),
+            )),
+        )(input)
+    }
+}
+
+impl<'a> EbmlSize for Block<'a> {
+    fn capacity(&self) -> usize {
+        // track number, 2 bytes of timestamp and 1 byte of flags
+        vint_size(self.track_number).unwrap_or(0) as usize + 3 + self.data.len()
+    }
+}
+
+fn gen_block<'a, 'b>(
+    b: &'a Block,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let mut flags = lacing_flags(&b.lacing);
+
+        if b.invisible {
+            flags |= 0b00001000u8;
+        }
+
+        let byte_capacity = vint_size(b.capacity() as u64)?;
+        gen_ebml_master(
+            0xA1,
+            byte_capacity,
+            tuple((
+                gen_vint(b.track_number),
+                |i| set_be_i16(i, b.timestamp),
+                move |i| set_be_u8(i, flags),
+                gen_slice(&b.data),
+            )),
+        )(input)
+    }
+}
+
+impl EbmlSize for BlockAdditions {
+    fn capacity(&self) -> usize {
+        self.block_more
+            .iter()
+            .fold(0, |acc, more| acc + more.size(0xA6))
+    }
+}
+
+fn gen_block_additions<'a, 'b>(
+    b: &'a BlockAdditions,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(b.capacity() as u64)?;
+        gen_ebml_master(
+            0x75A1,
+            byte_capacity,
+            gen_many(&b.block_more, gen_block_more),
+        )(input)
+    }
+}
+
+impl EbmlSize for BlockMore {
+    fn capacity(&self) -> usize {
+        self.block_additional.size(0xA5) + self.block_add_id.size(0xEE)
+    }
+}
+
+fn gen_block_more<'a, 'b>(
+    b: &'a BlockMore,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(b.capacity() as u64)?;
+        gen_ebml_master(
+            0xA6,
+            byte_capacity,
+            tuple((
+                gen_ebml_binary(0xA5, &b.block_additional),
+                gen_ebml_uint(0xEE, b.block_add_id),
+            )),
+        )(input)
+    }
+}
+
+impl EbmlSize for Cues {
+    fn capacity(&self) -> usize {
+        self.cue_points
+            .iter()
+            .fold(0, |acc, cue_point| acc + cue_point.size(0xBB))
+    }
+}
+
+pub(crate) fn gen_cues<'a, 'b>(
+    c: &'a Cues,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(c.capacity() as u64)?;
+        gen_ebml_master(
+            0x1C53BB6B,
+            byte_capacity,
+            gen_many(&c.cue_points, gen_cue_point),
+        )(input)
+    }
+}
+
+impl EbmlSize for CuePoint {
+    fn capacity(&self) -> usize {
+        self.time.size(0xB3)
+            + self
+                .track_positions
+                .iter()
+                .fold(0, |acc, positions| acc + positions.size(0xB7))
+    }
+}
+
+fn gen_cue_point<'a, 'b>(
+    c: &'a CuePoint,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(c.capacity() as u64)?;
+        gen_ebml_master(
+            0xBB,
+            byte_capacity,
+            tuple((
+                gen_ebml_uint(0xB3, c.time),
+                gen_many(&c.track_positions, gen_cue_track_positions),
+            )),
+        )(input)
+    }
+}
+
+impl EbmlSize for CueTrackPositions {
+    fn capacity(&self) -> usize {
+        self.track.size(0xF7)
+            + self.cluster_position.size(0xF1)
+            + self.relative_position.size(0xF0)
+            + self.duration.size(0xB2)
+            + self.block_number.size(0x5378)
+            + self.codec_state.size(0xEA)
+            + self
+                .references
+                .iter()
+                .fold(0, |acc, reference| acc + reference.size(0xDB))
+    }
+}
+
+fn gen_cue_track_positions<'a, 'b>(
+    c: &'a CueTrackPositions,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(c.capacity() as u64)?;
+        gen_ebml_master(
+            0xB7,
+            byte_capacity,
+            tuple((
+                gen_ebml_uint(0xF7, c.track),
+                gen_ebml_uint(0xF1, c.cluster_position),
+                gen_opt_copy(c.relative_position, |v| gen_ebml_uint(0xF0, v)),
+                gen_opt_copy(c.duration, |v| gen_ebml_uint(0xB2, v)),
+                gen_opt_copy(c.block_number, |v| gen_ebml_uint(0x5378, v)),
+                gen_ebml_uint(0xEA, c.codec_state),
+                gen_many(&c.references, gen_cue_reference),
+            )),
+        )(input)
+    }
+}
+
+impl EbmlSize for CueReference {
+    fn capacity(&self) -> usize {
+        self.time.size(0x96)
+    }
+}
+
+fn gen_cue_reference<'a, 'b>(
+    c: &'a CueReference,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(c.capacity() as u64)?;
+        gen_ebml_master(0xDB, byte_capacity, gen_ebml_uint(0x96, c.time))(input)
+    }
+}
+
+impl EbmlSize for Tags {
+    fn capacity(&self) -> usize {
+        self.tags.iter().fold(0, |acc, tag| acc + tag.size(0x7373))
+    }
+}
+
+pub(crate) fn gen_tags<'a, 'b>(
+    t: &'a Tags,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(t.capacity() as u64)?;
+        gen_ebml_master(0x1254C367, byte_capacity, gen_many(&t.tags, gen_tag))(input)
+    }
+}
+
+impl EbmlSize for Tag {
+    fn capacity(&self) -> usize {
+        self.targets.size(0x63C0)
+            + self
+                .simple_tags
+                .iter()
+                .fold(0, |acc, simple_tag| acc + simple_tag.size(0x67C8))
+    }
+}
+
+fn gen_tag<'a, 'b>(
+    t: &'a Tag,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(t.capacity() as u64)?;
+        gen_ebml_master(
+            0x7373,
+            byte_capacity,
+            tuple((
+                gen_targets(&t.targets),
+                gen_many(&t.simple_tags, |s| move |i| gen_simple_tag(i, s)),
+            )),
+        )(input)
+    }
+}
+
+impl EbmlSize for Targets {
+    fn capacity(&self) -> usize {
+        let uids = |uids: &[u64], id| uids.iter().fold(0, |acc, uid| acc + uid.size(id));
+
+        self.target_type_value.size(0x68CA)
+            + self.target_type.size(0x63CA)
+            + uids(&self.track_uid, 0x63C5)
+            + uids(&self.edition_uid, 0x63C9)
+            + uids(&self.chapter_uid, 0x63C4)
+            + uids(&self.attachment_uid, 0x63C6)
+    }
+}
+
+fn gen_targets<'a, 'b>(
+    t: &'a Targets,
+) -> impl Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError> + 'a {
+    move |input| {
+        let byte_capacity = vint_size(t.capacity() as u64)?;
+        gen_ebml_master(
+            0x63C0,
+            byte_capacity,
+            tuple((
+                gen_ebml_uint(0x68CA, t.target_type_value),
+                gen_opt(t.target_type.as_ref(), |v| gen_ebml_str(0x63CA, v)),
+                gen_many(&t.track_uid, |&v| gen_ebml_uint(0x63C5, v)),
+                gen_many(&t.edition_uid, |&v| gen_ebml_uint(0x63C9, v)),
+                gen_many(&t.chapter_uid, |&v| gen_ebml_uint(0x63C4, v)),
+                gen_many(&t.attachment_uid, |&v| gen_ebml_uint(0x63C6, v)),
+            )),
+        )(input)
+    }
+}
+
+impl EbmlSize for SimpleTag {
+    fn capacity(&self) -> usize {
+        self.name.size(0x45A3)
+            + self.language.size(0x447A)
+            + self.language_bcp47.size(0x447B)
+            + self.default.size(0x4484)
+            + self.string.size(0x4487)
+            + self.binary.size(0x4485)
+            + self
+                .simple_tags
+                .iter()
+                .fold(0, |acc, simple_tag| acc + simple_tag.size(0x67C8))
+    }
+}
+
+// A plain function rather than a generator, as SimpleTags nest
+fn gen_simple_tag<'b>(
+    input: (&'b mut [u8], usize),
+    s: &SimpleTag,
+) -> Result<(&'b mut [u8], usize), GenError> {
+    let byte_capacity = vint_size(s.capacity() as u64)?;
+    gen_ebml_master(
+        0x67C8,
+        byte_capacity,
+        tuple((
+            gen_ebml_str(0x45A3, &s.name),
+            gen_ebml_str(0x447A, &s.language),
+            gen_opt(s.language_bcp47.as_ref(), |v| gen_ebml_str(0x447B, v)),
+            gen_ebml_uint(0x4484, s.default),
+            gen_opt(s.string.as_ref(), |v| gen_ebml_str(0x4487, v)),
+            gen_opt(s.binary.as_ref(), |v| gen_ebml_binary(0x4485, v)),
+            gen_many(&s.simple_tags, |s| move |i| gen_simple_tag(i, s)),
+        )),
+    )(input)
+}
+
 #[allow(dead_code)]
 fn gen_laced_frames<'a>(
     input: (&'a mut [u8], usize),
@@ -501,7 +971,7 @@ fn gen_fixed_size_laced_frames<'a>(
 mod tests {
     use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
 
-    use crate::elements::SegmentElement;
+    use crate::elements::{BlockKind, EquirectangularBounds, ProjectionType, SegmentElement};
 
     use super::*;
 
@@ -562,4 +1032,261 @@ mod tests {
         TestResult::from_bool(test_seek_head_serializer(seeks))
       }
     }
+
+    #[test]
+    fn cluster_simple_blocks() {
+        let cluster = Cluster {
+            timestamp: 42,
+            silent_tracks: Some(SilentTracks {
+                silent_track_number: vec![3, 300],
+            }),
+            position: None,
+            prev_size: None,
+            simple_block: vec![
+                SimpleBlock {
+                    track_number: 1,
+                    timestamp: 0,
+                    keyframe: true,
+                    invisible: false,
+                    lacing: Lacing::None,
+                    discardable: false,
+                    data: vec![1, 2, 3].into(),
+                },
+                SimpleBlock {
+                    track_number: 2,
+                    timestamp: -20,
+                    keyframe: false,
+                    invisible: true,
+                    lacing: Lacing::FixedSize,
+                    discardable: true,
+                    data: vec![1, 4, 4, 5, 5].into(),
+                },
+            ],
+            block_group: Vec::new(),
+            block_order: Vec::new(),
+        };
+
+        let mut data = vec![0; cluster.size(0x1F43B675)];
+        gen_cluster(&cluster)((&mut data[..], 0)).unwrap();
+
+        match crate::elements::segment_element(&data) {
+            Ok((_, SegmentElement::Cluster(c))) => assert_eq!(c, cluster),
+            e => panic!("parse error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn cluster_block_groups() {
+        let simple_block = |timestamp| SimpleBlock {
+            track_number: 1,
+            timestamp,
+            keyframe: true,
+            invisible: false,
+            lacing: Lacing::None,
+            discardable: false,
+            data: vec![1, 2, 3].into(),
+        };
+        let cluster = Cluster {
+            timestamp: 1000,
+            silent_tracks: None,
+            position: None,
+            prev_size: None,
+            simple_block: vec![simple_block(0), simple_block(40)],
+            block_group: vec![BlockGroup {
+                block: Block {
+                    track_number: 2,
+                    timestamp: 20,
+                    invisible: true,
+                    lacing: Lacing::Xiph,
+                    data: vec![1, 4, 5, 5, 5, 5, 5].into(),
+                },
+                block_additions: Some(BlockAdditions {
+                    block_more: vec![BlockMore {
+                        block_additional: vec![7, 7],
+                        block_add_id: 4,
+                    }],
+                }),
+                block_duration: Some(300),
+                reference_priority: 0,
+                reference_block: vec![-20, 200],
+                codec_state: Some(vec![9]),
+                discard_padding: Some(-6_500_000),
+            }],
+            block_order: vec![
+                BlockKind::SimpleBlock,
+                BlockKind::BlockGroup,
+                BlockKind::SimpleBlock,
+            ],
+        };
+
+        let mut data = vec![0; cluster.size(0x1F43B675)];
+        gen_cluster(&cluster)((&mut data[..], 0)).unwrap();
+
+        match crate::elements::segment_element(&data) {
+            Ok((_, SegmentElement::Cluster(c))) => assert_eq!(c, cluster),
+            e => panic!("parse error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn tracks() {
+        let tracks = Tracks {
+            tracks: vec![TrackEntry {
+                track_number: 1,
+                track_uid: 1,
+                track_type: 1,
+                codec_id: String::from("V_MPEG2"),
+                default_duration: Some(40_000_000),
+                default_decoded_field_duration: Some(20_000_000),
+                video: Some(Video {
+                    flag_interlaced: 1,
+                    pixel_width: 720,
+                    pixel_height: 576,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+        };
+
+        let mut data = vec![0; tracks.size(0x1654AE6B)];
+        gen_tracks(&tracks)((&mut data[..], 0)).unwrap();
+        assert!(data.windows(3).any(|w| w == [0x23, 0x4E, 0x7A]));
+
+        let (_, parsed) = crate::ebml::ebml_element::<Tracks>(0x1654AE6B)(&data).unwrap();
+        assert_eq!(parsed, tracks);
+        assert_eq!(
+            parsed.tracks[0].default_decoded_field_duration,
+            Some(20_000_000)
+        );
+    }
+
+    #[test]
+    fn audio() {
+        let audio = Audio {
+            sampling_frequency: 24000.0,
+            output_sampling_frequency: Some(48000.0),
+            channels: 2,
+            bit_depth: Some(16),
+            ..Default::default()
+        };
+
+        let mut data = vec![0; audio.size(0xE1)];
+        gen_track_entry_audio(&audio)((&mut data[..], 0)).unwrap();
+
+        let (_, parsed) = crate::ebml::ebml_element::<Audio>(0xE1)(&data).unwrap();
+        assert_eq!(parsed, audio);
+    }
+
+    #[test]
+    fn video_colour() {
+        let video = Video {
+            pixel_width: 3840,
+            pixel_height: 2160,
+            colour: Some(Colour {
+                matrix_coefficients: 9,
+                bits_per_channel: 10,
+                range: 1,
+                transfer_characteristics: 16,
+                primaries: 9,
+                max_cll: Some(1000),
+                max_fall: Some(400),
+                mastering_metadata: Some(MasteringMetadata {
+                    primary_r_chromaticity_x: Some(0.708),
+                    primary_r_chromaticity_y: Some(0.292),
+                    primary_g_chromaticity_x: Some(0.17),
+                    primary_g_chromaticity_y: Some(0.797),
+                    primary_b_chromaticity_x: Some(0.131),
+                    primary_b_chromaticity_y: Some(0.046),
+                    white_point_chromaticity_x: Some(0.3127),
+                    white_point_chromaticity_y: Some(0.329),
+                    luminance_max: Some(1000.0),
+                    luminance_min: Some(0.0001),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let mut data = vec![0; video.size(0xE0)];
+        gen_track_entry_video(&video)((&mut data[..], 0)).unwrap();
+
+        let (_, parsed) = crate::ebml::ebml_element::<Video>(0xE0)(&data).unwrap();
+        assert_eq!(parsed, video);
+
+        let colour = parsed.colour().unwrap();
+        assert!(colour.is_hdr());
+        assert_eq!(colour.max_cll, Some(1000));
+        let mastering = colour.mastering_metadata.as_ref().unwrap();
+        assert_eq!(mastering.luminance_max, Some(1000.0));
+    }
+
+    #[test]
+    fn video_projection() {
+        let mut private = vec![0; 4];
+        for bound in [0u32, 0x4000_0000, 0x1000_0000, 0] {
+            private.extend(bound.to_be_bytes());
+        }
+        let video = Video {
+            pixel_width: 3840,
+            pixel_height: 1920,
+            projection: Some(Projection {
+                projection_type: 1,
+                projection_private: Some(private),
+                projection_pose_yaw: 90.0,
+                projection_pose_pitch: 0.0,
+                projection_pose_roll: -45.0,
+            }),
+            ..Default::default()
+        };
+
+        let mut data = vec![0; video.size(0xE0)];
+        gen_track_entry_video(&video)((&mut data[..], 0)).unwrap();
+
+        let (_, parsed) = crate::ebml::ebml_element::<Video>(0xE0)(&data).unwrap();
+        assert_eq!(parsed, video);
+
+        let projection = parsed.projection().unwrap();
+        assert_eq!(projection.kind(), Some(ProjectionType::Equirectangular));
+        assert_eq!(
+            projection.equirectangular_bounds(),
+            Some(EquirectangularBounds {
+                top: 0,
+                bottom: 0x4000_0000,
+                left: 0x1000_0000,
+                right: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn void_sizes() {
+        for size in [2, 9, 128, 129, 300] {
+            let mut data = vec![0; size];
+            let (_, len) = gen_void(size)((&mut data[..], 0)).unwrap();
+            assert_eq!(len, size);
+
+            match crate::elements::segment_element(&data) {
+                Ok((i, SegmentElement::Void(_))) => assert!(i.is_empty()),
+                e => panic!("parse error for size {size}: {e:?}"),
+            }
+        }
+
+        assert!(gen_void(1)((&mut [0][..], 0)).is_err());
+    }
+
+    #[test]
+    fn encode_void() {
+        for size in [Void::min_overhead(), 100, 128, 129, 5000] {
+            let data = Void::encode(size).unwrap();
+            assert_eq!(data.len(), size);
+            assert_eq!(Void::parse(&data), Ok((&[][..], size)));
+        }
+
+        let data = Void::encode(129).unwrap();
+        assert_eq!(data.len() - 120, Void::max_overhead());
+
+        for size in 0..Void::min_overhead() {
+            assert!(matches!(Void::encode(size), Err(Error::Serialize(_))));
+        }
+    }
 }
@@ -1,3 +1,6 @@
+// The cookie-factory generator signatures are verbose by nature.
+#![allow(clippy::type_complexity)]
+
 pub mod cookie_utils;
 pub mod ebml;
 pub mod elements;
@@ -0,0 +1,311 @@
+//! Splitting of a file into hard-linked Segments, one per chapter.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::chapters::MatroskaChapterPlayer;
+use crate::elements::Uuid;
+use crate::file::{fnv1a_128, MatroskaFile};
+use crate::reader::Frame;
+use crate::writer::MatroskaEncoder;
+use crate::Error;
+
+/// Splits a file into several files, like `mkvmerge --split chapters:all`.
+///
+/// The Segments written are hard-linked by their PrevUID and NextUID, so
+/// that [MatroskaLinkedReader](crate::linked::MatroskaLinkedReader) plays
+/// them as a single stream.
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// use matroska::file::MatroskaFile;
+/// use matroska::splitter::MatroskaSplitter;
+///
+/// let file = MatroskaFile::open("movie.mkv")?;
+/// for path in MatroskaSplitter::split_at_chapters(file, Path::new("chapters"))? {
+///     println!("{}", path.display());
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaSplitter;
+
+impl MatroskaSplitter {
+    /// Writes a file for each top-level chapter of the default edition of
+    /// `src` in `dst_dir`, named `split-001.mkv`, `split-002.mkv` and so on,
+    /// and returns their paths.
+    ///
+    /// Each file starts at the keyframe of the first video track at or
+    /// before the start of its chapter, or of the first track if there is
+    /// no video, and its timestamps are shifted to start at 0. The disabled
+    /// chapters are skipped, and the chapters starting at the same keyframe
+    /// share a file. The content before the first chapter goes in the first
+    /// file. The Chapters, Tags and Attachments are not copied.
+    ///
+    /// The frames keep their durations, written as BlockDurations when
+    /// they differ from the DefaultDuration of their track, so that the
+    /// subtitles keep their end times.
+    ///
+    /// Fails with [Error::NoChapters] if the file has no chapters.
+    pub fn split_at_chapters<R: Read + Seek>(
+        mut src: MatroskaFile<R>,
+        dst_dir: &Path,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let mut starts: Vec<u64> = src
+            .chapters()
+            .and_then(MatroskaChapterPlayer::from_chapters)
+            .map(|player| player.edition().chapters.iter())
+            .into_iter()
+            .flatten()
+            .filter(|chapter| chapter.flag_enabled)
+            .map(|chapter| chapter.time_start)
+            .collect();
+        if starts.is_empty() {
+            return Err(Error::NoChapters);
+        }
+        starts.sort_unstable();
+
+        let tracks = src.tracks().map(|t| t.tracks.clone()).unwrap_or_default();
+        let key_track = tracks
+            .iter()
+            .find(|track| track.track_type == 1)
+            .or(tracks.first())
+            .map(|track| track.track_number);
+        let mut info = src.info().cloned().unwrap_or_default();
+        let uid = src.fingerprint()?.uid;
+        let timestamp_scale = match info.timestamp_scale {
+            0 => 1_000_000,
+            scale => scale,
+        };
+
+        // The keyframes of the key track give the timestamps of the splits
+        let mut keyframes = Vec::new();
+        let mut end = 0;
+        for_each_frame(&mut src, |frame| {
+            if Some(frame.track_number) == key_track && frame.keyframe {
+                keyframes.push(frame.timestamp_ns);
+            }
+            let duration_ns = frame.duration_ns.unwrap_or(0);
+            end = end.max(frame.timestamp_ns.saturating_add(duration_ns));
+            Ok(())
+        })?;
+
+        let mut splits: Vec<u64> = starts
+            .iter()
+            .filter_map(|&start| keyframes.iter().rev().find(|&&t| t <= start).copied())
+            .filter(|&split| split > keyframes.first().copied().unwrap_or(0))
+            .collect();
+        splits.dedup();
+        splits.insert(0, 0);
+
+        let uids: Vec<Uuid> = (0..splits.len())
+            .map(|i| {
+                let mut data = uid.as_bytes().to_vec();
+                data.extend((i as u64).to_be_bytes());
+                Uuid::from_u128(fnv1a_128(&data))
+            })
+            .collect();
+        let paths: Vec<PathBuf> = (1..=splits.len())
+            .map(|i| dst_dir.join(format!("split-{i:03}.mkv")))
+            .collect();
+        let filename = |i: usize| {
+            paths
+                .get(i)
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+        };
+
+        let mut outputs = Vec::new();
+        for (i, &start) in splits.iter().enumerate() {
+            let part_end = splits.get(i + 1).copied().unwrap_or(end).max(start);
+            info.segment_uid = Some(uids[i]);
+            info.segment_filename = filename(i);
+            info.prev_uid = i.checked_sub(1).map(|prev| uids[prev]);
+            info.prev_filename = i.checked_sub(1).and_then(filename);
+            info.next_uid = uids.get(i + 1).copied();
+            info.next_filename = filename(i + 1);
+            info.duration = Some(part_end.saturating_sub(start) as f64 / timestamp_scale as f64);
+
+            let out = BufWriter::new(File::create(&paths[i])?);
+            outputs.push(MatroskaEncoder::new(out, info.clone(), tracks.clone())?);
+        }
+
+        // The frames stored after the keyframe starting a file go in it
+        let mut current = 0;
+        for_each_frame(&mut src, |frame| {
+            if Some(frame.track_number) == key_track
+                && frame.keyframe
+                && splits.get(current + 1) == Some(&frame.timestamp_ns)
+            {
+                current += 1;
+            }
+            let timestamp = frame.timestamp_ns.saturating_sub(splits[current]);
+
+            // The durations other than the DefaultDuration of the track are
+            // kept as BlockDurations
+            let default_duration = tracks
+                .iter()
+                .find(|track| track.track_number == frame.track_number)
+                .and_then(|track| track.default_duration);
+            match frame.duration_ns.filter(|&d| Some(d) != default_duration) {
+                Some(duration_ns) => outputs[current].push_frame_with_duration(
+                    frame.track_number,
+                    timestamp,
+                    duration_ns,
+                    frame.keyframe,
+                    &frame.data,
+                ),
+                None => outputs[current].push_frame(
+                    frame.track_number,
+                    timestamp,
+                    frame.keyframe,
+                    &frame.data,
+                ),
+            }
+        })?;
+
+        for output in outputs {
+            output.finish()?;
+        }
+
+        Ok(paths)
+    }
+}
+
+// Calls `f` with the frames of all the tracks, in storage order, from the
+// start of the Segment
+fn for_each_frame<R: Read + Seek>(
+    src: &mut MatroskaFile<R>,
+    mut f: impl FnMut(Frame) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let reader = src.reader_mut();
+    reader.seek_to_byte_offset(0)?;
+
+    let mut pending = VecDeque::new();
+    while reader.read_frames(None, &mut pending)? {
+        pending.drain(..).try_for_each(&mut f)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::Info;
+    use crate::linked::MatroskaLinkedReader;
+    use crate::serializer::ebml::encode_element;
+    use crate::test_utils;
+
+    #[test]
+    fn split_at_chapters() {
+        let track = test_utils::track;
+        let info = Info {
+            segment_uid: Some(Uuid::from_u128(1)),
+            ..test_utils::info()
+        };
+        let mut encoder = MatroskaEncoder::new(
+            Cursor::new(Vec::new()),
+            info,
+            vec![
+                track(1, 1, "V_VP9"),
+                track(2, 2, "A_OPUS"),
+                track(3, 17, "S_TEXT/UTF8"),
+            ],
+        )
+        .unwrap();
+
+        // A video keyframe every second, and a subtitle every second
+        for i in 0..30u64 {
+            encoder
+                .push_frame(1, i * 100_000_000, i % 10 == 0, &[1])
+                .unwrap();
+            encoder.push_frame(2, i * 100_000_000, true, &[2]).unwrap();
+            if i % 10 == 5 {
+                encoder
+                    .push_frame_with_duration(3, i * 100_000_000, 400_000_000, true, b"text")
+                    .unwrap();
+            }
+        }
+        let mut data = encoder.finish().unwrap().into_inner();
+
+        // Chapters at 0, 1.5 and 1.9 seconds, the last one starting at the
+        // same keyframe as the second one
+        let chapter = |uid: u8, start: u64| {
+            let mut atom = encode_element(0x73C4, &[uid]).unwrap();
+            atom.extend(encode_element(0x91, &start.to_be_bytes()).unwrap());
+            encode_element(0xB6, &atom).unwrap()
+        };
+        let mut edition = Vec::new();
+        for (uid, start) in [(1, 0), (2, 1_500_000_000), (3, 1_900_000_000)] {
+            edition.extend(chapter(uid, start));
+        }
+        let edition = encode_element(0x45B9, &edition).unwrap();
+        data.extend(encode_element(0x1043A770, &edition).unwrap());
+
+        let directory =
+            std::env::temp_dir().join(format!("matroska-splitter-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let file = MatroskaFile::new(Cursor::new(data)).unwrap();
+        let paths = MatroskaSplitter::split_at_chapters(file, &directory).unwrap();
+        assert_eq!(paths.len(), 2);
+
+        let parts: Vec<_> = paths
+            .iter()
+            .map(|path| MatroskaFile::open(path).unwrap())
+            .collect();
+        let info = |i: usize| parts[i].info().unwrap();
+        assert_eq!(info(0).next_uid, info(1).segment_uid);
+        assert_eq!(info(1).prev_uid, info(0).segment_uid);
+        assert_eq!(info(0).prev_uid, None);
+        assert_eq!(info(1).next_uid, None);
+        assert_eq!(info(1).prev_filename.as_deref(), Some("split-001.mkv"));
+        assert_eq!(info(0).duration, Some(1000.0));
+        assert_eq!(info(1).duration, Some(1900.0));
+
+        let mut second =
+            crate::reader::MatroskaReader::new(File::open(&paths[1]).unwrap()).unwrap();
+        let frames: Vec<_> = second.frames(1).map(|frame| frame.unwrap()).collect();
+        assert_eq!(frames.len(), 20);
+        assert!(frames[0].keyframe);
+        assert_eq!(frames[0].timestamp_ns, 0);
+
+        let mut second =
+            crate::reader::MatroskaReader::new(File::open(&paths[1]).unwrap()).unwrap();
+        let subtitles: Vec<_> = second
+            .frames(3)
+            .map(|frame| frame.unwrap())
+            .map(|frame| (frame.timestamp_ns, frame.duration_ns))
+            .collect();
+        assert_eq!(
+            subtitles,
+            [
+                (500_000_000, Some(400_000_000)),
+                (1_500_000_000, Some(400_000_000))
+            ]
+        );
+
+        let video: Vec<_> = MatroskaLinkedReader::open(&paths[0], &directory, 1)
+            .unwrap()
+            .map(|frame| frame.unwrap().timestamp_ns)
+            .collect();
+        assert_eq!(video, (0..30).map(|i| i * 100_000_000).collect::<Vec<_>>());
+
+        drop(parts);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let file = MatroskaFile::new(Cursor::new(
+            std::fs::read("assets/bbb-vp9-opus.webm").unwrap(),
+        ))
+        .unwrap();
+        assert!(matches!(
+            MatroskaSplitter::split_at_chapters(file, &std::env::temp_dir()),
+            Err(Error::NoChapters)
+        ));
+    }
+}
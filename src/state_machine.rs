@@ -0,0 +1,402 @@
+//! Incremental parsing of a Matroska stream from byte slices fed by the
+//! caller, which also builds without the `std` feature.
+//!
+//! Unlike [MatroskaReader](crate::reader::MatroskaReader), the
+//! [StateMachine] neither reads from a source nor buffers data: it parses
+//! what it is given and reports how many bytes it consumed, the caller
+//! feeds the rest again along with the following data. The module only
+//! uses `core` and `alloc`.
+
+use core::cmp::min;
+
+use nom::sequence::pair;
+
+use crate::ebml::{
+    check_id, ebml_element, ebml_header, element_size, is_unknown_size, vid, vint, EbmlHeader,
+    EbmlResult, Error,
+};
+use crate::elements::{segment, segment_element, BlockGroup, SegmentElement, SimpleBlock};
+
+/// An element parsed by [StateMachine::feed].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    Header(EbmlHeader),
+    /// The start of the Segment, with the size of its data if known.
+    SegmentStart {
+        size: Option<u64>,
+    },
+    /// A Top-Level Element other than a Cluster.
+    Element(SegmentElement<'a>),
+    /// The start of a Cluster, with the size of its data if known. Its
+    /// children follow, up to [Event::ClusterEnd].
+    ClusterStart {
+        size: Option<u64>,
+    },
+    ClusterTimestamp(u64),
+    SimpleBlock(SimpleBlock<'a>),
+    BlockGroup(BlockGroup<'a>),
+    ClusterEnd,
+    /// The end of a Segment of known size, no more data is parsed after it.
+    SegmentEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    SegmentHeader,
+    Segment,
+    Cluster,
+    Done,
+}
+
+/// Parser of a Matroska stream fed with byte slices.
+///
+/// An element is only parsed once the slice holds all of it, except for
+/// the Segment and the Clusters, whose children are reported one by one,
+/// and the Void and unknown elements, which are skipped across calls.
+///
+/// ```
+/// use matroska::state_machine::{Event, StateMachine};
+///
+/// let data = std::fs::read("assets/bbb-vp9-opus.webm")?;
+/// let mut machine = StateMachine::new();
+/// let mut pending = Vec::new();
+/// let mut blocks = 0;
+///
+/// for chunk in data.chunks(4096) {
+///     pending.extend_from_slice(chunk);
+///     loop {
+///         // The events borrow `pending`, which is drained afterwards
+///         let mut events = [const { None }; 64];
+///         let (consumed, n) = machine
+///             .feed(&pending, &mut events)
+///             .map_err(matroska::Error::from)?;
+///         blocks += events[..n]
+///             .iter()
+///             .filter(|e| matches!(e, Some(Event::SimpleBlock(_))))
+///             .count();
+///         pending.drain(..consumed);
+///         if consumed == 0 {
+///             break;
+///         }
+///     }
+/// }
+/// assert_eq!(blocks, 75);
+/// # Ok::<(), matroska::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct StateMachine {
+    state: State,
+    // Bytes left in the Segment and the Cluster data, `None` when unknown
+    segment_remaining: Option<u64>,
+    cluster_remaining: Option<u64>,
+    // Bytes left of an element being skipped
+    skip: u64,
+}
+
+impl Default for StateMachine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachine {
+    /// Creates a state machine expecting the EBML Header first.
+    pub fn new() -> Self {
+        StateMachine {
+            state: State::Header,
+            segment_remaining: None,
+            cluster_remaining: None,
+            skip: 0,
+        }
+    }
+
+    /// Parses as many elements of `data` as fit in `events` and returns
+    /// the number of bytes consumed with the parsed elements and the
+    /// number of events written at the start of `events`.
+    ///
+    /// The bytes that were not consumed start an incomplete element, or
+    /// one whose events did not fit, and have to be passed again at the
+    /// start of the next call. Nothing is parsed if `events` holds fewer
+    /// than [MIN_EVENTS] slots.
+    pub fn feed<'a>(
+        &mut self,
+        data: &'a [u8],
+        events: &mut [Option<Event<'a>>],
+    ) -> Result<(usize, usize), Error> {
+        let mut events = Events {
+            slots: events,
+            len: 0,
+        };
+        let mut input = data;
+
+        while self.state != State::Done && events.free() >= MIN_EVENTS {
+            if self.skip > 0 {
+                let n = min(self.skip, input.len() as u64);
+                self.skip -= n;
+                input = &input[n as usize..];
+                self.advance(n, &mut events);
+                if self.skip > 0 {
+                    break;
+                }
+                continue;
+            }
+
+            match self.step(input) {
+                Ok((rest, event)) => {
+                    let n = (input.len() - rest.len()) as u64;
+                    input = rest;
+                    if let Some(event) = event {
+                        events.push(event);
+                    }
+                    self.advance(n, &mut events);
+                }
+                Err(nom::Err::Incomplete(_)) => break,
+                Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => return Err(e),
+            }
+        }
+
+        Ok((data.len() - input.len(), events.len))
+    }
+
+    // Parses the next element for the current state.
+    fn step<'a>(&mut self, input: &'a [u8]) -> EbmlResult<'a, Option<Event<'a>>> {
+        match self.state {
+            State::Header => {
+                check_id(0x1A45DFA3)(input)?;
+                let data = complete_element(input)?;
+                let (_, header) = ebml_header(data)?;
+                self.state = State::SegmentHeader;
+                Ok((&input[data.len()..], Some(Event::Header(header))))
+            }
+            State::SegmentHeader => {
+                let (i, (_, size)) = segment(input)?;
+                let size = size.filter(|&s| !is_unknown_size(s, (input.len() - i.len()) as u64));
+                self.segment_remaining = size;
+                self.state = State::Segment;
+                Ok((i, Some(Event::SegmentStart { size })))
+            }
+            State::Segment => {
+                let (i, (id, size)) = pair(vid, vint)(input)?;
+                match id {
+                    0x1F43B675 => {
+                        let header_len = (input.len() - i.len()) as u64;
+                        let size = Some(size).filter(|&s| !is_unknown_size(s, header_len));
+                        self.cluster_remaining = size;
+                        self.state = State::Cluster;
+                        Ok((i, Some(Event::ClusterStart { size })))
+                    }
+                    0x114D9B74 | 0x1549A966 | 0x1043A770 | 0x1254C367 | 0x1941A469 | 0x1654AE6B
+                    | 0x1C53BB6B => {
+                        let data = complete_element(input)?;
+                        let (_, element) = segment_element(data)?;
+                        Ok((&input[data.len()..], Some(Event::Element(element))))
+                    }
+                    // Void and unknown elements are not kept in memory
+                    0xEC => {
+                        self.skip = size;
+                        Ok((i, Some(Event::Element(SegmentElement::Void(size as usize)))))
+                    }
+                    id => {
+                        self.skip = size;
+                        Ok((
+                            i,
+                            Some(Event::Element(SegmentElement::Unknown(
+                                id,
+                                Some(size as usize),
+                            ))),
+                        ))
+                    }
+                }
+            }
+            State::Cluster => {
+                let (i, (id, size)) = pair(vid, vint)(input)?;
+                match id {
+                    0xE7 => {
+                        let data = complete_element(input)?;
+                        let (_, timestamp) = ebml_element::<u64>(0xE7)(data)?;
+                        Ok((
+                            &input[data.len()..],
+                            Some(Event::ClusterTimestamp(timestamp)),
+                        ))
+                    }
+                    0xA3 => {
+                        let data = complete_element(input)?;
+                        let (_, block) = ebml_element::<SimpleBlock>(0xA3)(data)?;
+                        Ok((&input[data.len()..], Some(Event::SimpleBlock(block))))
+                    }
+                    0xA0 => {
+                        let data = complete_element(input)?;
+                        let (_, group) = ebml_element::<BlockGroup>(0xA0)(data)?;
+                        Ok((&input[data.len()..], Some(Event::BlockGroup(group))))
+                    }
+                    // Position, PrevSize, CRC-32 and Void
+                    0xA7 | 0xAB | 0xBF | 0xEC => {
+                        self.skip = size;
+                        Ok((i, None))
+                    }
+                    // A Top-Level Element ends a Cluster of unknown size
+                    _ if self.cluster_remaining.is_none() => {
+                        self.state = State::Segment;
+                        Ok((input, Some(Event::ClusterEnd)))
+                    }
+                    _ => {
+                        self.skip = size;
+                        Ok((i, None))
+                    }
+                }
+            }
+            State::Done => Ok((input, None)),
+        }
+    }
+
+    // Accounts for `n` bytes consumed, ending the Cluster and the Segment
+    // when all of their data was consumed.
+    fn advance(&mut self, n: u64, events: &mut Events<'_, '_>) {
+        if self.state == State::Cluster {
+            if let Some(remaining) = &mut self.cluster_remaining {
+                *remaining = remaining.saturating_sub(n);
+                if *remaining == 0 && self.skip == 0 {
+                    events.push(Event::ClusterEnd);
+                    self.state = State::Segment;
+                }
+            }
+        }
+
+        if matches!(self.state, State::Segment | State::Cluster) {
+            if let Some(remaining) = &mut self.segment_remaining {
+                *remaining = remaining.saturating_sub(n);
+                if *remaining == 0 && self.skip == 0 {
+                    if self.state == State::Cluster {
+                        events.push(Event::ClusterEnd);
+                    }
+                    events.push(Event::SegmentEnd);
+                    self.state = State::Done;
+                }
+            }
+        }
+    }
+}
+
+/// The number of event slots [StateMachine::feed] needs to parse an
+/// element: the element itself, and the ends of its Cluster and Segment.
+pub const MIN_EVENTS: usize = 3;
+
+// The events written by a call to `feed`.
+struct Events<'e, 'a> {
+    slots: &'e mut [Option<Event<'a>>],
+    len: usize,
+}
+
+impl<'a> Events<'_, 'a> {
+    fn free(&self) -> usize {
+        self.slots.len() - self.len
+    }
+
+    fn push(&mut self, event: Event<'a>) {
+        self.slots[self.len] = Some(event);
+        self.len += 1;
+    }
+}
+
+// The whole element at the start of the input.
+fn complete_element(input: &[u8]) -> Result<&[u8], nom::Err<Error>> {
+    let (_, size) = element_size(input)?;
+    if input.len() < size {
+        return Err(nom::Err::Incomplete(nom::Needed::new(size - input.len())));
+    }
+
+    Ok(&input[..size])
+}
+
+#[cfg(test)]
+#[allow(non_upper_case_globals)]
+mod tests {
+    use super::*;
+    use crate::reader::MatroskaReader;
+
+    const webm: &[u8] = include_bytes!("../assets/bbb-vp9-opus.webm");
+
+    // Feeds the data in chunks of `chunk_size` bytes with room for
+    // `room` events per call, keeping the events as owned values.
+    fn parse(data: &[u8], chunk_size: usize, room: usize) -> Vec<String> {
+        let mut machine = StateMachine::new();
+        let mut pending = Vec::new();
+        let mut events = Vec::new();
+
+        for chunk in data.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            loop {
+                let mut slots = vec![None; room];
+                let (consumed, n) = machine.feed(&pending, &mut slots).unwrap();
+                assert!(n <= slots.len());
+                events.extend(slots[..n].iter().flatten().map(|e| format!("{e:?}")));
+                pending.drain(..consumed);
+                if consumed == 0 {
+                    break;
+                }
+            }
+        }
+        assert!(pending.is_empty());
+
+        events
+    }
+
+    #[test]
+    fn feed() {
+        let whole = parse(webm, webm.len(), 1024);
+        assert!(whole[0].starts_with("Header("));
+        assert_eq!(whole[1], "SegmentStart { size: Some(23293) }");
+        assert_eq!(whole.last().unwrap(), "SegmentEnd");
+
+        // Same events whatever the split of the data
+        for chunk_size in [1, 7, 100, 4096] {
+            assert_eq!(parse(webm, chunk_size, 1024), whole, "{chunk_size}");
+        }
+
+        // And whatever the room for the events
+        for room in [MIN_EVENTS, 4, 10] {
+            assert_eq!(parse(webm, webm.len(), room), whole, "{room}");
+        }
+
+        // Same blocks as the reader
+        let mut reader = MatroskaReader::new(std::io::Cursor::new(webm)).unwrap();
+        let mut expected = Vec::new();
+        while let Some(element) = reader.next_element().unwrap() {
+            if let SegmentElement::Cluster(cluster) = element {
+                expected.push(String::from("ClusterStart"));
+                expected.push(format!("ClusterTimestamp({})", cluster.timestamp));
+                expected.extend(
+                    cluster
+                        .simple_block
+                        .iter()
+                        .map(|b| format!("SimpleBlock({b:?})")),
+                );
+                expected.push(String::from("ClusterEnd"));
+            }
+        }
+        let clusters: Vec<_> = whole
+            .iter()
+            .filter(|e| e.starts_with("Cluster") || e.starts_with("SimpleBlock"))
+            .map(|e| match e.starts_with("ClusterStart") {
+                true => String::from("ClusterStart"),
+                false => e.clone(),
+            })
+            .collect();
+        assert_eq!(clusters, expected);
+    }
+
+    #[test]
+    fn invalid_data() {
+        let mut machine = StateMachine::new();
+        let mut events = [const { None }; MIN_EVENTS];
+        assert_eq!(machine.feed(&[], &mut events).unwrap(), (0, 0));
+        assert_eq!(machine.feed(&webm[..10], &mut events).unwrap(), (0, 0));
+        assert!(StateMachine::new()
+            .feed(b"not a matroska file", &mut events)
+            .is_err());
+
+        // Too little room for any event
+        assert_eq!(machine.feed(webm, &mut events[..2]).unwrap(), (0, 0));
+    }
+}
@@ -0,0 +1,184 @@
+//! Statistics of the frames of the tracks, for the analysis of their
+//! bitrate patterns.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::elements::SegmentElement;
+use crate::reader::MatroskaReader;
+use crate::Error;
+
+/// Number of buckets of a [FrameSizeHistogram].
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+/// The distribution of the frame sizes of a track, in bytes.
+///
+/// The buckets split the range from `min` to `max` into
+/// [HISTOGRAM_BUCKETS] ranges of the same width, so a CBR track has all its
+/// frames in a few buckets while the keyframes of a VBR track stand out in
+/// the last ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSizeHistogram {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    /// Number of frames in each bucket, from the smallest sizes to the
+    /// largest.
+    pub buckets: [u64; HISTOGRAM_BUCKETS],
+}
+
+impl FrameSizeHistogram {
+    fn new(sizes: &[u64]) -> Self {
+        let min = sizes.iter().copied().min().unwrap_or(0);
+        let max = sizes.iter().copied().max().unwrap_or(0);
+        let total: u128 = sizes.iter().map(|&size| u128::from(size)).sum();
+
+        let mut histogram = FrameSizeHistogram {
+            count: sizes.len() as u64,
+            min,
+            max,
+            mean: if sizes.is_empty() {
+                0.0
+            } else {
+                total as f64 / sizes.len() as f64
+            },
+            buckets: [0; HISTOGRAM_BUCKETS],
+        };
+        for &size in sizes {
+            histogram.buckets[histogram.bucket(size)] += 1;
+        }
+
+        histogram
+    }
+
+    /// The smallest size of the bucket at `index`.
+    pub fn bucket_start(&self, index: usize) -> u64 {
+        let range = u128::from(self.max - self.min) + 1;
+        self.min + (range * index as u128).div_ceil(HISTOGRAM_BUCKETS as u128) as u64
+    }
+
+    fn bucket(&self, size: u64) -> usize {
+        let range = u128::from(self.max - self.min) + 1;
+        (u128::from(size - self.min) * HISTOGRAM_BUCKETS as u128 / range) as usize
+    }
+}
+
+/// Collects the sizes of the frames of every track.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::reader::MatroskaReader;
+/// use matroska::stats::MatroskaFrameStats;
+///
+/// let mut reader = MatroskaReader::new(File::open("video.mkv")?)?;
+/// for (track, histogram) in MatroskaFrameStats::collect(&mut reader)? {
+///     println!("track {track}: {} bytes on average", histogram.mean);
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaFrameStats;
+
+impl MatroskaFrameStats {
+    /// Reads the Clusters from the current position of `reader` to the end
+    /// of the Segment and returns the histogram of the frame sizes of each
+    /// track, by track number.
+    ///
+    /// The frames of a laced block are counted separately. Only their sizes
+    /// are kept, the frame data is never copied.
+    pub fn collect<R: Read>(
+        reader: &mut MatroskaReader<R>,
+    ) -> Result<HashMap<u64, FrameSizeHistogram>, Error> {
+        let mut sizes: HashMap<u64, Vec<u64>> = HashMap::new();
+
+        while let Some(element) = reader.next_element()? {
+            let SegmentElement::Cluster(cluster) = element else {
+                continue;
+            };
+
+            let blocks = cluster
+                .simple_block
+                .iter()
+                .map(|b| (b.track_number, b.frames()));
+            let groups = cluster
+                .block_group
+                .iter()
+                .map(|g| (g.block.track_number, g.block.frames()));
+            for (track_number, frames) in blocks.chain(groups) {
+                sizes
+                    .entry(track_number)
+                    .or_default()
+                    .extend(frames?.iter().map(|frame| frame.len() as u64));
+            }
+        }
+
+        Ok(sizes
+            .into_iter()
+            .map(|(track_number, sizes)| (track_number, FrameSizeHistogram::new(&sizes)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::test_utils;
+    use crate::writer::RawBlock;
+
+    #[test]
+    fn frame_sizes() {
+        let track = |track_number| test_utils::track(track_number, 1, "V_VP9");
+
+        // A keyframe spike on the track 1 and constant sizes on the track 2
+        let data = [0; 1000];
+        let block = |track_number, timestamp, size| RawBlock {
+            track_number,
+            timestamp,
+            keyframe: size == 1000,
+            data: &data[..size],
+        };
+        let data = test_utils::segment(
+            vec![track(1), track(2)],
+            &[
+                (0, &[block(1, 0, 1000), block(2, 0, 50), block(1, 40, 100)]),
+                (80, &[block(1, 0, 190), block(2, 0, 50)]),
+            ],
+        );
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let stats = MatroskaFrameStats::collect(&mut reader).unwrap();
+        assert_eq!(stats.len(), 2);
+
+        let video = &stats[&1];
+        assert_eq!((video.count, video.min, video.max), (3, 100, 1000));
+        assert!((video.mean - 430.0).abs() < 1e-9);
+        assert_eq!(video.buckets, [2, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(video.bucket_start(0), 100);
+        assert_eq!(video.bucket_start(9), 911);
+
+        let constant = &stats[&2];
+        assert_eq!((constant.count, constant.min, constant.max), (2, 50, 50));
+        assert_eq!(constant.buckets[0], 2);
+    }
+
+    #[test]
+    fn frame_count() {
+        let path = "assets/bbb-vp9-opus.webm";
+        let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+        let stats = MatroskaFrameStats::collect(&mut reader).unwrap();
+
+        for (&track_number, histogram) in &stats {
+            let mut reader = MatroskaReader::new(std::fs::File::open(path).unwrap()).unwrap();
+            let frames: Vec<_> = reader
+                .frames(track_number)
+                .map(|frame| frame.unwrap().data.len() as u64)
+                .collect();
+            assert_eq!(histogram.count, frames.len() as u64);
+            assert_eq!(histogram.max, frames.iter().copied().max().unwrap());
+            assert_eq!(histogram.buckets.iter().sum::<u64>(), histogram.count);
+        }
+    }
+}
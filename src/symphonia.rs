@@ -0,0 +1,152 @@
+//! Conversion of the tracks to the [Track] of Symphonia, available with the
+//! `symphonia` feature.
+
+use symphonia_core::audio::Channels;
+use symphonia_core::codecs::{self, CodecParameters, CodecType};
+use symphonia_core::formats::Track;
+
+use crate::elements::TrackEntry;
+
+/// The [Track] of `entry`, with the codec of its CodecID, its sampling
+/// frequency, channels and bit depth, and its CodecPrivate as extra data.
+///
+/// The codec is [CODEC_TYPE_NULL](codecs::CODEC_TYPE_NULL) for the codecs
+/// Symphonia does not decode, such as the video codecs. The channels are
+/// the first ones of the WAVE order, as Matroska does not store their
+/// positions.
+impl From<&TrackEntry> for Track {
+    fn from(entry: &TrackEntry) -> Self {
+        let mut params = CodecParameters::new();
+        params.for_codec(codec_type(entry));
+
+        if let Some(audio) = &entry.audio {
+            let sample_rate = audio.output_sample_rate().as_hz();
+            params.with_sample_rate(sample_rate);
+            if let Some(channels) = channels(audio.channels) {
+                params.with_channels(channels);
+            }
+            if let Some(bits) = audio.bit_depth.and_then(|bits| u32::try_from(bits).ok()) {
+                params.with_bits_per_sample(bits);
+            }
+            // CodecDelay is in nanoseconds
+            if entry.codec_delay > 0 {
+                let delay = u128::from(entry.codec_delay) * u128::from(sample_rate) / 1_000_000_000;
+                params.with_delay(u32::try_from(delay).unwrap_or(u32::MAX));
+            }
+        }
+        if let Some(data) = &entry.codec_private {
+            params.with_extra_data(data.clone().into_boxed_slice());
+        }
+
+        let mut track = Track::new(entry.track_number as u32, params);
+        track.language = Some(entry.effective_language().to_owned());
+        track
+    }
+}
+
+impl From<TrackEntry> for Track {
+    fn from(entry: TrackEntry) -> Self {
+        Track::from(&entry)
+    }
+}
+
+// The codec of the CodecID, with the bit depth for PCM.
+fn codec_type(entry: &TrackEntry) -> CodecType {
+    let bits = entry.audio.as_ref().and_then(|audio| audio.bit_depth);
+
+    match (entry.codec_id.as_str(), bits) {
+        ("A_OPUS", _) => codecs::CODEC_TYPE_OPUS,
+        ("A_VORBIS", _) => codecs::CODEC_TYPE_VORBIS,
+        ("A_FLAC", _) => codecs::CODEC_TYPE_FLAC,
+        ("A_ALAC", _) => codecs::CODEC_TYPE_ALAC,
+        ("A_EAC3", _) => codecs::CODEC_TYPE_EAC3,
+        ("A_DTS", _) => codecs::CODEC_TYPE_DCA,
+        ("A_MPEG/L1", _) => codecs::CODEC_TYPE_MP1,
+        ("A_MPEG/L2", _) => codecs::CODEC_TYPE_MP2,
+        ("A_MPEG/L3", _) => codecs::CODEC_TYPE_MP3,
+        (id, _) if id == "A_AAC" || id.starts_with("A_AAC/") => codecs::CODEC_TYPE_AAC,
+        ("A_PCM/INT/LIT", Some(8)) => codecs::CODEC_TYPE_PCM_U8,
+        ("A_PCM/INT/LIT", Some(16)) => codecs::CODEC_TYPE_PCM_S16LE,
+        ("A_PCM/INT/LIT", Some(24)) => codecs::CODEC_TYPE_PCM_S24LE,
+        ("A_PCM/INT/LIT", Some(32)) => codecs::CODEC_TYPE_PCM_S32LE,
+        ("A_PCM/INT/BIG", Some(8)) => codecs::CODEC_TYPE_PCM_U8,
+        ("A_PCM/INT/BIG", Some(16)) => codecs::CODEC_TYPE_PCM_S16BE,
+        ("A_PCM/INT/BIG", Some(24)) => codecs::CODEC_TYPE_PCM_S24BE,
+        ("A_PCM/INT/BIG", Some(32)) => codecs::CODEC_TYPE_PCM_S32BE,
+        ("A_PCM/FLOAT/IEEE", Some(32)) => codecs::CODEC_TYPE_PCM_F32LE,
+        ("A_PCM/FLOAT/IEEE", Some(64)) => codecs::CODEC_TYPE_PCM_F64LE,
+        _ => codecs::CODEC_TYPE_NULL,
+    }
+}
+
+// The first `count` channels of the WAVE order.
+fn channels(count: u64) -> Option<Channels> {
+    match count {
+        1..=32 => Channels::from_bits(((1u64 << count) - 1) as u32),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::reader::MatroskaReader;
+
+    #[test]
+    fn bbb_tracks() {
+        let webm = include_bytes!("../assets/bbb-vp9-opus.webm");
+        let mut reader = MatroskaReader::new(Cursor::new(&webm[..])).unwrap();
+        let entries = reader.track_list().unwrap().to_vec();
+
+        let video = Track::from(&entries[0]);
+        assert_eq!(video.id, 1);
+        assert_eq!(video.codec_params.codec, codecs::CODEC_TYPE_NULL);
+        assert_eq!(video.codec_params.sample_rate, None);
+
+        let opus = &entries[1];
+        let audio = Track::from(opus.clone());
+        let params = &audio.codec_params;
+        assert_eq!(audio.id, 2);
+        assert_eq!(params.codec, codecs::CODEC_TYPE_OPUS);
+        assert_eq!(params.sample_rate, Some(48000));
+        assert_eq!(params.channels, Some(Channels::FRONT_LEFT));
+        assert_eq!(params.bits_per_sample, Some(32));
+        assert_eq!(params.extra_data.as_deref(), opus.codec_private.as_deref());
+        assert!(params.extra_data.as_ref().unwrap().starts_with(b"OpusHead"));
+        assert_eq!(params.delay, Some(312));
+        assert_eq!(audio.language.as_deref(), Some(opus.effective_language()));
+    }
+
+    #[test]
+    fn pcm() {
+        let mut entry = TrackEntry {
+            track_number: 3,
+            codec_id: String::from("A_PCM/INT/LIT"),
+            audio: Some(crate::elements::Audio {
+                sampling_frequency: 44100.0,
+                channels: 6,
+                bit_depth: Some(24),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let track = Track::from(&entry);
+        assert_eq!(track.codec_params.codec, codecs::CODEC_TYPE_PCM_S24LE);
+        assert_eq!(track.codec_params.bits_per_sample, Some(24));
+        assert_eq!(track.codec_params.channels.unwrap().count(), 6);
+        assert!(track
+            .codec_params
+            .channels
+            .unwrap()
+            .contains(Channels::LFE1));
+        assert_eq!(track.codec_params.extra_data, None);
+
+        entry.audio.as_mut().unwrap().bit_depth = Some(12);
+        assert_eq!(
+            Track::from(&entry).codec_params.codec,
+            codecs::CODEC_TYPE_NULL
+        );
+    }
+}
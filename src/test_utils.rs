@@ -0,0 +1,45 @@
+//! Fixtures shared by the tests of the modules.
+
+use std::io::Cursor;
+
+use crate::elements::{Info, TrackEntry};
+use crate::writer::{MatroskaWriter, RawBlock};
+
+/// An Info with a TimestampScale of 1 ms.
+pub(crate) fn info() -> Info {
+    Info {
+        timestamp_scale: 1_000_000,
+        ..Default::default()
+    }
+}
+
+/// A track whose UID is its number.
+pub(crate) fn track(track_number: u64, track_type: u64, codec_id: &str) -> TrackEntry {
+    TrackEntry {
+        track_number,
+        track_uid: track_number,
+        track_type,
+        codec_id: String::from(codec_id),
+        ..Default::default()
+    }
+}
+
+/// A block of `track_number` holding 4 bytes.
+pub(crate) fn block(track_number: u64, timestamp: i16, keyframe: bool) -> RawBlock<'static> {
+    RawBlock {
+        track_number,
+        timestamp,
+        keyframe,
+        data: &[0; 4],
+    }
+}
+
+/// A file with the [info] and `tracks`, holding a Cluster for each
+/// timestamp and blocks of `clusters`.
+pub(crate) fn segment(tracks: Vec<TrackEntry>, clusters: &[(u64, &[RawBlock<'_>])]) -> Vec<u8> {
+    let mut writer = MatroskaWriter::new(Cursor::new(Vec::new()), info(), tracks).unwrap();
+    for &(timestamp, blocks) in clusters {
+        writer.write_cluster(timestamp, blocks).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
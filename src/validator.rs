@@ -0,0 +1,594 @@
+//! Checks of the constraints of the specification that parsing alone does
+//! not enforce, and of the interleaving of the tracks, aimed at verifying
+//! the output of muxers.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+use crate::elements::{Cluster, SeekHead, SegmentElement, Tracks};
+use crate::reader::MatroskaReader;
+use crate::Error;
+
+/// A violation of the specification found by [MatroskaValidator].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The path of the offending element, e.g.
+    /// `\Segment\Tracks\TrackEntry\TrackUID`.
+    pub path: &'static str,
+    pub description: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.description)
+    }
+}
+
+/// Reads a whole Segment and checks that:
+///
+/// - the Info and Tracks elements are present, and the Top-Level Elements
+///   that cannot be repeated occur only once;
+/// - the track numbers and UIDs are not zero and unique;
+/// - every SeekHead entry points to an element with the expected ID;
+/// - the Cluster timestamps are increasing and the blocks belong to a
+///   track;
+/// - the Cluster Positions match the offsets of the Clusters.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::validator::MatroskaValidator;
+///
+/// for error in MatroskaValidator::validate(File::open("video.mkv")?)? {
+///     println!("{error}");
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MatroskaValidator {
+    errors: Vec<ValidationError>,
+    // ID of the Top-Level Elements by position in the Segment
+    elements: HashMap<u64, u32>,
+    seek_heads: Vec<SeekHead>,
+    tracks: Vec<u64>,
+    last_cluster_timestamp: Option<u64>,
+}
+
+impl MatroskaValidator {
+    /// Reads the Segment of `source` and returns the violations found.
+    ///
+    /// Errors are only returned when the file cannot be read or parsed.
+    pub fn validate<R: Read>(source: R) -> Result<Vec<ValidationError>, Error> {
+        let mut reader = MatroskaReader::new(source)?;
+        let mut validator = MatroskaValidator::default();
+
+        loop {
+            let position = reader.next_element_position();
+            match reader.next_element()? {
+                Some(element) => validator.check_element(position, &element),
+                None => break,
+            }
+        }
+
+        Ok(validator.finish())
+    }
+
+    /// Reads the Segment of `source` and only checks that the Position of
+    /// each Cluster, when present, is the offset of the Cluster from the
+    /// start of the Segment data.
+    ///
+    /// A mismatch points to a broken muxer, or to a file that was cut or
+    /// edited without rewriting the Clusters. A Position of 0, written by
+    /// live streams, is accepted anywhere.
+    pub fn verify_cluster_positions<R: Read>(source: R) -> Result<Vec<ValidationError>, Error> {
+        let mut reader = MatroskaReader::new(source)?;
+        let mut validator = MatroskaValidator::default();
+
+        loop {
+            let position = reader.next_element_position();
+            match reader.next_element()? {
+                Some(SegmentElement::Cluster(cluster)) => {
+                    validator.check_cluster_position(position, &cluster)
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        Ok(validator.errors)
+    }
+
+    fn error(&mut self, path: &'static str, description: impl Into<String>) {
+        self.errors.push(ValidationError {
+            path,
+            description: description.into(),
+        });
+    }
+
+    fn check_element(&mut self, position: u64, element: &SegmentElement<'_>) {
+        let id = element.id();
+        let unique = match element {
+            SegmentElement::Info(_) => Some(r"\Segment\Info"),
+            SegmentElement::Tracks(_) => Some(r"\Segment\Tracks"),
+            SegmentElement::Chapters(_) => Some(r"\Segment\Chapters"),
+            SegmentElement::Cues(_) => Some(r"\Segment\Cues"),
+            SegmentElement::Attachments(_) => Some(r"\Segment\Attachments"),
+            _ => None,
+        };
+        if let Some(path) = unique {
+            if self.elements.values().any(|&other| other == id) {
+                self.error(path, "the element occurs more than once");
+            }
+        }
+        self.elements.insert(position, id);
+
+        match element {
+            SegmentElement::SeekHead(seek_head) => self.seek_heads.push(seek_head.clone()),
+            SegmentElement::Tracks(tracks) => self.check_tracks(tracks),
+            SegmentElement::Cluster(cluster) => {
+                self.check_cluster_position(position, cluster);
+                self.check_cluster(cluster);
+            }
+            _ => {}
+        }
+    }
+
+    fn check_tracks(&mut self, tracks: &Tracks) {
+        let mut uids = Vec::new();
+        for track in &tracks.tracks {
+            if track.track_number == 0 {
+                self.error(
+                    r"\Segment\Tracks\TrackEntry\TrackNumber",
+                    "the track number is 0",
+                );
+            } else if self.tracks.contains(&track.track_number) {
+                self.error(
+                    r"\Segment\Tracks\TrackEntry\TrackNumber",
+                    format!("the track number {} is not unique", track.track_number),
+                );
+            }
+            self.tracks.push(track.track_number);
+
+            if track.track_uid == 0 {
+                self.error(r"\Segment\Tracks\TrackEntry\TrackUID", "the track UID is 0");
+            } else if uids.contains(&track.track_uid) {
+                self.error(
+                    r"\Segment\Tracks\TrackEntry\TrackUID",
+                    format!("the track UID {:#X} is not unique", track.track_uid),
+                );
+            }
+            uids.push(track.track_uid);
+        }
+    }
+
+    fn check_cluster(&mut self, cluster: &Cluster<'_>) {
+        if let Some(last) = self.last_cluster_timestamp {
+            if cluster.timestamp < last {
+                self.error(
+                    r"\Segment\Cluster\Timestamp",
+                    format!(
+                        "the timestamp {} is lower than the one of the previous Cluster, {last}",
+                        cluster.timestamp
+                    ),
+                );
+            }
+        }
+        self.last_cluster_timestamp = Some(cluster.timestamp);
+
+        let simple_blocks = cluster
+            .simple_block
+            .iter()
+            .map(|b| (r"\Segment\Cluster\SimpleBlock", b.track_number));
+        let blocks = cluster
+            .block_group
+            .iter()
+            .map(|g| (r"\Segment\Cluster\BlockGroup\Block", g.block.track_number));
+        let mut unknown: Vec<_> = simple_blocks
+            .chain(blocks)
+            .filter(|(_, track)| !self.tracks.contains(track))
+            .collect();
+        unknown.dedup();
+        for (path, track) in unknown {
+            self.error(
+                path,
+                format!("the block belongs to the unknown track {track}"),
+            );
+        }
+    }
+
+    fn check_cluster_position(&mut self, position: u64, cluster: &Cluster<'_>) {
+        match cluster.position {
+            Some(found) if found != 0 && found != position => self.error(
+                r"\Segment\Cluster\Position",
+                format!("the Position is {found} but the Cluster starts at {position}"),
+            ),
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<ValidationError> {
+        for (id, path) in [
+            (0x1549A966, r"\Segment\Info"),
+            (0x1654AE6B, r"\Segment\Tracks"),
+        ] {
+            if !self.elements.values().any(|&other| other == id) {
+                self.error(path, "the mandatory element is missing");
+            }
+        }
+
+        let seeks: Vec<_> = self
+            .seek_heads
+            .iter()
+            .flat_map(|seek_head| &seek_head.positions)
+            .map(|seek| (u32::from_be_bytes(seek.id), seek.position))
+            .collect();
+        for (id, position) in seeks {
+            match self.elements.get(&position) {
+                Some(&found) if found == id => {}
+                Some(&found) => self.error(
+                    r"\Segment\SeekHead\Seek\SeekPosition",
+                    format!("the element at {position} is {found:#X} instead of {id:#X}"),
+                ),
+                None => self.error(
+                    r"\Segment\SeekHead\Seek\SeekPosition",
+                    format!("no element of ID {id:#X} starts at {position}"),
+                ),
+            }
+        }
+
+        self.errors
+    }
+}
+
+/// A track whose blocks are stored too far ahead of another track, found by
+/// [MatroskaChecker::check_interleaving].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterleavingWarning {
+    /// A block of `track` is stored `gap_ms` milliseconds after the last
+    /// block of another track of the same Cluster.
+    TrackAhead { track: u64, gap_ms: u64 },
+}
+
+impl fmt::Display for InterleavingWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterleavingWarning::TrackAhead { track, gap_ms } => {
+                write!(f, "the track {track} is {gap_ms} ms ahead of another track")
+            }
+        }
+    }
+}
+
+/// The timestamps of the blocks of a whole Segment, in storage order, for
+/// the checks needing all of them.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::validator::MatroskaChecker;
+///
+/// let checker = MatroskaChecker::scan(File::open("video.mkv")?)?;
+/// for warning in checker.check_interleaving(1000) {
+///     println!("{warning}");
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MatroskaChecker {
+    timestamp_scale: u64,
+    // Track and timestamp of the blocks of each Cluster
+    clusters: Vec<Vec<(u64, u64)>>,
+}
+
+impl MatroskaChecker {
+    /// Reads the blocks of the Segment of `source`.
+    pub fn scan<R: Read>(source: R) -> Result<Self, Error> {
+        let mut reader = MatroskaReader::new(source)?;
+        let mut clusters = Vec::new();
+
+        while let Some(element) = reader.next_element()? {
+            if let SegmentElement::Cluster(cluster) = element {
+                let blocks = cluster
+                    .blocks()
+                    .map(|block| {
+                        let timestamp = cluster.block_timestamp(block.timestamp());
+                        (block.track_number(), timestamp)
+                    })
+                    .collect();
+                clusters.push(blocks);
+            }
+        }
+
+        Ok(MatroskaChecker {
+            timestamp_scale: reader.timestamp_scale(),
+            clusters,
+        })
+    }
+
+    /// Reports the tracks getting more than `max_gap_ms` ahead of another
+    /// track of the same Cluster, once per track and Cluster with the
+    /// largest gap.
+    ///
+    /// Each block is compared with the latest block stored so far of the
+    /// other tracks of its Cluster, so that sparse tracks such as subtitles
+    /// are only compared where they have blocks.
+    pub fn check_interleaving(&self, max_gap_ms: u64) -> Vec<InterleavingWarning> {
+        let mut warnings = Vec::new();
+        // Latest timestamp stored of each track
+        let mut latest = HashMap::new();
+
+        for blocks in &self.clusters {
+            let mut tracks: Vec<u64> = blocks.iter().map(|&(track, _)| track).collect();
+            tracks.sort_unstable();
+            tracks.dedup();
+
+            let mut gaps = HashMap::new();
+            for &(track, timestamp) in blocks {
+                let timestamp = *latest
+                    .entry(track)
+                    .and_modify(|t: &mut u64| *t = (*t).max(timestamp))
+                    .or_insert(timestamp);
+
+                let behind = tracks
+                    .iter()
+                    .filter(|&&other| other != track)
+                    .filter_map(|other| latest.get(other))
+                    .min();
+                if let Some(&behind) = behind {
+                    let gap = timestamp.saturating_sub(behind);
+                    let max = gaps.entry(track).or_insert(0);
+                    *max = gap.max(*max);
+                }
+            }
+
+            for track in tracks {
+                let gap_ns = u128::from(gaps.get(&track).copied().unwrap_or(0))
+                    * u128::from(self.timestamp_scale);
+                let gap_ms = u64::try_from(gap_ns / 1_000_000).unwrap_or(u64::MAX);
+                if gap_ms > max_gap_ms {
+                    warnings.push(InterleavingWarning::TrackAhead { track, gap_ms });
+                }
+            }
+        }
+
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::{
+        Block, BlockGroup, BlockKind, Cluster, Info, Lacing, SimpleBlock, TrackEntry,
+    };
+    use crate::test_utils;
+    use crate::writer::{MatroskaWriter, RawBlock};
+
+    #[test]
+    fn valid_files() {
+        for path in [
+            "assets/single_stream.mkv",
+            "assets/bbb-vp9-opus.webm",
+            "assets/matroska_test_w1_1/test1.mkv",
+        ] {
+            let errors = MatroskaValidator::validate(std::fs::File::open(path).unwrap()).unwrap();
+            assert_eq!(errors, [], "{path}");
+        }
+    }
+
+    #[test]
+    fn invalid_file() {
+        let track = TrackEntry {
+            track_uid: 7,
+            ..test_utils::track(1, 2, "A_OPUS")
+        };
+        let tracks = vec![track.clone(), track];
+
+        let mut writer =
+            MatroskaWriter::new(Cursor::new(Vec::new()), Info::default(), tracks).unwrap();
+        for timestamp in [1000, 0] {
+            let block = RawBlock {
+                track_number: 2,
+                timestamp: 0,
+                keyframe: true,
+                data: &[0; 4],
+            };
+            writer.write_cluster(timestamp, &[block]).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+
+        let errors = MatroskaValidator::validate(Cursor::new(&data)).unwrap();
+        let paths: Vec<_> = errors.iter().map(|e| e.path).collect();
+        assert_eq!(
+            paths,
+            [
+                r"\Segment\Tracks\TrackEntry\TrackNumber",
+                r"\Segment\Tracks\TrackEntry\TrackUID",
+                r"\Segment\Cluster\SimpleBlock",
+                r"\Segment\Cluster\Timestamp",
+                r"\Segment\Cluster\SimpleBlock",
+            ]
+        );
+        assert_eq!(
+            errors[1].to_string(),
+            r"\Segment\Tracks\TrackEntry\TrackUID: the track UID 0x7 is not unique"
+        );
+
+        // Point the SeekHead entry of the Info to the Tracks
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        reader.next_element().unwrap();
+        let seek_head = reader.seek_head().unwrap();
+        let info_position = seek_head.positions[0].position as u16;
+        let tracks_position = seek_head.positions[1].position as u16;
+
+        let offset = data
+            .windows(5)
+            .position(|w| w[..3] == [0x53, 0xAC, 0x82] && w[3..] == info_position.to_be_bytes())
+            .unwrap();
+        let mut broken = data.clone();
+        broken[offset + 3..offset + 5].copy_from_slice(&tracks_position.to_be_bytes());
+
+        let errors = MatroskaValidator::validate(Cursor::new(&broken)).unwrap();
+        let expected = ValidationError {
+            path: r"\Segment\SeekHead\Seek\SeekPosition",
+            description: format!(
+                "the element at {tracks_position} is 0x1654AE6B instead of 0x1549A966"
+            ),
+        };
+        assert!(errors.contains(&expected), "{errors:?}");
+    }
+
+    #[test]
+    fn cluster_positions() {
+        use crate::ebml::ebml_header;
+        use crate::serializer::ebml::{encode_element, encode_master};
+
+        let webm = std::fs::read("assets/bbb-vp9-opus.webm").unwrap();
+        let (rest, _) = ebml_header(&webm).unwrap();
+        let mut data = webm[..webm.len() - rest.len()].to_vec();
+        data.extend(encode_master(0x18538067, None).unwrap());
+        let segment_start = data.len();
+
+        let mut track = encode_element(0xD7, &[1]).unwrap();
+        track.extend(encode_element(0x73C5, &[1]).unwrap());
+        track.extend(encode_element(0x83, &[2]).unwrap());
+        track.extend(encode_element(0x86, b"A_OPUS").unwrap());
+        let track = encode_element(0xAE, &track).unwrap();
+        let mut info = encode_element(0x4D80, b"test").unwrap();
+        info.extend(encode_element(0x5741, b"test").unwrap());
+        data.extend(encode_element(0x1549A966, &info).unwrap());
+        data.extend(encode_element(0x1654AE6B, &track).unwrap());
+
+        // The second Cluster claims to start 10 bytes after its offset, the
+        // third one has the Position of live streams
+        for (timestamp, shift) in [(0u8, Some(0)), (20, Some(10)), (40, None)] {
+            let offset = data.len() - segment_start;
+            let position = shift.map_or(0, |shift| offset + shift);
+            let mut cluster = encode_element(0xE7, &[timestamp]).unwrap();
+            cluster.extend(encode_element(0xA7, &(position as u16).to_be_bytes()).unwrap());
+            cluster.extend(encode_element(0xA3, &[0x81, 0x00, 0x00, 0x80, 0x00]).unwrap());
+            data.extend(encode_element(0x1F43B675, &cluster).unwrap());
+        }
+
+        let errors = MatroskaValidator::verify_cluster_positions(Cursor::new(&data)).unwrap();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].path, r"\Segment\Cluster\Position");
+        assert!(errors[0].description.starts_with("the Position is"));
+
+        let errors = MatroskaValidator::validate(Cursor::new(&data)).unwrap();
+        assert!(errors
+            .iter()
+            .any(|e| e.path == r"\Segment\Cluster\Position"));
+
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let errors =
+            MatroskaValidator::verify_cluster_positions(std::fs::File::open(path).unwrap())
+                .unwrap();
+        assert_eq!(errors, []);
+    }
+
+    #[test]
+    fn interleaving() {
+        for path in [
+            "assets/bbb-vp9-opus.webm",
+            "assets/matroska_test_w1_1/test1.mkv",
+        ] {
+            let checker = MatroskaChecker::scan(std::fs::File::open(path).unwrap()).unwrap();
+            assert_eq!(checker.check_interleaving(1000), [], "{path}");
+        }
+
+        let track = |track_number| test_utils::track(track_number, 2, "A_OPUS");
+
+        // The track 1 is 1.5 seconds ahead in the first Cluster, and the
+        // track 2 catches up in the second one
+        let block = |track_number, timestamp| test_utils::block(track_number, timestamp, true);
+        let data = test_utils::segment(
+            vec![track(1), track(2)],
+            &[
+                (0, &[block(1, 0), block(2, 0), block(1, 1500), block(2, 20)]),
+                (2000, &[block(2, 0), block(1, 20), block(2, 40)]),
+            ],
+        );
+
+        let checker = MatroskaChecker::scan(Cursor::new(&data)).unwrap();
+        assert_eq!(
+            checker.check_interleaving(1000),
+            [InterleavingWarning::TrackAhead {
+                track: 1,
+                gap_ms: 1500,
+            }]
+        );
+        assert_eq!(checker.check_interleaving(1500), []);
+        assert_eq!(checker.check_interleaving(100).len(), 2);
+        assert_eq!(
+            checker.check_interleaving(0)[0].to_string(),
+            "the track 1 is 1500 ms ahead of another track"
+        );
+    }
+
+    #[test]
+    fn interleaving_block_groups() {
+        let track = |track_number| test_utils::track(track_number, 2, "A_OPUS");
+        // A TimestampScale of 0 is read as the default one
+        let info = Info {
+            timestamp_scale: 0,
+            ..Default::default()
+        };
+        let mut writer =
+            MatroskaWriter::new(Cursor::new(Vec::new()), info, vec![track(1), track(2)]).unwrap();
+
+        // The BlockGroup of the track 2 is stored between the SimpleBlocks
+        // of the track 1, which then gets 1.5 seconds ahead of it
+        let simple_block = |timestamp| SimpleBlock {
+            track_number: 1,
+            timestamp,
+            keyframe: true,
+            invisible: false,
+            lacing: Lacing::None,
+            discardable: false,
+            data: (&[0; 4][..]).into(),
+        };
+        writer
+            .write_cluster_element(&Cluster {
+                timestamp: 0,
+                silent_tracks: None,
+                position: None,
+                prev_size: None,
+                simple_block: vec![simple_block(0), simple_block(1500)],
+                block_group: vec![BlockGroup {
+                    block: Block {
+                        track_number: 2,
+                        timestamp: 0,
+                        invisible: false,
+                        lacing: Lacing::None,
+                        data: (&[0; 4][..]).into(),
+                    },
+                    block_additions: None,
+                    block_duration: Some(20),
+                    reference_priority: 0,
+                    reference_block: Vec::new(),
+                    codec_state: None,
+                    discard_padding: None,
+                }],
+                block_order: vec![
+                    BlockKind::SimpleBlock,
+                    BlockKind::BlockGroup,
+                    BlockKind::SimpleBlock,
+                ],
+            })
+            .unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let checker = MatroskaChecker::scan(Cursor::new(&data)).unwrap();
+        assert_eq!(
+            checker.check_interleaving(1000),
+            [InterleavingWarning::TrackAhead {
+                track: 1,
+                gap_ms: 1500,
+            }]
+        );
+    }
+}
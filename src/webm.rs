@@ -0,0 +1,250 @@
+//! The restrictions of the WebM profile of Matroska, and a reader
+//! enforcing them.
+
+use std::fmt;
+use std::io::Read;
+
+use crate::elements::{Chapters, SegmentElement, TrackEntry, Tracks};
+use crate::reader::MatroskaReader;
+use crate::Error;
+
+const VIDEO_CODECS: [&str; 3] = ["V_VP8", "V_VP9", "V_AV1"];
+const AUDIO_CODECS: [&str; 2] = ["A_VORBIS", "A_OPUS"];
+const TEXT_CODECS: [&str; 4] = [
+    "D_WEBVTT/SUBTITLES",
+    "D_WEBVTT/CAPTIONS",
+    "D_WEBVTT/DESCRIPTIONS",
+    "D_WEBVTT/METADATA",
+];
+
+/// A difference between a file and the WebM profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebmViolation {
+    /// The DocType of the EBML Header is not `webm`.
+    DocType(String),
+    /// The codec of the track is not allowed for its track type.
+    Codec { track_number: u64, codec_id: String },
+    /// The track uses a ContentCompression, only encryption is allowed.
+    ContentCompression { track_number: u64 },
+    /// An element not supported by WebM, identified by its path.
+    Element(&'static str),
+}
+
+impl fmt::Display for WebmViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebmViolation::DocType(doc_type) => write!(f, "the DocType is {doc_type:?}"),
+            WebmViolation::Codec {
+                track_number,
+                codec_id,
+            } => write!(f, "the track {track_number} uses the codec {codec_id}"),
+            WebmViolation::ContentCompression { track_number } => {
+                write!(f, "the track {track_number} is compressed")
+            }
+            WebmViolation::Element(path) => write!(f, "{path} is not allowed"),
+        }
+    }
+}
+
+/// Reads a WebM file, failing with [Error::InvalidWebm] on the elements
+/// that do not follow the WebM profile.
+///
+/// The DocType is checked on creation, then the Tracks, Chapters and
+/// Attachments when they are read.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::webm::WebmReader;
+///
+/// let mut reader = WebmReader::new(File::open("video.webm")?)?;
+/// while let Some(element) = reader.next_element()? {
+///     println!("{:#X}", element.id());
+/// }
+/// if let Some(track) = reader.video_track() {
+///     println!("video: {}", track.codec_id);
+/// }
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct WebmReader<R> {
+    reader: MatroskaReader<R>,
+}
+
+impl<R: Read> WebmReader<R> {
+    /// Creates a reader, failing if the DocType is not `webm`.
+    pub fn new(source: R) -> Result<Self, Error> {
+        Self::from_reader(MatroskaReader::new(source)?)
+    }
+
+    /// Wraps a reader configured with a
+    /// [MatroskaReaderBuilder](crate::reader::MatroskaReaderBuilder),
+    /// failing if the DocType is not `webm` or if the elements read so far
+    /// do not follow the WebM profile.
+    pub fn from_reader(reader: MatroskaReader<R>) -> Result<Self, Error> {
+        reader.validate_webm_profile().map_err(Error::InvalidWebm)?;
+        Ok(WebmReader { reader })
+    }
+
+    /// Reads the next Top-Level Element, see
+    /// [MatroskaReader::next_element].
+    pub fn next_element(&mut self) -> Result<Option<SegmentElement<'_>>, Error> {
+        let element = self.reader.next_element()?;
+        let violations = match &element {
+            Some(SegmentElement::Tracks(tracks)) => check_tracks(tracks),
+            Some(SegmentElement::Chapters(chapters)) => check_chapters(chapters),
+            Some(SegmentElement::Attachments(_)) => {
+                vec![WebmViolation::Element(r"\Segment\Attachments")]
+            }
+            _ => Vec::new(),
+        };
+        if !violations.is_empty() {
+            return Err(Error::InvalidWebm(violations));
+        }
+
+        Ok(element)
+    }
+
+    /// The first video track, once the Tracks have been read.
+    pub fn video_track(&self) -> Option<&TrackEntry> {
+        self.track(1)
+    }
+
+    /// The first audio track, once the Tracks have been read.
+    pub fn audio_track(&self) -> Option<&TrackEntry> {
+        self.track(2)
+    }
+
+    fn track(&self, track_type: u64) -> Option<&TrackEntry> {
+        self.reader
+            .tracks()?
+            .tracks
+            .iter()
+            .find(|track| track.track_type == track_type)
+    }
+
+    /// The underlying reader.
+    pub fn reader(&self) -> &MatroskaReader<R> {
+        &self.reader
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> MatroskaReader<R> {
+        self.reader
+    }
+}
+
+// Checks the codecs and the encodings of the tracks.
+pub(crate) fn check_tracks(tracks: &Tracks) -> Vec<WebmViolation> {
+    let mut violations = Vec::new();
+    for track in &tracks.tracks {
+        let codecs = match track.track_type {
+            1 => &VIDEO_CODECS[..],
+            2 => &AUDIO_CODECS[..],
+            0x11 | 0x21 => &TEXT_CODECS[..],
+            _ => &[],
+        };
+        if !codecs.contains(&track.codec_id.as_str()) {
+            violations.push(WebmViolation::Codec {
+                track_number: track.track_number,
+                codec_id: track.codec_id.clone(),
+            });
+        }
+
+        let compressed = track
+            .content_encodings
+            .iter()
+            .flat_map(|encodings| &encodings.content_encoding)
+            .any(|encoding| encoding.encoding_type == 0);
+        if compressed {
+            violations.push(WebmViolation::ContentCompression {
+                track_number: track.track_number,
+            });
+        }
+    }
+
+    violations
+}
+
+// Ordered editions are the only part of the Chapters WebM leaves out.
+pub(crate) fn check_chapters(chapters: &Chapters) -> Vec<WebmViolation> {
+    if chapters.editions.iter().any(|edition| edition.flag_ordered) {
+        vec![WebmViolation::Element(
+            r"\Segment\Chapters\EditionEntry\EditionFlagOrdered",
+        )]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::{ContentEncoding, ContentEncodings};
+
+    #[test]
+    fn webm_reader() {
+        let file = File::open("assets/bbb-vp9-opus.webm").unwrap();
+        let mut reader = WebmReader::new(file).unwrap();
+        while reader.next_element().unwrap().is_some() {}
+        assert_eq!(reader.video_track().unwrap().codec_id, "V_VP9");
+        assert_eq!(reader.audio_track().unwrap().codec_id, "A_OPUS");
+        assert_eq!(reader.into_inner().validate_webm_profile(), Ok(()));
+
+        let file = File::open("assets/matroska_test_w1_1/test1.mkv").unwrap();
+        match WebmReader::new(file) {
+            Err(Error::InvalidWebm(violations)) => assert_eq!(
+                violations,
+                [WebmViolation::DocType(String::from("matroska"))]
+            ),
+            _ => panic!("expected a DocType violation"),
+        }
+        assert!(WebmReader::new(Cursor::new(b"not a webm file")).is_err());
+    }
+
+    #[test]
+    fn validate_webm_profile() {
+        let path = "assets/matroska_test_w1_1/test1.mkv";
+        let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        while reader.tracks().is_none() {
+            reader.next_element().unwrap();
+        }
+
+        let violations = reader.validate_webm_profile().unwrap_err();
+        assert_eq!(
+            violations[0],
+            WebmViolation::DocType(String::from("matroska"))
+        );
+        assert_eq!(
+            violations[1..],
+            [
+                WebmViolation::Codec {
+                    track_number: 1,
+                    codec_id: String::from("V_MS/VFW/FOURCC"),
+                },
+                WebmViolation::Codec {
+                    track_number: 2,
+                    codec_id: String::from("A_MPEG/L3"),
+                },
+            ]
+        );
+
+        let mut tracks = reader.tracks().unwrap().clone();
+        tracks.tracks[1].codec_id = String::from("A_OPUS");
+        tracks.tracks[1].content_encodings = Some(ContentEncodings {
+            content_encoding: vec![ContentEncoding {
+                order: 0,
+                scope: 1,
+                encoding_type: 0,
+                compression: None,
+                encryption: None,
+            }],
+        });
+        assert_eq!(
+            check_tracks(&tracks)[1..],
+            [WebmViolation::ContentCompression { track_number: 2 }]
+        );
+    }
+}
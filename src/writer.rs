@@ -0,0 +1,1072 @@
+use std::borrow::Cow;
+use std::io::{Seek, SeekFrom, Write};
+
+use cookie_factory::GenError;
+
+use crate::ebml::EbmlHeader;
+use crate::elements::{
+    Block, BlockGroup, BlockKind, Cluster, CuePoint, CueTrackPositions, Cues, Info, Lacing,
+    Seek as SeekEntry, SeekHead, SilentTracks, SimpleBlock, Tags, TrackEntry, Tracks,
+};
+use crate::serializer::ebml::{gen_ebml_header, EbmlSize};
+use crate::serializer::elements::{
+    gen_cluster, gen_cues, gen_info, gen_seek_head, gen_segment_header, gen_tags, gen_tracks,
+    gen_void,
+};
+use crate::Error;
+
+// Space kept at the start of the Segment for the SeekHead, which can only
+// be written once the position of every Top-Level Element is known.
+const SEEK_HEAD_RESERVED_SIZE: usize = 256;
+
+/// A frame to write in a Cluster with [MatroskaWriter::write_cluster].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawBlock<'a> {
+    pub track_number: u64,
+    /// Timestamp relative to the timestamp of the Cluster.
+    pub timestamp: i16,
+    pub keyframe: bool,
+    pub data: &'a [u8],
+}
+
+/// Writes a Matroska file to any [Write] + [Seek] destination.
+///
+/// The EBML Header, Info and Tracks are written on creation, the Clusters
+/// are then written one by one with [MatroskaWriter::write_cluster].
+/// [MatroskaWriter::finish] writes the Cues and the SeekHead, and sets the
+/// size of the Segment.
+///
+/// ```no_run
+/// use std::fs::File;
+///
+/// use matroska::elements::{Info, TrackEntry};
+/// use matroska::writer::{MatroskaWriter, RawBlock};
+///
+/// let info = Info {
+///     timestamp_scale: 1_000_000,
+///     muxing_app: String::from("example"),
+///     writing_app: String::from("example"),
+///     ..Default::default()
+/// };
+/// let track = TrackEntry {
+///     track_number: 1,
+///     track_uid: 1,
+///     track_type: 2,
+///     codec_id: String::from("A_OPUS"),
+///     ..Default::default()
+/// };
+///
+/// let mut writer = MatroskaWriter::new(File::create("audio.mka")?, info, vec![track])?;
+/// writer.write_cluster(
+///     0,
+///     &[RawBlock {
+///         track_number: 1,
+///         timestamp: 0,
+///         keyframe: true,
+///         data: &[0xFC, 0xFF, 0xFE],
+///     }],
+/// )?;
+/// writer.finish()?;
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaWriter<W> {
+    out: W,
+    // Offset in the destination of the first byte of the Segment data
+    segment_start: u64,
+    // Positions relative to the start of the Segment data
+    info_position: u64,
+    tracks_position: u64,
+    cues_position: Option<u64>,
+    tags_position: Option<u64>,
+    // Position and size of the Void the SeekHead is written over
+    seek_head_placeholder: (u64, usize),
+    cue_points: Vec<CuePoint>,
+}
+
+impl<W: Write + Seek> MatroskaWriter<W> {
+    /// Creates a writer and writes the EBML Header, the Info and the Tracks.
+    pub fn new(mut out: W, info: Info, tracks: Vec<TrackEntry>) -> Result<Self, Error> {
+        let header = ebml_header();
+        let tracks = Tracks { tracks };
+
+        let mut buf = vec![0; header.size(0x1A45DFA3) + 12 + SEEK_HEAD_RESERVED_SIZE];
+        let len = serialize(&mut buf, gen_ebml_header(&header))?;
+        let len = len + serialize(&mut buf[len..], gen_segment_header(None))?;
+        serialize(&mut buf[len..], gen_void(SEEK_HEAD_RESERVED_SIZE))?;
+        out.write_all(&buf[..len])?;
+
+        let segment_start = out.stream_position()?;
+        out.write_all(&buf[len..])?;
+
+        let info_position = out.stream_position()? - segment_start;
+        let mut buf = vec![0; info.size(0x1549A966)];
+        serialize(&mut buf, gen_info(&info))?;
+        out.write_all(&buf)?;
+
+        let tracks_position = out.stream_position()? - segment_start;
+        let mut buf = vec![0; tracks.size(0x1654AE6B)];
+        serialize(&mut buf, gen_tracks(&tracks))?;
+        out.write_all(&buf)?;
+
+        Ok(MatroskaWriter {
+            out,
+            segment_start,
+            info_position,
+            tracks_position,
+            cues_position: None,
+            tags_position: None,
+            seek_head_placeholder: (0, SEEK_HEAD_RESERVED_SIZE),
+            cue_points: Vec::new(),
+        })
+    }
+
+    /// Writes a Void large enough for a SeekHead of `max_entries` entries,
+    /// which the SeekHead is then written over instead of the space
+    /// reserved at the start of the Segment.
+    pub fn write_seek_head_placeholder(&mut self, max_entries: usize) -> Result<(), Error> {
+        let seek_head = SeekHead {
+            positions: vec![
+                SeekEntry {
+                    id: [0xFF; 4],
+                    position: u64::MAX,
+                };
+                max_entries
+            ],
+        };
+        let size = seek_head.size(0x114D9B74);
+
+        let position = self.out.stream_position()? - self.segment_start;
+        let mut buf = vec![0; size];
+        serialize(&mut buf, gen_void(size))?;
+        self.out.write_all(&buf)?;
+        self.seek_head_placeholder = (position, size);
+
+        Ok(())
+    }
+
+    /// Writes the SeekHead of the elements written so far over its
+    /// placeholder, padded with a Void, then moves back to the end of the
+    /// destination.
+    ///
+    /// It is called by [MatroskaWriter::finish], calling it before keeps
+    /// the file readable if the writing stops.
+    pub fn finalize_seek_head(&mut self) -> Result<(), Error> {
+        let mut seek_head = SeekHead {
+            positions: vec![
+                SeekEntry {
+                    id: [0x15, 0x49, 0xA9, 0x66],
+                    position: self.info_position,
+                },
+                SeekEntry {
+                    id: [0x16, 0x54, 0xAE, 0x6B],
+                    position: self.tracks_position,
+                },
+            ],
+        };
+        if let Some(position) = self.cues_position {
+            seek_head.positions.push(SeekEntry {
+                id: [0x1C, 0x53, 0xBB, 0x6B],
+                position,
+            });
+        }
+        if let Some(position) = self.tags_position {
+            seek_head.positions.push(SeekEntry {
+                id: [0x12, 0x54, 0xC3, 0x67],
+                position,
+            });
+        }
+
+        let (position, size) = self.seek_head_placeholder;
+        let mut buf = vec![0; size];
+        let len = serialize(&mut buf, gen_seek_head(&seek_head))?;
+        if len < size {
+            serialize(&mut buf[len..], gen_void(size - len))?;
+        }
+
+        let end = self.out.stream_position()?;
+        self.out
+            .seek(SeekFrom::Start(self.segment_start + position))?;
+        self.out.write_all(&buf)?;
+        self.out.seek(SeekFrom::Start(end))?;
+
+        Ok(())
+    }
+
+    /// Writes a Cluster holding `blocks` as SimpleBlocks.
+    ///
+    /// The first keyframe of each track in the Cluster gets a Cue Point.
+    pub fn write_cluster(&mut self, timestamp: u64, blocks: &[RawBlock<'_>]) -> Result<(), Error> {
+        self.write_cluster_with_silent_tracks(timestamp, blocks, &[])
+    }
+
+    /// Writes a Cluster holding `blocks`, listing `silent_tracks` as the
+    /// tracks without blocks in it, see [MatroskaWriter::write_cluster].
+    pub fn write_cluster_with_silent_tracks(
+        &mut self,
+        timestamp: u64,
+        blocks: &[RawBlock<'_>],
+        silent_tracks: &[u64],
+    ) -> Result<(), Error> {
+        let cluster_position = self.out.stream_position()? - self.segment_start;
+        write_cluster(
+            &mut self.out,
+            cluster_position,
+            timestamp,
+            blocks,
+            silent_tracks,
+            &mut self.cue_points,
+        )?;
+
+        Ok(())
+    }
+
+    /// Writes `cluster` as it is, with its SimpleBlocks and BlockGroups in
+    /// the order of [Cluster::blocks].
+    ///
+    /// The first keyframe of each track in the Cluster gets a Cue Point,
+    /// a BlockGroup being a keyframe when it has no ReferenceBlock.
+    pub fn write_cluster_element(&mut self, cluster: &Cluster<'_>) -> Result<(), Error> {
+        let cluster_position = self.out.stream_position()? - self.segment_start;
+        write_cluster_element(
+            &mut self.out,
+            cluster_position,
+            cluster,
+            &mut self.cue_points,
+        )?;
+
+        Ok(())
+    }
+
+    /// Writes a Tags element, which the SeekHead then points to.
+    ///
+    /// Only the last Tags written is listed in the SeekHead.
+    pub fn write_tags(&mut self, tags: &Tags) -> Result<(), Error> {
+        self.tags_position = Some(self.out.stream_position()? - self.segment_start);
+
+        let mut buf = vec![0; tags.size(0x1254C367)];
+        serialize(&mut buf, gen_tags(tags))?;
+        self.out.write_all(&buf)?;
+
+        Ok(())
+    }
+
+    /// Writes the Cues and the SeekHead, sets the Segment size and returns
+    /// the destination.
+    pub fn finish(mut self) -> Result<W, Error> {
+        if !self.cue_points.is_empty() {
+            self.cues_position = Some(self.out.stream_position()? - self.segment_start);
+
+            // Blocks of a Cluster are not necessarily in presentation order
+            self.cue_points.sort_by_key(|cue_point| cue_point.time);
+            let cues = Cues {
+                cue_points: std::mem::take(&mut self.cue_points),
+            };
+
+            let mut buf = vec![0; cues.size(0x1C53BB6B)];
+            serialize(&mut buf, gen_cues(&cues))?;
+            self.out.write_all(&buf)?;
+        }
+
+        self.finalize_seek_head()?;
+        let end = self.out.stream_position()?;
+
+        let mut buf = [0; 12];
+        serialize(&mut buf, gen_segment_header(Some(end - self.segment_start)))?;
+        self.out.seek(SeekFrom::Start(self.segment_start - 12))?;
+        self.out.write_all(&buf)?;
+
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// Writes a Matroska file to any [Write] destination, including pipes and
+/// sockets that cannot seek.
+///
+/// Frames are pushed one by one with [MatroskaEncoder::push_frame] and
+/// grouped in Clusters, each Cluster being written as soon as it is
+/// complete. As the Segment size cannot be set afterwards, it is left
+/// unknown, and the SeekHead is written last by [MatroskaEncoder::finish],
+/// after the Cues.
+///
+/// The Info and the Tracks are written with the first frame, tracks can
+/// be added with [MatroskaEncoder::add_track_with_delay] until then.
+///
+/// ```no_run
+/// use std::io::stdout;
+///
+/// use matroska::elements::{Info, TrackEntry};
+/// use matroska::writer::MatroskaEncoder;
+///
+/// let info = Info {
+///     timestamp_scale: 1_000_000,
+///     muxing_app: String::from("example"),
+///     writing_app: String::from("example"),
+///     ..Default::default()
+/// };
+/// let track = TrackEntry {
+///     track_number: 1,
+///     track_uid: 1,
+///     track_type: 2,
+///     codec_id: String::from("A_OPUS"),
+///     ..Default::default()
+/// };
+///
+/// let mut encoder = MatroskaEncoder::new(stdout(), info, vec![track])?;
+/// encoder.push_frame(1, 0, true, &[0xFC, 0xFF, 0xFE])?;
+/// encoder.push_frame(1, 20_000_000, true, &[0xFC, 0xFF, 0xFE])?;
+/// encoder.finish()?;
+/// # Ok::<(), matroska::Error>(())
+/// ```
+pub struct MatroskaEncoder<W> {
+    out: W,
+    // Number of bytes written since the start of the Segment data
+    position: u64,
+    timestamp_scale: u64,
+    max_cluster_duration: u64,
+    max_cluster_size: usize,
+    info_position: u64,
+    tracks_position: u64,
+    // Info and Tracks, until they are written with the first frame
+    pending_header: Option<(Info, Tracks)>,
+    // The Cluster being filled, and the size of its frames
+    cluster: Cluster<'static>,
+    cluster_size: usize,
+    // Timestamp of the last frame by track number, in Segment ticks
+    last_timestamps: Vec<(u64, u64)>,
+    cue_points: Vec<CuePoint>,
+    // Shifts of the timestamps by track number, in nanoseconds
+    delays: Vec<(u64, i64)>,
+}
+
+impl<W: Write> MatroskaEncoder<W> {
+    /// Creates an encoder and writes the EBML Header and the start of the
+    /// Segment.
+    pub fn new(mut out: W, info: Info, tracks: Vec<TrackEntry>) -> Result<Self, Error> {
+        let header = ebml_header();
+
+        let mut buf = vec![0; header.size(0x1A45DFA3) + 12];
+        let len = serialize(&mut buf, gen_ebml_header(&header))?;
+        serialize(&mut buf[len..], gen_segment_header(None))?;
+        out.write_all(&buf)?;
+
+        Ok(MatroskaEncoder {
+            out,
+            position: 0,
+            timestamp_scale: if info.timestamp_scale == 0 {
+                1_000_000
+            } else {
+                info.timestamp_scale
+            },
+            max_cluster_duration: 5_000_000_000,
+            max_cluster_size: 5 * 1024 * 1024,
+            info_position: 0,
+            tracks_position: 0,
+            pending_header: Some((info, Tracks { tracks })),
+            cluster: empty_cluster(0),
+            cluster_size: 0,
+            last_timestamps: Vec::new(),
+            cue_points: Vec::new(),
+            delays: Vec::new(),
+        })
+    }
+
+    /// Sets the longest time span of a Cluster, in nanoseconds. Defaults
+    /// to 5 seconds.
+    ///
+    /// A Cluster also ends when the timestamp of a frame cannot be stored
+    /// relative to the Cluster timestamp.
+    pub fn with_max_cluster_duration(mut self, duration_ns: u64) -> Self {
+        self.max_cluster_duration = duration_ns;
+        self
+    }
+
+    /// Sets the size of the frame data after which a Cluster is written.
+    /// Defaults to 5 MiB.
+    pub fn with_max_cluster_size(mut self, size: usize) -> Self {
+        self.max_cluster_size = size;
+        self
+    }
+
+    /// Adds a track whose frames are shifted by `delay_ns`, to correct
+    /// its sync with the other tracks.
+    ///
+    /// A negative delay presents the frames earlier, the frames that would
+    /// then start before the Segment are dropped. A positive delay
+    /// presents them later, leaving a gap before the first frame of the
+    /// track, which players fill with silence or black.
+    ///
+    /// Fails with [Error::TracksWritten] once a frame was pushed.
+    pub fn add_track_with_delay(&mut self, entry: TrackEntry, delay_ns: i64) -> Result<(), Error> {
+        let (_, tracks) = self.pending_header.as_mut().ok_or(Error::TracksWritten)?;
+
+        self.delays
+            .retain(|&(number, _)| number != entry.track_number);
+        self.delays.push((entry.track_number, delay_ns));
+        tracks
+            .tracks
+            .retain(|t| t.track_number != entry.track_number);
+        tracks.tracks.push(entry);
+
+        Ok(())
+    }
+
+    /// Adds a frame of `track` presented at `timestamp_ns`, before the
+    /// delay of the track is applied.
+    ///
+    /// The Cluster being filled is written first if the frame does not
+    /// fit in it. The frame is dropped if the delay of the track moves it
+    /// before the start of the Segment.
+    pub fn push_frame(
+        &mut self,
+        track: u64,
+        timestamp_ns: u64,
+        keyframe: bool,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.push(track, timestamp_ns, None, keyframe, data)
+    }
+
+    /// Adds a frame of `track` presented at `timestamp_ns` for
+    /// `duration_ns`, like the subtitles, see [MatroskaEncoder::push_frame].
+    ///
+    /// The frame is written in a BlockGroup with a BlockDuration, which
+    /// references the previous frame of the track unless it is a keyframe.
+    pub fn push_frame_with_duration(
+        &mut self,
+        track: u64,
+        timestamp_ns: u64,
+        duration_ns: u64,
+        keyframe: bool,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.push(track, timestamp_ns, Some(duration_ns), keyframe, data)
+    }
+
+    /// Writes the last Cluster, the Cues and the SeekHead, and returns the
+    /// destination.
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.write_header()?;
+        self.write_cluster()?;
+
+        let mut seek_head = SeekHead {
+            positions: vec![
+                SeekEntry {
+                    id: [0x15, 0x49, 0xA9, 0x66],
+                    position: self.info_position,
+                },
+                SeekEntry {
+                    id: [0x16, 0x54, 0xAE, 0x6B],
+                    position: self.tracks_position,
+                },
+            ],
+        };
+
+        if !self.cue_points.is_empty() {
+            seek_head.positions.push(SeekEntry {
+                id: [0x1C, 0x53, 0xBB, 0x6B],
+                position: self.position,
+            });
+
+            self.cue_points.sort_by_key(|cue_point| cue_point.time);
+            let cues = Cues {
+                cue_points: std::mem::take(&mut self.cue_points),
+            };
+
+            let mut buf = vec![0; cues.size(0x1C53BB6B)];
+            serialize(&mut buf, gen_cues(&cues))?;
+            self.out.write_all(&buf)?;
+        }
+
+        let mut buf = vec![0; seek_head.size(0x114D9B74)];
+        serialize(&mut buf, gen_seek_head(&seek_head))?;
+        self.out.write_all(&buf)?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+
+    // Writes the Info and the Tracks if they were not written yet
+    fn write_header(&mut self) -> Result<(), Error> {
+        let Some((info, tracks)) = self.pending_header.take() else {
+            return Ok(());
+        };
+
+        let info_size = info.size(0x1549A966);
+        let tracks_size = tracks.size(0x1654AE6B);
+        let mut buf = vec![0; info_size + tracks_size];
+        serialize(&mut buf, gen_info(&info))?;
+        serialize(&mut buf[info_size..], gen_tracks(&tracks))?;
+        self.out.write_all(&buf)?;
+
+        self.info_position = self.position;
+        self.tracks_position = self.position + info_size as u64;
+        self.position += buf.len() as u64;
+
+        Ok(())
+    }
+
+    // Adds a frame as a SimpleBlock, or as a BlockGroup if it has a
+    // duration
+    fn push(
+        &mut self,
+        track: u64,
+        timestamp_ns: u64,
+        duration_ns: Option<u64>,
+        keyframe: bool,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        self.write_header()?;
+
+        let timestamp_ns = match self.delays.iter().find(|&&(number, _)| number == track) {
+            Some(&(_, delay)) => match timestamp_ns.checked_add_signed(delay) {
+                Some(timestamp_ns) => timestamp_ns,
+                None if delay < 0 => return Ok(()),
+                None => u64::MAX,
+            },
+            None => timestamp_ns,
+        };
+        let timestamp = timestamp_ns / self.timestamp_scale;
+
+        let max_duration = self.max_cluster_duration / self.timestamp_scale;
+        let relative = timestamp as i128 - self.cluster.timestamp as i128;
+        let fits = relative.unsigned_abs() <= u128::from(max_duration)
+            && i16::try_from(relative).is_ok()
+            && self.cluster_size < self.max_cluster_size;
+
+        if !fits || self.cluster.block_order.is_empty() {
+            self.write_cluster()?;
+            self.cluster.timestamp = timestamp;
+        }
+
+        let relative = (timestamp as i128 - self.cluster.timestamp as i128) as i16;
+        let previous = self
+            .last_timestamps
+            .iter()
+            .position(|&(number, _)| number == track);
+        let reference = match previous {
+            Some(i) => {
+                let previous = std::mem::replace(&mut self.last_timestamps[i].1, timestamp);
+                (previous as i128 - timestamp as i128).clamp(i64::MIN.into(), i64::MAX.into())
+                    as i64
+            }
+            None => {
+                self.last_timestamps.push((track, timestamp));
+                0
+            }
+        };
+
+        self.cluster_size += data.len();
+        match duration_ns {
+            Some(duration_ns) => {
+                self.cluster.block_group.push(BlockGroup {
+                    block: Block {
+                        track_number: track,
+                        timestamp: relative,
+                        invisible: false,
+                        lacing: Lacing::None,
+                        data: Cow::Owned(data.to_vec()),
+                    },
+                    block_additions: None,
+                    block_duration: Some(duration_ns / self.timestamp_scale),
+                    reference_priority: 0,
+                    reference_block: if keyframe {
+                        Vec::new()
+                    } else {
+                        vec![reference]
+                    },
+                    codec_state: None,
+                    discard_padding: None,
+                });
+                self.cluster.block_order.push(BlockKind::BlockGroup);
+            }
+            None => {
+                self.cluster.simple_block.push(SimpleBlock {
+                    track_number: track,
+                    timestamp: relative,
+                    keyframe,
+                    invisible: false,
+                    lacing: Lacing::None,
+                    discardable: false,
+                    data: Cow::Owned(data.to_vec()),
+                });
+                self.cluster.block_order.push(BlockKind::SimpleBlock);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Writes the frames pushed since the last call as a Cluster
+    fn write_cluster(&mut self) -> Result<(), Error> {
+        if self.cluster.block_order.is_empty() {
+            return Ok(());
+        }
+
+        let cluster = std::mem::replace(&mut self.cluster, empty_cluster(0));
+        self.position +=
+            write_cluster_element(&mut self.out, self.position, &cluster, &mut self.cue_points)?
+                as u64;
+        self.out.flush()?;
+
+        self.cluster_size = 0;
+
+        Ok(())
+    }
+}
+
+fn empty_cluster(timestamp: u64) -> Cluster<'static> {
+    Cluster {
+        timestamp,
+        silent_tracks: None,
+        position: None,
+        prev_size: None,
+        simple_block: Vec::new(),
+        block_group: Vec::new(),
+        block_order: Vec::new(),
+    }
+}
+
+fn ebml_header() -> EbmlHeader {
+    EbmlHeader {
+        version: 1,
+        read_version: 1,
+        max_id_length: 4,
+        max_size_length: 8,
+        doc_type: String::from("matroska"),
+        doc_type_version: 4,
+        doc_type_read_version: 2,
+    }
+}
+
+// Writes a Cluster holding `blocks` as SimpleBlocks, see
+// `write_cluster_element`.
+fn write_cluster<W: Write>(
+    out: &mut W,
+    cluster_position: u64,
+    timestamp: u64,
+    blocks: &[RawBlock<'_>],
+    silent_tracks: &[u64],
+    cue_points: &mut Vec<CuePoint>,
+) -> Result<usize, Error> {
+    let cluster = Cluster {
+        timestamp,
+        silent_tracks: (!silent_tracks.is_empty()).then(|| SilentTracks {
+            silent_track_number: silent_tracks.to_vec(),
+        }),
+        position: None,
+        prev_size: None,
+        simple_block: blocks
+            .iter()
+            .map(|block| SimpleBlock {
+                track_number: block.track_number,
+                timestamp: block.timestamp,
+                keyframe: block.keyframe,
+                invisible: false,
+                lacing: Lacing::None,
+                discardable: false,
+                data: Cow::Borrowed(block.data),
+            })
+            .collect(),
+        block_group: Vec::new(),
+        block_order: Vec::new(),
+    };
+
+    write_cluster_element(out, cluster_position, &cluster, cue_points)
+}
+
+// Writes a Cluster, adding a Cue Point for the first keyframe of each
+// track, and returns its size.
+fn write_cluster_element<W: Write>(
+    out: &mut W,
+    cluster_position: u64,
+    cluster: &Cluster<'_>,
+    cue_points: &mut Vec<CuePoint>,
+) -> Result<usize, Error> {
+    let mut buf = vec![0; cluster.size(0x1F43B675)];
+    serialize(&mut buf, gen_cluster(cluster))?;
+    out.write_all(&buf)?;
+
+    let mut indexed = Vec::new();
+    for block in cluster.blocks().filter(|block| block.keyframe()) {
+        if indexed.contains(&block.track_number()) {
+            continue;
+        }
+        indexed.push(block.track_number());
+
+        cue_points.push(CuePoint {
+            time: cluster.block_timestamp(block.timestamp()),
+            track_positions: vec![CueTrackPositions {
+                track: block.track_number(),
+                cluster_position,
+                relative_position: None,
+                duration: None,
+                block_number: None,
+                codec_state: 0,
+                references: Vec::new(),
+            }],
+        });
+    }
+
+    Ok(buf.len())
+}
+
+// Runs a generator on a buffer sized with EbmlSize, returning the number
+// of bytes written.
+pub(crate) fn serialize<'b, G>(buf: &'b mut [u8], gen: G) -> Result<usize, Error>
+where
+    G: Fn((&'b mut [u8], usize)) -> Result<(&'b mut [u8], usize), GenError>,
+{
+    gen((buf, 0)).map(|(_, len)| len).map_err(Error::Serialize)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::elements::{Audio, SegmentElement, Video};
+    use crate::reader::MatroskaReader;
+    use crate::test_utils;
+
+    fn tracks() -> Vec<TrackEntry> {
+        vec![
+            TrackEntry {
+                track_number: 1,
+                track_uid: 0x1234,
+                track_type: 1,
+                codec_id: String::from("V_AV1"),
+                video: Some(Video {
+                    pixel_width: 64,
+                    pixel_height: 48,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            TrackEntry {
+                track_number: 2,
+                track_uid: 0x5678,
+                track_type: 2,
+                name: Some(String::from("Commentary")),
+                codec_id: String::from("A_OPUS"),
+                attachment_links: vec![7, 300],
+                flag_original: true,
+                flag_commentary: true,
+                audio: Some(Audio {
+                    sampling_frequency: 48000.0,
+                    channels: 2,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let info = Info {
+            title: Some(String::from("Round trip")),
+            muxing_app: String::from("matroska-rs"),
+            writing_app: String::from("matroska-rs"),
+            ..test_utils::info()
+        };
+
+        let mut writer =
+            MatroskaWriter::new(Cursor::new(Vec::new()), info.clone(), tracks()).unwrap();
+        for (timestamp, video) in [(0u64, [0xAA; 10]), (1000, [0xBB; 10])] {
+            writer
+                .write_cluster(
+                    timestamp,
+                    &[
+                        RawBlock {
+                            track_number: 2,
+                            timestamp: 0,
+                            keyframe: true,
+                            data: &[1, 2, 3],
+                        },
+                        RawBlock {
+                            track_number: 1,
+                            timestamp: 20,
+                            keyframe: true,
+                            data: &video,
+                        },
+                        RawBlock {
+                            track_number: 1,
+                            timestamp: 60,
+                            keyframe: false,
+                            data: &video[..5],
+                        },
+                    ],
+                )
+                .unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(reader.header().doc_type, "matroska");
+        let segment_size = reader.segment_size().unwrap() as usize;
+
+        let elements = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(elements.len(), 7, "elements: {elements:?}");
+        assert!(matches!(elements[1], SegmentElement::Void(_)));
+
+        let SegmentElement::SeekHead(seek_head) = &elements[0] else {
+            panic!("expected a SeekHead, got {:?}", elements[0]);
+        };
+        assert_eq!(seek_head.positions.len(), 3);
+        assert_eq!(
+            seek_head.positions[0].position,
+            SEEK_HEAD_RESERVED_SIZE as u64
+        );
+
+        assert_eq!(elements[2], SegmentElement::Info(info));
+
+        let SegmentElement::Tracks(t) = &elements[3] else {
+            panic!("expected Tracks, got {:?}", elements[3]);
+        };
+        assert_eq!(t.tracks.len(), 2);
+        assert_eq!(t.tracks[0].codec_id, "V_AV1");
+        assert_eq!(t.tracks[1].audio.as_ref().unwrap().channels, 2);
+        assert_eq!(t.tracks[0].name, None);
+        assert_eq!(t.tracks[1].name.as_deref(), Some("Commentary"));
+        assert_eq!(t.tracks[1].attachment_links, [7, 300]);
+        assert!(t.tracks[1].flag_commentary && t.tracks[1].flag_original);
+        assert!(!t.tracks[0].flag_commentary);
+
+        let (SegmentElement::Cluster(c1), SegmentElement::Cluster(c2)) =
+            (&elements[4], &elements[5])
+        else {
+            panic!("expected Clusters, got {:?}", &elements[4..6]);
+        };
+        assert_eq!(c1.timestamp, 0);
+        assert_eq!(c2.timestamp, 1000);
+        assert_eq!(c2.simple_block.len(), 3);
+        assert_eq!(c2.simple_block[1].data, &[0xBB; 10][..]);
+        assert!(!c2.simple_block[2].keyframe);
+
+        let SegmentElement::Cues(cues) = &elements[6] else {
+            panic!("expected Cues, got {:?}", elements[6]);
+        };
+        let times: Vec<_> = cues.cue_points.iter().map(|cp| cp.time).collect();
+        assert_eq!(times, [0, 20, 1000, 1020]);
+
+        // The Segment spans the rest of the file
+        let header_size = reader.header().size(0x1A45DFA3);
+        assert_eq!(data.len(), header_size + 12 + segment_size);
+
+        let offset = reader.seek_to_timestamp(1, 1_500_000_000).unwrap();
+        match reader.next_element().unwrap() {
+            Some(SegmentElement::Cluster(c)) => assert_eq!(c.timestamp, 1000),
+            e => panic!("expected a Cluster at {offset}, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn seek_head_placeholder() {
+        let block = RawBlock {
+            track_number: 1,
+            timestamp: 0,
+            keyframe: true,
+            data: &[0xAA; 10],
+        };
+        let mut writer =
+            MatroskaWriter::new(Cursor::new(Vec::new()), Info::default(), tracks()).unwrap();
+        writer.write_seek_head_placeholder(3).unwrap();
+        writer.write_cluster(0, &[block]).unwrap();
+
+        // The SeekHead can be written before the end
+        writer.finalize_seek_head().unwrap();
+        let partial = writer.out.get_ref().clone();
+        writer.write_cluster(1000, &[block]).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&partial)).unwrap();
+        let elements = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert!(matches!(elements[0], SegmentElement::Void(_)));
+        let SegmentElement::SeekHead(seek_head) = &elements[3] else {
+            panic!("expected a SeekHead, got {:?}", elements[3]);
+        };
+        assert_eq!(seek_head.positions.len(), 2);
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let elements = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        let ids: Vec<_> = elements.iter().map(SegmentElement::id).collect();
+        assert_eq!(
+            ids,
+            [0xEC, 0x1549A966, 0x1654AE6B, 0x114D9B74, 0xEC, 0x1F43B675, 0x1F43B675, 0x1C53BB6B]
+        );
+        let SegmentElement::SeekHead(seek_head) = &elements[3] else {
+            unreachable!();
+        };
+        assert_eq!(seek_head.positions.len(), 3);
+        reader
+            .seek_in_segment(seek_head.positions[2].position)
+            .unwrap();
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::Cues(_))
+        ));
+
+        // The Cues do not fit
+        let mut writer =
+            MatroskaWriter::new(Cursor::new(Vec::new()), Info::default(), tracks()).unwrap();
+        writer.write_seek_head_placeholder(2).unwrap();
+        writer.write_cluster(0, &[block]).unwrap();
+        assert!(matches!(writer.finish(), Err(Error::Serialize(_))));
+    }
+
+    #[test]
+    fn encode_to_stream() {
+        let info = test_utils::info();
+
+        // Vec<u8> does not implement Seek
+        let mut encoder = MatroskaEncoder::new(Vec::new(), info, tracks())
+            .unwrap()
+            .with_max_cluster_duration(1_000_000_000)
+            .with_max_cluster_size(10);
+        for i in 0..8u64 {
+            encoder
+                .push_frame(1, i * 400_000_000, i % 3 == 0, &[i as u8; 4])
+                .unwrap();
+        }
+        encoder.push_frame(2, 3_000_000_000, true, &[0; 6]).unwrap();
+        encoder.push_frame(2, 3_020_000_000, true, &[1; 6]).unwrap();
+        encoder.push_frame(2, 3_040_000_000, true, &[2; 6]).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(reader.segment_size(), None);
+        let elements = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert!(matches!(elements[0], SegmentElement::Info(_)));
+        assert!(matches!(elements[1], SegmentElement::Tracks(_)));
+        let clusters: Vec<_> = elements
+            .iter()
+            .filter_map(|element| match element {
+                SegmentElement::Cluster(c) => Some((
+                    c.timestamp,
+                    c.simple_block
+                        .iter()
+                        .map(|b| b.timestamp)
+                        .collect::<Vec<_>>(),
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            clusters,
+            [
+                (0, vec![0, 400, 800]),
+                (1200, vec![0, 400, 800]),
+                (2400, vec![0, 400, 600]),
+                (3020, vec![0, 20]),
+            ]
+        );
+
+        let SegmentElement::SeekHead(seek_head) = elements.last().unwrap() else {
+            panic!("expected a SeekHead, got {:?}", elements.last());
+        };
+        assert_eq!(seek_head.positions.len(), 3);
+        assert_eq!(seek_head.positions[0].position, 0);
+
+        let times: Vec<_> = reader
+            .cues()
+            .unwrap()
+            .cue_points
+            .iter()
+            .map(|cp| cp.time)
+            .collect();
+        assert_eq!(times, [0, 1200, 2400, 3000, 3020]);
+
+        reader
+            .seek_in_segment(seek_head.positions[1].position)
+            .unwrap();
+        assert!(matches!(
+            reader.next_element().unwrap(),
+            Some(SegmentElement::Tracks(_))
+        ));
+    }
+
+    #[test]
+    fn track_delay() {
+        let info = test_utils::info();
+        let mut tracks = tracks();
+        let audio = tracks.pop().unwrap();
+        let video = tracks.pop().unwrap();
+        let mut encoder = MatroskaEncoder::new(Vec::new(), info, Vec::new()).unwrap();
+        encoder.add_track_with_delay(video, 100_000_000).unwrap();
+        encoder
+            .add_track_with_delay(audio.clone(), -20_000_000)
+            .unwrap();
+        encoder
+            .add_track_with_delay(audio.clone(), -30_000_000)
+            .unwrap();
+        for timestamp in [0, 40_000_000, 80_000_000] {
+            encoder.push_frame(1, timestamp, true, &[1]).unwrap();
+            encoder.push_frame(2, timestamp, true, &[2]).unwrap();
+        }
+        assert!(matches!(
+            encoder.add_track_with_delay(audio, 0),
+            Err(Error::TracksWritten)
+        ));
+        let data = encoder.finish().unwrap();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let timestamps = |reader: &mut MatroskaReader<_>, track| {
+            reader.seek_to_byte_offset(0).unwrap();
+            reader
+                .frames(track)
+                .map(|frame| frame.unwrap().timestamp_ns / 1_000_000)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(reader.track_list().unwrap().len(), 2);
+        assert_eq!(timestamps(&mut reader, 1), [100, 140, 180]);
+        // The first frame would start before the Segment
+        assert_eq!(timestamps(&mut reader, 2), [10, 50]);
+    }
+
+    #[test]
+    fn frame_durations() {
+        let info = test_utils::info();
+        let mut encoder = MatroskaEncoder::new(Vec::new(), info, tracks()).unwrap();
+        encoder.push_frame(1, 0, true, &[1]).unwrap();
+        encoder
+            .push_frame_with_duration(1, 40_000_000, 40_000_000, false, &[2])
+            .unwrap();
+        encoder
+            .push_frame_with_duration(2, 40_000_000, 20_000_000, true, &[3])
+            .unwrap();
+        let data = encoder.finish().unwrap();
+
+        let mut reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let cluster = reader.clusters().next().unwrap().unwrap().cluster;
+        let blocks: Vec<_> = cluster
+            .blocks()
+            .map(|block| (block.track_number(), block.keyframe(), block.duration()))
+            .collect();
+        assert_eq!(
+            blocks,
+            [(1, true, None), (1, false, Some(40)), (2, true, Some(20))]
+        );
+        assert_eq!(cluster.block_group[0].reference_block, [-40]);
+    }
+
+    #[test]
+    fn without_clusters() {
+        let writer =
+            MatroskaWriter::new(Cursor::new(Vec::new()), Info::default(), tracks()).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let reader = MatroskaReader::new(Cursor::new(&data)).unwrap();
+        let elements = reader.collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(elements.len(), 4);
+        match &elements[0] {
+            SegmentElement::SeekHead(seek_head) => assert_eq!(seek_head.positions.len(), 2),
+            e => panic!("expected a SeekHead, got {e:?}"),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! The parsers must fail cleanly, and never panic, on arbitrary input.
+//!
+//! See `fuzz/` for the cargo-fuzz targets running the same entry points
+//! without a time limit.
+
+#![allow(non_upper_case_globals)]
+
+use std::io::Cursor;
+
+use proptest::prelude::*;
+
+use matroska::codec_private::{av1_config, avc_config, hevc_config, opus_head, VorbisHeaders};
+use matroska::ebml::{ebml_float, ebml_header, elem_size, signed_vint, skip_element, vid, vint};
+use matroska::elements::{block, segment, segment_element, simple_block, SegmentElement};
+use matroska::index::MatroskaIndex;
+use matroska::reader::MatroskaReader;
+
+const webm: &[u8] = include_bytes!("../assets/bbb-vp9-opus.webm");
+
+// Inputs of 0 to 8192 bytes
+fn bytes() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 0..=8192)
+}
+
+fn parse_all(data: &[u8]) {
+    let _ = ebml_header(data);
+    let _ = segment(data);
+    let _ = vint(data);
+    let _ = signed_vint(data);
+    let _ = vid(data);
+    let _ = elem_size(data);
+    let _ = ebml_float(data);
+    let _ = skip_element(data);
+    let _ = avc_config(data);
+    let _ = hevc_config(data);
+    let _ = av1_config(data);
+    let _ = opus_head(data);
+    let _ = VorbisHeaders::from_codec_private(data);
+    let _ = MatroskaReader::probe(data);
+
+    if let Ok((_, block)) = block(data) {
+        let _ = block.frames();
+    }
+    if let Ok((_, block)) = simple_block(data) {
+        let _ = block.frames();
+    }
+    if let Ok((_, element)) = segment_element(data) {
+        check_element(&element);
+    }
+}
+
+fn check_element(element: &SegmentElement<'_>) {
+    if let SegmentElement::Cluster(cluster) = element {
+        for block in &cluster.simple_block {
+            let _ = block.frames();
+        }
+        for group in &cluster.block_group {
+            let _ = group.block.frames();
+        }
+    }
+    if let SegmentElement::Tracks(tracks) = element {
+        for track in &tracks.tracks {
+            let _ = track.parsed_codec_private();
+        }
+    }
+}
+
+// Reads the whole Segment, as long as the data can be parsed.
+fn read_all(data: &[u8]) {
+    // The readers allocate their buffer first, the EBML Header is checked
+    // beforehand to keep the tests fast
+    if ebml_header(data).is_err() {
+        let _ = MatroskaIndex::build(Cursor::new(data));
+        return;
+    }
+
+    if let Ok(mut reader) = MatroskaReader::new(Cursor::new(data)) {
+        while let Ok(Some(element)) = reader.next_element() {
+            check_element(&element);
+        }
+    }
+
+    let mut reader = match MatroskaReader::new(Cursor::new(data)) {
+        Ok(reader) => reader.with_error_recovery(true),
+        Err(_) => return,
+    };
+    while let Ok(Some(_)) = reader.next_element() {}
+
+    let _ = MatroskaIndex::build(Cursor::new(data));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(500))]
+
+    #[test]
+    fn arbitrary_bytes(data in bytes()) {
+        parse_all(&data);
+        read_all(&data);
+    }
+
+    // Arbitrary bytes rarely start with a known ID, so the input is
+    // prefixed with the header of a Top-Level Element, optionally followed
+    // by a CRC-32 Element. The declared size may not match the content.
+    #[test]
+    fn arbitrary_elements(
+        selector: u8,
+        declared in prop_oneof![Just(None), (0..16usize).prop_map(Some), (0..=8192usize).prop_map(Some)],
+        with_crc: bool,
+        content in bytes(),
+    ) {
+        let ids: [&[u8]; 9] = [
+            &[0x1A, 0x45, 0xDF, 0xA3],
+            &[0x11, 0x4D, 0x9B, 0x74],
+            &[0x15, 0x49, 0xA9, 0x66],
+            &[0x16, 0x54, 0xAE, 0x6B],
+            &[0x1F, 0x43, 0xB6, 0x75],
+            &[0x1C, 0x53, 0xBB, 0x6B],
+            &[0x10, 0x43, 0xA7, 0x70],
+            &[0x12, 0x54, 0xC3, 0x67],
+            &[0x19, 0x41, 0xA4, 0x69],
+        ];
+        let crc: &[u8] = if with_crc { &[0xBF, 0x84, 0, 0, 0, 0] } else { &[] };
+        let size = declared.unwrap_or(crc.len() + content.len());
+        let size = (size as u64 | 0x1000_0000).to_be_bytes();
+        let input = [ids[selector as usize % ids.len()], &size[4..], crc, &content].concat();
+
+        parse_all(&input);
+    }
+}
+
+#[test]
+fn crc_longer_than_element() {
+    // An Info of 1 byte starting with a CRC-32 Element of 6 bytes
+    let input = [0x15, 0x49, 0xA9, 0x66, 0x81, 0xBF, 0x84, 0, 0, 0, 0];
+    assert!(segment_element(&input).is_err());
+    assert!(skip_element(&input).is_err());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(100))]
+
+    // Overwrites bytes of a valid file, so that parsing gets deep into
+    // the elements before failing
+    #[test]
+    fn corrupted_file(changes in prop::collection::vec(any::<(u16, u8)>(), 0..16)) {
+        let mut data = webm.to_vec();
+        for (position, value) in changes {
+            let len = data.len();
+            data[position as usize % len] = value;
+        }
+
+        read_all(&data);
+    }
+}
@@ -0,0 +1,63 @@
+//! The JSON output of the `to_json` example must keep matching
+//! `tests/metadata.schema.json`, so that the field names and types stay
+//! stable across refactors.
+
+#![cfg(feature = "serde")]
+
+use std::fs::File;
+
+use serde_json::Value;
+
+use matroska::reader::{MatroskaMetadata, MatroskaReader};
+
+fn schema() -> jsonschema::Validator {
+    let schema = std::fs::read_to_string("tests/metadata.schema.json").unwrap();
+    jsonschema::validator_for(&serde_json::from_str(&schema).unwrap()).unwrap()
+}
+
+// The metadata as printed by the example
+fn to_json(path: &str) -> Value {
+    let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+    let metadata = reader.metadata_only().unwrap();
+    serde_json::from_str(&serde_json::to_string_pretty(&metadata).unwrap()).unwrap()
+}
+
+#[test]
+fn metadata_matches_schema() {
+    let schema = schema();
+
+    for path in [
+        "assets/bbb-vp9-opus.webm",
+        "assets/single_stream.mkv",
+        "assets/matroska_test_w1_1/test1.mkv",
+        "assets/matroska_test_w1_1/test5.mkv",
+        "assets/matroska_test_w1_1/test8.mkv",
+    ] {
+        let json = to_json(path);
+        let errors: Vec<_> = schema.iter_errors(&json).map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), "{path}: {errors:?}");
+
+        // The output can be read back
+        let metadata: MatroskaMetadata = serde_json::from_value(json).unwrap();
+        let mut reader = MatroskaReader::new(File::open(path).unwrap()).unwrap();
+        assert_eq!(metadata, reader.metadata_only().unwrap(), "{path}");
+    }
+}
+
+#[test]
+fn schema_rejects_renamed_fields() {
+    let schema = schema();
+
+    let mut json = to_json("assets/bbb-vp9-opus.webm");
+    let tracks = json["tracks"]
+        .as_object_mut()
+        .unwrap()
+        .remove("tracks")
+        .unwrap();
+    json["tracks"]["entries"] = tracks;
+    assert!(!schema.is_valid(&json));
+
+    let mut json = to_json("assets/bbb-vp9-opus.webm");
+    json["info"]["timestamp_scale"] = Value::from("1000000");
+    assert!(!schema.is_valid(&json));
+}
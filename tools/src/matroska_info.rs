@@ -1,15 +1,17 @@
+// err-derive generates its trait impls inside an anonymous const.
+#![allow(non_local_definitions)]
+
 use std::env;
 use std::fs::File;
 use std::io::{self, Read};
-use std::time::Duration;
 
 use circular::Buffer;
 use err_derive::Error;
 use nom::{Err, Offset};
 
 use matroska::ebml::ebml_header;
-use matroska::elements::{segment, segment_element, SegmentElement, Uuid};
-use matroska::serializer::ebml::EbmlSize;
+use matroska::elements::{segment, segment_element, SegmentElement};
+use matroska::reader::DEFAULT_BUFFER_SIZE;
 
 #[derive(Debug, Error)]
 pub enum InfoError {
@@ -47,8 +49,7 @@ fn main() -> Result<(), InfoError> {
 fn run(filename: &str) -> Result<(), InfoError> {
     let mut file = File::open(filename)?;
 
-    let capacity = 5_242_880;
-    let mut b = Buffer::with_capacity(capacity);
+    let mut b = Buffer::with_capacity(DEFAULT_BUFFER_SIZE);
 
     // we write into the `&mut[u8]` returned by `space()`
     let sz = file.read(b.space())?;
@@ -57,17 +58,7 @@ fn run(filename: &str) -> Result<(), InfoError> {
     let length = {
         let res = ebml_header(b.data());
         if let Ok((remaining, header)) = res {
-            println!("+ EBML head");
-            println!("|+ EBML version: {}", header.version);
-            println!("|+ EBML read version: {}", header.read_version);
-            println!("|+ Maximum EBML ID length: {}", header.max_id_length);
-            println!("|+ Maximum EBML size length: {}", header.max_size_length);
-            println!("|+ Document type: {}", header.doc_type);
-            println!("|+ Document type version: {}", header.doc_type_version);
-            println!(
-                "|+ Document type read version: {}",
-                header.doc_type_read_version
-            );
+            println!("{header}");
 
             b.data().offset(remaining)
         } else {
@@ -130,37 +121,7 @@ fn run(filename: &str) -> Result<(), InfoError> {
 
             match element {
                 SegmentElement::SeekHead(s) => {
-                    println!("|+ Seek head at {:#0x} size {}", 0x0, b.data().offset(i));
-                    for seek in s.positions.iter() {
-                        let element_size = seek.size(0x4DBB);
-                        let id_size = seek.id.size(0x53AB);
-                        let position_size = seek.position.size(0x53AC);
-
-                        println!("| + Seek entry size {}", element_size);
-
-                        // FIXME: Make the formatting similar to mkvinfo again
-                        print!(
-                            "|  + Seek ID: {} {} {} {}",
-                            seek.id[0], seek.id[1], seek.id[2], seek.id[3]
-                        );
-
-                        let name = match seek.id {
-                            [0x11, 0x4D, 0x9B, 0x74] => " (KaxSeekHead)",
-                            [0x12, 0x54, 0xC3, 0x67] => " (KaxTags)",
-                            [0x15, 0x49, 0xA9, 0x66] => " (KaxInfo)",
-                            [0x16, 0x54, 0xAE, 0x6B] => " (KaxTracks)",
-                            [0x1C, 0x53, 0xBB, 0x6B] => " (KaxCues)",
-                            [0x1F, 0x43, 0xB6, 0x75] => " (KaxCluster)",
-                            _ => "",
-                        };
-
-                        println!("{} at {:#0x} size {}", name, 0x0, id_size);
-                        println!(
-                            "|  + Seek position: {} size {}",
-                            seek.position, position_size
-                        );
-                    }
-
+                    println!("{s}");
                     if seek_head.is_some() {
                         return Err(InfoError::SeekHeadElement);
                     } else {
@@ -168,33 +129,7 @@ fn run(filename: &str) -> Result<(), InfoError> {
                     }
                 }
                 SegmentElement::Info(i) => {
-                    println!("|+ Segment information");
-                    println!(
-                        "| + Segment UID: {}",
-                        i.segment_uid.map(format_uid).unwrap_or(String::new())
-                    );
-                    println!("| + Timestamp scale: {}", i.timestamp_scale);
-                    if let Some(f) = i.duration {
-                        let nanos = f * i.timestamp_scale as f64;
-                        let d = Duration::from_nanos(nanos.round() as u64);
-                        println!("| + Duration: {}", format_duration(d));
-                    }
-                    println!("| + Multiplexing application: {}", i.muxing_app);
-                    println!("| + Writing application: {}", i.writing_app);
-                    if let Some(ref date) = i.date_utc {
-                        use time::format_description::well_known::Rfc3339;
-                        use time::{Date, Duration};
-
-                        let formatted = Date::from_ordinal_date(2001, 1)
-                            .unwrap()
-                            .midnight()
-                            .assume_utc()
-                            .saturating_add(Duration::nanoseconds(date.0))
-                            .format(&Rfc3339)
-                            .unwrap();
-
-                        println!("| + Date: {formatted}");
-                    }
+                    println!("{i}");
                     if info.is_some() {
                         return Err(InfoError::InfoElement);
                     } else {
@@ -202,57 +137,18 @@ fn run(filename: &str) -> Result<(), InfoError> {
                     }
                 }
                 SegmentElement::Tracks(t) => {
-                    //eprintln!("got tracks: {:#?}", t);
-                    println!("|+ Tracks");
-                    for tr in t.tracks.iter() {
-                        println!("| + Track");
-                        println!("|  + Track number: {}", tr.track_number);
-                        println!("|  + Track UID: {}", tr.track_uid);
-                        println!("|  + Track type: {}", tr.track_type);
-                        println!("|  + Lacing flag: {}", tr.flag_lacing);
-                        println!("|  + Default flag: {}", tr.flag_default);
-                        println!("|  + Language: {}", tr.language);
-                        println!("|  + Codec ID: {}", tr.codec_id);
-                        println!(
-                            "|  + Codec private: length {}",
-                            tr.codec_private.as_ref().map(|v| v.len()).unwrap_or(0)
-                        );
-
-                        if let Some(ref v) = tr.video {
-                            println!("|  + Video track");
-                            println!("|    + Pixel width: {}", v.pixel_width);
-                            println!("|    + Pixel height: {}", v.pixel_height);
-                            println!("|    + Interlaced: {}", v.flag_interlaced);
-                            if let Some(width) = v.display_width {
-                                println!("|    + Display width: {}", width);
-                            }
-                            if let Some(height) = v.display_height {
-                                println!("|    + Display height: {}", height);
-                            }
-                            println!("|    + Display unit: {}", v.display_unit);
-                        }
-
-                        if let Some(ref a) = tr.audio {
-                            println!("|  + Audio track");
-                            println!("|    + Sampling frequency: {}", a.sampling_frequency);
-                            if let Some(frequency) = a.output_sampling_frequency {
-                                println!("|    + Output sampling freqeuncy: {}", frequency);
-                            }
-                            println!("|    + Channels: {}", a.channels);
-                            if let Some(bit_depth) = a.bit_depth {
-                                println!("|    + Bit depth: {}", bit_depth);
-                            }
-                        }
-                    }
+                    println!("{t}");
                     if tracks.is_some() {
                         return Err(InfoError::TracksElement);
                     } else {
                         tracks = Some(t);
                     }
                 }
-                SegmentElement::Void(s) => {
-                    println!("|+ EbmlVoid (size: {})", s);
-                }
+                el @ (SegmentElement::Cues(_)
+                | SegmentElement::Chapters(_)
+                | SegmentElement::Attachments(_)
+                | SegmentElement::Tags(_)
+                | SegmentElement::Void(_)) => println!("{el}"),
                 el => return Err(InfoError::UnexpectedElement(format!("{:?}", el))),
             }
 
@@ -299,20 +195,10 @@ fn run(filename: &str) -> Result<(), InfoError> {
                         "seek head, info or tracks element".to_string(),
                     ));
                 }
-                SegmentElement::Cluster(c) => {
-                    println!("|+ Cluster");
-                    println!("|+   Timestamp: {}", c.timestamp);
-                    println!("|+   Position: {:?}", c.position);
-                    println!("|+   Prev size: {:?}", c.prev_size);
-                    println!("|+   Simple block: {} elements", c.simple_block.len());
-                    println!("|+   Block group: {} elements", c.block_group.len());
-                }
-                SegmentElement::Void(s) => {
-                    println!("|+ EbmlVoid (size: {})", s);
-                }
                 SegmentElement::Unknown(id, data) => {
                     return Err(InfoError::UnknownElement(_consumed, id, data))
                 }
+                el => println!("{el}"),
             }
 
             b.data().offset(i)
@@ -323,18 +209,3 @@ fn run(filename: &str) -> Result<(), InfoError> {
 
     Ok(())
 }
-
-fn format_uid(uid: Uuid) -> String {
-    uid.as_bytes()
-        .iter()
-        .map(|b| format!("{b:#x}"))
-        .collect::<Vec<_>>()
-        .join(" ")
-}
-
-fn format_duration(dur: Duration) -> String {
-    let hrs = dur.as_secs() / 3600;
-    let mins = (dur.as_secs() % 3600) / 60;
-    let secs = dur.as_secs_f64() % 60.0;
-    format!("{hrs:02}:{mins:02}:{secs:02.9}")
-}